@@ -14,8 +14,8 @@
 use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
 use anyhow::Context;
-use clap::Args;
-use ethers::types::H256;
+use clap::{builder::PossibleValuesParser, Args};
+use ethers::types::{H256, I256};
 use rundler_builder::{
     self, BuilderEvent, BuilderEventKind, BuilderTask, BuilderTaskArgs, LocalBuilderBuilder,
     TransactionSenderType,
@@ -26,6 +26,7 @@ use rundler_task::{
     server::{connect_with_retries_shutdown, format_socket_addr},
     spawn_tasks_with_shutdown,
 };
+use rundler_types::TransactionType;
 use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
 
@@ -90,6 +91,36 @@ pub struct BuilderArgs {
     )]
     redis_lock_ttl_millis: u64,
 
+    /// Private keys of backup signers, in priority order, that the builder
+    /// fails over to when the primary signer is underfunded or stuck.
+    #[arg(
+        long = "builder.backup_private_keys",
+        name = "builder.backup_private_keys",
+        env = "BUILDER_BACKUP_PRIVATE_KEYS",
+        value_delimiter = ','
+    )]
+    backup_private_keys: Vec<String>,
+
+    /// Balance in wei below which a signer is skipped in favor of the next
+    /// one. Only used with backup signers.
+    #[arg(
+        long = "builder.signer_min_balance",
+        name = "builder.signer_min_balance",
+        env = "BUILDER_SIGNER_MIN_BALANCE",
+        default_value = "0"
+    )]
+    signer_min_balance: u128,
+
+    /// Number of seconds a signer's transaction may stay unmined at the same
+    /// nonce before the signer is considered stuck. Only used with backup signers.
+    #[arg(
+        long = "builder.signer_stuck_timeout_secs",
+        name = "builder.signer_stuck_timeout_secs",
+        env = "BUILDER_SIGNER_STUCK_TIMEOUT_SECS",
+        default_value = "120"
+    )]
+    signer_stuck_timeout_secs: u64,
+
     /// Maximum number of ops to include in one bundle.
     #[arg(
         long = "builder.max_bundle_size",
@@ -99,6 +130,17 @@ pub struct BuilderArgs {
     )]
     max_bundle_size: u64,
 
+    /// Percentage by which to increase the handleOps gas estimate to leave
+    /// headroom for on-chain state changes between estimation and inclusion.
+    /// The result is capped at the block gas limit.
+    #[arg(
+        long = "builder.handle_ops_gas_limit_increase_percent",
+        name = "builder.handle_ops_gas_limit_increase_percent",
+        env = "BUILDER_HANDLE_OPS_GAS_LIMIT_INCREASE_PERCENT",
+        default_value = "0"
+    )]
+    handle_ops_gas_limit_increase_percent: u64,
+
     /// If present, the url of the ETH provider that will be used to send
     /// transactions. Defaults to the value of `node_http`.
     #[arg(
@@ -108,6 +150,26 @@ pub struct BuilderArgs {
     )]
     pub submit_url: Option<String>,
 
+    /// If present, the url of the ETH provider that will be used for read-only
+    /// simulation calls, e.g. an archive or replica node. Defaults to the value
+    /// of `node_http`.
+    #[arg(
+        long = "builder.simulation_url",
+        name = "builder.simulation_url",
+        env = "BUILDER_SIMULATION_URL"
+    )]
+    pub simulation_url: Option<String>,
+
+    /// If present, the websocket url of the ETH node, used to subscribe to
+    /// pending transactions for faster drop detection. If not set, the node's
+    /// mempool is only polled.
+    #[arg(
+        long = "builder.node_ws",
+        name = "builder.node_ws",
+        env = "BUILDER_NODE_WS"
+    )]
+    pub node_ws: Option<String>,
+
     /// Choice of what sender type to to use for transaction submission.
     /// Defaults to the value of `raw`. Other options inclue `flashbots`,
     /// `conditional` and `polygon_bloxroute`
@@ -120,6 +182,17 @@ pub struct BuilderArgs {
     )]
     pub sender_type: TransactionSenderType,
 
+    /// Type of transaction used to submit bundles. Use `legacy` on chains
+    /// that don't support EIP-1559 transactions.
+    #[arg(
+        long = "builder.transaction_type",
+        name = "builder.transaction_type",
+        env = "BUILDER_TRANSACTION_TYPE",
+        value_parser = PossibleValuesParser::new(["eip1559", "legacy"]),
+        default_value = "eip1559"
+    )]
+    transaction_type: String,
+
     /// After submitting a bundle transaction, the maximum number of blocks to
     /// wait for that transaction to mine before we try resending with higher
     /// gas fees.
@@ -131,6 +204,17 @@ pub struct BuilderArgs {
     )]
     max_blocks_to_wait_for_mine: u64,
 
+    /// Number of consecutive polls that must report a bundle transaction as
+    /// dropped before it is treated as dropped. Zero never treats it as dropped,
+    /// as some providers report spurious drops.
+    #[arg(
+        long = "builder.drop_confirmation_polls",
+        name = "builder.drop_confirmation_polls",
+        env = "BUILDER_DROP_CONFIRMATION_POLLS",
+        default_value = "0"
+    )]
+    drop_confirmation_polls: u64,
+
     /// Percentage amount to increase gas fees when retrying a transaction after
     /// it failed to mine.
     #[arg(
@@ -152,6 +236,142 @@ pub struct BuilderArgs {
     )]
     max_fee_increases: u64,
 
+    /// Minimum projected profit, in wei, for a bundle to be sent. The projected
+    /// profit is the gas payments of the bundle's ops minus the cost of the
+    /// bundle transaction, and may be negative. If not set, bundles are sent
+    /// regardless of profit.
+    #[arg(
+        long = "builder.min_bundle_profit",
+        name = "builder.min_bundle_profit",
+        env = "BUILDER_MIN_BUNDLE_PROFIT",
+        allow_hyphen_values = true
+    )]
+    min_bundle_profit: Option<i128>,
+
+    /// Number of times a sender's ops are excluded from bundles for accessing
+    /// the address of another sender in the bundle after which the sender's
+    /// reputation is penalized. Zero never penalizes the sender.
+    #[arg(
+        long = "builder.accessed_other_sender_penalty_threshold",
+        name = "builder.accessed_other_sender_penalty_threshold",
+        env = "BUILDER_ACCESSED_OTHER_SENDER_PENALTY_THRESHOLD",
+        default_value = "0"
+    )]
+    accessed_other_sender_penalty_threshold: u64,
+
+    /// Maximum number of ops in a bundle that use the same unstaked paymaster,
+    /// factory or aggregator. Zero does not limit them.
+    #[arg(
+        long = "builder.max_unstaked_entity_bundle_ops",
+        name = "builder.max_unstaked_entity_bundle_ops",
+        env = "BUILDER_MAX_UNSTAKED_ENTITY_BUNDLE_OPS",
+        default_value = "0"
+    )]
+    max_unstaked_entity_bundle_ops: u64,
+
+    /// Maximum length, in bytes, of an aggregator signature. Aggregators that
+    /// return longer signatures are left out of the bundle. Zero does not
+    /// limit the length.
+    #[arg(
+        long = "builder.max_aggregator_signature_length",
+        name = "builder.max_aggregator_signature_length",
+        env = "BUILDER_MAX_AGGREGATOR_SIGNATURE_LENGTH",
+        default_value = "1024"
+    )]
+    max_aggregator_signature_length: u64,
+
+    /// If set, ops are simulated against the pending block instead of the
+    /// latest block
+    #[arg(
+        long = "builder.simulate_against_pending",
+        name = "builder.simulate_against_pending",
+        env = "BUILDER_SIMULATE_AGAINST_PENDING",
+        required = false,
+        num_args = 0
+    )]
+    simulate_against_pending: bool,
+
+    /// Minimum per op of the node's gas estimate for a bundle. Bundles with a
+    /// lower estimate are skipped. Zero disables the check.
+    #[arg(
+        long = "builder.min_gas_estimate_per_op",
+        name = "builder.min_gas_estimate_per_op",
+        env = "BUILDER_MIN_GAS_ESTIMATE_PER_OP",
+        default_value = "0"
+    )]
+    min_gas_estimate_per_op: u64,
+
+    /// If set, ops with init code are dropped when their factory has no code
+    /// or their sender is already deployed
+    #[arg(
+        long = "builder.check_init_code",
+        name = "builder.check_init_code",
+        env = "BUILDER_CHECK_INIT_CODE",
+        required = false,
+        num_args = 0
+    )]
+    check_init_code: bool,
+
+    /// Maximum percentage of the bundle size that ops sponsored by a single
+    /// paymaster can take up. Zero does not limit the share.
+    #[arg(
+        long = "builder.max_paymaster_bundle_percent",
+        name = "builder.max_paymaster_bundle_percent",
+        env = "BUILDER_MAX_PAYMASTER_BUNDLE_PERCENT",
+        default_value = "0"
+    )]
+    max_paymaster_bundle_percent: u64,
+
+    /// Number of consecutive bundle send failures after which bundling is
+    /// paused for the cooldown. Zero disables pausing.
+    #[arg(
+        long = "builder.circuit_breaker_failure_threshold",
+        name = "builder.circuit_breaker_failure_threshold",
+        env = "BUILDER_CIRCUIT_BREAKER_FAILURE_THRESHOLD",
+        default_value = "5"
+    )]
+    circuit_breaker_failure_threshold: u64,
+
+    /// Number of seconds to pause bundling after repeated send failures.
+    #[arg(
+        long = "builder.circuit_breaker_cooldown_secs",
+        name = "builder.circuit_breaker_cooldown_secs",
+        env = "BUILDER_CIRCUIT_BREAKER_COOLDOWN_SECS",
+        default_value = "60"
+    )]
+    circuit_breaker_cooldown_secs: u64,
+
+    /// Maximum number of seconds to build a bundle. Bundle attempts that take
+    /// longer are abandoned and retried on the next block.
+    #[arg(
+        long = "builder.make_bundle_timeout_secs",
+        name = "builder.make_bundle_timeout_secs",
+        env = "BUILDER_MAKE_BUNDLE_TIMEOUT_SECS",
+        default_value = "30"
+    )]
+    make_bundle_timeout_secs: u64,
+
+    /// Minimum number of ops to send a bundle. Smaller bundles wait for more
+    /// ops, up to the maximum bundle delay, unless one of their ops is about
+    /// to expire.
+    #[arg(
+        long = "builder.min_bundle_ops",
+        name = "builder.min_bundle_ops",
+        env = "BUILDER_MIN_BUNDLE_OPS",
+        default_value = "1"
+    )]
+    min_bundle_ops: usize,
+
+    /// Maximum number of seconds to hold a bundle with fewer than the minimum
+    /// number of ops
+    #[arg(
+        long = "builder.max_bundle_delay_secs",
+        name = "builder.max_bundle_delay_secs",
+        env = "BUILDER_MAX_BUNDLE_DELAY_SECS",
+        default_value = "30"
+    )]
+    max_bundle_delay_secs: u64,
+
     /// If using Polygon Mainnet, the auth header to use
     /// for Bloxroute polygon_private_tx sender
     #[arg(
@@ -188,6 +408,10 @@ impl BuilderArgs {
             .clone()
             .context("should have a node HTTP URL")?;
         let submit_url = self.submit_url.clone().unwrap_or_else(|| rpc_url.clone());
+        let simulation_url = self
+            .simulation_url
+            .clone()
+            .unwrap_or_else(|| rpc_url.clone());
 
         let mempool_configs = match &common.mempool_config_path {
             Some(path) => {
@@ -212,19 +436,46 @@ impl BuilderArgs {
                 .context("should be a valid aws region")?,
             redis_uri: self.redis_uri.clone(),
             redis_lock_ttl_millis: self.redis_lock_ttl_millis,
+            backup_private_keys: self.backup_private_keys.clone(),
+            signer_min_balance: self.signer_min_balance.into(),
+            signer_stuck_timeout: Duration::from_secs(self.signer_stuck_timeout_secs),
             chain_id: common.chain_id,
             max_bundle_size: self.max_bundle_size,
             max_bundle_gas: common.max_bundle_gas,
+            handle_ops_gas_limit_increase_percent: self.handle_ops_gas_limit_increase_percent,
             submit_url,
+            simulation_url,
+            node_ws: self.node_ws.clone(),
             bundle_priority_fee_overhead_percent: common.bundle_priority_fee_overhead_percent,
             priority_fee_mode,
+            use_dynamic_max_priority_fee: !common.disable_dynamic_max_priority_fee,
+            static_max_priority_fee: common.static_max_priority_fee.into(),
+            min_bundle_profit: self.min_bundle_profit.map(I256::from),
+            accessed_other_sender_penalty_threshold: self.accessed_other_sender_penalty_threshold,
+            max_unstaked_entity_bundle_ops: self.max_unstaked_entity_bundle_ops,
+            max_aggregator_signature_length: self.max_aggregator_signature_length,
+            simulate_against_pending: self.simulate_against_pending,
+            min_gas_estimate_per_op: self.min_gas_estimate_per_op,
+            check_init_code: self.check_init_code,
+            max_paymaster_bundle_percent: self.max_paymaster_bundle_percent,
             sender_type: self.sender_type,
+            transaction_type: self
+                .transaction_type
+                .parse::<TransactionType>()
+                .context("should parse transaction type")?,
             eth_poll_interval: Duration::from_millis(common.eth_poll_interval_millis),
             sim_settings: common.into(),
+            calldata_gas: common.into(),
             mempool_configs,
             max_blocks_to_wait_for_mine: self.max_blocks_to_wait_for_mine,
             replacement_fee_percent_increase: self.replacement_fee_percent_increase,
+            drop_confirmation_polls: self.drop_confirmation_polls,
             max_fee_increases: self.max_fee_increases,
+            circuit_breaker_failure_threshold: self.circuit_breaker_failure_threshold,
+            circuit_breaker_cooldown: Duration::from_secs(self.circuit_breaker_cooldown_secs),
+            make_bundle_timeout: Duration::from_secs(self.make_bundle_timeout_secs),
+            min_bundle_ops: self.min_bundle_ops,
+            max_bundle_delay: Duration::from_secs(self.max_bundle_delay_secs),
             remote_address,
             bloxroute_auth_header: self.bloxroute_auth_header.clone(),
             num_bundle_builders: common.num_builders,
@@ -247,12 +498,23 @@ pub struct BuilderCliArgs {
         global = true
     )]
     pool_url: String,
+
+    #[arg(
+        long = "builder.pool_grpc_compression",
+        name = "builder.pool_grpc_compression",
+        env = "BUILDER_POOL_GRPC_COMPRESSION",
+        required = false,
+        num_args = 0,
+        global = true
+    )]
+    pool_grpc_compression: bool,
 }
 
 pub async fn run(builder_args: BuilderCliArgs, common_args: CommonArgs) -> anyhow::Result<()> {
     let BuilderCliArgs {
         builder: builder_args,
         pool_url,
+        pool_grpc_compression,
     } = builder_args;
 
     let (event_sender, event_rx) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
@@ -268,7 +530,7 @@ pub async fn run(builder_args: BuilderCliArgs, common_args: CommonArgs) -> anyho
     let pool = connect_with_retries_shutdown(
         "op pool from builder",
         &pool_url,
-        RemotePoolClient::connect,
+        |url| RemotePoolClient::connect_with_compression(url, pool_grpc_compression),
         tokio::signal::ctrl_c(),
     )
     .await?;
@@ -28,7 +28,8 @@ use pool::PoolCliArgs;
 use rpc::RpcCliArgs;
 use rundler_rpc::EthApiSettings;
 use rundler_sim::{
-    EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings, MIN_CALL_GAS_LIMIT,
+    gas::CalldataGasConstants, EstimationSettings, PrecheckSettings, PriorityFeeMode,
+    SimulationSettings, MIN_CALL_GAS_LIMIT,
 };
 
 use ethers::types::{Address, H256};
@@ -198,6 +199,44 @@ pub struct CommonArgs {
     )]
     validation_estimation_gas_fee: u64,
 
+    /// If set, gas estimates include a breakdown of the pre-verification gas
+    #[arg(
+        long = "pre_verification_gas_breakdown",
+        name = "pre_verification_gas_breakdown",
+        env = "PRE_VERIFICATION_GAS_BREAKDOWN",
+        required = false,
+        num_args = 0,
+        global = true
+    )]
+    pre_verification_gas_breakdown: bool,
+
+    #[arg(
+        long = "calldata_zero_byte_gas",
+        name = "calldata_zero_byte_gas",
+        env = "CALLDATA_ZERO_BYTE_GAS",
+        default_value = "4",
+        global = true
+    )]
+    calldata_zero_byte_gas: u64,
+
+    #[arg(
+        long = "calldata_non_zero_byte_gas",
+        name = "calldata_non_zero_byte_gas",
+        env = "CALLDATA_NON_ZERO_BYTE_GAS",
+        default_value = "16",
+        global = true
+    )]
+    calldata_non_zero_byte_gas: u64,
+
+    #[arg(
+        long = "transaction_gas_overhead",
+        name = "transaction_gas_overhead",
+        env = "TRANSACTION_GAS_OVERHEAD",
+        default_value = "21000",
+        global = true
+    )]
+    transaction_gas_overhead: u64,
+
     #[arg(
         long = "bundle_priority_fee_overhead_percent",
         name = "bundle_priority_fee_overhead_percent",
@@ -223,6 +262,24 @@ pub struct CommonArgs {
     )]
     priority_fee_mode_value: u64,
 
+    #[arg(
+        long = "disable_dynamic_max_priority_fee",
+        name = "disable_dynamic_max_priority_fee",
+        env = "DISABLE_DYNAMIC_MAX_PRIORITY_FEE",
+        required = false,
+        num_args = 0,
+        global = true
+    )]
+    disable_dynamic_max_priority_fee: bool,
+
+    #[arg(
+        long = "static_max_priority_fee",
+        name = "static_max_priority_fee",
+        env = "STATIC_MAX_PRIORITY_FEE",
+        default_value = "0"
+    )]
+    static_max_priority_fee: u128,
+
     #[arg(
         long = "base_fee_accept_percent",
         name = "base_fee_accept_percent",
@@ -331,6 +388,8 @@ impl TryFrom<&CommonArgs> for EstimationSettings {
             max_call_gas,
             max_simulate_handle_ops_gas: value.max_simulate_handle_ops_gas,
             validation_estimation_gas_fee: value.validation_estimation_gas_fee,
+            pre_verification_gas_breakdown: value.pre_verification_gas_breakdown,
+            calldata_gas: value.into(),
         })
     }
 }
@@ -348,12 +407,25 @@ impl TryFrom<&CommonArgs> for PrecheckSettings {
                 value.priority_fee_mode_kind.as_str(),
                 value.priority_fee_mode_value,
             )?,
+            use_dynamic_max_priority_fee: !value.disable_dynamic_max_priority_fee,
+            static_max_priority_fee: value.static_max_priority_fee.into(),
             base_fee_accept_percent: value.base_fee_accept_percent,
             pre_verification_gas_accept_percent: value.pre_verification_gas_accept_percent,
+            calldata_gas: value.into(),
         })
     }
 }
 
+impl From<&CommonArgs> for CalldataGasConstants {
+    fn from(value: &CommonArgs) -> Self {
+        Self {
+            zero_byte: value.calldata_zero_byte_gas,
+            non_zero_byte: value.calldata_non_zero_byte_gas,
+            fixed_overhead: value.transaction_gas_overhead,
+        }
+    }
+}
+
 impl From<&CommonArgs> for SimulationSettings {
     fn from(value: &CommonArgs) -> Self {
         Self::new(
@@ -367,7 +439,10 @@ impl From<&CommonArgs> for SimulationSettings {
 
 impl From<&CommonArgs> for EthApiSettings {
     fn from(value: &CommonArgs) -> Self {
-	Self::new(value.user_operation_event_block_distance)
+        Self {
+            max_verification_gas: Some(value.max_verification_gas.into()),
+            ..Self::new(value.user_operation_event_block_distance)
+        }
     }
 }
 
@@ -14,9 +14,9 @@
 use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
 use anyhow::Context;
-use clap::Args;
+use clap::{builder::PossibleValuesParser, Args};
 use ethers::types::{Chain, H256};
-use rundler_pool::{LocalPoolBuilder, PoolConfig, PoolTask, PoolTaskArgs};
+use rundler_pool::{EvictionPolicy, LocalPoolBuilder, PoolConfig, PoolTask, PoolTaskArgs};
 use rundler_sim::MempoolConfig;
 use rundler_task::spawn_tasks_with_shutdown;
 use rundler_utils::emit::{self, EVENT_CHANNEL_CAPACITY};
@@ -58,6 +58,25 @@ pub struct PoolArgs {
     )]
     pub max_size_in_bytes: usize,
 
+    /// Maximum number of operations in the pool, 0 for no limit
+    #[arg(
+        long = "pool.max_size_in_ops",
+        name = "pool.max_size_in_ops",
+        env = "POOL_MAX_SIZE_IN_OPS",
+        default_value = "0"
+    )]
+    pub max_size_in_ops: usize,
+
+    /// Which operation to evict when the pool is full
+    #[arg(
+        long = "pool.eviction_policy",
+        name = "pool.eviction_policy",
+        env = "POOL_EVICTION_POLICY",
+        value_parser = PossibleValuesParser::new(["lowest_fee", "oldest"]),
+        default_value = "lowest_fee"
+    )]
+    pub eviction_policy: String,
+
     #[arg(
         long = "pool.same_sender_mempool_count",
         name = "pool.same_sender_mempool_count",
@@ -95,6 +114,21 @@ pub struct PoolArgs {
     )]
     pub chain_history_size: Option<u64>,
 
+    #[arg(
+        long = "pool.reorg_reset_threshold",
+        name = "pool.reorg_reset_threshold",
+        env = "POOL_REORG_RESET_THRESHOLD"
+    )]
+    pub reorg_reset_threshold: Option<u64>,
+
+    #[arg(
+        long = "pool.chain_load_blocks_concurrency",
+        name = "pool.chain_load_blocks_concurrency",
+        env = "POOL_CHAIN_LOAD_BLOCKS_CONCURRENCY",
+        default_value = "1"
+    )]
+    pub chain_load_blocks_concurrency: usize,
+
     #[arg(
         long = "pool.chain_update_channel_capacity",
         name = "pool.chain_update_channel_capacity",
@@ -117,6 +151,68 @@ pub struct PoolArgs {
         default_value = "10"
     )]
     pub throttled_entity_live_blocks: u64,
+
+    /// Maximum time in seconds an unmined op can stay in the pool. Zero
+    /// never evicts ops for their age.
+    #[arg(
+        long = "pool.op_ttl_seconds",
+        name = "pool.op_ttl_seconds",
+        env = "POOL_OP_TTL_SECONDS",
+        default_value = "1800"
+    )]
+    pub op_ttl_seconds: u64,
+
+    /// Half-life, in hours, of entity reputation counts. If not set, counts
+    /// decay by 1/24 every hour as defined by the spec.
+    #[arg(
+        long = "pool.reputation_half_life_hours",
+        name = "pool.reputation_half_life_hours",
+        env = "POOL_REPUTATION_HALF_LIFE_HOURS"
+    )]
+    pub reputation_half_life_hours: Option<u64>,
+
+    /// Number of consecutive times builders can report that an op failed
+    /// simulation with validation rule violations before it is removed.
+    #[arg(
+        long = "pool.max_simulation_failures",
+        name = "pool.max_simulation_failures",
+        env = "POOL_MAX_SIMULATION_FAILURES",
+        default_value = "1"
+    )]
+    pub max_simulation_failures: u64,
+
+    /// Number of consecutive times builders can report that an op's simulation
+    /// errored for a reason other than a validation rule violation, such as a
+    /// revert or a node error, before it is removed. Zero never removes ops for
+    /// these errors.
+    #[arg(
+        long = "pool.max_simulation_errors",
+        name = "pool.max_simulation_errors",
+        env = "POOL_MAX_SIMULATION_ERRORS",
+        default_value = "10"
+    )]
+    pub max_simulation_errors: u64,
+
+    /// Gzip compress gRPC payloads. Clients must also enable compression
+    /// for their requests to be compressed.
+    #[arg(
+        long = "pool.grpc_compression",
+        name = "pool.grpc_compression",
+        env = "POOL_GRPC_COMPRESSION",
+        required = false,
+        num_args = 0
+    )]
+    pub grpc_compression: bool,
+
+    /// Signing addresses of the peer bundlers allowed to forward user
+    /// operations to this pool
+    #[arg(
+        long = "pool.trusted_peers",
+        name = "pool.trusted_peers",
+        env = "POOL_TRUSTED_PEERS",
+        value_delimiter = ','
+    )]
+    pub trusted_peers: Vec<String>,
 }
 
 impl PoolArgs {
@@ -146,6 +242,11 @@ impl PoolArgs {
         };
         tracing::info!("Mempool channel configs: {:?}", mempool_channel_configs);
 
+        let eviction_policy = self
+            .eviction_policy
+            .parse::<EvictionPolicy>()
+            .context("should parse eviction policy")?;
+
         let pool_configs = common
             .entry_points
             .iter()
@@ -160,6 +261,8 @@ impl PoolArgs {
                     min_replacement_fee_increase_percentage: self
                         .min_replacement_fee_increase_percentage,
                     max_size_of_pool_bytes: self.max_size_in_bytes,
+                    max_pool_size: self.max_size_in_ops,
+                    eviction_policy,
                     blocklist: blocklist.clone(),
                     allowlist: allowlist.clone(),
                     precheck_settings: common.try_into()?,
@@ -167,6 +270,13 @@ impl PoolArgs {
                     mempool_channel_configs: mempool_channel_configs.clone(),
                     throttled_entity_mempool_count: self.throttled_entity_mempool_count,
                     throttled_entity_live_blocks: self.throttled_entity_live_blocks,
+                    op_ttl: (self.op_ttl_seconds > 0)
+                        .then_some(Duration::from_secs(self.op_ttl_seconds)),
+                    reputation_half_life: self
+                        .reputation_half_life_hours
+                        .map(|hours| Duration::from_secs(hours * 60 * 60)),
+                    max_simulation_failures: self.max_simulation_failures,
+                    max_simulation_errors: self.max_simulation_errors,
                 })
             })
             .collect::<anyhow::Result<Vec<PoolConfig>>>()?;
@@ -176,6 +286,8 @@ impl PoolArgs {
             chain_history_size: self
                 .chain_history_size
                 .unwrap_or_else(|| default_chain_history_size(common.chain_id)),
+            reorg_reset_threshold: self.reorg_reset_threshold,
+            chain_load_blocks_concurrency: self.chain_load_blocks_concurrency,
             http_url: common
                 .node_http
                 .clone()
@@ -183,7 +295,14 @@ impl PoolArgs {
             http_poll_interval: Duration::from_millis(common.eth_poll_interval_millis),
             pool_configs,
             remote_address,
+            grpc_compression: self.grpc_compression,
             chain_update_channel_capacity: self.chain_update_channel_capacity.unwrap_or(1024),
+            trusted_peers: self
+                .trusted_peers
+                .iter()
+                .map(|peer| peer.parse())
+                .collect::<Result<_, _>>()
+                .context("Invalid pool.trusted_peers argument")?,
         })
     }
 }
@@ -65,6 +65,16 @@ pub struct RpcArgs {
     )]
     timeout_seconds: String,
 
+    /// Timeouts for specific methods, overriding the timeout for RPC requests,
+    /// as comma-separated `method=seconds` entries
+    #[arg(
+        long = "rpc.method_timeouts",
+        name = "rpc.method_timeouts",
+        env = "RPC_METHOD_TIMEOUTS",
+        value_delimiter = ','
+    )]
+    method_timeouts: Vec<String>,
+
     /// Maximum number of concurrent connections
     #[arg(
         long = "rpc.max_connections",
@@ -73,6 +83,59 @@ pub struct RpcArgs {
         default_value = "100"
     )]
     max_connections: u32,
+
+    /// Senders allowed to submit user operations. If empty, all senders are
+    /// allowed.
+    #[arg(
+        long = "rpc.allowed_senders",
+        name = "rpc.allowed_senders",
+        env = "RPC_ALLOWED_SENDERS",
+        value_delimiter = ','
+    )]
+    allowed_senders: Vec<String>,
+
+    /// Senders that are not allowed to submit user operations
+    #[arg(
+        long = "rpc.denied_senders",
+        name = "rpc.denied_senders",
+        env = "RPC_DENIED_SENDERS",
+        value_delimiter = ','
+    )]
+    denied_senders: Vec<String>,
+
+    /// Senders whose bundler tips are accepted, as they pay them under an
+    /// off-chain agreement. Operations from other senders that declare a tip
+    /// are rejected.
+    #[arg(
+        long = "rpc.bundler_tip_senders",
+        name = "rpc.bundler_tip_senders",
+        env = "RPC_BUNDLER_TIP_SENDERS",
+        value_delimiter = ','
+    )]
+    bundler_tip_senders: Vec<String>,
+
+    /// Return partial receipts, with the bundle transaction hash and no block
+    /// fields, for user operations in a bundle that is not yet mined
+    #[arg(
+        long = "rpc.partial_receipts",
+        name = "rpc.partial_receipts",
+        env = "RPC_PARTIAL_RECEIPTS",
+        required = false,
+        num_args = 0
+    )]
+    partial_receipts: bool,
+
+    /// Reject user operations whose signature recovers to an address other
+    /// than the sender's `owner()`, as happens when they are signed for another
+    /// chain. Only suitable when all accounts are plain ECDSA owner accounts.
+    #[arg(
+        long = "rpc.check_signature_chain",
+        name = "rpc.check_signature_chain",
+        env = "RPC_CHECK_SIGNATURE_CHAIN",
+        required = false,
+        num_args = 0
+    )]
+    check_signature_chain: bool,
 }
 
 impl RpcArgs {
@@ -92,6 +155,30 @@ impl RpcArgs {
             .map(|api| api.parse())
             .collect::<Result<Vec<_>, _>>()?;
 
+        let eth_api_settings = EthApiSettings {
+            allowed_senders: self
+                .allowed_senders
+                .iter()
+                .map(|sender| sender.parse())
+                .collect::<Result<_, _>>()
+                .context("Invalid rpc.allowed_senders argument")?,
+            denied_senders: self
+                .denied_senders
+                .iter()
+                .map(|sender| sender.parse())
+                .collect::<Result<_, _>>()
+                .context("Invalid rpc.denied_senders argument")?,
+            bundler_tip_senders: self
+                .bundler_tip_senders
+                .iter()
+                .map(|sender| sender.parse())
+                .collect::<Result<_, _>>()
+                .context("Invalid rpc.bundler_tip_senders argument")?,
+            partial_receipts: self.partial_receipts,
+            check_signature_chain: self.check_signature_chain,
+            ..eth_api_settings
+        };
+
         Ok(RpcTaskArgs {
             port: self.port,
             host: self.host.clone(),
@@ -111,6 +198,17 @@ impl RpcArgs {
             eth_api_settings,
             estimation_settings,
             rpc_timeout: Duration::from_secs(self.timeout_seconds.parse()?),
+            rpc_method_timeouts: self
+                .method_timeouts
+                .iter()
+                .map(|entry| {
+                    let (method, seconds) = entry
+                        .split_once('=')
+                        .context("method timeout should be method=seconds")?;
+                    Ok((method.to_string(), Duration::from_secs(seconds.parse()?)))
+                })
+                .collect::<anyhow::Result<_>>()
+                .context("Invalid rpc.method_timeouts argument")?,
             max_connections: self.max_connections,
         })
     }
@@ -131,6 +229,16 @@ pub struct RpcCliArgs {
     )]
     pool_url: String,
 
+    #[arg(
+        long = "rpc.pool_grpc_compression",
+        name = "rpc.pool_grpc_compression",
+        env = "RPC_POOL_GRPC_COMPRESSION",
+        required = false,
+        num_args = 0,
+        global = true
+    )]
+    pool_grpc_compression: bool,
+
     #[arg(
         long = "rpc.builder_url",
         name = "rpc.builder_url",
@@ -145,6 +253,7 @@ pub async fn run(rpc_args: RpcCliArgs, common_args: CommonArgs) -> anyhow::Resul
     let RpcCliArgs {
         rpc: rpc_args,
         pool_url,
+        pool_grpc_compression,
         builder_url,
     } = rpc_args;
 
@@ -158,7 +267,7 @@ pub async fn run(rpc_args: RpcCliArgs, common_args: CommonArgs) -> anyhow::Resul
     let pool = connect_with_retries_shutdown(
         "op pool from rpc",
         &pool_url,
-        RemotePoolClient::connect,
+        |url| RemotePoolClient::connect_with_compression(url, pool_grpc_compression),
         tokio::signal::ctrl_c(),
     )
     .await?;
@@ -13,44 +13,58 @@
 
 use std::{
     cmp,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    fmt::{self, Display},
     future::Future,
     mem,
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
 use anyhow::Context;
 use async_trait::async_trait;
-use ethers::types::{Address, BlockId, Bytes, H256, U256};
+use ethers::{
+    abi::{self, Token},
+    types::{Address, BlockId, BlockNumber, Bytes, H256, I256, U256},
+    utils::keccak256,
+};
 use futures::future;
 use futures_util::TryFutureExt;
 use linked_hash_map::LinkedHashMap;
 #[cfg(test)]
 use mockall::automock;
-use rundler_pool::{PoolOperation, PoolServer};
+use rundler_pool::{PoolOperation, PoolServer, ReputationStatus};
 use rundler_provider::{EntryPoint, HandleOpsOut, Provider};
 use rundler_sim::{
-    gas::{self, GasOverheads},
+    gas::{self, CalldataGasConstants, GasOverheads},
     EntityInfo, EntityInfos, ExpectedStorage, FeeEstimator, PriorityFeeMode, SimulationError,
     SimulationResult, SimulationViolation, Simulator, ViolationError,
 };
 use rundler_types::{
     Entity, EntityType, EntityUpdate, EntityUpdateType, GasFees, Timestamp, UserOperation,
-    UserOpsPerAggregator,
+    UserOperationId, UserOpsPerAggregator,
 };
 use rundler_utils::{emit::WithEntryPoint, math};
 use tokio::{sync::broadcast, try_join};
 use tracing::{error, info, warn};
 use rundler_types::hybrid_compute;
 
-use crate::emit::{BuilderEvent, OpRejectionReason, SkipReason};
+use crate::{
+    bundle_scorer::{BundleScorer, BundlerTipScorer},
+    emit::{BuilderEvent, OpRejectionReason, SkipReason},
+    gas_oracle::{FeeEstimatorGasOracle, GasOracle, GasOracleContext},
+};
 
 /// A user op must be valid for at least this long into the future to be included.
 const TIME_RANGE_BUFFER: Duration = Duration::from_secs(60);
+/// Maximum number of times to restart building a bundle because the head block
+/// changed while its ops were being simulated.
+const MAX_HEAD_CHANGE_RESTARTS: u64 = 1;
 /// Extra buffer percent to add on the bundle transaction gas estimate to be sure it will be enough
 const BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT: u64 = 5;
+/// Maximum number of ops from a throttled entity that can be included in a single bundle.
+const THROTTLED_ENTITY_BUNDLE_COUNT: usize = 1;
 
 #[derive(Debug, Default)]
 pub(crate) struct Bundle {
@@ -59,7 +73,48 @@ pub(crate) struct Bundle {
     pub(crate) gas_fees: GasFees,
     pub(crate) expected_storage: ExpectedStorage,
     pub(crate) rejected_ops: Vec<UserOperation>,
+    /// Ops that failed simulation with validation rule violations. These are
+    /// reported to the pool, which removes an op once it fails too many times.
+    pub(crate) failed_ops: Vec<UserOperation>,
+    /// Ops whose simulation errored for another reason. These are reported to
+    /// the pool, which removes an op once the errors persist.
+    pub(crate) errored_ops: Vec<UserOperation>,
     pub(crate) entity_updates: Vec<EntityUpdate>,
+    /// Gas payments of the ops to the beneficiary minus the cost of the bundle
+    /// transaction at current fees. Negative if the bundle loses money.
+    pub(crate) projected_profit: I256,
+    /// Aggregators whose ops were left out of the bundle, and why
+    pub(crate) dropped_aggregators: Vec<DroppedAggregator>,
+    /// Earliest time after which one of the ops is no longer valid, if any
+    pub(crate) earliest_valid_until: Option<Timestamp>,
+}
+
+/// An aggregator whose ops were left out of a bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct DroppedAggregator {
+    pub(crate) address: Address,
+    pub(crate) reason: AggregatorDropReason,
+}
+
+/// Why an aggregator's ops were left out of a bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum AggregatorDropReason {
+    /// The aggregator rejected the ops and returned no signature
+    Rejected,
+    /// The aggregator returned an empty signature or one over the maximum length
+    InvalidSignature,
+    /// Computing the aggregator's signature failed
+    Error(String),
+}
+
+impl Display for AggregatorDropReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected => write!(f, "aggregator rejected the ops"),
+            Self::InvalidSignature => write!(f, "aggregator returned an invalid signature"),
+            Self::Error(message) => write!(f, "failed to compute signature: {message}"),
+        }
+    }
 }
 
 impl Bundle {
@@ -70,13 +125,33 @@ impl Bundle {
             .sum()
     }
 
+    /// Returns true if the bundle has no ops to send, including when it only
+    /// has aggregator groups without any ops.
     pub(crate) fn is_empty(&self) -> bool {
-        self.ops_per_aggregator.is_empty()
+        self.iter_ops().next().is_none()
     }
 
     pub(crate) fn iter_ops(&self) -> impl Iterator<Item = &UserOperation> + '_ {
         self.ops_per_aggregator.iter().flat_map(|ops| &ops.user_ops)
     }
+
+    /// Deterministic identifier for the bundle, computed from the ordered op
+    /// hashes, the beneficiary, and the gas parameters. Used to correlate logs
+    /// across the proposer, sender, and tracker.
+    pub(crate) fn hash(&self, entry_point: Address, chain_id: u64, beneficiary: Address) -> H256 {
+        let op_hashes = self
+            .iter_ops()
+            .map(|op| Token::FixedBytes(op.op_hash(entry_point, chain_id).0.to_vec()))
+            .collect();
+        keccak256(abi::encode(&[
+            Token::Array(op_hashes),
+            Token::Address(beneficiary),
+            Token::Uint(self.gas_estimate),
+            Token::Uint(self.gas_fees.max_fee_per_gas),
+            Token::Uint(self.gas_fees.max_priority_fee_per_gas),
+        ]))
+        .into()
+    }
 }
 
 #[cfg_attr(test, automock)]
@@ -98,9 +173,18 @@ where
     simulator: S,
     entry_point: E,
     provider: Arc<P>,
+    simulation_provider: Arc<P>,
     settings: Settings,
-    fee_estimator: FeeEstimator<P>,
+    gas_oracle: Arc<dyn GasOracle>,
+    bundle_scorer: Option<Arc<dyn BundleScorer>>,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    // Gas limit of the most recently seen block, keyed by block hash
+    block_gas_limit: Mutex<Option<(H256, U256)>>,
+    // Reputation statuses of entities fetched from the pool, reset when the
+    // block changes
+    reputation_statuses: Mutex<(H256, HashMap<Address, ReputationStatus>)>,
+    // Exclusions for accessing another sender in the bundle, keyed by sender
+    accessed_other_sender_counts: Mutex<HashMap<Address, u64>>,
 }
 
 #[derive(Debug)]
@@ -108,9 +192,37 @@ pub(crate) struct Settings {
     pub(crate) chain_id: u64,
     pub(crate) max_bundle_size: u64,
     pub(crate) max_bundle_gas: u64,
+    pub(crate) handle_ops_gas_limit_increase_percent: u64,
     pub(crate) beneficiary: Address,
     pub(crate) bundle_priority_fee_overhead_percent: u64,
     pub(crate) priority_fee_mode: PriorityFeeMode,
+    pub(crate) use_dynamic_max_priority_fee: bool,
+    pub(crate) static_max_priority_fee: U256,
+    /// Bundles with a lower projected profit are not sent, if set
+    pub(crate) min_bundle_profit: Option<I256>,
+    /// Number of times a sender's ops are excluded for accessing another sender
+    /// after which the sender's reputation is penalized, 0 to never penalize
+    pub(crate) accessed_other_sender_penalty_threshold: u64,
+    /// Maximum number of ops in a bundle that use the same unstaked
+    /// paymaster, factory or aggregator, 0 for no limit
+    pub(crate) max_unstaked_entity_bundle_ops: u64,
+    /// Aggregator signatures longer than this are rejected along with the
+    /// aggregator's ops, 0 for no limit
+    pub(crate) max_aggregator_signature_length: u64,
+    /// Simulate ops against the pending block instead of the latest block
+    pub(crate) simulate_against_pending: bool,
+    /// Bundles whose node gas estimate is below this amount per op are
+    /// skipped, 0 to never estimate
+    pub(crate) min_gas_estimate_per_op: u64,
+    /// Drop ops with init code whose factory has no code or whose sender is
+    /// already deployed
+    pub(crate) check_init_code: bool,
+    /// Maximum percentage of the bundle size that ops sponsored by a single
+    /// paymaster can take up, 0 for no limit
+    pub(crate) max_paymaster_bundle_percent: u64,
+    /// Calldata gas costs used in the static portion of the pre-verification
+    /// gas and the fixed overhead of the bundle transaction
+    pub(crate) calldata_gas: CalldataGasConstants,
 }
 
 #[async_trait]
@@ -122,13 +234,53 @@ where
     C: PoolServer,
 {
     async fn make_bundle(&self, required_fees: Option<GasFees>) -> anyhow::Result<Bundle> {
-        let (ops, (block_hash, _), (bundle_fees, base_fee)) = try_join!(
+        for _ in 0..MAX_HEAD_CHANGE_RESTARTS {
+            if let Some(bundle) = self.build_bundle(required_fees, true).await? {
+                return Ok(bundle);
+            }
+            info!(
+                "Builder index: {}, head block changed during bundle simulation, restarting build",
+                self.builder_index
+            );
+        }
+        // Don't restart indefinitely on chains with short block times
+        self.build_bundle(required_fees, false)
+            .await?
+            .context("bundle should be built when not checking for head changes")
+    }
+}
+
+impl<S, E, P, C> BundleProposerImpl<S, E, P, C>
+where
+    S: Simulator,
+    E: EntryPoint,
+    P: Provider,
+    C: PoolServer,
+{
+    /// Builds a bundle from the ops in the pool. If `check_head` is set and the
+    /// head block changes while the ops are being simulated, returns `None` so
+    /// that the build can be restarted against consistent state.
+    async fn build_bundle(
+        &self,
+        required_fees: Option<GasFees>,
+        check_head: bool,
+    ) -> anyhow::Result<Option<Bundle>> {
+        // Ops are simulated against this block, so take it from the simulation
+        // provider, which may lag behind the primary provider
+        let (ops, (block_hash, _), base_fee) = try_join!(
             self.get_ops_from_pool(),
-            self.provider
+            self.simulation_provider
                 .get_latest_block_hash_and_number()
                 .map_err(anyhow::Error::from),
-            self.fee_estimator.required_bundle_fees(required_fees)
+            self.provider.get_base_fee().map_err(anyhow::Error::from)
         )?;
+        let (bundle_fees, pre_verification_gas_overhead) = self
+            .gas_oracle
+            .bundle_fees(GasOracleContext {
+                min_fees: required_fees,
+                base_fee,
+            })
+            .await?;
 
         // Limit the amount of gas in the bundle
         tracing::debug!(
@@ -137,6 +289,31 @@ where
             ops.len(),
         );
 
+        let senders: HashSet<Address> = ops.iter().map(|op| op.uo.sender).collect();
+        self.accessed_other_sender_counts
+            .lock()
+            .unwrap()
+            .retain(|sender, _| senders.contains(sender));
+
+        // Cap the ops included from throttled and unstaked entities, and from
+        // any single paymaster
+        let ops = self.limit_throttled_entities(ops, block_hash).await?;
+        let ops = self.limit_unstaked_entities(ops);
+        let mut ops = self.limit_paymaster_share(ops);
+
+        // Among ops with equal scores, or equal fees without a scorer, prefer
+        // ops backed by staked entities
+        if let Some(bundle_scorer) = &self.bundle_scorer {
+            order_ops_by_score(&mut ops, bundle_scorer.as_ref());
+        } else if ops.iter().any(|op| !op.bundler_tip.is_zero()) {
+            order_ops_by_score(
+                &mut ops,
+                &BundlerTipScorer::new(self.settings.chain_id, self.settings.calldata_gas),
+            );
+        } else {
+            order_staked_ops_first(&mut ops);
+        }
+
         // Do an initial filtering of ops that we want to simulate.
         let (ops, gas_limit) = self.limit_user_operations_for_simulation(ops);
         tracing::debug!(
@@ -147,7 +324,7 @@ where
         );
 
         // Determine fees required for ops to be included in a bundle
-        let required_op_fees = self.fee_estimator.required_op_fees(bundle_fees);
+        let required_op_fees = self.settings.priority_fee_mode.required_fees(bundle_fees);
 
         let all_paymaster_addresses = ops
             .iter()
@@ -157,7 +334,15 @@ where
         // Filter ops and simulate
         let simulation_futures = ops
             .into_iter()
-            .map(|op| self.filter_and_simulate(op, block_hash, base_fee, required_op_fees))
+            .map(|op| {
+                self.filter_and_simulate(
+                    op,
+                    block_hash,
+                    base_fee,
+                    required_op_fees,
+                    pre_verification_gas_overhead,
+                )
+            })
             .collect::<Vec<_>>();
 
         let ops_with_simulations_future = future::join_all(simulation_futures);
@@ -170,15 +355,34 @@ where
             .into_iter()
             .flatten()
             .collect::<Vec<_>>();
+
+        // Ops were simulated against `block_hash`, so don't estimate gas
+        // against a different head
+        if check_head && !ops_with_simulations.is_empty() {
+            let (latest_block_hash, _) = self
+                .simulation_provider
+                .get_latest_block_hash_and_number()
+                .await?;
+            if latest_block_hash != block_hash {
+                return Ok(None);
+            }
+        }
 	if ops_with_simulations.len() > 0 {
 	    println!("HC bundle_proposer before assemble_context len {:?}", ops_with_simulations.len());
 	}
         let mut context = self
-            .assemble_context(ops_with_simulations, balances_by_paymaster)
-            .await;
+            .assemble_context(ops_with_simulations, balances_by_paymaster, block_hash)
+            .await?;
         while !context.is_empty() {
             let gas_estimate = self.estimate_gas_rejecting_failed_ops(&mut context).await?;
             if let Some(gas_estimate) = gas_estimate {
+                if self
+                    .node_gas_estimate_below_minimum(&context, gas_estimate)
+                    .await
+                {
+                    // Leave the ops in the pool for a later block
+                    return Ok(Some(context.into_skipped_bundle(bundle_fees, I256::zero())));
+                }
                 tracing::debug!(
                     "Builder index: {}, bundle proposal succeeded with {} ops and {:?} gas limit",
                     self.builder_index,
@@ -186,64 +390,119 @@ where
                     gas_estimate
                 );
 
+                let projected_profit =
+                    self.projected_profit(&context, gas_estimate, bundle_fees, base_fee);
+                if let Some(min_bundle_profit) = self.settings.min_bundle_profit {
+                    if projected_profit < min_bundle_profit {
+                        info!(
+                            "Builder index: {}, skipping bundle with {} op(s) with projected profit {projected_profit} below minimum {min_bundle_profit}",
+                            self.builder_index,
+                            context.iter_ops().count(),
+                        );
+                        return Ok(Some(
+                            context.into_skipped_bundle(bundle_fees, projected_profit),
+                        ));
+                    }
+                }
+
+                let gas_estimate = self
+                    .apply_gas_limit_increase(gas_estimate, block_hash)
+                    .await?;
+
                 let mut expected_storage = ExpectedStorage::default();
                 for op in context.iter_ops_with_simulations() {
                     expected_storage.merge(&op.simulation.expected_storage)?;
                 }
 
-                return Ok(Bundle {
+                let earliest_valid_until = context
+                    .iter_ops_with_simulations()
+                    .map(|op| op.simulation.valid_time_range.valid_until)
+                    .min();
+                let bundle = Bundle {
                     ops_per_aggregator: context.to_ops_per_aggregator(),
                     gas_estimate,
                     gas_fees: bundle_fees,
                     expected_storage,
                     rejected_ops: context.rejected_ops.iter().map(|po| po.0.clone()).collect(),
+                    failed_ops: context.failed_ops,
+                    errored_ops: context.errored_ops,
                     entity_updates: context.entity_updates.into_values().collect(),
-                });
+                    projected_profit,
+                    dropped_aggregators: context.dropped_aggregators,
+                    earliest_valid_until,
+                };
+                info!(
+                    "Builder index: {}, proposed bundle {:?} with {} op(s)",
+                    self.builder_index,
+                    bundle.hash(
+                        self.entry_point.address(),
+                        self.settings.chain_id,
+                        self.settings.beneficiary
+                    ),
+                    bundle.len()
+                );
+                return Ok(Some(bundle));
             }
             info!("Bundle gas estimation failed. Retrying after removing rejected op(s).");
         }
-        Ok(Bundle {
+        Ok(Some(Bundle {
             rejected_ops: context.rejected_ops.iter().map(|po| po.0.clone()).collect(),
+            failed_ops: context.failed_ops,
+            errored_ops: context.errored_ops,
             entity_updates: context.entity_updates.into_values().collect(),
             gas_fees: bundle_fees,
+            dropped_aggregators: context.dropped_aggregators,
             ..Default::default()
-        })
+        }))
     }
-}
 
-impl<S, E, P, C> BundleProposerImpl<S, E, P, C>
-where
-    S: Simulator,
-    E: EntryPoint,
-    P: Provider,
-    C: PoolServer,
-{
     pub(crate) fn new(
         builder_index: u64,
         pool: C,
         simulator: S,
         entry_point: E,
         provider: Arc<P>,
+        simulation_provider: Arc<P>,
         settings: Settings,
         event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     ) -> Self {
+        let fee_estimator = FeeEstimator::new(
+            provider.clone(),
+            settings.chain_id,
+            settings.priority_fee_mode,
+            settings.bundle_priority_fee_overhead_percent,
+            settings.use_dynamic_max_priority_fee,
+            settings.static_max_priority_fee,
+        );
         Self {
             builder_index,
             pool,
             simulator,
             entry_point,
-            provider: provider.clone(),
-            fee_estimator: FeeEstimator::new(
-                provider,
-                settings.chain_id,
-                settings.priority_fee_mode,
-                settings.bundle_priority_fee_overhead_percent,
-            ),
+            provider,
+            simulation_provider,
+            gas_oracle: Arc::new(FeeEstimatorGasOracle::new(fee_estimator)),
+            bundle_scorer: None,
             settings,
             event_sender,
+            block_gas_limit: Mutex::new(None),
+            reputation_statuses: Mutex::new((H256::zero(), HashMap::new())),
+            accessed_other_sender_counts: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Prices bundles with the given gas oracle instead of the default one
+    pub(crate) fn with_gas_oracle(mut self, gas_oracle: Arc<dyn GasOracle>) -> Self {
+        self.gas_oracle = gas_oracle;
+        self
+    }
+
+    /// Orders candidate ops with the given scorer instead of the pool's order
+    pub(crate) fn with_bundle_scorer(mut self, bundle_scorer: Arc<dyn BundleScorer>) -> Self {
+        self.bundle_scorer = Some(bundle_scorer);
+        self
+    }
+
     // Filter and simulate a single op. Returns None if the op should be skipped.
     //
     // Filters on:
@@ -256,15 +515,17 @@ where
         block_hash: H256,
         base_fee: U256,
         required_op_fees: GasFees,
+        pre_verification_gas_overhead: U256,
     ) -> Option<(PoolOperation, Result<SimulationResult, SimulationError>)> {
         println!("HC filter_and_simulate op {:?}", op);
+        let op_hash = op.op_hash(self.settings.chain_id);
         // filter by fees
         if op.uo.max_fee_per_gas < required_op_fees.max_fee_per_gas
             || op.uo.max_priority_fee_per_gas < required_op_fees.max_priority_fee_per_gas
         {
             self.emit(BuilderEvent::skipped_op(
                 self.builder_index,
-                self.op_hash(&op.uo),
+                op_hash,
                 SkipReason::InsufficientFees {
                     required_fees: required_op_fees,
                     actual_fees: GasFees {
@@ -276,19 +537,95 @@ where
             return None;
         }
 
+        // Drop ops whose paymaster has no code, e.g. because it self-destructed after
+        // the op entered the pool. They would otherwise fail gas estimation with an
+        // AA30 error on every bundle attempt.
+        if let Some(paymaster) = op.uo.paymaster() {
+            let code = self
+                .simulation_provider
+                .get_code(paymaster, Some(block_hash))
+                .await
+                .map_err(|e| {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_index,
+                        op_hash,
+                        SkipReason::Other {
+                            reason: Arc::new(format!(
+                                "Failed to load code of paymaster {paymaster:?}: {e:?}, skipping"
+                            )),
+                        },
+                    ));
+                    e
+                })
+                .ok()?;
+            if code.is_empty() {
+                info!("Op {op_hash:?} uses paymaster {paymaster:?} with no code, removing it");
+                return Some((
+                    op,
+                    Err(SimulationError {
+                        violation_error: ViolationError::Other(anyhow::anyhow!(
+                            "paymaster {paymaster:?} has no code"
+                        )),
+                        entity_infos: None,
+                    }),
+                ));
+            }
+        }
+
+        // Drop ops whose init code can never succeed: a factory with no code
+        // fails with AA13, and an already deployed sender fails with AA10.
+        if let Some(factory) = op.uo.factory().filter(|_| self.settings.check_init_code) {
+            let (factory_code, sender_code) = try_join!(
+                self.simulation_provider.get_code(factory, Some(block_hash)),
+                self.simulation_provider
+                    .get_code(op.uo.sender, Some(block_hash)),
+            )
+            .map_err(|e| {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    op_hash,
+                    SkipReason::Other {
+                        reason: Arc::new(format!(
+                            "Failed to load code of factory {factory:?} or sender: {e:?}, skipping"
+                        )),
+                    },
+                ));
+                e
+            })
+            .ok()?;
+            let error = if factory_code.is_empty() {
+                Some(format!("factory {factory:?} has no code"))
+            } else if !sender_code.is_empty() {
+                Some(format!("sender {:?} is already deployed", op.uo.sender))
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                info!("Op {op_hash:?} has init code but {error}, removing it");
+                return Some((
+                    op,
+                    Err(SimulationError {
+                        violation_error: ViolationError::Other(anyhow::anyhow!(error)),
+                        entity_infos: None,
+                    }),
+                ));
+            }
+        }
+
         // Check if the pvg is enough
-        let mut required_pvg = gas::calc_required_pre_verification_gas(
+        let required_pvg = gas::calc_required_pre_verification_gas(
             &op.uo,
             self.entry_point.address(),
-            self.provider.clone(),
+            self.simulation_provider.clone(),
             self.settings.chain_id,
+            &self.settings.calldata_gas,
             base_fee,
         )
         .await
         .map_err(|e| {
             self.emit(BuilderEvent::skipped_op(
                 self.builder_index,
-                self.op_hash(&op.uo),
+                op_hash,
                 SkipReason::Other {
                     reason: Arc::new(format!(
                         "Failed to calculate required pre-verification gas for op: {e:?}, skipping"
@@ -298,6 +635,7 @@ where
             e
         })
         .ok()?;
+        let mut required_pvg = required_pvg.saturating_add(pre_verification_gas_overhead);
 	let hc_hash = op.uo.op_hc_hash();
 	if let Some(hc_pvg) = hybrid_compute::hc_get_pvg(hc_hash) {
 	    println!("HC pvg override for op_hash {:?} {:?} {:?}", hc_hash, required_pvg, hc_pvg);
@@ -311,7 +649,7 @@ where
         if op.uo.pre_verification_gas < required_pvg {
             self.emit(BuilderEvent::skipped_op(
                 self.builder_index,
-                self.op_hash(&op.uo),
+                op_hash,
                 SkipReason::InsufficientPreVerificationGas {
                     base_fee,
                     op_fees: GasFees {
@@ -326,9 +664,14 @@ where
         }
 
         // Simulate
+        let simulation_block = self.pending_block_id().unwrap_or(BlockId::Hash(block_hash));
         let result = self
             .simulator
-            .simulate_validation(op.uo.clone(), Some(block_hash), Some(op.expected_code_hash))
+            .simulate_validation(
+                op.uo.clone(),
+                Some(simulation_block),
+                Some(op.expected_code_hash),
+            )
             .await;
         let result = match result {
             Ok(success) => (op, Ok(success)),
@@ -336,19 +679,22 @@ where
                 SimulationError {
                     violation_error: ViolationError::Violations(_),
                     entity_infos: _,
-                } => (op, Err(error)),
+                } => {
+                    info!("Op {op_hash:?} failed simulation with violations: {error:?}");
+                    (op, Err(error))
+                }
                 SimulationError {
-                    violation_error: ViolationError::Other(error),
+                    violation_error: ViolationError::Other(ref other),
                     entity_infos: _,
                 } => {
                     self.emit(BuilderEvent::skipped_op(
                         self.builder_index,
-                        self.op_hash(&op.uo),
+                        op_hash,
                         SkipReason::Other {
-                            reason: Arc::new(format!("Failed to simulate op: {error:?}, skipping")),
+                            reason: Arc::new(format!("Failed to simulate op: {other:?}, skipping")),
                         },
                     ));
-                    return None;
+                    (op, Err(error))
                 }
             },
         };
@@ -360,22 +706,49 @@ where
         &self,
         ops_with_simulations: Vec<(PoolOperation, Result<SimulationResult, SimulationError>)>,
         mut balances_by_paymaster: HashMap<Address, U256>,
-    ) -> ProposalContext {
+        block_hash: H256,
+    ) -> anyhow::Result<ProposalContext> {
+        // Only one op per sender and nonce can succeed, keep the highest paying
+        let (ops_with_simulations, duplicate_ops) = dedup_ops_by_id(ops_with_simulations);
+        for op in duplicate_ops {
+            self.emit(BuilderEvent::skipped_op(
+                self.builder_index,
+                self.op_hash(&op.uo),
+                SkipReason::DuplicateNonce,
+            ));
+        }
         let all_sender_addresses: HashSet<Address> = ops_with_simulations
             .iter()
             .map(|(op, _)| op.uo.sender)
             .collect();
+        let deployed_senders: HashSet<Address> = ops_with_simulations
+            .iter()
+            .filter(|(op, _)| !op.uo.init_code.is_empty())
+            .map(|(op, _)| op.uo.sender)
+            .collect();
+        let ops_with_simulations = order_sender_deployments(ops_with_simulations);
+        // Aggregator of the included op deploying each sender
+        let mut included_deployments = HashMap::<Address, Option<Address>>::new();
         let mut context = ProposalContext::new();
         let mut paymasters_to_reject = Vec::<EntityInfo>::new();
 
         let ov = GasOverheads::default();
-        let mut gas_spent = ov.transaction_gas_overhead;
+        let mut gas_spent = U256::from(self.settings.calldata_gas.fixed_overhead);
         let mut cleanup_keys:Vec<H256> = Vec::new();
 
         for (po, simulation) in ops_with_simulations {
             let op = po.clone().uo;
             let simulation = match simulation {
                 Ok(simulation) => simulation,
+                Err(SimulationError {
+                    violation_error: ViolationError::Other(_),
+                    entity_infos: _,
+                }) => {
+                    // The op was already skipped, and the pool removes it if the
+                    // errors persist
+                    context.errored_ops.push(op);
+                    continue;
+                }
                 Err(error) => {
                     self.emit(BuilderEvent::rejected_op(
                         self.builder_index,
@@ -392,8 +765,8 @@ where
                         // try to use EntityInfos from the latest simulation, but if it doesn't exist use the EntityInfos from the previous simulation
                         let infos = entity_infos.map_or(po.entity_infos, |e| e);
                         context.process_simulation_violations(violations, infos);
-                        context.rejected_ops.push((op, po.entity_infos));
                     }
+                    context.failed_ops.push(op);
                     continue;
                 }
             };
@@ -418,6 +791,7 @@ where
             let mut required_gas = get_gas_required_for_op(
                 gas_spent,
                 self.settings.chain_id,
+                &self.settings.calldata_gas,
                 ov,
                 &op,
                 simulation.requires_post_op,
@@ -434,6 +808,21 @@ where
                 continue;
             }
 
+            // An op for a sender deployed in this bundle must execute after the deploying op,
+            // which is only guaranteed if the deploying op was included in the same group.
+            if op.init_code.is_empty()
+                && deployed_senders.contains(&op.sender)
+                && included_deployments.get(&op.sender) != Some(&simulation.aggregator_address())
+            {
+                info!("Excluding op from {:?} because its deploying op could not be ordered before it.", op.sender);
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op),
+                    SkipReason::UndeployedSender,
+                ));
+                continue;
+            }
+
             if let Some(&other_sender) = simulation
                 .accessed_addresses
                 .iter()
@@ -447,20 +836,34 @@ where
                     self.op_hash(&op),
                     SkipReason::AccessedOtherSender { other_sender },
                 ));
+                // Repeated offenders are penalized as if they invalidated a bundle
+                if self.record_accessed_other_sender(op.sender) {
+                    context.add_entity_update(Entity::account(op.sender), po.entity_infos);
+                }
                 continue;
             }
             if let Some(paymaster) = op.paymaster() {
-                let Some(balance) = balances_by_paymaster.get_mut(&paymaster) else {
-                    error!("Op had paymaster with unknown balance, but balances should have been loaded for all paymasters in bundle.");
-                    continue;
+                let balance = match balances_by_paymaster.entry(paymaster) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        // Balances should have been loaded for all paymasters in the bundle,
+                        // fetch any that were missed rather than dropping the op.
+                        warn!("Op had paymaster {paymaster:?} with unknown balance, fetching its deposit.");
+                        let deposit = self
+                            .entry_point
+                            .balance_of(paymaster, Some(BlockId::Hash(block_hash)))
+                            .await
+                            .context("entry point should return deposit for paymaster")?;
+                        entry.insert(deposit)
+                    }
                 };
-                let max_cost = op.max_gas_cost();
-                if *balance < max_cost {
+                let required_prefund = op.required_prefund();
+                if *balance < required_prefund {
                     info!("Rejected paymaster {paymaster:?} because its balance {balance:?} was too low.");
                     paymasters_to_reject.push(po.entity_infos.paymaster.unwrap());
                     continue;
                 } else {
-                    *balance -= max_cost;
+                    *balance -= required_prefund;
                 }
             }
 
@@ -468,6 +871,7 @@ where
             gas_spent += gas::user_operation_execution_gas_limit(
                 &op,
                 self.settings.chain_id,
+                &self.settings.calldata_gas,
                 false,
                 simulation.requires_post_op,
             );
@@ -486,6 +890,12 @@ where
                 cleanup_keys.push(hc_ent.clone().unwrap().map_key);
             }
 
+            if !op.init_code.is_empty() {
+                included_deployments
+                    .entry(op.sender)
+                    .or_insert(simulation.aggregator_address());
+            }
+
             context
                 .groups_by_aggregator
                 .entry(simulation.aggregator_address())
@@ -514,13 +924,17 @@ where
                 context.reject_entity(Entity::paymaster(paymaster.address), paymaster.is_staked);
         }
         self.compute_all_aggregator_signatures(&mut context).await;
-        context
+        Ok(context)
     }
 
-    async fn reject_index(&self, context: &mut ProposalContext, i: usize) {
-        let changed_aggregator = context.reject_index(i);
+    async fn reject_index(&self, context: &mut ProposalContext, i: usize) -> anyhow::Result<()> {
+        let changed_aggregator = context.reject_index(i).map_err(|e| {
+            ProposerMetrics::increment_entry_point_index_out_of_bounds(self.builder_index);
+            e
+        })?;
         self.compute_aggregator_signatures(context, &changed_aggregator)
             .await;
+        Ok(())
     }
 
     async fn reject_entity(&self, context: &mut ProposalContext, entity: Entity, is_staked: bool) {
@@ -546,17 +960,105 @@ where
         aggregators: impl IntoIterator<Item = &'a Address>,
     ) {
         let signature_futures = aggregators.into_iter().filter_map(|&aggregator| {
-            context
-                .groups_by_aggregator
-                .get(&Some(aggregator))
-                .map(|group| self.aggregate_signatures(aggregator, group))
+            let group = context.groups_by_aggregator.get(&Some(aggregator))?;
+            let op_hashes = self.group_op_hashes(group);
+            // Skip groups whose signature was already computed for the same ops
+            if group.signed_op_hashes.as_ref() == Some(&op_hashes) {
+                return None;
+            }
+            Some(self.aggregate_signatures(aggregator, group, op_hashes))
         });
         let signatures = future::join_all(signature_futures).await;
-        for (aggregator, result) in signatures {
-            context.apply_aggregation_signature_result(aggregator, result);
+        for (aggregator, op_hashes, result) in signatures {
+            context.apply_aggregation_signature_result(
+                aggregator,
+                op_hashes,
+                result,
+                self.settings.max_aggregator_signature_length,
+            );
         }
     }
 
+    /// Increases the bundle gas estimate by the configured percentage to leave
+    /// headroom for state changes before inclusion. The result is capped at
+    /// the block gas limit, but is never lowered below the original estimate.
+    async fn apply_gas_limit_increase(
+        &self,
+        gas_estimate: U256,
+        block_hash: H256,
+    ) -> anyhow::Result<U256> {
+        let increase_percent = self.settings.handle_ops_gas_limit_increase_percent;
+        if increase_percent == 0 {
+            return Ok(gas_estimate);
+        }
+        let block_gas_limit = self.get_block_gas_limit(block_hash).await?;
+        let increased = math::increase_by_percent(gas_estimate, increase_percent);
+        Ok(cmp::max(gas_estimate, cmp::min(increased, block_gas_limit)))
+    }
+
+    /// Returns the gas limit of the given block, only querying the provider
+    /// when the block differs from the last one seen.
+    async fn get_block_gas_limit(&self, block_hash: H256) -> anyhow::Result<U256> {
+        let cached = *self.block_gas_limit.lock().unwrap();
+        if let Some((cached_hash, gas_limit)) = cached {
+            if cached_hash == block_hash {
+                return Ok(gas_limit);
+            }
+        }
+        let gas_limit = self
+            .simulation_provider
+            .get_block_gas_limit(block_hash)
+            .await
+            .context("should get block gas limit")?;
+        *self.block_gas_limit.lock().unwrap() = Some((block_hash, gas_limit));
+        Ok(gas_limit)
+    }
+
+    /// Returns true if the node's `eth_estimateGas` of the bundle is below the
+    /// configured minimum per op. A tiny estimate means the entry point or node
+    /// is misbehaving, and the bundle would run out of gas on chain.
+    async fn node_gas_estimate_below_minimum(&self, context: &ProposalContext, gas: U256) -> bool {
+        if self.settings.min_gas_estimate_per_op == 0 {
+            return false;
+        }
+        let op_count = context.iter_ops().count();
+        let min_gas_estimate = U256::from(self.settings.min_gas_estimate_per_op) * op_count;
+        let node_estimate = self
+            .entry_point
+            .estimate_handle_ops_gas(
+                context.to_ops_per_aggregator(),
+                self.settings.beneficiary,
+                gas,
+                self.pending_block_id(),
+            )
+            .await;
+        match node_estimate {
+            Ok(node_estimate) if node_estimate < min_gas_estimate => {
+                warn!(
+                    "Builder index: {}, skipping bundle with {op_count} op(s) as the node's gas estimate {node_estimate} is below the minimum of {min_gas_estimate}",
+                    self.builder_index,
+                );
+                true
+            }
+            Ok(_) => false,
+            Err(error) => {
+                // The bundle already passed the handleOps call, so only a
+                // successful estimate can hold it back
+                warn!("Failed to estimate bundle gas with the node: {error:?}");
+                false
+            }
+        }
+    }
+
+    /// Returns the pending block if ops are simulated against it, so that the
+    /// bundle is checked against the same state its ops were simulated in.
+    /// Otherwise `None`.
+    fn pending_block_id(&self) -> Option<BlockId> {
+        self.settings
+            .simulate_against_pending
+            .then_some(BlockId::Number(BlockNumber::Pending))
+    }
+
     /// Estimates the gas needed to send this bundle. If successful, returns the
     /// amount of gas, but if not then mutates the context to remove whichever
     /// op(s) caused the failure.
@@ -567,7 +1069,7 @@ where
         // sum up the gas needed for all the ops in the bundle
         // and apply an overhead multiplier
         let gas = math::increase_by_percent(
-            context.get_bundle_gas_limit(self.settings.chain_id),
+            context.get_bundle_gas_limit(self.settings.chain_id, &self.settings.calldata_gas),
             BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
         );
 
@@ -579,6 +1081,7 @@ where
                 context.to_ops_per_aggregator(),
                 self.settings.beneficiary,
                 gas,
+                self.pending_block_id(),
             )
             .await
             .context("should call handle ops with candidate bundle")?;
@@ -586,9 +1089,15 @@ where
         match handle_ops_out {
             HandleOpsOut::Success => Ok(Some(gas)),
             HandleOpsOut::FailedOp(index, message) => {
+                let Ok(op_with_sim) = context.get_op_at(index) else {
+                    // An out of range index can't be attributed to any op, and
+                    // rejecting it fails, aborting the build
+                    self.reject_index(context, index).await?;
+                    return Ok(None);
+                };
                 self.emit(BuilderEvent::rejected_op(
                     self.builder_index,
-                    self.op_hash(&context.get_op_at(index)?.op),
+                    self.op_hash(&op_with_sim.op),
                     OpRejectionReason::FailedInBundle {
                         message: Arc::new(message.clone()),
                     },
@@ -607,6 +1116,15 @@ where
                 self.process_post_op_revert(context, gas).await?;
                 Ok(None)
             }
+            HandleOpsOut::EntryPointError(error) => {
+                anyhow::bail!("handle ops reverted with an unhandled entry point error: {error}")
+            }
+            HandleOpsOut::Reverted(selector) => {
+                anyhow::bail!(
+                    "handle ops reverted with an unknown error selector {}",
+                    Bytes::from(selector.to_vec())
+                )
+            }
         }
     }
 
@@ -626,6 +1144,164 @@ where
             .context("should get ops from pool")
     }
 
+    /// Skips ops once a throttled entity already has the maximum number of ops
+    /// allowed per bundle. Ops are considered in the order given by the pool.
+    async fn limit_throttled_entities(
+        &self,
+        ops: Vec<PoolOperation>,
+        block_hash: H256,
+    ) -> anyhow::Result<Vec<PoolOperation>> {
+        let addresses: HashSet<Address> = ops
+            .iter()
+            .flat_map(|op| op.uo.entities().map(|entity| entity.address))
+            .collect();
+        let statuses = self.get_reputation_statuses(addresses, block_hash).await?;
+        let throttled: HashSet<Address> = statuses
+            .into_iter()
+            .filter(|(_, status)| *status == ReputationStatus::Throttled)
+            .map(|(address, _)| address)
+            .collect();
+        if throttled.is_empty() {
+            return Ok(ops);
+        }
+
+        let mut op_counts: HashMap<Address, usize> = HashMap::new();
+        let mut ops_in_bundle = Vec::with_capacity(ops.len());
+        for op in ops {
+            let throttled_entities = op
+                .uo
+                .entities()
+                .filter(|entity| throttled.contains(&entity.address))
+                .collect::<Vec<_>>();
+            if let Some(entity) = throttled_entities.iter().find(|entity| {
+                op_counts.get(&entity.address).copied().unwrap_or_default()
+                    >= THROTTLED_ENTITY_BUNDLE_COUNT
+            }) {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op.uo),
+                    SkipReason::ThrottledEntity { entity: *entity },
+                ));
+                continue;
+            }
+            for entity in throttled_entities {
+                *op_counts.entry(entity.address).or_default() += 1;
+            }
+            ops_in_bundle.push(op);
+        }
+        Ok(ops_in_bundle)
+    }
+
+    /// Returns the reputation statuses of the given entities. Statuses are
+    /// cached for the block, so rebuilding a bundle against the same block,
+    /// e.g. to bump fees, only queries the pool for entities it hasn't seen.
+    async fn get_reputation_statuses(
+        &self,
+        addresses: HashSet<Address>,
+        block_hash: H256,
+    ) -> anyhow::Result<HashMap<Address, ReputationStatus>> {
+        let mut statuses = {
+            let mut cache = self.reputation_statuses.lock().unwrap();
+            if cache.0 != block_hash {
+                *cache = (block_hash, HashMap::new());
+            }
+            addresses
+                .iter()
+                .filter_map(|address| cache.1.get(address).map(|status| (*address, *status)))
+                .collect::<HashMap<_, _>>()
+        };
+        let missing = addresses
+            .into_iter()
+            .filter(|address| !statuses.contains_key(address));
+        let fetched = future::try_join_all(missing.map(|address| async move {
+            self.pool
+                .get_reputation_status(self.entry_point.address(), address)
+                .await
+                .map(|status| (address, status))
+                .context("should get reputation status from pool")
+        }))
+        .await?;
+
+        let mut cache = self.reputation_statuses.lock().unwrap();
+        if cache.0 == block_hash {
+            cache.1.extend(fetched.iter().copied());
+        }
+        statuses.extend(fetched);
+        Ok(statuses)
+    }
+
+    /// Skips ops once an unstaked paymaster, factory or aggregator already has
+    /// the maximum number of ops allowed per bundle. Stake status is the entry
+    /// point's stake info recorded when the op was validated by the pool.
+    fn limit_unstaked_entities(&self, ops: Vec<PoolOperation>) -> Vec<PoolOperation> {
+        let max_ops = self.settings.max_unstaked_entity_bundle_ops;
+        if max_ops == 0 {
+            return ops;
+        }
+
+        let mut op_counts: HashMap<Address, u64> = HashMap::new();
+        let mut ops_in_bundle = Vec::with_capacity(ops.len());
+        for op in ops {
+            let infos = &op.entity_infos;
+            let unstaked_entities = [
+                (EntityType::Paymaster, infos.paymaster),
+                (EntityType::Factory, infos.factory),
+                (EntityType::Aggregator, infos.aggregator),
+            ]
+            .into_iter()
+            .filter_map(|(kind, info)| {
+                info.filter(|info| !info.is_staked)
+                    .map(|info| Entity::new(kind, info.address))
+            })
+            .collect::<Vec<_>>();
+            if let Some(entity) = unstaked_entities.iter().find(|entity| {
+                op_counts.get(&entity.address).copied().unwrap_or_default() >= max_ops
+            }) {
+                self.emit(BuilderEvent::skipped_op(
+                    self.builder_index,
+                    self.op_hash(&op.uo),
+                    SkipReason::UnstakedEntity { entity: *entity },
+                ));
+                continue;
+            }
+            for entity in unstaked_entities {
+                *op_counts.entry(entity.address).or_default() += 1;
+            }
+            ops_in_bundle.push(op);
+        }
+        ops_in_bundle
+    }
+
+    /// Skips ops once their paymaster already sponsors the maximum share of the
+    /// bundle, so that one paymaster can't crowd out the ops of others. The
+    /// share is taken of the maximum bundle size.
+    fn limit_paymaster_share(&self, ops: Vec<PoolOperation>) -> Vec<PoolOperation> {
+        let percent = self.settings.max_paymaster_bundle_percent;
+        if percent == 0 || percent >= 100 {
+            return ops;
+        }
+        let max_ops = cmp::max(self.settings.max_bundle_size * percent / 100, 1);
+
+        let mut op_counts: HashMap<Address, u64> = HashMap::new();
+        let mut ops_in_bundle = Vec::with_capacity(ops.len());
+        for op in ops {
+            if let Some(paymaster) = op.uo.paymaster() {
+                let count = op_counts.entry(paymaster).or_default();
+                if *count >= max_ops {
+                    self.emit(BuilderEvent::skipped_op(
+                        self.builder_index,
+                        self.op_hash(&op.uo),
+                        SkipReason::PaymasterShare { paymaster },
+                    ));
+                    continue;
+                }
+                *count += 1;
+            }
+            ops_in_bundle.push(op);
+        }
+        ops_in_bundle
+    }
+
     async fn get_balances_by_paymaster(
         &self,
         addresses: impl IntoIterator<Item = Address>,
@@ -644,21 +1320,30 @@ where
         Ok(HashMap::from_iter(addresses_and_deposits))
     }
 
+    fn group_op_hashes(&self, group: &AggregatorGroup) -> Vec<H256> {
+        group
+            .ops_with_simulations
+            .iter()
+            .map(|op_with_simulation| self.op_hash(&op_with_simulation.op))
+            .collect()
+    }
+
     async fn aggregate_signatures(
         &self,
         aggregator: Address,
         group: &AggregatorGroup,
-    ) -> (Address, anyhow::Result<Option<Bytes>>) {
+        op_hashes: Vec<H256>,
+    ) -> (Address, Vec<H256>, anyhow::Result<Option<Bytes>>) {
         let ops = group
             .ops_with_simulations
             .iter()
             .map(|op_with_simulation| op_with_simulation.op.clone())
             .collect();
-        let result = Arc::clone(&self.provider)
+        let result = Arc::clone(&self.simulation_provider)
             .aggregate_signatures(aggregator, ops)
             .await
             .map_err(anyhow::Error::from);
-        (aggregator, result)
+        (aggregator, op_hashes, result)
     }
 
     async fn process_failed_op(
@@ -708,7 +1393,7 @@ where
                 info!(
                     "Rejected op because it failed during gas estimation with message {message}."
                 );
-                self.reject_index(context, index).await;
+                self.reject_index(context, index).await?;
                 return Ok(());
             }
         };
@@ -768,7 +1453,7 @@ where
                     message: Arc::new("post op reverted leading to entry point revert".to_owned()),
                 },
             ));
-            self.reject_index(context, index).await;
+            self.reject_index(context, index).await?;
         }
 
         Ok(())
@@ -788,7 +1473,12 @@ where
         }];
         let ret = self
             .entry_point
-            .call_handle_ops(bundle, self.settings.beneficiary, gas)
+            .call_handle_ops(
+                bundle,
+                self.settings.beneficiary,
+                gas,
+                self.pending_block_id(),
+            )
             .await;
         match ret {
             Ok(out) => {
@@ -821,7 +1511,12 @@ where
         let bundle = vec![group];
         let ret = self
             .entry_point
-            .call_handle_ops(bundle, self.settings.beneficiary, gas)
+            .call_handle_ops(
+                bundle,
+                self.settings.beneficiary,
+                gas,
+                self.pending_block_id(),
+            )
             .await;
         match ret {
             Ok(out) => {
@@ -856,6 +1551,7 @@ where
             let gas = gas::user_operation_execution_gas_limit(
                 &op.uo,
                 self.settings.chain_id,
+                &self.settings.calldata_gas,
                 false,
                 false,
             );
@@ -878,6 +1574,62 @@ where
         )
     }
 
+    /// Returns the projected profit of the beneficiary for a bundle: the gas
+    /// payments of its ops minus the cost of the bundle transaction, both at
+    /// the current base fee.
+    ///
+    /// Each op is assumed to pay for the gas measured before execution plus its
+    /// full call gas limit.
+    fn projected_profit(
+        &self,
+        context: &ProposalContext,
+        gas_estimate: U256,
+        bundle_fees: GasFees,
+        base_fee: U256,
+    ) -> I256 {
+        let gas_price = |fees: GasFees| {
+            cmp::min(
+                fees.max_fee_per_gas,
+                base_fee.saturating_add(fees.max_priority_fee_per_gas),
+            )
+        };
+        let revenue = context
+            .iter_ops_with_simulations()
+            .fold(U256::zero(), |revenue, op| {
+                let op_gas = op
+                    .simulation
+                    .pre_op_gas
+                    .saturating_add(op.op.call_gas_limit);
+                let op_fees = GasFees {
+                    max_fee_per_gas: op.op.max_fee_per_gas,
+                    max_priority_fee_per_gas: op.op.max_priority_fee_per_gas,
+                };
+                revenue.saturating_add(op_gas.saturating_mul(gas_price(op_fees)))
+            });
+        let cost = gas_estimate.saturating_mul(gas_price(bundle_fees));
+        I256::try_from(revenue)
+            .unwrap_or(I256::MAX)
+            .saturating_sub(I256::try_from(cost).unwrap_or(I256::MAX))
+    }
+
+    /// Records that an op from the sender was excluded for accessing another
+    /// sender. Returns true once this has happened enough times that the
+    /// sender's reputation should be penalized.
+    fn record_accessed_other_sender(&self, sender: Address) -> bool {
+        let threshold = self.settings.accessed_other_sender_penalty_threshold;
+        if threshold == 0 {
+            return false;
+        }
+        let mut counts = self.accessed_other_sender_counts.lock().unwrap();
+        let count = counts.entry(sender).or_default();
+        *count += 1;
+        if *count < threshold {
+            return false;
+        }
+        counts.remove(&sender);
+        true
+    }
+
     fn emit(&self, event: BuilderEvent) {
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: self.entry_point.address(),
@@ -914,14 +1666,19 @@ impl OpWithSimulation {
 struct ProposalContext {
     groups_by_aggregator: LinkedHashMap<Option<Address>, AggregatorGroup>,
     rejected_ops: Vec<(UserOperation, EntityInfos)>,
+    failed_ops: Vec<UserOperation>,
+    errored_ops: Vec<UserOperation>,
     // This is a BTreeMap so that the conversion to a Vec<EntityUpdate> is deterministic, mainly for tests
     entity_updates: BTreeMap<Address, EntityUpdate>,
+    dropped_aggregators: Vec<DroppedAggregator>,
 }
 
 #[derive(Debug, Default)]
 struct AggregatorGroup {
     ops_with_simulations: Vec<OpWithSimulation>,
     signature: Bytes,
+    // Hashes of the ops, in order, that `signature` was computed over
+    signed_op_hashes: Option<Vec<H256>>,
 }
 
 impl ProposalContext {
@@ -929,7 +1686,10 @@ impl ProposalContext {
         Self {
             groups_by_aggregator: LinkedHashMap::<Option<Address>, AggregatorGroup>::new(),
             rejected_ops: Vec::<(UserOperation, EntityInfos)>::new(),
+            failed_ops: vec![],
+            errored_ops: vec![],
             entity_updates: BTreeMap::new(),
+            dropped_aggregators: vec![],
         }
     }
 
@@ -940,18 +1700,45 @@ impl ProposalContext {
     fn apply_aggregation_signature_result(
         &mut self,
         aggregator: Address,
+        op_hashes: Vec<H256>,
         result: anyhow::Result<Option<Bytes>>,
+        max_signature_length: u64,
     ) {
         match result {
-            Ok(Some(sig)) => self.groups_by_aggregator[&Some(aggregator)].signature = sig,
-            Ok(None) => self.reject_aggregator(aggregator),
+            // Groups are removed once their last op is, so the group always
+            // has ops that need a signature here
+            Ok(Some(sig)) if sig.is_empty() => {
+                warn!("Aggregator {aggregator:?} returned an empty signature, rejecting its ops");
+                self.drop_aggregator(aggregator, AggregatorDropReason::InvalidSignature);
+            }
+            Ok(Some(sig))
+                if max_signature_length != 0 && sig.len() as u64 > max_signature_length =>
+            {
+                warn!(
+                    "Aggregator {aggregator:?} returned a signature of {} bytes, above the limit of {max_signature_length}, rejecting its ops",
+                    sig.len()
+                );
+                self.drop_aggregator(aggregator, AggregatorDropReason::InvalidSignature);
+            }
+            Ok(Some(sig)) => {
+                let group = &mut self.groups_by_aggregator[&Some(aggregator)];
+                group.signature = sig;
+                group.signed_op_hashes = Some(op_hashes);
+            }
+            Ok(None) => self.drop_aggregator(aggregator, AggregatorDropReason::Rejected),
             Err(error) => {
                 error!("Failed to compute aggregator signature: {error}");
-                self.groups_by_aggregator.remove(&Some(aggregator));
+                self.drop_aggregator(aggregator, AggregatorDropReason::Error(error.to_string()));
             }
         }
     }
 
+    fn drop_aggregator(&mut self, address: Address, reason: AggregatorDropReason) {
+        self.reject_aggregator(address);
+        self.dropped_aggregators
+            .push(DroppedAggregator { address, reason });
+    }
+
     fn get_op_at(&self, index: usize) -> anyhow::Result<&OpWithSimulation> {
         let mut remaining_i = index;
         for group in self.groups_by_aggregator.values() {
@@ -964,9 +1751,9 @@ impl ProposalContext {
     }
 
     /// Returns the address of the op's aggregator if the aggregator's signature
-    /// may need to be recomputed.
+    /// may need to be recomputed, or an error if the index is out of bounds.
     #[must_use = "rejected op but did not update aggregator signatures"]
-    fn reject_index(&mut self, i: usize) -> Option<Address> {
+    fn reject_index(&mut self, i: usize) -> anyhow::Result<Option<Address>> {
         let mut remaining_i = i;
         let mut found_aggregator: Option<Option<Address>> = None;
         for (&aggregator, group) in &mut self.groups_by_aggregator {
@@ -996,8 +1783,10 @@ impl ProposalContext {
             remaining_i -= group.ops_with_simulations.len();
         }
         let Some(found_aggregator) = found_aggregator else {
-            error!("The entry point indicated a failed op at index {i}, but the bundle size is only {}", i - remaining_i);
-            return None;
+            anyhow::bail!(
+                "The entry point indicated a failed op at index {i}, but the bundle size is only {}",
+                i - remaining_i
+            );
         };
         // If we just removed the last op from a group, delete that group.
         // Otherwise, the signature is invalidated and we need to recompute it.
@@ -1006,9 +1795,9 @@ impl ProposalContext {
             .is_empty()
         {
             self.groups_by_aggregator.remove(&found_aggregator);
-            None
+            Ok(None)
         } else {
-            found_aggregator
+            Ok(found_aggregator)
         }
     }
 
@@ -1079,8 +1868,23 @@ impl ProposalContext {
         changed_aggregators
     }
 
-    fn to_ops_per_aggregator(&self) -> Vec<UserOpsPerAggregator> {
-        self.groups_by_aggregator
+    /// Returns a bundle without any ops that still reports the ops rejected
+    /// and the entity updates made while building it
+    fn into_skipped_bundle(self, gas_fees: GasFees, projected_profit: I256) -> Bundle {
+        Bundle {
+            rejected_ops: self.rejected_ops.iter().map(|po| po.0.clone()).collect(),
+            failed_ops: self.failed_ops,
+            errored_ops: self.errored_ops,
+            entity_updates: self.entity_updates.into_values().collect(),
+            gas_fees,
+            projected_profit,
+            dropped_aggregators: self.dropped_aggregators,
+            ..Default::default()
+        }
+    }
+
+    fn to_ops_per_aggregator(&self) -> Vec<UserOpsPerAggregator> {
+        self.groups_by_aggregator
             .iter()
             .map(|(&aggregator, group)| UserOpsPerAggregator {
                 user_ops: group
@@ -1094,15 +1898,16 @@ impl ProposalContext {
             .collect()
     }
 
-    fn get_bundle_gas_limit(&self, chain_id: u64) -> U256 {
+    fn get_bundle_gas_limit(&self, chain_id: u64, calldata_gas: &CalldataGasConstants) -> U256 {
         let ov = GasOverheads::default();
-        let mut gas_spent = ov.transaction_gas_overhead;
+        let mut gas_spent = U256::from(calldata_gas.fixed_overhead);
         let mut max_gas = U256::zero();
         for op_with_sim in self.iter_ops_with_simulations() {
             let op = &op_with_sim.op;
             let required_gas = get_gas_required_for_op(
                 gas_spent,
                 chain_id,
+                calldata_gas,
                 ov,
                 op,
                 op_with_sim.simulation.requires_post_op,
@@ -1111,6 +1916,7 @@ impl ProposalContext {
             gas_spent += gas::user_operation_gas_limit(
                 op,
                 chain_id,
+                calldata_gas,
                 false,
                 op_with_sim.simulation.requires_post_op,
             );
@@ -1287,9 +2093,120 @@ impl ProposalContext {
     }
 }
 
+/// Sorts ops by descending score, and ops with equal scores so that ops whose
+/// paymaster or factory is staked come first. The score and stake status of a
+/// sender's first op apply to all its ops so that their relative order is kept.
+fn order_ops_by_score(ops: &mut [PoolOperation], bundle_scorer: &dyn BundleScorer) {
+    let mut score_by_sender = HashMap::<Address, U256>::new();
+    for op in ops.iter() {
+        score_by_sender
+            .entry(op.uo.sender)
+            .or_insert_with(|| bundle_scorer.score(op));
+    }
+    let staked_by_sender = staked_by_sender(ops);
+    // Stable sort, so ops with equal scores and stake keep the pool's order
+    ops.sort_by_key(|op| {
+        (
+            cmp::Reverse(score_by_sender[&op.uo.sender]),
+            !staked_by_sender[&op.uo.sender],
+        )
+    });
+}
+
+/// Reorders each run of consecutive ops with equal max fees, as ordered by the
+/// pool, so that ops whose paymaster or factory is staked come first. The stake
+/// status of a sender's first op applies to all its ops so that their relative
+/// order is kept.
+fn order_staked_ops_first(ops: &mut [PoolOperation]) {
+    let staked_by_sender = staked_by_sender(ops);
+    let mut start = 0;
+    while start < ops.len() {
+        let max_fee_per_gas = ops[start].uo.max_fee_per_gas;
+        let end = start
+            + ops[start..]
+                .iter()
+                .take_while(|op| op.uo.max_fee_per_gas == max_fee_per_gas)
+                .count();
+        // Stable sort, so ops otherwise keep the pool's order
+        ops[start..end].sort_by_key(|op| !staked_by_sender[&op.uo.sender]);
+        start = end;
+    }
+}
+
+/// Returns whether the paymaster or factory of each sender's first op is staked
+fn staked_by_sender(ops: &[PoolOperation]) -> HashMap<Address, bool> {
+    let mut staked_by_sender = HashMap::<Address, bool>::new();
+    for op in ops {
+        let infos = &op.entity_infos;
+        let is_staked = infos.paymaster.is_some_and(|info| info.is_staked)
+            || infos.factory.is_some_and(|info| info.is_staked);
+        staked_by_sender.entry(op.uo.sender).or_insert(is_staked);
+    }
+    staked_by_sender
+}
+
+/// Keeps only the op with the highest fees for each sender and nonce, as any
+/// other op with the same sender and nonce would fail in the bundle. Returns
+/// the kept ops in their original order, along with the dropped ops.
+fn dedup_ops_by_id<T>(
+    ops: Vec<(PoolOperation, T)>,
+) -> (Vec<(PoolOperation, T)>, Vec<PoolOperation>) {
+    let fees = |op: &PoolOperation| (op.uo.max_priority_fee_per_gas, op.uo.max_fee_per_gas);
+    let mut best_by_id = HashMap::<UserOperationId, usize>::new();
+    for (i, (op, _)) in ops.iter().enumerate() {
+        match best_by_id.entry(op.uo.id()) {
+            Entry::Occupied(mut entry) => {
+                if fees(op) > fees(&ops[*entry.get()].0) {
+                    entry.insert(i);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+        }
+    }
+    let mut kept = Vec::with_capacity(best_by_id.len());
+    let mut dropped = vec![];
+    for (i, (op, value)) in ops.into_iter().enumerate() {
+        if best_by_id[&op.uo.id()] == i {
+            kept.push((op, value));
+        } else {
+            dropped.push(op);
+        }
+    }
+    (kept, dropped)
+}
+
+/// Reorders ops so that any op for a sender deployed by another op in the list comes after
+/// the deploying op. Otherwise the original order is kept.
+fn order_sender_deployments<T>(ops: Vec<(PoolOperation, T)>) -> Vec<(PoolOperation, T)> {
+    let deployed_senders: HashSet<Address> = ops
+        .iter()
+        .filter(|(op, _)| !op.uo.init_code.is_empty())
+        .map(|(op, _)| op.uo.sender)
+        .collect();
+    let mut deferred = HashMap::<Address, Vec<(PoolOperation, T)>>::new();
+    let mut deployed = HashSet::new();
+    let mut ordered = Vec::with_capacity(ops.len());
+    for (op, value) in ops {
+        let sender = op.uo.sender;
+        let is_deployment = !op.uo.init_code.is_empty();
+        if !is_deployment && deployed_senders.contains(&sender) && !deployed.contains(&sender) {
+            deferred.entry(sender).or_default().push((op, value));
+            continue;
+        }
+        ordered.push((op, value));
+        if is_deployment && deployed.insert(sender) {
+            ordered.extend(deferred.remove(&sender).unwrap_or_default());
+        }
+    }
+    ordered
+}
+
 fn get_gas_required_for_op(
     gas_spent: U256,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     ov: GasOverheads,
     op: &UserOperation,
     requires_post_op: bool,
@@ -1301,12 +2218,21 @@ fn get_gas_required_for_op(
     };
 
     gas_spent
-        + gas::user_operation_pre_verification_gas_limit(op, chain_id, false)
+        + gas::user_operation_pre_verification_gas_limit(op, chain_id, calldata_gas, false)
         + op.verification_gas_limit * 2
         + op.call_gas_limit
         + post_exec_req_gas
 }
 
+struct ProposerMetrics {}
+
+impl ProposerMetrics {
+    // used when the entry point reports a failed op index that is not in the bundle
+    fn increment_entry_point_index_out_of_bounds(builder_index: u64) {
+        metrics::increment_counter!("builder_entry_point_index_out_of_bounds", "builder_index" => builder_index.to_string());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::anyhow;
@@ -1314,12 +2240,14 @@ mod tests {
         types::{H160, U64},
         utils::parse_units,
     };
+    use mockall::predicate::eq;
     use rundler_pool::MockPoolServer;
     use rundler_provider::{AggregatorSimOut, MockEntryPoint, MockProvider};
     use rundler_sim::{MockSimulator, SimulationViolation, ViolationError};
     use rundler_types::ValidTimeRange;
 
     use super::*;
+    use crate::emit::BuilderEventKind;
 
     #[tokio::test]
     async fn test_singleton_valid_bundle() {
@@ -1342,7 +2270,7 @@ mod tests {
                 + op.verification_gas_limit * 2
                 + op.call_gas_limit
                 + ov.bundle_transaction_gas_buffer
-                + ov.transaction_gas_overhead,
+                + CalldataGasConstants::default().fixed_overhead,
             BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
         );
 
@@ -1371,11 +2299,13 @@ mod tests {
         }])
         .await;
         assert!(bundle.ops_per_aggregator.is_empty());
-        assert_eq!(bundle.rejected_ops, vec![op]);
+        // The pool decides whether to remove the op
+        assert!(bundle.rejected_ops.is_empty());
+        assert_eq!(bundle.failed_ops, vec![op]);
     }
 
     #[tokio::test]
-    async fn test_drops_but_not_rejects_on_simulation_failure() {
+    async fn test_reports_simulation_error_to_pool() {
         let op = default_op();
         let bundle = simple_make_bundle(vec![MockOp {
             op: op.clone(),
@@ -1388,7 +2318,10 @@ mod tests {
         }])
         .await;
         assert!(bundle.ops_per_aggregator.is_empty());
+        // The pool decides whether the error is persistent
         assert!(bundle.rejected_ops.is_empty());
+        assert!(bundle.failed_ops.is_empty());
+        assert_eq!(bundle.errored_ops, vec![op]);
     }
 
     #[tokio::test]
@@ -1407,7 +2340,7 @@ mod tests {
         }])
         .await;
         assert!(bundle.ops_per_aggregator.is_empty());
-        assert_eq!(bundle.rejected_ops, vec![op]);
+        assert_eq!(bundle.failed_ops, vec![op]);
     }
 
     #[tokio::test]
@@ -1465,51 +2398,322 @@ mod tests {
                 ..Default::default()
             }]
         );
-        assert!(bundle.rejected_ops.is_empty())
+        assert!(bundle.rejected_ops.is_empty());
+        // No reputation penalty by default
+        assert!(bundle.entity_updates.is_empty());
     }
 
     #[tokio::test]
-    async fn test_drops_but_not_rejects_op_with_too_low_max_priority_fee() {
-        // With 10% required overhead on priority fee, op1 should be excluded
-        // but op2 accepted.
-        let base_fee = U256::from(1000);
-        let max_priority_fee_per_gas = U256::from(50);
-        let op1 = op_with_sender_and_fees(address(1), 2054.into(), 54.into());
-        let op2 = op_with_sender_and_fees(address(2), 2055.into(), 55.into());
-        let bundle = mock_make_bundle(
+    async fn test_penalizes_sender_repeatedly_accessing_another_sender() {
+        let op1 = op_with_sender(address(1));
+        let op2 = op_with_sender(address(2));
+        let proposer = mock_proposer(
+            vec![
+                MockOp {
+                    op: op1,
+                    simulation_result: Box::new(|| {
+                        Ok(SimulationResult {
+                            accessed_addresses: [address(1), address(2)].into(),
+                            ..Default::default()
+                        })
+                    }),
+                },
+                MockOp {
+                    op: op2,
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success, HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                accessed_other_sender_penalty_threshold: 2,
+                ..Default::default()
+            },
+        );
+
+        let bundle = proposer.make_bundle(None).await.unwrap();
+        assert!(bundle.entity_updates.is_empty());
+
+        let bundle = proposer.make_bundle(None).await.unwrap();
+        assert_eq!(
+            bundle.entity_updates,
+            vec![EntityUpdate {
+                entity: Entity::account(address(1)),
+                update_type: EntityUpdateType::UnstakedInvalidation,
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_orders_op_after_sender_deployment() {
+        let deploy_op = op_with_sender_factory(address(1), address(2));
+        let follow_up_op = UserOperation {
+            nonce: 1.into(),
+            ..op_with_sender(address(1))
+        };
+        let bundle = simple_make_bundle(vec![
+            MockOp {
+                op: follow_up_op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            },
+            MockOp {
+                op: deploy_op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            },
+        ])
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![deploy_op.clone(), follow_up_op.clone()],
+                ..Default::default()
+            }]
+        );
+
+        // Without the deploying op the follow-up op is excluded, but not rejected
+        let bundle = simple_make_bundle(vec![
+            MockOp {
+                op: follow_up_op,
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            },
+            MockOp {
+                op: deploy_op,
+                simulation_result: Box::new(|| {
+                    Err(SimulationError {
+                        violation_error: ViolationError::Other(anyhow!("simulation failed")),
+                        entity_infos: None,
+                    })
+                }),
+            },
+        ])
+        .await;
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_limits_ops_from_throttled_paymaster() {
+        let paymaster = address(10);
+        let op1 = op_with_sender_paymaster(address(1), paymaster);
+        let op2 = op_with_sender_paymaster(address(2), paymaster);
+        let deposit = parse_units("1", "ether").unwrap().into();
+
+        let bundle = mock_make_bundle_with_settings(
             vec![
                 MockOp {
                     op: op1.clone(),
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                 },
                 MockOp {
-                    op: op2.clone(),
+                    op: op2,
                     simulation_result: Box::new(|| Ok(SimulationResult::default())),
                 },
             ],
             vec![],
             vec![HandleOpsOut::Success],
+            vec![deposit],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                throttled_entities: vec![paymaster],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reputation_statuses_cached_per_block() {
+        let paymaster = address(10);
+        let op = op_with_sender_paymaster(address(1), paymaster);
+        let ops = vec![PoolOperation {
+            uo: op,
+            ..Default::default()
+        }];
+
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(address(123));
+        // Queried once per entity and distinct block
+        let mut pool_client = MockPoolServer::new();
+        pool_client
+            .expect_get_reputation_status()
+            .times(4)
+            .returning(|_, _| Ok(ReputationStatus::Ok));
+        let (event_sender, _) = broadcast::channel(16);
+        let proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            MockSimulator::new(),
+            entry_point,
+            Arc::new(MockProvider::new()),
+            Arc::new(MockProvider::new()),
+            test_settings(),
+            event_sender,
+        );
+
+        for block_hash in [hash(1), hash(1), hash(2)] {
+            let limited = proposer
+                .limit_throttled_entities(ops.clone(), block_hash)
+                .await
+                .unwrap();
+            assert_eq!(limited, ops);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits_ops_from_unstaked_paymaster() {
+        let paymaster = address(10);
+        let staked_paymaster = address(11);
+        let op1 = op_with_sender_paymaster(address(1), paymaster);
+        let op2 = op_with_sender_paymaster(address(2), paymaster);
+        let op3 = op_with_sender_paymaster(address(3), paymaster);
+        let op4 = op_with_sender_paymaster(address(4), staked_paymaster);
+        let op5 = op_with_sender_paymaster(address(5), staked_paymaster);
+        let op6 = op_with_sender_paymaster(address(6), staked_paymaster);
+        let deposit = parse_units("1", "ether").unwrap().into();
+
+        let bundle = mock_make_bundle_with_settings(
+            [&op1, &op2, &op3, &op4, &op5, &op6]
+                .into_iter()
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                })
+                .collect(),
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![deposit, deposit],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                staked_entities: vec![staked_paymaster],
+                max_unstaked_entity_bundle_ops: 2,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // Only the unstaked paymaster is capped
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op4, op5, op6, op1, op2],
+                ..Default::default()
+            }]
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_caps_ops_of_dominant_paymaster() {
+        let dominant_paymaster = address(10);
+        let other_paymaster = address(11);
+        let dominant_ops = (1..=8)
+            .map(|i| op_with_sender_paymaster(address(i), dominant_paymaster))
+            .collect::<Vec<_>>();
+        let other_ops = (9..=10)
+            .map(|i| op_with_sender_paymaster(address(i), other_paymaster))
+            .collect::<Vec<_>>();
+        let deposit = parse_units("1", "ether").unwrap().into();
+
+        // Half of the maximum bundle size of 10 ops
+        let bundle = mock_make_bundle_with_settings(
+            dominant_ops
+                .iter()
+                .chain(&other_ops)
+                .map(|op| MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                })
+                .collect(),
             vec![],
-            base_fee,
-            max_priority_fee_per_gas,
+            vec![HandleOpsOut::Success],
+            vec![deposit, deposit],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                max_paymaster_bundle_percent: 50,
+                ..Default::default()
+            },
         )
         .await;
+
+        let expected_ops = dominant_ops[..5]
+            .iter()
+            .chain(&other_ops)
+            .cloned()
+            .collect::<Vec<_>>();
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
-                user_ops: vec![op2],
+                user_ops: expected_ops,
                 ..Default::default()
-            }],
+            }]
         );
         assert!(bundle.rejected_ops.is_empty());
     }
 
     #[tokio::test]
-    async fn test_drops_but_not_rejects_op_with_too_low_max_fee_per_gas() {
+    async fn test_orders_staked_paymaster_op_first() {
+        let staked_paymaster = address(11);
+        let op1 = op_with_sender_paymaster(address(1), address(10));
+        let op2 = op_with_sender_paymaster(address(2), staked_paymaster);
+        let deposit = parse_units("1", "ether").unwrap().into();
+
+        // Both ops have equal fees, so the pool's order is kept except for stake
+        let bundle = mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![deposit, deposit],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                staked_entities: vec![staked_paymaster],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2, op1],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drops_but_not_rejects_op_with_too_low_max_priority_fee() {
+        // With 10% required overhead on priority fee, op1 should be excluded
+        // but op2 accepted.
         let base_fee = U256::from(1000);
         let max_priority_fee_per_gas = U256::from(50);
-        let op1 = op_with_sender_and_fees(address(1), 1054.into(), 55.into());
-        let op2 = op_with_sender_and_fees(address(2), 1055.into(), 55.into());
+        let op1 = op_with_sender_and_fees(address(1), 2054.into(), 54.into());
+        let op2 = op_with_sender_and_fees(address(2), 2055.into(), 55.into());
         let bundle = mock_make_bundle(
             vec![
                 MockOp {
@@ -1528,13 +2732,6 @@ mod tests {
             max_priority_fee_per_gas,
         )
         .await;
-        assert_eq!(
-            bundle.gas_fees,
-            GasFees {
-                max_fee_per_gas: 1050.into(),
-                max_priority_fee_per_gas: 50.into(),
-            }
-        );
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
@@ -1546,12 +2743,11 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_drops_but_not_rejects_op_with_too_low_pvg() {
+    async fn test_keeps_higher_fee_op_with_duplicate_sender_and_nonce() {
         let base_fee = U256::from(1000);
         let max_priority_fee_per_gas = U256::from(50);
-        let mut op1 = op_with_sender_and_fees(address(1), 1054.into(), 55.into());
-        op1.pre_verification_gas = 0.into(); // Should be dropped but not rejected
-        let op2 = op_with_sender_and_fees(address(2), 1055.into(), 55.into());
+        let op1 = op_with_sender_and_fees(address(1), 2100.into(), 100.into());
+        let op2 = op_with_sender_and_fees(address(1), 2200.into(), 200.into());
         let bundle = mock_make_bundle(
             vec![
                 MockOp {
@@ -1570,13 +2766,6 @@ mod tests {
             max_priority_fee_per_gas,
         )
         .await;
-        assert_eq!(
-            bundle.gas_fees,
-            GasFees {
-                max_fee_per_gas: 1050.into(),
-                max_priority_fee_per_gas: 50.into(),
-            }
-        );
         assert_eq!(
             bundle.ops_per_aggregator,
             vec![UserOpsPerAggregator {
@@ -1588,15 +2777,194 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_aggregators() {
-        // One op with no aggregator, two from aggregator A, and one from
-        // aggregator B.
-        let unaggregated_op = op_with_sender(address(1));
-        let aggregated_op_a1 = op_with_sender(address(2));
-        let aggregated_op_a2 = op_with_sender(address(3));
-        let aggregated_op_b = op_with_sender(address(4));
-        let aggregator_a_address = address(10);
-        let aggregator_b_address = address(11);
+    async fn test_drops_op_below_static_max_priority_fee() {
+        // The network reports a zero priority fee, but with dynamic fees disabled the
+        // static fee plus 10% required overhead should exclude op1 and accept op2.
+        let base_fee = U256::from(1000);
+        let op1 = op_with_sender_and_fees(address(1), 2054.into(), 54.into());
+        let op2 = op_with_sender_and_fees(address(2), 2055.into(), 55.into());
+        let bundle = mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            U256::zero(),
+            MockSettings {
+                static_max_priority_fee: Some(50.into()),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }],
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drops_but_not_rejects_op_with_too_low_max_fee_per_gas() {
+        let base_fee = U256::from(1000);
+        let max_priority_fee_per_gas = U256::from(50);
+        let op1 = op_with_sender_and_fees(address(1), 1054.into(), 55.into());
+        let op2 = op_with_sender_and_fees(address(2), 1055.into(), 55.into());
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+        )
+        .await;
+        assert_eq!(
+            bundle.gas_fees,
+            GasFees {
+                max_fee_per_gas: 1050.into(),
+                max_priority_fee_per_gas: 50.into(),
+            }
+        );
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }],
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drops_but_not_rejects_op_with_too_low_pvg() {
+        let base_fee = U256::from(1000);
+        let max_priority_fee_per_gas = U256::from(50);
+        let mut op1 = op_with_sender_and_fees(address(1), 1054.into(), 55.into());
+        op1.pre_verification_gas = 0.into(); // Should be dropped but not rejected
+        let op2 = op_with_sender_and_fees(address(2), 1055.into(), 55.into());
+        let bundle = mock_make_bundle(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            base_fee,
+            max_priority_fee_per_gas,
+        )
+        .await;
+        assert_eq!(
+            bundle.gas_fees,
+            GasFees {
+                max_fee_per_gas: 1050.into(),
+                max_priority_fee_per_gas: 50.into(),
+            }
+        );
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }],
+        );
+        assert!(bundle.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_projected_profit() {
+        // The op pays 2 wei per gas while the bundle pays 1 wei per gas
+        let op = UserOperation {
+            call_gas_limit: 100_000.into(),
+            ..op_with_sender_and_fees(address(1), 2.into(), 2.into())
+        };
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| {
+                    Ok(SimulationResult {
+                        pre_op_gas: 50_000.into(),
+                        ..Default::default()
+                    })
+                }),
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::one(),
+        )
+        .await;
+
+        let gas_estimate = bundle.gas_estimate.as_u64() as i64;
+        assert_eq!(
+            bundle.projected_profit,
+            I256::from(2 * 150_000 - gas_estimate)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skips_bundle_below_min_profit() {
+        let op = default_op();
+        let bundle = mock_make_bundle_with_settings(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                min_bundle_profit: Some(I256::one()),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+        assert_eq!(bundle.projected_profit, I256::zero());
+    }
+
+    #[tokio::test]
+    async fn test_aggregators() {
+        // One op with no aggregator, two from aggregator A, and one from
+        // aggregator B.
+        let unaggregated_op = op_with_sender(address(1));
+        let aggregated_op_a1 = op_with_sender(address(2));
+        let aggregated_op_a2 = op_with_sender(address(3));
+        let aggregated_op_b = op_with_sender(address(4));
+        let aggregator_a_address = address(10);
+        let aggregator_b_address = address(11);
         let op_a1_aggregated_sig = 11;
         let op_a2_aggregated_sig = 12;
         let op_b_aggregated_sig = 21;
@@ -1769,6 +3137,26 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_fails_on_out_of_bounds_failed_op_index() {
+        let op = default_op();
+        let result = try_mock_make_bundle_with_settings(
+            vec![MockOp {
+                op,
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            }],
+            vec![],
+            vec![HandleOpsOut::FailedOp(5, "AA99: bad index".to_string())],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings::default(),
+        )
+        .await;
+
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_bundle_gas_limit_simple() {
         // Limit is 10M
@@ -1851,12 +3239,14 @@ mod tests {
                     },
                 ],
                 signature: Default::default(),
+                signed_op_hashes: None,
             },
         );
         let context = ProposalContext {
             groups_by_aggregator,
             rejected_ops: vec![],
             entity_updates: BTreeMap::new(),
+            dropped_aggregators: vec![],
         };
 
         // The gas requirement from the execution of the first UO is: g >= p_1 + 2v_1 + c_1 + 5000
@@ -1868,7 +3258,10 @@ mod tests {
             + 5_000
             + 21_000;
 
-        assert_eq!(context.get_bundle_gas_limit(chain_id), expected_gas_limit);
+        assert_eq!(
+            context.get_bundle_gas_limit(chain_id, &CalldataGasConstants::default()),
+            expected_gas_limit
+        );
     }
 
     #[tokio::test]
@@ -1897,14 +3290,16 @@ mod tests {
                     },
                 ],
                 signature: Default::default(),
+                signed_op_hashes: None,
             },
         );
         let context = ProposalContext {
             groups_by_aggregator,
             rejected_ops: vec![],
             entity_updates: BTreeMap::new(),
+            dropped_aggregators: vec![],
         };
-        let gas_limit = context.get_bundle_gas_limit(chain_id);
+        let gas_limit = context.get_bundle_gas_limit(chain_id, &CalldataGasConstants::default());
 
         // The gas requirement from the execution of the first UO is: g >= p_1 + 3v_1 + c_1
         // The gas requirement from the execution of the second UO is: g >= p_1 + 3v_1 + c_1 + p_2 + 2v_2 + c_2 + 5000
@@ -2048,37 +3443,1069 @@ mod tests {
         );
     }
 
-    struct MockOp {
-        op: UserOperation,
-        simulation_result: Box<dyn Fn() -> Result<SimulationResult, SimulationError> + Send + Sync>,
+    #[tokio::test]
+    async fn test_rejects_aggregator_with_empty_signature() {
+        let bundle = make_bundle_with_aggregator_signature(Bytes::new(), 0).await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op_with_sender(address(1))],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(
+            bundle.dropped_aggregators,
+            vec![DroppedAggregator {
+                address: address(10),
+                reason: AggregatorDropReason::InvalidSignature,
+            }]
+        );
     }
 
-    struct MockAggregator {
-        address: Address,
-        signature: Box<dyn Fn() -> anyhow::Result<Option<Bytes>> + Send + Sync>,
+    #[tokio::test]
+    async fn test_rejects_aggregator_with_over_long_signature() {
+        let bundle = make_bundle_with_aggregator_signature(Bytes::from(vec![1; 65]), 64).await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op_with_sender(address(1))],
+                ..Default::default()
+            }]
+        );
     }
 
-    async fn simple_make_bundle(mock_ops: Vec<MockOp>) -> Bundle {
-        mock_make_bundle(
-            mock_ops,
-            vec![],
-            vec![HandleOpsOut::Success],
-            vec![],
-            U256::zero(),
-            U256::zero(),
+    #[tokio::test]
+    async fn test_accepts_aggregator_signature_at_max_length() {
+        let aggregator_signature = Bytes::from(vec![1; 64]);
+        let bundle = make_bundle_with_aggregator_signature(aggregator_signature.clone(), 64).await;
+
+        assert_eq!(bundle.ops_per_aggregator.len(), 2);
+        let aggregated = bundle
+            .ops_per_aggregator
+            .iter()
+            .find(|ops| ops.aggregator == address(10))
+            .unwrap();
+        assert_eq!(aggregated.signature, aggregator_signature);
+    }
+
+    #[tokio::test]
+    async fn test_reports_aggregator_that_rejected_ops() {
+        let bundle = make_bundle_with_aggregator(Box::new(|| Ok(None)), 0).await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op_with_sender(address(1))],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(
+            bundle.dropped_aggregators,
+            vec![DroppedAggregator {
+                address: address(10),
+                reason: AggregatorDropReason::Rejected,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reports_aggregator_that_failed() {
+        let bundle =
+            make_bundle_with_aggregator(Box::new(|| Err(anyhow!("aggregator is down"))), 0).await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op_with_sender(address(1))],
+                ..Default::default()
+            }]
+        );
+        assert_eq!(bundle.dropped_aggregators.len(), 1);
+        assert_eq!(bundle.dropped_aggregators[0].address, address(10));
+        assert!(matches!(
+            &bundle.dropped_aggregators[0].reason,
+            AggregatorDropReason::Error(message) if message.contains("aggregator is down")
+        ));
+    }
+
+    // Makes a bundle from one unaggregated op and one op from an aggregator
+    // that returns `aggregator_signature`
+    async fn make_bundle_with_aggregator_signature(
+        aggregator_signature: Bytes,
+        max_aggregator_signature_length: u64,
+    ) -> Bundle {
+        make_bundle_with_aggregator(
+            Box::new(move || Ok(Some(aggregator_signature.clone()))),
+            max_aggregator_signature_length,
         )
         .await
     }
 
-    async fn mock_make_bundle(
-        mock_ops: Vec<MockOp>,
-        mock_aggregators: Vec<MockAggregator>,
-        mock_handle_ops_call_results: Vec<HandleOpsOut>,
-        mock_paymaster_deposits: Vec<U256>,
-        base_fee: U256,
-        max_priority_fee_per_gas: U256,
+    // Makes a bundle from one unaggregated op and one op from the aggregator
+    // at `address(10)`, whose signature result is `signature`
+    async fn make_bundle_with_aggregator(
+        signature: Box<dyn Fn() -> anyhow::Result<Option<Bytes>> + Send + Sync>,
+        max_aggregator_signature_length: u64,
     ) -> Bundle {
-        let entry_point_address = address(123);
+        let aggregator_address = address(10);
+        mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op_with_sender(address(1)),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op_with_sender(address(2)),
+                    simulation_result: Box::new(move || {
+                        Ok(SimulationResult {
+                            aggregator: Some(AggregatorSimOut {
+                                address: aggregator_address,
+                                signature: bytes(3),
+                            }),
+                            ..Default::default()
+                        })
+                    }),
+                },
+            ],
+            vec![MockAggregator {
+                address: aggregator_address,
+                signature,
+            }],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                max_aggregator_signature_length,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_aggregation_uses_simulation_provider() {
+        let aggregator_address = address(1);
+        let op = op_with_sender(address(2));
+        let aggregator_signature = 3;
+        let op_aggregated_sig = 4;
+
+        let bundle = mock_make_bundle(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(move || {
+                    Ok(SimulationResult {
+                        aggregator: Some(AggregatorSimOut {
+                            address: aggregator_address,
+                            signature: bytes(op_aggregated_sig),
+                        }),
+                        ..Default::default()
+                    })
+                }),
+            }],
+            vec![MockAggregator {
+                address: aggregator_address,
+                signature: Box::new(move || Ok(Some(bytes(aggregator_signature)))),
+            }],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+        )
+        .await;
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                aggregator: aggregator_address,
+                signature: bytes(aggregator_signature),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aggregation_reuses_signature_for_unchanged_group() {
+        let aggregator_address = address(1);
+        let ops = [op_with_sender(address(2)), op_with_sender(address(3))];
+
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(address(123));
+        // Called once for the original group and once after an op is rejected
+        let mut simulation_provider = MockProvider::new();
+        simulation_provider
+            .expect_aggregate_signatures()
+            .times(2)
+            .returning(|_, ops| Ok(Some(bytes(ops.len() as u8))));
+        let (event_sender, _) = broadcast::channel(16);
+        let proposer = BundleProposerImpl::new(
+            0,
+            MockPoolServer::new(),
+            MockSimulator::new(),
+            entry_point,
+            Arc::new(MockProvider::new()),
+            Arc::new(simulation_provider),
+            Settings {
+                max_bundle_size: 2,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        let mut context = ProposalContext::new();
+        context.groups_by_aggregator.insert(
+            Some(aggregator_address),
+            AggregatorGroup {
+                ops_with_simulations: ops
+                    .iter()
+                    .map(|op| OpWithSimulation {
+                        op: op.clone(),
+                        simulation: SimulationResult::default(),
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        );
+
+        proposer
+            .compute_all_aggregator_signatures(&mut context)
+            .await;
+        proposer
+            .compute_all_aggregator_signatures(&mut context)
+            .await;
+        assert_eq!(
+            context.groups_by_aggregator[&Some(aggregator_address)].signature,
+            bytes(2)
+        );
+
+        proposer.reject_index(&mut context, 0).await.unwrap();
+        assert_eq!(
+            context.groups_by_aggregator[&Some(aggregator_address)].signature,
+            bytes(1)
+        );
+    }
+
+    #[test]
+    fn test_bundle_hash() {
+        let entry_point = address(1);
+        let beneficiary = address(2);
+        let op1 = op_with_sender(address(3));
+        let op2 = op_with_sender(address(4));
+        let make_bundle = |user_ops: Vec<UserOperation>| Bundle {
+            ops_per_aggregator: vec![UserOpsPerAggregator {
+                user_ops,
+                ..Default::default()
+            }],
+            gas_estimate: U256::from(100_000),
+            ..Default::default()
+        };
+        let hash = make_bundle(vec![op1.clone()]).hash(entry_point, 1, beneficiary);
+
+        // Rejected ops and entity updates do not change the bundle that is sent
+        let with_rejections = Bundle {
+            rejected_ops: vec![op2.clone()],
+            entity_updates: vec![EntityUpdate {
+                entity: Entity::paymaster(address(5)),
+                update_type: EntityUpdateType::UnstakedInvalidation,
+            }],
+            ..make_bundle(vec![op1.clone()])
+        };
+        assert_eq!(
+            hash,
+            make_bundle(vec![op1.clone()]).hash(entry_point, 1, beneficiary)
+        );
+        assert_eq!(hash, with_rejections.hash(entry_point, 1, beneficiary));
+
+        assert_ne!(
+            hash,
+            make_bundle(vec![op1.clone(), op2]).hash(entry_point, 1, beneficiary)
+        );
+        assert_ne!(
+            hash,
+            make_bundle(vec![op1]).hash(entry_point, 1, address(6))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_ops_gas_limit_increase_percent() {
+        let op = op_with_gas(100_000.into(), 100_000.into(), 1_000_000.into(), false);
+        let make_bundle = |increase_percent| {
+            let op = op.clone();
+            mock_make_bundle_with_settings(
+                vec![MockOp {
+                    op,
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                }],
+                vec![],
+                vec![HandleOpsOut::Success],
+                vec![],
+                U256::zero(),
+                U256::zero(),
+                MockSettings {
+                    handle_ops_gas_limit_increase_percent: increase_percent,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let estimate = make_bundle(0).await.gas_estimate;
+        assert_eq!(make_bundle(50).await.gas_estimate, estimate * 3 / 2);
+        // The increased estimate is capped at the block gas limit
+        assert_eq!(
+            make_bundle(10_000).await.gas_estimate,
+            BLOCK_GAS_LIMIT.into()
+        );
+    }
+
+    #[derive(Debug)]
+    struct FixedGasOracle {
+        fees: GasFees,
+        pre_verification_gas_overhead: U256,
+    }
+
+    #[async_trait]
+    impl GasOracle for FixedGasOracle {
+        async fn bundle_fees(&self, _: GasOracleContext) -> anyhow::Result<(GasFees, U256)> {
+            Ok((self.fees, self.pre_verification_gas_overhead))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uses_custom_gas_oracle() {
+        let fees = GasFees {
+            max_fee_per_gas: 300.into(),
+            max_priority_fee_per_gas: 100.into(),
+        };
+        let op1 = op_with_sender_and_fees(address(1), 1000.into(), 1000.into());
+        // Only this op pays for the oracle's pre-verification gas overhead
+        let op2 = UserOperation {
+            pre_verification_gas: U256::from(DEFAULT_PVG + 1_000_000),
+            ..op_with_sender_and_fees(address(2), 1000.into(), 1000.into())
+        };
+
+        let bundle = mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                gas_oracle: Some(Arc::new(FixedGasOracle {
+                    fees,
+                    pre_verification_gas_overhead: 1_000_000.into(),
+                })),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(bundle.gas_fees, fees);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }]
+        );
+    }
+
+    // Scores ops with lower max fees higher, inverting the pool's order
+    #[derive(Debug)]
+    struct LowestFeeFirstScorer;
+
+    impl BundleScorer for LowestFeeFirstScorer {
+        fn score(&self, op: &PoolOperation) -> U256 {
+            U256::MAX - op.uo.max_fee_per_gas
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orders_ops_with_custom_scorer() {
+        let op1 = op_with_sender_and_fees(address(1), 2000.into(), 2000.into());
+        let op2 = op_with_sender_and_fees(address(2), 1000.into(), 1000.into());
+        let make_bundle = |bundle_scorer: Option<Arc<dyn BundleScorer>>| {
+            mock_make_bundle_with_settings(
+                vec![
+                    MockOp {
+                        op: op1.clone(),
+                        simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    },
+                    MockOp {
+                        op: op2.clone(),
+                        simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    },
+                ],
+                vec![],
+                vec![HandleOpsOut::Success],
+                vec![],
+                U256::zero(),
+                U256::zero(),
+                MockSettings {
+                    bundle_scorer,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let bundle = make_bundle(None).await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1.clone(), op2.clone()],
+                ..Default::default()
+            }]
+        );
+
+        let bundle = make_bundle(Some(Arc::new(LowestFeeFirstScorer))).await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2.clone(), op1.clone()],
+                ..Default::default()
+            }]
+        );
+    }
+
+    /// Scores the ops of one sender above all others
+    #[derive(Debug)]
+    struct PreferredSenderScorer(Address);
+
+    impl BundleScorer for PreferredSenderScorer {
+        fn score(&self, op: &PoolOperation) -> U256 {
+            U256::from(u8::from(op.uo.sender == self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stake_only_breaks_score_ties() {
+        let staked_paymaster = address(11);
+        let op1 = op_with_sender_paymaster(address(1), address(10));
+        let op2 = op_with_sender_paymaster(address(2), staked_paymaster);
+        let deposit = parse_units("1", "ether").unwrap().into();
+        let make_bundle = |bundle_scorer: Arc<dyn BundleScorer>| {
+            mock_make_bundle_with_settings(
+                vec![
+                    MockOp {
+                        op: op1.clone(),
+                        simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    },
+                    MockOp {
+                        op: op2.clone(),
+                        simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                    },
+                ],
+                vec![],
+                vec![HandleOpsOut::Success],
+                vec![deposit, deposit],
+                U256::zero(),
+                U256::zero(),
+                MockSettings {
+                    staked_entities: vec![staked_paymaster],
+                    bundle_scorer: Some(bundle_scorer),
+                    ..Default::default()
+                },
+            )
+        };
+
+        // Both ops have equal fees, and the scorer prefers the unstaked op
+        let bundle = make_bundle(Arc::new(PreferredSenderScorer(address(1)))).await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op1.clone(), op2.clone()],
+                ..Default::default()
+            }]
+        );
+
+        // With equal scores, the staked op comes first
+        let bundle = make_bundle(Arc::new(PreferredSenderScorer(address(3)))).await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2.clone(), op1.clone()],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_orders_op_with_bundler_tip_first() {
+        let op1 = op_with_sender_and_fees(address(1), 2000.into(), 2000.into());
+        let op2 = op_with_sender_and_fees(address(2), 1000.into(), 1000.into());
+        let bundle = mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                bundler_tips: vec![(address(2), parse_units("1", "ether").unwrap().into())],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // The tip outweighs the difference in gas fees
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2, op1],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulates_against_pending_block() {
+        let op = default_op();
+        // The mock simulator and entry point only accept the pending block id
+        // when the flag is set, so the whole build must run against it
+        let bundle = mock_make_bundle_with_settings(
+            vec![MockOp {
+                op: op.clone(),
+                simulation_result: Box::new(|| Ok(SimulationResult::default())),
+            }],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                simulate_against_pending: true,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skips_bundle_on_node_gas_estimate_below_minimum() {
+        let op = default_op();
+        let make_bundle = |node_gas_estimate: u64| {
+            mock_make_bundle_with_settings(
+                vec![MockOp {
+                    op: op.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                }],
+                vec![],
+                vec![HandleOpsOut::Success],
+                vec![],
+                U256::zero(),
+                U256::zero(),
+                MockSettings {
+                    min_gas_estimate_per_op: 50_000,
+                    node_gas_estimate: Some(node_gas_estimate.into()),
+                    ..Default::default()
+                },
+            )
+        };
+
+        // The ops are left in the pool rather than rejected or failing the build
+        let bundle = make_bundle(1_000).await;
+        assert!(bundle.ops_per_aggregator.is_empty());
+        assert!(bundle.rejected_ops.is_empty());
+
+        let bundle = make_bundle(100_000).await;
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drops_op_with_codeless_paymaster() {
+        let op1 = op_with_sender_paymaster(address(1), address(10));
+        let op2 = op_with_sender_paymaster(address(2), address(11));
+        let deposit = parse_units("1", "ether").unwrap().into();
+
+        // No handleOps call is expected to fail, as the codeless paymaster's op is
+        // dropped before gas estimation
+        let bundle = mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![deposit, deposit],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                codeless_addresses: vec![address(10)],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(bundle.rejected_ops, vec![op1]);
+        assert!(bundle.entity_updates.is_empty());
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_drops_op_with_undeployable_init_code() {
+        // Sender already deployed, but the op still carries init code
+        let op1 = op_with_sender_factory(address(1), address(10));
+        let op2 = op_with_sender_factory(address(2), address(11));
+        // Factory has no code
+        let op3 = op_with_sender_factory(address(3), address(12));
+
+        let bundle = mock_make_bundle_with_settings(
+            vec![
+                MockOp {
+                    op: op1.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op2.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+                MockOp {
+                    op: op3.clone(),
+                    simulation_result: Box::new(|| Ok(SimulationResult::default())),
+                },
+            ],
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+            MockSettings {
+                check_init_code: true,
+                codeless_addresses: vec![address(2), address(3), address(12)],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(bundle.rejected_ops, vec![op1, op3]);
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op2],
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetches_unknown_paymaster_deposit_on_demand() {
+        let paymaster = address(1);
+        let block_hash = hash(125);
+        let op = op_with_sender_paymaster(address(2), paymaster);
+
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(address(123));
+        entry_point
+            .expect_balance_of()
+            .with(eq(paymaster), eq(Some(BlockId::Hash(block_hash))))
+            .times(1)
+            .returning(|_, _| Ok(parse_units("1", "ether").unwrap().into()));
+        let (event_sender, _) = broadcast::channel(16);
+        let proposer = BundleProposerImpl::new(
+            0,
+            MockPoolServer::new(),
+            MockSimulator::new(),
+            entry_point,
+            Arc::new(MockProvider::new()),
+            Arc::new(MockProvider::new()),
+            test_settings(),
+            event_sender,
+        );
+
+        // The paymaster's deposit was not preloaded
+        let context = proposer
+            .assemble_context(
+                vec![(
+                    PoolOperation {
+                        uo: op.clone(),
+                        ..Default::default()
+                    },
+                    Ok(SimulationResult::default()),
+                )],
+                HashMap::new(),
+                block_hash,
+            )
+            .await
+            .unwrap();
+        assert_eq!(context.iter_ops().cloned().collect::<Vec<_>>(), vec![op]);
+        assert!(context.rejected_ops.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skipped_op_event_uses_pool_op_hash() {
+        let entry_point_address = address(123);
+        let op = op_with_sender_and_fees(address(1), U256::zero(), U256::zero());
+
+        let mut entry_point = MockEntryPoint::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let proposer = BundleProposerImpl::new(
+            0,
+            MockPoolServer::new(),
+            MockSimulator::new(),
+            entry_point,
+            Arc::new(MockProvider::new()),
+            Arc::new(MockProvider::new()),
+            test_settings(),
+            event_sender,
+        );
+
+        // The op is skipped for insufficient fees before it is simulated
+        let result = proposer
+            .filter_and_simulate(
+                PoolOperation {
+                    uo: op.clone(),
+                    entry_point: entry_point_address,
+                    ..Default::default()
+                },
+                hash(125),
+                U256::zero(),
+                GasFees {
+                    max_fee_per_gas: 1.into(),
+                    max_priority_fee_per_gas: 1.into(),
+                },
+                U256::zero(),
+            )
+            .await;
+        assert!(result.is_none());
+
+        // The hash matches the key the simulator uses for the op
+        let event = event_receiver.try_recv().unwrap();
+        assert!(matches!(
+            event.event.kind,
+            BuilderEventKind::SkippedOp { op_hash, .. } if op_hash == op.op_hash(entry_point_address, 0)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_block_gas_limit_cached_per_block() {
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(address(123));
+        // Queried once per distinct block
+        let mut simulation_provider = MockProvider::new();
+        simulation_provider
+            .expect_get_block_gas_limit()
+            .with(eq(hash(1)))
+            .times(1)
+            .returning(|_| Ok(1_000_000.into()));
+        simulation_provider
+            .expect_get_block_gas_limit()
+            .with(eq(hash(2)))
+            .times(1)
+            .returning(|_| Ok(2_000_000.into()));
+        let (event_sender, _) = broadcast::channel(16);
+        let proposer = BundleProposerImpl::new(
+            0,
+            MockPoolServer::new(),
+            MockSimulator::new(),
+            entry_point,
+            Arc::new(MockProvider::new()),
+            Arc::new(simulation_provider),
+            Settings {
+                handle_ops_gas_limit_increase_percent: 10_000,
+                ..test_settings()
+            },
+            event_sender,
+        );
+
+        for _ in 0..2 {
+            let gas = proposer
+                .apply_gas_limit_increase(100_000.into(), hash(1))
+                .await
+                .unwrap();
+            assert_eq!(gas, U256::from(1_000_000));
+        }
+        let gas = proposer
+            .apply_gas_limit_increase(100_000.into(), hash(2))
+            .await
+            .unwrap();
+        assert_eq!(gas, U256::from(2_000_000));
+    }
+
+    #[tokio::test]
+    async fn test_restarts_build_on_head_change() {
+        let entry_point_address = address(123);
+        let old_block_hash = hash(125);
+        let new_block_hash = hash(126);
+        let op = default_op();
+
+        let po = PoolOperation {
+            uo: op.clone(),
+            ..Default::default()
+        };
+        let mut pool_client = MockPoolServer::new();
+        pool_client
+            .expect_get_ops()
+            .times(2)
+            .returning(move |_, _, _| Ok(vec![po.clone()]));
+        pool_client
+            .expect_get_reputation_status()
+            .returning(|_, _| Ok(ReputationStatus::Ok));
+
+        // The head changes after the first simulation, so the op is simulated
+        // once against each head
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .withf(move |_, &block_id, _| block_id == Some(BlockId::Hash(old_block_hash)))
+            .times(1)
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+        simulator
+            .expect_simulate_validation()
+            .withf(move |_, &block_id, _| block_id == Some(BlockId::Hash(new_block_hash)))
+            .times(1)
+            .returning(|_, _, _| Ok(SimulationResult::default()));
+
+        // Gas is only estimated once the build is consistent with the head
+        let mut entry_point = MockEntryPoint::new();
+        entry_point
+            .expect_address()
+            .return_const(entry_point_address);
+        entry_point
+            .expect_call_handle_ops()
+            .times(1)
+            .returning(|_, _, _, _| Ok(HandleOpsOut::Success));
+
+        let mut simulation_provider = MockProvider::new();
+        let mut head_calls = 0;
+        simulation_provider
+            .expect_get_latest_block_hash_and_number()
+            .times(3)
+            .returning(move || {
+                head_calls += 1;
+                let block_hash = if head_calls == 1 {
+                    old_block_hash
+                } else {
+                    new_block_hash
+                };
+                Ok((block_hash, U64::zero()))
+            });
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::zero()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::zero()));
+
+        let (event_sender, _) = broadcast::channel(16);
+        let proposer = BundleProposerImpl::new(
+            0,
+            pool_client,
+            simulator,
+            entry_point,
+            Arc::new(provider),
+            Arc::new(simulation_provider),
+            test_settings(),
+            event_sender,
+        );
+        let bundle = proposer.make_bundle(None).await.unwrap();
+
+        assert_eq!(
+            bundle.ops_per_aggregator,
+            vec![UserOpsPerAggregator {
+                user_ops: vec![op],
+                ..Default::default()
+            }]
+        );
+    }
+
+    const BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+    struct MockOp {
+        op: UserOperation,
+        simulation_result: Box<dyn Fn() -> Result<SimulationResult, SimulationError> + Send + Sync>,
+    }
+
+    struct MockAggregator {
+        address: Address,
+        signature: Box<dyn Fn() -> anyhow::Result<Option<Bytes>> + Send + Sync>,
+    }
+
+    async fn simple_make_bundle(mock_ops: Vec<MockOp>) -> Bundle {
+        mock_make_bundle(
+            mock_ops,
+            vec![],
+            vec![HandleOpsOut::Success],
+            vec![],
+            U256::zero(),
+            U256::zero(),
+        )
+        .await
+    }
+
+    async fn mock_make_bundle(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: U256,
+        max_priority_fee_per_gas: U256,
+    ) -> Bundle {
+        mock_make_bundle_with_settings(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            MockSettings::default(),
+        )
+        .await
+    }
+
+    struct MockSettings {
+        handle_ops_gas_limit_increase_percent: u64,
+        // Disables the dynamic max priority fee when set
+        static_max_priority_fee: Option<U256>,
+        // Entities reported as throttled by the pool
+        throttled_entities: Vec<Address>,
+        // Paymasters and factories reported as staked in the pool's entity infos
+        staked_entities: Vec<Address>,
+        min_bundle_profit: Option<I256>,
+        accessed_other_sender_penalty_threshold: u64,
+        max_unstaked_entity_bundle_ops: u64,
+        max_aggregator_signature_length: u64,
+        simulate_against_pending: bool,
+        min_gas_estimate_per_op: u64,
+        // Bundle gas estimate returned by the node, the gas cap if not set
+        node_gas_estimate: Option<U256>,
+        check_init_code: bool,
+        max_paymaster_bundle_percent: u64,
+        // Paymasters, factories and senders whose code is reported as empty
+        codeless_addresses: Vec<Address>,
+        // Replaces the default gas oracle when set
+        gas_oracle: Option<Arc<dyn GasOracle>>,
+        bundle_scorer: Option<Arc<dyn BundleScorer>>,
+        // Bundler tips of the pool's ops, by sender
+        bundler_tips: Vec<(Address, U256)>,
+    }
+
+    impl Default for MockSettings {
+        fn default() -> Self {
+            Self {
+                handle_ops_gas_limit_increase_percent: 0,
+                static_max_priority_fee: None,
+                throttled_entities: vec![],
+                staked_entities: vec![],
+                min_bundle_profit: None,
+                accessed_other_sender_penalty_threshold: 0,
+                max_unstaked_entity_bundle_ops: 0,
+                max_aggregator_signature_length: 0,
+                simulate_against_pending: false,
+                min_gas_estimate_per_op: 0,
+                node_gas_estimate: None,
+                check_init_code: false,
+                max_paymaster_bundle_percent: 0,
+                codeless_addresses: vec![],
+                gas_oracle: None,
+                bundle_scorer: None,
+                bundler_tips: vec![],
+            }
+        }
+    }
+
+    async fn mock_make_bundle_with_settings(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: U256,
+        max_priority_fee_per_gas: U256,
+        mock_settings: MockSettings,
+    ) -> Bundle {
+        try_mock_make_bundle_with_settings(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            mock_settings,
+        )
+        .await
+        .expect("should make a bundle")
+    }
+
+    async fn try_mock_make_bundle_with_settings(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: U256,
+        max_priority_fee_per_gas: U256,
+        mock_settings: MockSettings,
+    ) -> anyhow::Result<Bundle> {
+        mock_proposer(
+            mock_ops,
+            mock_aggregators,
+            mock_handle_ops_call_results,
+            mock_paymaster_deposits,
+            base_fee,
+            max_priority_fee_per_gas,
+            mock_settings,
+        )
+        .make_bundle(None)
+        .await
+    }
+
+    fn mock_proposer(
+        mock_ops: Vec<MockOp>,
+        mock_aggregators: Vec<MockAggregator>,
+        mock_handle_ops_call_results: Vec<HandleOpsOut>,
+        mock_paymaster_deposits: Vec<U256>,
+        base_fee: U256,
+        max_priority_fee_per_gas: U256,
+        mock_settings: MockSettings,
+    ) -> BundleProposerImpl<MockSimulator, MockEntryPoint, MockProvider, MockPoolServer> {
+        let entry_point_address = address(123);
         let beneficiary = address(124);
         let current_block_hash = hash(125);
         let expected_code_hash = hash(126);
@@ -2087,7 +4514,24 @@ mod tests {
             .iter()
             .map(|MockOp { op, .. }| PoolOperation {
                 uo: op.clone(),
+                entry_point: entry_point_address,
                 expected_code_hash,
+                entity_infos: EntityInfos {
+                    paymaster: op.paymaster().map(|address| EntityInfo {
+                        address,
+                        is_staked: mock_settings.staked_entities.contains(&address),
+                    }),
+                    factory: op.factory().map(|address| EntityInfo {
+                        address,
+                        is_staked: mock_settings.staked_entities.contains(&address),
+                    }),
+                    ..Default::default()
+                },
+                bundler_tip: mock_settings
+                    .bundler_tips
+                    .iter()
+                    .find(|(sender, _)| *sender == op.sender)
+                    .map_or(U256::zero(), |(_, tip)| *tip),
                 ..Default::default()
             })
             .collect();
@@ -2096,16 +4540,36 @@ mod tests {
         pool_client
             .expect_get_ops()
             .returning(move |_, _, _| Ok(ops.clone()));
+        let throttled_entities = mock_settings.throttled_entities;
+        pool_client
+            .expect_get_reputation_status()
+            .returning(move |_, address| {
+                if throttled_entities.contains(&address) {
+                    Ok(ReputationStatus::Throttled)
+                } else {
+                    Ok(ReputationStatus::Ok)
+                }
+            });
 
         let simulations_by_op: HashMap<_, _> = mock_ops
             .into_iter()
             .map(|op| (op.op.op_hash(entry_point_address, 0), op.simulation_result))
             .collect();
+        let simulation_block = if mock_settings.simulate_against_pending {
+            BlockId::Number(BlockNumber::Pending)
+        } else {
+            BlockId::Hash(current_block_hash)
+        };
+        // handleOps runs against the pending block too, as ops that are only
+        // valid after pending transactions would fail at the latest block
+        let handle_ops_block = mock_settings
+            .simulate_against_pending
+            .then_some(BlockId::Number(BlockNumber::Pending));
         let mut simulator = MockSimulator::new();
         simulator
             .expect_simulate_validation()
-            .withf(move |_, &block_hash, &code_hash| {
-                block_hash == Some(current_block_hash) && code_hash == Some(expected_code_hash)
+            .withf(move |_, &block_id, &code_hash| {
+                block_id == Some(simulation_block) && code_hash == Some(expected_code_hash)
             })
             .returning(move |op, _, _| simulations_by_op[&op.op_hash(entry_point_address, 0)]());
         let mut entry_point = MockEntryPoint::new();
@@ -2116,9 +4580,14 @@ mod tests {
             entry_point
                 .expect_call_handle_ops()
                 .times(..=1)
-                .withf(move |_, &b, _| b == beneficiary)
-                .return_once(|_, _, _| Ok(call_res));
+                .withf(move |_, &b, _, &block_id| b == beneficiary && block_id == handle_ops_block)
+                .return_once(|_, _, _, _| Ok(call_res));
         }
+        let node_gas_estimate = mock_settings.node_gas_estimate;
+        entry_point
+            .expect_estimate_handle_ops_gas()
+            .withf(move |_, &b, _, &block_id| b == beneficiary && block_id == handle_ops_block)
+            .returning(move |_, _, gas, _| Ok(node_gas_estimate.unwrap_or(gas)));
         for deposit in mock_paymaster_deposits {
             entry_point
                 .expect_balance_of()
@@ -2130,19 +4599,37 @@ mod tests {
             .into_iter()
             .map(|agg| (agg.address, agg.signature))
             .collect();
+        // The primary provider is only used for fees. Simulation calls and the
+        // block they run against must come from the simulation provider, the
+        // primary provider panics on any unexpected call.
         let mut provider = MockProvider::new();
-        provider
-            .expect_get_latest_block_hash_and_number()
-            .returning(move || Ok((current_block_hash, U64::zero())));
         provider
             .expect_get_base_fee()
             .returning(move || Ok(base_fee));
         provider
             .expect_get_max_priority_fee()
             .returning(move || Ok(max_priority_fee_per_gas));
-        provider
+        let mut simulation_provider = MockProvider::new();
+        simulation_provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(move || Ok((current_block_hash, U64::zero())));
+        simulation_provider
+            .expect_get_block_gas_limit()
+            .returning(|_| Ok(BLOCK_GAS_LIMIT.into()));
+        simulation_provider
             .expect_aggregate_signatures()
             .returning(move |address, _| Ok(signatures_by_aggregator[&address]()?));
+        let codeless_addresses = mock_settings.codeless_addresses;
+        simulation_provider
+            .expect_get_code()
+            .withf(move |_, &block_hash| block_hash == Some(current_block_hash))
+            .returning(move |address, _| {
+                if codeless_addresses.contains(&address) {
+                    Ok(Bytes::default())
+                } else {
+                    Ok(bytes(1))
+                }
+            });
         let (event_sender, _) = broadcast::channel(16);
         let proposer = BundleProposerImpl::new(
             0,
@@ -2150,20 +4637,60 @@ mod tests {
             simulator,
             entry_point,
             Arc::new(provider),
+            Arc::new(simulation_provider),
             Settings {
-                chain_id: 0,
                 max_bundle_size,
-                max_bundle_gas: 10_000_000,
+                handle_ops_gas_limit_increase_percent: mock_settings
+                    .handle_ops_gas_limit_increase_percent,
                 beneficiary,
-                priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
-                bundle_priority_fee_overhead_percent: 0,
+                use_dynamic_max_priority_fee: mock_settings.static_max_priority_fee.is_none(),
+                static_max_priority_fee: mock_settings.static_max_priority_fee.unwrap_or_default(),
+                min_bundle_profit: mock_settings.min_bundle_profit,
+                accessed_other_sender_penalty_threshold: mock_settings
+                    .accessed_other_sender_penalty_threshold,
+                max_unstaked_entity_bundle_ops: mock_settings.max_unstaked_entity_bundle_ops,
+                max_aggregator_signature_length: mock_settings.max_aggregator_signature_length,
+                simulate_against_pending: mock_settings.simulate_against_pending,
+                min_gas_estimate_per_op: mock_settings.min_gas_estimate_per_op,
+                check_init_code: mock_settings.check_init_code,
+                max_paymaster_bundle_percent: mock_settings.max_paymaster_bundle_percent,
+                ..test_settings()
             },
             event_sender,
         );
-        proposer
-            .make_bundle(None)
-            .await
-            .expect("should make a bundle")
+        let proposer = match mock_settings.gas_oracle {
+            Some(gas_oracle) => proposer.with_gas_oracle(gas_oracle),
+            None => proposer,
+        };
+        match mock_settings.bundle_scorer {
+            Some(bundle_scorer) => proposer.with_bundle_scorer(bundle_scorer),
+            None => proposer,
+        }
+    }
+
+    /// Settings for a proposer that bundles a single op, with every optional
+    /// check and limit disabled
+    fn test_settings() -> Settings {
+        Settings {
+            chain_id: 0,
+            max_bundle_size: 1,
+            max_bundle_gas: 10_000_000,
+            handle_ops_gas_limit_increase_percent: 0,
+            beneficiary: address(124),
+            priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
+            bundle_priority_fee_overhead_percent: 0,
+            use_dynamic_max_priority_fee: true,
+            static_max_priority_fee: U256::zero(),
+            min_bundle_profit: None,
+            accessed_other_sender_penalty_threshold: 0,
+            max_unstaked_entity_bundle_ops: 0,
+            max_aggregator_signature_length: 0,
+            simulate_against_pending: false,
+            min_gas_estimate_per_op: 0,
+            check_init_code: false,
+            max_paymaster_bundle_percent: 0,
+            calldata_gas: CalldataGasConstants::default(),
+        }
     }
 
     fn address(n: u8) -> Address {
@@ -0,0 +1,59 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use ethers::types::U256;
+use rundler_pool::PoolOperation;
+use rundler_sim::gas::{self, CalldataGasConstants};
+
+/// Custom ordering of the candidate ops for a bundle, e.g. to weight ops by
+/// paymaster or op size on top of their fees.
+///
+/// Without a scorer, ops are considered in the pool's order, which is by
+/// descending max fee. Provide an implementation with
+/// `BuilderTask::with_bundle_scorer`.
+pub trait BundleScorer: Send + Sync + Debug + 'static {
+    /// Returns the sort key of an op. Ops with higher scores are considered
+    /// first. Among ops with equal scores, ops whose paymaster or factory is
+    /// staked come first, and the rest keep the pool's order.
+    fn score(&self, op: &PoolOperation) -> U256;
+}
+
+/// Scorer used when ops carry bundler tips and no custom scorer is set. Adds
+/// each op's bundler tip per unit of its gas limit to its max fee per gas, so
+/// that ops without tips keep the pool's order.
+#[derive(Debug)]
+pub(crate) struct BundlerTipScorer {
+    chain_id: u64,
+    calldata_gas: CalldataGasConstants,
+}
+
+impl BundlerTipScorer {
+    pub(crate) fn new(chain_id: u64, calldata_gas: CalldataGasConstants) -> Self {
+        Self {
+            chain_id,
+            calldata_gas,
+        }
+    }
+}
+
+impl BundleScorer for BundlerTipScorer {
+    fn score(&self, op: &PoolOperation) -> U256 {
+        let gas_limit =
+            gas::user_operation_gas_limit(&op.uo, self.chain_id, &self.calldata_gas, false, false);
+        op.uo
+            .max_fee_per_gas
+            .saturating_add(op.bundler_tip / gas_limit.max(U256::one()))
+    }
+}
@@ -11,23 +11,29 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
-use anyhow::{bail, Context};
+use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
 use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
 use futures_util::StreamExt;
 use rundler_pool::PoolServer;
-use rundler_provider::EntryPoint;
+use rundler_provider::{EntryPoint, HandleOpsOut};
 use rundler_sim::ExpectedStorage;
-use rundler_types::{EntityUpdate, GasFees, UserOperation};
+use rundler_types::{
+    EntityUpdate, GasFees, Timestamp, TransactionType, UserOperation, UserOpsPerAggregator,
+};
 use rundler_utils::emit::WithEntryPoint;
 use tokio::{
     join,
     sync::{broadcast, mpsc, oneshot},
+    time,
 };
 use tracing::{error, info, trace, warn};
 use rundler_types::hybrid_compute;
@@ -35,7 +41,7 @@ use rundler_types::hybrid_compute;
 use crate::{
     bundle_proposer::BundleProposer,
     emit::{BuilderEvent, BundleTxDetails},
-    transaction_tracker::{SendResult, TrackerUpdate, TransactionTracker},
+    transaction_tracker::{SendResult, TrackerError, TrackerUpdate, TransactionTracker},
 };
 
 #[async_trait]
@@ -47,6 +53,17 @@ pub(crate) trait BundleSender: Send + Sync + 'static {
 pub(crate) struct Settings {
     pub(crate) replacement_fee_percent_increase: u64,
     pub(crate) max_fee_increases: u64,
+    pub(crate) transaction_type: TransactionType,
+    /// Number of consecutive send failures that pauses bundling, 0 to never pause
+    pub(crate) circuit_breaker_failure_threshold: u64,
+    pub(crate) circuit_breaker_cooldown: Duration,
+    /// Maximum time to build a bundle, after which the attempt is abandoned
+    pub(crate) make_bundle_timeout: Duration,
+    /// Minimum number of ops to send a bundle. Smaller bundles are held until
+    /// more ops arrive, `max_bundle_delay` passes, or an op is about to expire.
+    pub(crate) min_bundle_ops: usize,
+    /// Maximum time to hold a bundle with fewer than `min_bundle_ops` ops
+    pub(crate) max_bundle_delay: Duration,
 }
 
 #[derive(Debug)]
@@ -68,6 +85,8 @@ where
     pool: C,
     settings: Settings,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    // When the builder started holding bundles below the minimum op count
+    held_since: Mutex<Option<Instant>>,
 }
 
 #[derive(Debug)]
@@ -75,6 +94,10 @@ struct BundleTx {
     tx: TypedTransaction,
     expected_storage: ExpectedStorage,
     op_hashes: Vec<H256>,
+    bundle_hash: H256,
+    ops_per_aggregator: Vec<UserOpsPerAggregator>,
+    gas_estimate: U256,
+    earliest_valid_until: Option<Timestamp>,
 }
 
 pub struct SendBundleRequest {
@@ -89,6 +112,9 @@ pub enum SendBundleResult {
         tx_hash: H256,
     },
     NoOperationsInitially,
+    HeldForMoreOps {
+        op_count: usize,
+    },
     NoOperationsAfterFeeIncreases {
         initial_op_count: usize,
         attempt_number: u64,
@@ -135,6 +161,11 @@ where
             }
         });
 
+        let mut circuit_breaker = CircuitBreaker::new(
+            self.settings.circuit_breaker_failure_threshold,
+            self.settings.circuit_breaker_cooldown,
+        );
+
         println!("HC starting send_bundles loop");
         loop {
             let mut send_bundle_response: Option<oneshot::Sender<SendBundleResult>> = None;
@@ -178,7 +209,22 @@ where
             // Wait for new block. Block number doesn't matter as the pool will only notify of new blocks
             // after the pool has updated its state. The bundle will be formed using the latest pool state
             // and can land in the next block
-            self.check_for_and_log_transaction_update().await;
+            if self.check_for_and_log_transaction_update().await {
+                // A bundle transaction mined, so sending is healthy again
+                circuit_breaker.record_success();
+            }
+            if !circuit_breaker.allows_send(Instant::now()) {
+                trace!("Bundling paused after repeated send failures");
+                if let Some(t) = send_bundle_response.take() {
+                    let result = SendBundleResult::Error(anyhow!(
+                        "bundling paused after repeated send failures"
+                    ));
+                    if t.send(result).is_err() {
+                        error!("Failed to send bundle result to manual caller");
+                    }
+                }
+                continue;
+            }
             let result = self.send_bundle_with_increasing_gas_fees().await;
 
             match &result {
@@ -195,6 +241,10 @@ where
                     }
 		}
                 SendBundleResult::NoOperationsInitially => trace!("No ops to send at block {}", last_block.unwrap_or_default().block_number),
+                SendBundleResult::HeldForMoreOps { op_count } => trace!(
+                    "Holding bundle with {op_count} op(s) at block {} for more ops",
+                    last_block.unwrap_or_default().block_number
+                ),
                 SendBundleResult::NoOperationsAfterFeeIncreases {
                     initial_op_count,
                     attempt_number,
@@ -206,12 +256,36 @@ where
 	            println!("HC send_bundle_with_increasing_gas_fees result {:?}", result);
 		    warn!("Bundle failed to mine after {} fee increases", self.settings.max_fee_increases);
 		}
+                SendBundleResult::Error(error) if is_tracker_busy(error) => {
+                    warn!("Transaction tracker busy, backing off until the next block");
+                }
                 SendBundleResult::Error(error) => {
                     println!("HC send_bundle_with_increasing_gas_fees result {:?}", result);
                     BuilderMetrics::increment_bundle_txns_failed(self.builder_index);
                     error!("Failed to send bundle. Will retry next block: {error:#?}");
                 }
             }
+            match &result {
+                // The tracker being busy is not a send failure
+                SendBundleResult::Error(error) if is_tracker_busy(error) => {}
+                // A bundle that never mined, even at the highest fees, is a send failure
+                SendBundleResult::Error(_) | SendBundleResult::StalledAtMaxFeeIncreases => {
+                    if circuit_breaker.record_failure(Instant::now()) {
+                        BuilderMetrics::increment_circuit_breaker_opened(self.builder_index);
+                        warn!(
+                            "Pausing bundling for {:?} after {} consecutive send failures",
+                            self.settings.circuit_breaker_cooldown,
+                            self.settings.circuit_breaker_failure_threshold
+                        );
+                    }
+                }
+                SendBundleResult::Success { .. } => circuit_breaker.record_success(),
+                // Nothing was sent, or the bundle emptied out before it mined, so
+                // there is no send outcome to record
+                SendBundleResult::NoOperationsInitially
+                | SendBundleResult::HeldForMoreOps { .. }
+                | SendBundleResult::NoOperationsAfterFeeIncreases { .. } => {}
+            }
 
             if let Some(t) = send_bundle_response.take() {
                 if t.send(result).is_err() {
@@ -255,21 +329,29 @@ where
             pool,
             settings,
             event_sender,
+            held_since: Mutex::new(None),
         }
     }
 
-    async fn check_for_and_log_transaction_update(&self) {
+    /// Checks for and logs an update to the tracked transaction. Returns true if
+    /// a bundle transaction was mined.
+    async fn check_for_and_log_transaction_update(&self) -> bool {
         let update = self.transaction_tracker.check_for_update_now().await;
         let update = match update {
             Ok(update) => update,
+            Err(TrackerError::Busy) => {
+                trace!("Transaction tracker busy, skipping transaction update check");
+                return false;
+            }
             Err(error) => {
                 error!("Failed to check for transaction updates: {error:#?}");
-                return;
+                return false;
             }
         };
         let Some(update) = update else {
-            return;
+            return false;
         };
+        let mined = matches!(update, TrackerUpdate::Mined { .. });
         match update {
             TrackerUpdate::Mined {
                 tx_hash,
@@ -309,6 +391,7 @@ where
                 info!("Replacement transaction underpriced")
             }
         };
+        mined
     }
 
     /// Constructs a bundle and sends it to the entry point as a transaction. If
@@ -333,11 +416,21 @@ where
 
     /// Helper function returning `Result` to be able to use `?`.
     async fn send_bundle_with_increasing_gas_fees_inner(&self) -> anyhow::Result<SendBundleResult> {
-        let (nonce, mut required_fees) = self.transaction_tracker.get_nonce_and_required_fees()?;
+        let (mut nonce, mut required_fees) =
+            self.transaction_tracker.get_nonce_and_required_fees()?;
         let mut initial_op_count: Option<usize> = None;
+        // A previously sent bundle to resend at a new nonce instead of building one
+        let mut resend_bundle_tx: Option<BundleTx> = None;
 
         for fee_increase_count in 0..=self.settings.max_fee_increases {
-            let Some(bundle_tx) = self.get_bundle_tx(nonce, required_fees).await? else {
+            let bundle_tx = match resend_bundle_tx.take() {
+                Some(bundle_tx) => Some(bundle_tx),
+                None => self.get_bundle_tx(nonce, required_fees).await?,
+            };
+            let Some(bundle_tx) = bundle_tx else {
+                if initial_op_count.is_none() {
+                    *self.held_since.lock().unwrap() = None;
+                }
                 self.emit(BuilderEvent::formed_bundle(
                     self.builder_index,
                     None,
@@ -357,10 +450,25 @@ where
                 });
             };
 	    println!("HC before BundleTx op_hashes {:?}", bundle_tx.op_hashes);
+            if initial_op_count.is_none()
+                && self.hold_for_more_ops(
+                    bundle_tx.op_hashes.len(),
+                    bundle_tx.earliest_valid_until,
+                    Instant::now(),
+                )
+            {
+                return Ok(SendBundleResult::HeldForMoreOps {
+                    op_count: bundle_tx.op_hashes.len(),
+                });
+            }
             let BundleTx {
                 tx,
                 expected_storage,
                 op_hashes,
+                bundle_hash,
+                ops_per_aggregator,
+                gas_estimate,
+                earliest_valid_until,
             } = bundle_tx;
             if initial_op_count.is_none() {
                 initial_op_count = Some(op_hashes.len());
@@ -372,17 +480,21 @@ where
 
             let send_result = self
                 .transaction_tracker
-                .send_transaction(tx.clone(), &expected_storage)
+                .send_transaction(tx.clone(), &expected_storage, bundle_hash)
                 .await?;
             let update = match send_result {
                 SendResult::TrackerUpdate(update) => update,
                 SendResult::TxHash(tx_hash) => {
+                    let result = self.mark_ops_submitted_in_pool(&op_hashes, tx_hash).await;
+                    if let Err(error) = result {
+                        error!("Failed to mark ops as submitted in pool: {error}");
+                    }
                     self.emit(BuilderEvent::formed_bundle(
                         self.builder_index,
                         Some(BundleTxDetails {
                             tx_hash,
                             tx,
-                            op_hashes: Arc::new(op_hashes),
+                            op_hashes: Arc::new(op_hashes.clone()),
                         }),
                         nonce.low_u64(),
                         fee_increase_count,
@@ -426,13 +538,39 @@ where
                     BuilderMetrics::increment_bundle_txns_dropped(self.builder_index);
                     info!("Previous transaction dropped by sender");
                 }
-                TrackerUpdate::NonceUsedForOtherTx { nonce } => {
+                TrackerUpdate::NonceUsedForOtherTx { nonce: used_nonce } => {
                     self.emit(BuilderEvent::nonce_used_for_other_transaction(
                         self.builder_index,
-                        nonce.low_u64(),
+                        used_nonce.low_u64(),
                     ));
                     BuilderMetrics::increment_bundle_txns_nonce_used(self.builder_index);
-                    bail!("nonce used by external transaction")
+                    // The ops were not included, so the same bundle may still be
+                    // valid at the next nonce
+                    let (next_nonce, _) = self.transaction_tracker.get_nonce_and_required_fees()?;
+                    let tx = self
+                        .revalidate_bundle_tx(
+                            ops_per_aggregator.clone(),
+                            gas_estimate,
+                            current_fees,
+                            next_nonce,
+                        )
+                        .await?;
+                    let Some(tx) = tx else {
+                        bail!("nonce used by external transaction")
+                    };
+                    info!("Bundle {bundle_hash:?} is still valid, resending it with nonce {next_nonce}");
+                    nonce = next_nonce;
+                    required_fees = None;
+                    resend_bundle_tx = Some(BundleTx {
+                        tx,
+                        expected_storage,
+                        op_hashes,
+                        bundle_hash,
+                        ops_per_aggregator,
+                        gas_estimate,
+                        earliest_valid_until,
+                    });
+                    continue;
                 }
                 TrackerUpdate::ReplacementUnderpriced => {
                     BuilderMetrics::increment_bundle_txn_replacement_underpriced(
@@ -455,6 +593,34 @@ where
         Ok(SendBundleResult::StalledAtMaxFeeIncreases)
     }
 
+    /// Returns true if a new bundle with `op_count` ops should wait for more
+    /// ops instead of being sent. Bundles are never held in manual bundling
+    /// mode, for longer than `settings.max_bundle_delay`, or past the time the
+    /// earliest of their ops expires.
+    fn hold_for_more_ops(
+        &self,
+        op_count: usize,
+        earliest_valid_until: Option<Timestamp>,
+        now: Instant,
+    ) -> bool {
+        let mut held_since = self.held_since.lock().unwrap();
+        if op_count >= self.settings.min_bundle_ops
+            || self.manual_bundling_mode.load(Ordering::Relaxed)
+        {
+            *held_since = None;
+            return false;
+        }
+        let held_for = now.saturating_duration_since(*held_since.get_or_insert(now));
+        let remaining_delay = self.settings.max_bundle_delay.saturating_sub(held_for);
+        let expiring = earliest_valid_until
+            .is_some_and(|valid_until| valid_until <= Timestamp::now() + remaining_delay);
+        if remaining_delay.is_zero() || expiring {
+            *held_since = None;
+            return false;
+        }
+        true
+    }
+
     /// Builds a bundle and returns some metadata and the transaction to send
     /// it, or `None` if there are no valid operations available.
     async fn get_bundle_tx(
@@ -462,11 +628,24 @@ where
         nonce: U256,
         required_fees: Option<GasFees>,
     ) -> anyhow::Result<Option<BundleTx>> {
-        let bundle = self
-            .proposer
-            .make_bundle(required_fees)
-            .await
-            .context("proposer should create bundle for builder")?;
+        let bundle = time::timeout(
+            self.settings.make_bundle_timeout,
+            self.proposer.make_bundle(required_fees),
+        )
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "proposer timed out creating bundle after {:?}",
+                self.settings.make_bundle_timeout
+            )
+        })?
+        .context("proposer should create bundle for builder")?;
+        for dropped in &bundle.dropped_aggregators {
+            warn!(
+                "Ops from aggregator {:?} were left out of the bundle: {}",
+                dropped.address, dropped.reason
+            );
+        }
         let remove_ops_future = async {
             let result = self.remove_ops_from_pool(&bundle.rejected_ops).await;
             if let Err(error) = result {
@@ -479,8 +658,21 @@ where
                 error!("Failed to update entities in pool: {error}");
             }
         };
-        join!(remove_ops_future, update_entities_future);
+        let record_failures_future = async {
+            let result = self
+                .record_simulation_failures_in_pool(&bundle.failed_ops, &bundle.errored_ops)
+                .await;
+            if let Err(error) = result {
+                error!("Failed to record simulation failures in pool: {error}");
+            }
+        };
+        join!(
+            remove_ops_future,
+            update_entities_future,
+            record_failures_future
+        );
         if bundle.is_empty() {
+            BuilderMetrics::increment_empty_bundles(self.builder_index);
             if !bundle.rejected_ops.is_empty() || !bundle.entity_updates.is_empty() {
                 info!(
                 "Empty bundle with {} rejected ops and {} rejected entities. Removing them from pool.",
@@ -490,8 +682,9 @@ where
             }
             return Ok(None);
         }
+        let bundle_hash = bundle.hash(self.entry_point.address(), self.chain_id, self.beneficiary);
         info!(
-            "Selected bundle with {} op(s), with {} rejected op(s) and {} updated entities",
+            "Selected bundle {bundle_hash:?} with {} op(s), with {} rejected op(s) and {} updated entities",
             bundle.len(),
             bundle.rejected_ops.len(),
             bundle.entity_updates.len()
@@ -499,19 +692,58 @@ where
         let op_hashes: Vec<_> = bundle.iter_ops().map(|op| self.op_hash(op)).collect();
         println!("HC bundle_sender bundle {:?} OH {:?}", bundle, op_hashes);
 	let mut tx = self.entry_point.get_send_bundle_transaction(
-            bundle.ops_per_aggregator,
+            bundle.ops_per_aggregator.clone(),
             self.beneficiary,
             bundle.gas_estimate,
             bundle.gas_fees,
+            self.settings.transaction_type,
         );
         tx.set_nonce(nonce);
         Ok(Some(BundleTx {
             tx,
             expected_storage: bundle.expected_storage,
             op_hashes,
+            bundle_hash,
+            ops_per_aggregator: bundle.ops_per_aggregator,
+            gas_estimate: bundle.gas_estimate,
+            earliest_valid_until: bundle.earliest_valid_until,
         }))
     }
 
+    /// Checks that a previously built bundle would still succeed against the
+    /// latest state and, if so, returns a transaction sending it with `nonce`.
+    async fn revalidate_bundle_tx(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator>,
+        gas_estimate: U256,
+        gas_fees: GasFees,
+        nonce: U256,
+    ) -> anyhow::Result<Option<TypedTransaction>> {
+        let result = self
+            .entry_point
+            .call_handle_ops(
+                ops_per_aggregator.clone(),
+                self.beneficiary,
+                gas_estimate,
+                None,
+            )
+            .await
+            .context("builder should revalidate the previous bundle")?;
+        if !matches!(result, HandleOpsOut::Success) {
+            info!("Previous bundle is no longer valid: {result:?}");
+            return Ok(None);
+        }
+        let mut tx = self.entry_point.get_send_bundle_transaction(
+            ops_per_aggregator,
+            self.beneficiary,
+            gas_estimate,
+            gas_fees,
+            self.settings.transaction_type,
+        );
+        tx.set_nonce(nonce);
+        Ok(Some(tx))
+    }
+
     async fn remove_ops_from_pool(&self, ops: &[UserOperation]) -> anyhow::Result<()> {
         //println!("HC remove_ops_from_pool {:?}", ops);
         self.pool
@@ -525,6 +757,35 @@ where
             .context("builder should remove rejected ops from pool")
     }
 
+    async fn record_simulation_failures_in_pool(
+        &self,
+        failed_ops: &[UserOperation],
+        errored_ops: &[UserOperation],
+    ) -> anyhow::Result<()> {
+        if failed_ops.is_empty() && errored_ops.is_empty() {
+            return Ok(());
+        }
+        self.pool
+            .record_simulation_failures(
+                self.entry_point.address(),
+                failed_ops.iter().map(|op| self.op_hash(op)).collect(),
+                errored_ops.iter().map(|op| self.op_hash(op)).collect(),
+            )
+            .await
+            .context("builder should record simulation failures in pool")
+    }
+
+    async fn mark_ops_submitted_in_pool(
+        &self,
+        op_hashes: &[H256],
+        tx_hash: H256,
+    ) -> anyhow::Result<()> {
+        self.pool
+            .mark_ops_submitted(self.entry_point.address(), op_hashes.to_vec(), tx_hash)
+            .await
+            .context("builder should mark submitted ops in the pool")
+    }
+
     async fn update_entities_in_pool(&self, entity_updates: &[EntityUpdate]) -> anyhow::Result<()> {
         self.pool
             .update_entities(self.entry_point.address(), entity_updates.to_vec())
@@ -544,6 +805,66 @@ where
     }
 }
 
+fn is_tracker_busy(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<TrackerError>(),
+        Some(TrackerError::Busy)
+    )
+}
+
+/// Pauses bundling after too many consecutive send failures, so that the
+/// builder does not keep forming and sending bundles every block while, for
+/// example, the node is down or the signer is out of funds.
+#[derive(Debug)]
+struct CircuitBreaker {
+    failure_threshold: u64,
+    cooldown: Duration,
+    consecutive_failures: u64,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u64, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            consecutive_failures: 0,
+            open_until: None,
+        }
+    }
+
+    /// Returns true if a bundle may be sent, closing the breaker once its
+    /// cooldown has elapsed.
+    fn allows_send(&mut self, now: Instant) -> bool {
+        match self.open_until {
+            Some(open_until) if now < open_until => false,
+            Some(_) => {
+                self.record_success();
+                true
+            }
+            None => true,
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    /// Records a send failure. Returns true if this failure opened the breaker.
+    fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures += 1;
+        if self.failure_threshold == 0
+            || self.open_until.is_some()
+            || self.consecutive_failures < self.failure_threshold
+        {
+            return false;
+        }
+        self.open_until = Some(now + self.cooldown);
+        true
+    }
+}
+
 struct BuilderMetrics {}
 
 impl BuilderMetrics {
@@ -577,6 +898,15 @@ impl BuilderMetrics {
         metrics::increment_counter!("builder_bundle_fee_increases", "builder_index" => builder_index.to_string());
     }
 
+    // used when the proposer returns a bundle with no ops, which is never sent
+    fn increment_empty_bundles(builder_index: u64) {
+        metrics::increment_counter!("builder_empty_bundle", "builder_index" => builder_index.to_string());
+    }
+
+    fn increment_circuit_breaker_opened(builder_index: u64) {
+        metrics::increment_counter!("builder_circuit_breaker_opened", "builder_index" => builder_index.to_string());
+    }
+
     fn increment_bundle_txn_replacement_underpriced(builder_index: u64) {
         metrics::increment_counter!("builder_bundle_replacement_underpriced", "builder_index" => builder_index.to_string());
     }
@@ -601,3 +931,518 @@ impl BuilderMetrics {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU64;
+
+    use ethers::types::Eip1559TransactionRequest;
+    use rundler_pool::MockPoolServer;
+    use rundler_provider::MockEntryPoint;
+    use rundler_types::UserOpsPerAggregator;
+
+    use super::*;
+    use crate::{
+        bundle_proposer::{Bundle, MockBundleProposer},
+        transaction_tracker::MockTransactionTracker,
+    };
+
+    #[tokio::test]
+    async fn test_empty_bundle_is_not_sent() {
+        let mut proposer = MockBundleProposer::new();
+        proposer.expect_make_bundle().times(1).returning(|_| {
+            Ok(Bundle {
+                // An aggregator group without any ops
+                ops_per_aggregator: vec![UserOpsPerAggregator::default()],
+                ..Default::default()
+            })
+        });
+        // Building or sending a transaction panics as there are no expectations for them
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(Address::zero());
+        let mut transaction_tracker = MockTransactionTracker::new();
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        let mut pool = MockPoolServer::new();
+        pool.expect_remove_ops().returning(|_, _| Ok(()));
+        pool.expect_update_entities().returning(|_, _| Ok(()));
+
+        let sender = create_sender(
+            proposer,
+            entry_point,
+            transaction_tracker,
+            pool,
+            test_settings(),
+        );
+
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::NoOperationsInitially
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reports_failed_ops_to_pool() {
+        let failed_op = UserOperation {
+            nonce: 1.into(),
+            ..Default::default()
+        };
+        let errored_op = UserOperation {
+            nonce: 2.into(),
+            ..Default::default()
+        };
+        let failed_op_hash = failed_op.op_hash(Address::zero(), 0);
+        let errored_op_hash = errored_op.op_hash(Address::zero(), 0);
+        let mut proposer = MockBundleProposer::new();
+        proposer.expect_make_bundle().times(1).returning(move |_| {
+            Ok(Bundle {
+                failed_ops: vec![failed_op.clone()],
+                errored_ops: vec![errored_op.clone()],
+                ..Default::default()
+            })
+        });
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(Address::zero());
+        let mut transaction_tracker = MockTransactionTracker::new();
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        // Failed ops are left for the pool to remove, rather than removed directly
+        let mut pool = MockPoolServer::new();
+        pool.expect_remove_ops()
+            .withf(|_, hashes| hashes.is_empty())
+            .returning(|_, _| Ok(()));
+        pool.expect_update_entities().returning(|_, _| Ok(()));
+        pool.expect_record_simulation_failures()
+            .withf(move |_, hashes, error_hashes| {
+                hashes == &vec![failed_op_hash] && error_hashes == &vec![errored_op_hash]
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let sender = create_sender(
+            proposer,
+            entry_point,
+            transaction_tracker,
+            pool,
+            test_settings(),
+        );
+
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::NoOperationsInitially
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resends_valid_bundle_after_nonce_used_for_other_tx() {
+        let mut proposer = MockBundleProposer::new();
+        // The bundle is only built once
+        proposer.expect_make_bundle().times(1).returning(|_| {
+            Ok(Bundle {
+                ops_per_aggregator: vec![UserOpsPerAggregator {
+                    user_ops: vec![UserOperation::default()],
+                    ..Default::default()
+                }],
+                gas_estimate: 100_000.into(),
+                ..Default::default()
+            })
+        });
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(Address::zero());
+        entry_point
+            .expect_get_send_bundle_transaction()
+            .times(2)
+            .returning(|_, _, _, _, _| Eip1559TransactionRequest::new().into());
+        entry_point
+            .expect_call_handle_ops()
+            .times(1)
+            .returning(|_, _, _, _| Ok(HandleOpsOut::Success));
+
+        let mut transaction_tracker = MockTransactionTracker::new();
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .times(1)
+            .returning(|| Ok((U256::zero(), None)));
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .times(1)
+            .returning(|| Ok((U256::one(), None)));
+        transaction_tracker
+            .expect_send_transaction()
+            .withf(|tx, _, _| tx.nonce() == Some(&U256::zero()))
+            .times(1)
+            .returning(|_, _, _| Ok(SendResult::TxHash(H256::repeat_byte(1))));
+        transaction_tracker
+            .expect_send_transaction()
+            .withf(|tx, _, _| tx.nonce() == Some(&U256::one()))
+            .times(1)
+            .returning(|_, _, _| Ok(SendResult::TxHash(H256::repeat_byte(2))));
+        transaction_tracker
+            .expect_wait_for_update()
+            .times(1)
+            .returning(|| {
+                Ok(TrackerUpdate::NonceUsedForOtherTx {
+                    nonce: U256::zero(),
+                })
+            });
+        transaction_tracker
+            .expect_wait_for_update()
+            .times(1)
+            .returning(|| {
+                Ok(TrackerUpdate::Mined {
+                    tx_hash: H256::repeat_byte(2),
+                    nonce: U256::one(),
+                    block_number: 1,
+                    attempt_number: 0,
+                    gas_limit: None,
+                    gas_used: None,
+                })
+            });
+        let mut pool = MockPoolServer::new();
+        pool.expect_remove_ops().returning(|_, _| Ok(()));
+        pool.expect_update_entities().returning(|_, _| Ok(()));
+        pool.expect_mark_ops_submitted().returning(|_, _, _| Ok(()));
+
+        let sender = create_sender(
+            proposer,
+            entry_point,
+            transaction_tracker,
+            pool,
+            test_settings(),
+        );
+
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::Success { tx_hash, .. } if tx_hash == H256::repeat_byte(2)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sends_bundle_with_primary_entry_point() {
+        let primary_entry_point_address = Address::repeat_byte(1);
+        let mut proposer = MockBundleProposer::new();
+        proposer.expect_make_bundle().times(1).returning(|_| {
+            Ok(Bundle {
+                ops_per_aggregator: vec![UserOpsPerAggregator {
+                    user_ops: vec![UserOperation::default()],
+                    ..Default::default()
+                }],
+                gas_estimate: 100_000.into(),
+                ..Default::default()
+            })
+        });
+        // The proposer simulates through the simulation provider, but the
+        // sender builds the transaction with the entry point on the primary
+        // provider, and the tracker sends it through the primary provider
+        let mut entry_point = MockEntryPoint::new();
+        entry_point
+            .expect_address()
+            .return_const(primary_entry_point_address);
+        entry_point
+            .expect_get_send_bundle_transaction()
+            .times(1)
+            .returning(move |_, _, _, _, _| {
+                Eip1559TransactionRequest::new()
+                    .to(primary_entry_point_address)
+                    .into()
+            });
+
+        let mut transaction_tracker = MockTransactionTracker::new();
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        transaction_tracker
+            .expect_send_transaction()
+            .withf(move |tx, _, _| tx.to_addr() == Some(&primary_entry_point_address))
+            .times(1)
+            .returning(|_, _, _| Ok(SendResult::TxHash(H256::repeat_byte(1))));
+        transaction_tracker
+            .expect_wait_for_update()
+            .times(1)
+            .returning(|| {
+                Ok(TrackerUpdate::Mined {
+                    tx_hash: H256::repeat_byte(1),
+                    nonce: U256::zero(),
+                    block_number: 1,
+                    attempt_number: 0,
+                    gas_limit: None,
+                    gas_used: None,
+                })
+            });
+        let mut pool = MockPoolServer::new();
+        pool.expect_remove_ops().returning(|_, _| Ok(()));
+        pool.expect_update_entities().returning(|_, _| Ok(()));
+        pool.expect_mark_ops_submitted().returning(|_, _, _| Ok(()));
+
+        let sender = create_sender(
+            proposer,
+            entry_point,
+            transaction_tracker,
+            pool,
+            test_settings(),
+        );
+
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::Success { tx_hash, .. } if tx_hash == H256::repeat_byte(1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_holds_bundle_until_min_ops() {
+        let mut proposer = MockBundleProposer::new();
+        let mut seq = mockall::Sequence::new();
+        for op_count in [1, 2] {
+            proposer
+                .expect_make_bundle()
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(move |_| {
+                    Ok(Bundle {
+                        ops_per_aggregator: vec![UserOpsPerAggregator {
+                            user_ops: vec![UserOperation::default(); op_count],
+                            ..Default::default()
+                        }],
+                        gas_estimate: 100_000.into(),
+                        ..Default::default()
+                    })
+                });
+        }
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(Address::zero());
+        entry_point
+            .expect_get_send_bundle_transaction()
+            .returning(|_, _, _, _, _| Eip1559TransactionRequest::new().into());
+
+        // Only the bundle with two ops is sent
+        let mut transaction_tracker = MockTransactionTracker::new();
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        transaction_tracker
+            .expect_send_transaction()
+            .times(1)
+            .returning(|_, _, _| Ok(SendResult::TxHash(H256::repeat_byte(1))));
+        transaction_tracker
+            .expect_wait_for_update()
+            .times(1)
+            .returning(|| {
+                Ok(TrackerUpdate::Mined {
+                    tx_hash: H256::repeat_byte(1),
+                    nonce: U256::zero(),
+                    block_number: 1,
+                    attempt_number: 0,
+                    gas_limit: None,
+                    gas_used: None,
+                })
+            });
+        let mut pool = MockPoolServer::new();
+        pool.expect_remove_ops().returning(|_, _| Ok(()));
+        pool.expect_update_entities().returning(|_, _| Ok(()));
+        pool.expect_mark_ops_submitted().returning(|_, _, _| Ok(()));
+
+        let sender = min_ops_sender(proposer, entry_point, transaction_tracker, pool);
+
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::HeldForMoreOps { op_count: 1 }
+        ));
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::Success { tx_hash, .. } if tx_hash == H256::repeat_byte(1)
+        ));
+    }
+
+    #[test]
+    fn test_holds_bundle_until_max_delay() {
+        let sender = min_ops_sender(
+            MockBundleProposer::new(),
+            MockEntryPoint::new(),
+            MockTransactionTracker::new(),
+            MockPoolServer::new(),
+        );
+        let now = Instant::now();
+
+        assert!(sender.hold_for_more_ops(1, None, now));
+        assert!(sender.hold_for_more_ops(1, None, now + Duration::from_secs(30)));
+        assert!(!sender.hold_for_more_ops(1, None, now + Duration::from_secs(60)));
+        // Holding starts over after a bundle is sent
+        assert!(sender.hold_for_more_ops(1, None, now + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_does_not_hold_bundle_with_expiring_op() {
+        let sender = min_ops_sender(
+            MockBundleProposer::new(),
+            MockEntryPoint::new(),
+            MockTransactionTracker::new(),
+            MockPoolServer::new(),
+        );
+        let now = Instant::now();
+
+        assert!(sender.hold_for_more_ops(
+            1,
+            Some(Timestamp::now() + Duration::from_secs(3600)),
+            now
+        ));
+        assert!(!sender.hold_for_more_ops(
+            1,
+            Some(Timestamp::now() + Duration::from_secs(10)),
+            now
+        ));
+    }
+
+    fn test_settings() -> Settings {
+        Settings {
+            replacement_fee_percent_increase: 10,
+            max_fee_increases: 7,
+            transaction_type: TransactionType::Eip1559,
+            circuit_breaker_failure_threshold: 0,
+            circuit_breaker_cooldown: Duration::from_secs(60),
+            make_bundle_timeout: Duration::from_secs(60),
+            min_bundle_ops: 0,
+            max_bundle_delay: Duration::ZERO,
+        }
+    }
+
+    fn create_sender<B: BundleProposer>(
+        proposer: B,
+        entry_point: MockEntryPoint,
+        transaction_tracker: MockTransactionTracker,
+        pool: MockPoolServer,
+        settings: Settings,
+    ) -> BundleSenderImpl<B, MockEntryPoint, MockTransactionTracker, MockPoolServer> {
+        let (_, send_bundle_receiver) = mpsc::channel(1);
+        let (event_sender, _) = broadcast::channel(16);
+        BundleSenderImpl::new(
+            0,
+            Arc::new(AtomicBool::new(false)),
+            send_bundle_receiver,
+            0,
+            Address::zero(),
+            proposer,
+            entry_point,
+            transaction_tracker,
+            pool,
+            settings,
+            event_sender,
+        )
+    }
+
+    // Sender holding bundles with fewer than two ops for up to a minute
+    fn min_ops_sender(
+        proposer: MockBundleProposer,
+        entry_point: MockEntryPoint,
+        transaction_tracker: MockTransactionTracker,
+        pool: MockPoolServer,
+    ) -> BundleSenderImpl<MockBundleProposer, MockEntryPoint, MockTransactionTracker, MockPoolServer>
+    {
+        create_sender(
+            proposer,
+            entry_point,
+            transaction_tracker,
+            pool,
+            Settings {
+                min_bundle_ops: 2,
+                max_bundle_delay: Duration::from_secs(60),
+                ..test_settings()
+            },
+        )
+    }
+
+    // Proposer whose first bundle never finishes, and returns empty bundles after
+    #[derive(Default)]
+    struct SlowProposer {
+        calls: AtomicU64,
+    }
+
+    #[async_trait]
+    impl BundleProposer for SlowProposer {
+        async fn make_bundle(&self, _required_fees: Option<GasFees>) -> anyhow::Result<Bundle> {
+            if self.calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                time::sleep(Duration::from_secs(3600)).await;
+            }
+            Ok(Bundle::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_make_bundle_times_out() {
+        let mut entry_point = MockEntryPoint::new();
+        entry_point.expect_address().return_const(Address::zero());
+        let mut transaction_tracker = MockTransactionTracker::new();
+        transaction_tracker
+            .expect_get_nonce_and_required_fees()
+            .returning(|| Ok((U256::zero(), None)));
+        let mut pool = MockPoolServer::new();
+        pool.expect_remove_ops().returning(|_, _| Ok(()));
+        pool.expect_update_entities().returning(|_, _| Ok(()));
+
+        let sender = create_sender(
+            SlowProposer::default(),
+            entry_point,
+            transaction_tracker,
+            pool,
+            Settings {
+                make_bundle_timeout: Duration::from_millis(10),
+                ..test_settings()
+            },
+        );
+
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::Error(error) if error.to_string().contains("timed out")
+        ));
+        // The next block's attempt goes ahead as usual
+        assert!(matches!(
+            sender.send_bundle_with_increasing_gas_fees().await,
+            SendBundleResult::NoOperationsInitially
+        ));
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_after_consecutive_failures() {
+        let cooldown = Duration::from_secs(60);
+        let mut breaker = CircuitBreaker::new(3, cooldown);
+        let now = Instant::now();
+
+        assert!(!breaker.record_failure(now));
+        assert!(!breaker.record_failure(now));
+        assert!(breaker.allows_send(now));
+        assert!(breaker.record_failure(now));
+        assert!(!breaker.allows_send(now));
+
+        // Closes once the cooldown has elapsed
+        assert!(!breaker.allows_send(now + cooldown / 2));
+        assert!(breaker.allows_send(now + cooldown));
+        assert!(!breaker.record_failure(now + cooldown));
+    }
+
+    #[test]
+    fn test_circuit_breaker_success_resets_failures() {
+        let mut breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(!breaker.record_failure(now));
+        breaker.record_success();
+        assert!(!breaker.record_failure(now));
+        assert!(breaker.allows_send(now));
+
+        assert!(breaker.record_failure(now));
+        breaker.record_success();
+        assert!(breaker.allows_send(now));
+    }
+
+    #[test]
+    fn test_circuit_breaker_disabled() {
+        let mut breaker = CircuitBreaker::new(0, Duration::from_secs(60));
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(!breaker.record_failure(now));
+        }
+        assert!(breaker.allows_send(now));
+    }
+}
@@ -15,7 +15,7 @@ use std::{fmt::Display, sync::Arc};
 
 use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
 use rundler_sim::SimulationError;
-use rundler_types::{GasFees, ValidTimeRange};
+use rundler_types::{Entity, GasFees, ValidTimeRange};
 use rundler_utils::strs;
 
 /// Builder event
@@ -185,6 +185,20 @@ pub enum SkipReason {
     },
     /// Bundle ran out of space by gas limit to include the operation
     GasLimit,
+    /// Operation's sender is deployed by another operation in the bundle that
+    /// could not be ordered before it
+    UndeployedSender,
+    /// Operation uses a throttled entity that already has the maximum number
+    /// of operations in the bundle
+    ThrottledEntity { entity: Entity },
+    /// Operation uses an unstaked entity that already has the maximum number
+    /// of operations in the bundle
+    UnstakedEntity { entity: Entity },
+    /// Operation's paymaster already sponsors the maximum share of the bundle
+    PaymasterShare { paymaster: Address },
+    /// Another operation in the bundle has the same sender and nonce and pays
+    /// higher fees
+    DuplicateNonce,
     /// Other reason, typically internal errors
     Other { reason: Arc<String> },
 }
@@ -0,0 +1,66 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use ethers::types::U256;
+use rundler_provider::Provider;
+use rundler_sim::FeeEstimator;
+use rundler_types::GasFees;
+
+/// Context in which a bundle is priced
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GasOracleContext {
+    /// Fees the bundle must at least pay, set when replacing a bundle
+    /// transaction that is still pending
+    pub min_fees: Option<GasFees>,
+    /// Base fee of the latest block
+    pub base_fee: U256,
+}
+
+/// Source of the fees used for bundle transactions.
+///
+/// Builders on chains with bespoke fee markets can provide their own
+/// implementation, see `BuilderTask::with_gas_oracle`.
+#[async_trait]
+pub trait GasOracle: Send + Sync + Debug + 'static {
+    /// Returns the fees to use for a bundle transaction, along with the
+    /// pre-verification gas overhead each op must pay on top of the standard
+    /// requirement for the chain.
+    async fn bundle_fees(&self, context: GasOracleContext) -> anyhow::Result<(GasFees, U256)>;
+}
+
+/// Default gas oracle, pricing bundles from the network priority fee and the
+/// latest base fee
+#[derive(Debug)]
+pub(crate) struct FeeEstimatorGasOracle<P> {
+    fee_estimator: FeeEstimator<P>,
+}
+
+impl<P> FeeEstimatorGasOracle<P> {
+    pub(crate) fn new(fee_estimator: FeeEstimator<P>) -> Self {
+        Self { fee_estimator }
+    }
+}
+
+#[async_trait]
+impl<P: Provider> GasOracle for FeeEstimatorGasOracle<P> {
+    async fn bundle_fees(&self, context: GasOracleContext) -> anyhow::Result<(GasFees, U256)> {
+        let fees = self
+            .fee_estimator
+            .required_bundle_fees_at_base_fee(context.base_fee, context.min_fees)
+            .await?;
+        Ok((fees, U256::zero()))
+    }
+}
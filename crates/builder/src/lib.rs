@@ -22,9 +22,15 @@
 mod bundle_proposer;
 mod bundle_sender;
 
+mod bundle_scorer;
+pub use bundle_scorer::BundleScorer;
+
 mod emit;
 pub use emit::{BuilderEvent, BuilderEventKind};
 
+mod gas_oracle;
+pub use gas_oracle::{GasOracle, GasOracleContext};
+
 mod sender;
 pub use sender::TransactionSenderType;
 
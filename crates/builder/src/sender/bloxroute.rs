@@ -29,6 +29,7 @@ use jsonrpsee::{
 };
 use reqwest::header::{HeaderMap, HeaderValue};
 use rundler_sim::ExpectedStorage;
+use rundler_types::GasFees;
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use tokio::time;
@@ -56,9 +57,10 @@ where
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
+        gas_fees: GasFees,
         _expected_storage: &ExpectedStorage,
     ) -> Result<SentTxInfo> {
-        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx).await?;
+        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx, gas_fees).await?;
         let tx_hash = self.client.send_transaction(raw_tx).await?;
         Ok(SentTxInfo { nonce, tx_hash })
     }
@@ -21,6 +21,7 @@ use ethers::{
 };
 use ethers_signers::Signer;
 use rundler_sim::ExpectedStorage;
+use rundler_types::GasFees;
 use serde_json::json;
 use tonic::async_trait;
 
@@ -46,9 +47,10 @@ where
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
+        gas_fees: GasFees,
         expected_storage: &ExpectedStorage,
     ) -> Result<SentTxInfo> {
-        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx).await?;
+        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx, gas_fees).await?;
 
         let tx_hash = self
             .provider
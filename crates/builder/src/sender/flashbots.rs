@@ -38,6 +38,7 @@ use jsonrpsee::{
     http_client::{transport::HttpBackend, HttpClient, HttpClientBuilder},
 };
 use pin_project::pin_project;
+use rundler_types::GasFees;
 use serde::{de, Deserialize, Serialize};
 use serde_json::{value::RawValue, Value};
 use tonic::async_trait;
@@ -65,9 +66,10 @@ where
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
+        gas_fees: GasFees,
         _expected_storage: &ExpectedStorage,
     ) -> Result<SentTxInfo> {
-        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx).await?;
+        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx, gas_fees).await?;
 
         let tx_hash = self.client.send_transaction(raw_tx).await?;
 
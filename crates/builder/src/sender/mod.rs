@@ -14,10 +14,11 @@
 mod bloxroute;
 mod conditional;
 mod flashbots;
+mod multi;
 mod raw;
 use std::{str::FromStr, sync::Arc, time::Duration};
 
-use anyhow::{bail, Context, Error};
+use anyhow::{bail, ensure, Context, Error};
 use async_trait::async_trait;
 pub(crate) use bloxroute::PolygonBloxrouteTransactionSender;
 pub(crate) use conditional::ConditionalTransactionSender;
@@ -34,8 +35,10 @@ use ethers_signers::Signer;
 pub(crate) use flashbots::FlashbotsTransactionSender;
 #[cfg(test)]
 use mockall::automock;
+pub(crate) use multi::{MultiSignerSettings, MultiSignerTransactionSender};
 pub(crate) use raw::RawTransactionSender;
 use rundler_sim::ExpectedStorage;
+use rundler_types::GasFees;
 use serde::Serialize;
 
 #[derive(Debug)]
@@ -68,9 +71,13 @@ pub(crate) type Result<T> = std::result::Result<T, TxSenderError>;
 #[enum_dispatch(TransactionSenderEnum<_C,_S>)]
 #[cfg_attr(test, automock)]
 pub(crate) trait TransactionSender: Send + Sync + 'static {
+    /// Signs and sends a transaction. The given gas fees are applied to the
+    /// transaction before signing and take precedence over any fees the
+    /// provider would otherwise estimate.
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
+        gas_fees: GasFees,
         expected_storage: &ExpectedStorage,
     ) -> Result<SentTxInfo>;
 
@@ -199,11 +206,14 @@ pub(crate) enum SenderConstructorErrors {
 async fn fill_and_sign<C, S>(
     provider: &SignerMiddleware<Arc<Provider<C>>, S>,
     mut tx: TypedTransaction,
+    gas_fees: GasFees,
 ) -> anyhow::Result<(Bytes, U256)>
 where
     C: JsonRpcClient + 'static,
     S: Signer + 'static,
 {
+    // Fees are set before filling so that the provider never estimates its own.
+    set_gas_fees(&mut tx, gas_fees)?;
     provider
         .fill_transaction(&mut tx, None)
         .await
@@ -219,6 +229,31 @@ where
     Ok((tx.rlp_signed(&signature), nonce))
 }
 
+/// Overwrites the fees on a transaction with the given gas fees. Legacy transactions
+/// use the max fee as their gas price, other transaction types are left unchanged.
+///
+/// Returns an error if the priority fee of an EIP-1559 transaction exceeds its
+/// max fee, which nodes would otherwise reject with a less descriptive error.
+fn set_gas_fees(tx: &mut TypedTransaction, gas_fees: GasFees) -> anyhow::Result<()> {
+    match tx {
+        TypedTransaction::Eip1559(inner) => {
+            ensure!(
+                gas_fees.max_priority_fee_per_gas <= gas_fees.max_fee_per_gas,
+                "max priority fee per gas {} should not exceed max fee per gas {}",
+                gas_fees.max_priority_fee_per_gas,
+                gas_fees.max_fee_per_gas
+            );
+            inner.max_fee_per_gas = Some(gas_fees.max_fee_per_gas);
+            inner.max_priority_fee_per_gas = Some(gas_fees.max_priority_fee_per_gas);
+        }
+        TypedTransaction::Legacy(inner) => {
+            inner.gas_price = Some(gas_fees.max_fee_per_gas);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 impl From<ProviderError> for TxSenderError {
     fn from(value: ProviderError) -> Self {
         match &value {
@@ -249,3 +284,90 @@ impl From<jsonrpsee::core::Error> for TxSenderError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        providers::MockProvider,
+        signers::LocalWallet,
+        types::{Eip1559TransactionRequest, TransactionRequest},
+        utils::rlp::Rlp,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signed_tx_carries_given_fees() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .nonce(0)
+            .gas(100_000)
+            .chain_id(1)
+            .max_fee_per_gas(1)
+            .max_priority_fee_per_gas(1)
+            .into();
+        let gas_fees = GasFees {
+            max_fee_per_gas: U256::from(2_000_000_000),
+            max_priority_fee_per_gas: U256::from(150_000_000),
+        };
+
+        set_gas_fees(&mut tx, gas_fees).unwrap();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+        let raw_tx = tx.rlp_signed(&signature);
+        let (signed_tx, _) = TypedTransaction::decode_signed(&Rlp::new(&raw_tx)).unwrap();
+
+        assert_eq!(GasFees::from(&signed_tx), gas_fees);
+    }
+
+    #[tokio::test]
+    async fn test_fill_and_sign_uses_given_fees() {
+        let wallet: LocalWallet =
+            "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+                .parse()
+                .unwrap();
+        // The mock client has no queued responses, so any fee estimation
+        // request made while filling the transaction would fail.
+        let provider = SignerMiddleware::new(
+            Arc::new(Provider::new(MockProvider::new())),
+            wallet.with_chain_id(1_u64),
+        );
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .nonce(7)
+            .gas(100_000)
+            .chain_id(1)
+            .into();
+        let gas_fees = GasFees {
+            max_fee_per_gas: U256::from(2_000_000_000),
+            max_priority_fee_per_gas: U256::from(150_000_000),
+        };
+
+        let (raw_tx, nonce) = fill_and_sign(&provider, tx, gas_fees).await.unwrap();
+        let (signed_tx, _) = TypedTransaction::decode_signed(&Rlp::new(&raw_tx)).unwrap();
+
+        assert_eq!(nonce, U256::from(7));
+        assert_eq!(GasFees::from(&signed_tx), gas_fees);
+    }
+
+    #[test]
+    fn test_rejects_priority_fee_above_max_fee() {
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new().into();
+        let gas_fees = GasFees {
+            max_fee_per_gas: U256::from(100),
+            max_priority_fee_per_gas: U256::from(101),
+        };
+
+        let error = set_gas_fees(&mut tx, gas_fees).unwrap_err();
+        assert!(error.to_string().contains("should not exceed max fee"));
+        // The transaction is left unchanged
+        assert_eq!(GasFees::from(&tx), GasFees::default());
+
+        // Legacy transactions only have a gas price, so there is nothing to check
+        let mut tx: TypedTransaction = TransactionRequest::new().into();
+        set_gas_fees(&mut tx, gas_fees).unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, TransactionReceipt, H256, U256,
+};
+use rundler_provider::Provider;
+use rundler_sim::ExpectedStorage;
+use rundler_types::GasFees;
+use tracing::warn;
+
+use super::{Result, SentTxInfo, TransactionSender, TxSenderError, TxStatus};
+
+/// Settings for choosing between the signers of a `MultiSignerTransactionSender`
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MultiSignerSettings {
+    /// Balance below which a signer is skipped in favor of the next one
+    pub(crate) min_balance: U256,
+    /// How long a signer's transaction may stay unmined at the same nonce
+    /// before the signer is considered stuck
+    pub(crate) stuck_timeout: Duration,
+}
+
+/// Transaction sender that holds a prioritized list of signers and sends
+/// each transaction from the first one that is healthy.
+///
+/// A signer is unhealthy if its balance is below the minimum, if it failed to
+/// send for lack of funds and has not been topped up since, or if a transaction
+/// it sent has been unmined at the same nonce for longer than the stuck timeout.
+/// Signers are checked in priority order before every send, so the sender
+/// returns to the primary signer as soon as it is healthy again. The sender
+/// only switches signers while the active signer has no pending transaction,
+/// so that a transaction is not abandoned at a nonce that may still mine,
+/// unless that transaction has stayed pending for longer than the stuck timeout.
+#[derive(Debug)]
+pub(crate) struct MultiSignerTransactionSender<P, T> {
+    provider: Arc<P>,
+    signers: Vec<T>,
+    settings: MultiSignerSettings,
+    state: Mutex<MultiSignerState>,
+}
+
+#[derive(Debug, Default)]
+struct MultiSignerState {
+    active: usize,
+    // Transactions at the last nonce sent by each signer
+    sent: HashMap<Address, SentTxs>,
+    // Balance of each signer when it last failed to send for lack of funds
+    underfunded: HashMap<Address, U256>,
+}
+
+#[derive(Clone, Debug)]
+struct SentTxs {
+    nonce: U256,
+    // Transactions at the nonce, latest last
+    tx_hashes: Vec<H256>,
+    first_sent_at: Instant,
+    // When a transaction at the nonce was first seen pending
+    pending_since: Option<Instant>,
+}
+
+#[async_trait]
+impl<P, T> TransactionSender for MultiSignerTransactionSender<P, T>
+where
+    P: Provider,
+    T: TransactionSender,
+{
+    async fn send_transaction(
+        &self,
+        mut tx: TypedTransaction,
+        gas_fees: GasFees,
+        expected_storage: &ExpectedStorage,
+    ) -> Result<SentTxInfo> {
+        let active = self.state.lock().unwrap().active;
+        let index = self.select_signer(active).await?;
+        let signer = &self.signers[index];
+        let address = signer.address();
+        if index != active {
+            // The transaction's nonce was chosen for the previous signer
+            let nonce = self
+                .provider
+                .get_transaction_count(address)
+                .await
+                .context("should get nonce of new signer")?;
+            warn!("Switching builder signer to {address:?} at nonce {nonce}");
+            tx.set_from(address);
+            tx.set_nonce(nonce);
+        }
+
+        let sent_tx = match signer
+            .send_transaction(tx, gas_fees, expected_storage)
+            .await
+        {
+            Ok(sent_tx) => sent_tx,
+            Err(error) => {
+                if is_insufficient_funds(&error) {
+                    let balance = self
+                        .provider
+                        .get_balance(address, None)
+                        .await
+                        .context("should get balance of underfunded signer")?;
+                    warn!("Builder signer {address:?} has insufficient funds, balance {balance}");
+                    self.state
+                        .lock()
+                        .unwrap()
+                        .underfunded
+                        .insert(address, balance);
+                }
+                return Err(error);
+            }
+        };
+
+        let mut state = self.state.lock().unwrap();
+        // Only switch once the new signer has sent, so that callers keep
+        // tracking the previous signer if the send fails
+        state.active = index;
+        match state.sent.get_mut(&address) {
+            // Replacements at the same nonce keep the original send time
+            Some(sent) if sent.nonce == sent_tx.nonce => sent.tx_hashes.push(sent_tx.tx_hash),
+            _ => {
+                state.sent.insert(
+                    address,
+                    SentTxs {
+                        nonce: sent_tx.nonce,
+                        tx_hashes: vec![sent_tx.tx_hash],
+                        first_sent_at: Instant::now(),
+                        pending_since: None,
+                    },
+                );
+            }
+        }
+        Ok(sent_tx)
+    }
+
+    async fn get_transaction_status(&self, tx_hash: H256) -> Result<TxStatus> {
+        self.sending_signer(tx_hash)
+            .get_transaction_status(tx_hash)
+            .await
+    }
+
+    async fn wait_until_mined(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>> {
+        self.sending_signer(tx_hash).wait_until_mined(tx_hash).await
+    }
+
+    fn address(&self) -> Address {
+        self.active_signer().address()
+    }
+}
+
+impl<P, T> MultiSignerTransactionSender<P, T>
+where
+    P: Provider,
+    T: TransactionSender,
+{
+    /// Creates a sender from signers in priority order. The first signer is the primary.
+    pub(crate) fn new(
+        provider: Arc<P>,
+        signers: Vec<T>,
+        settings: MultiSignerSettings,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(!signers.is_empty(), "should have at least one signer");
+        Ok(Self {
+            provider,
+            signers,
+            settings,
+            state: Mutex::new(MultiSignerState::default()),
+        })
+    }
+
+    fn active_signer(&self) -> &T {
+        &self.signers[self.state.lock().unwrap().active]
+    }
+
+    /// Returns the signer that sent the transaction, or the active signer if
+    /// the transaction is not at the last nonce sent by any signer.
+    fn sending_signer(&self, tx_hash: H256) -> &T {
+        let state = self.state.lock().unwrap();
+        self.signers
+            .iter()
+            .find(|signer| {
+                state
+                    .sent
+                    .get(&signer.address())
+                    .is_some_and(|sent| sent.tx_hashes.contains(&tx_hash))
+            })
+            .unwrap_or(&self.signers[state.active])
+    }
+
+    /// Returns the index of the highest priority healthy signer. The active
+    /// signer is kept if no signer is healthy, or if it has a pending
+    /// transaction that has not been pending for longer than the stuck timeout.
+    async fn select_signer(&self, active: usize) -> anyhow::Result<usize> {
+        if self.signers.len() == 1 {
+            return Ok(0);
+        }
+        if let Some(pending_since) = self.pending_since(&self.signers[active]).await? {
+            if pending_since.elapsed() < self.settings.stuck_timeout {
+                return Ok(active);
+            }
+            warn!(
+                "Builder signer {:?} has a transaction pending for {:?}",
+                self.signers[active].address(),
+                pending_since.elapsed()
+            );
+        }
+        for (index, signer) in self.signers.iter().enumerate() {
+            if self.is_healthy(signer.address()).await? {
+                return Ok(index);
+            }
+        }
+        warn!(
+            "No healthy builder signer, staying with {:?}",
+            self.signers[active].address()
+        );
+        Ok(active)
+    }
+
+    /// If the latest transaction sent by the signer has neither mined nor
+    /// dropped, returns when a transaction at its nonce was first seen pending.
+    async fn pending_since(&self, signer: &T) -> anyhow::Result<Option<Instant>> {
+        let address = signer.address();
+        let sent = self.state.lock().unwrap().sent.get(&address).cloned();
+        let Some(sent) = sent else {
+            return Ok(None);
+        };
+        let nonce = self
+            .provider
+            .get_transaction_count(address)
+            .await
+            .context("should get signer nonce")?;
+        if nonce > sent.nonce {
+            return Ok(None);
+        }
+        let Some(&tx_hash) = sent.tx_hashes.last() else {
+            return Ok(None);
+        };
+        let status = signer
+            .get_transaction_status(tx_hash)
+            .await
+            .context("should get status of signer's latest transaction")?;
+        if !matches!(status, TxStatus::Pending) {
+            return Ok(None);
+        }
+        let mut state = self.state.lock().unwrap();
+        let Some(sent) = state.sent.get_mut(&address) else {
+            return Ok(None);
+        };
+        Ok(Some(*sent.pending_since.get_or_insert_with(Instant::now)))
+    }
+
+    async fn is_healthy(&self, address: Address) -> anyhow::Result<bool> {
+        let balance = self
+            .provider
+            .get_balance(address, None)
+            .await
+            .context("should get signer balance")?;
+        if balance < self.settings.min_balance {
+            return Ok(false);
+        }
+        let (sent, underfunded_balance) = {
+            let state = self.state.lock().unwrap();
+            (
+                state
+                    .sent
+                    .get(&address)
+                    .map(|sent| (sent.nonce, sent.first_sent_at)),
+                state.underfunded.get(&address).copied(),
+            )
+        };
+        if let Some(underfunded_balance) = underfunded_balance {
+            if balance <= underfunded_balance {
+                return Ok(false);
+            }
+            self.state.lock().unwrap().underfunded.remove(&address);
+        }
+        let Some((sent_nonce, first_sent_at)) = sent else {
+            return Ok(true);
+        };
+        if first_sent_at.elapsed() < self.settings.stuck_timeout {
+            return Ok(true);
+        }
+        let nonce = self
+            .provider
+            .get_transaction_count(address)
+            .await
+            .context("should get signer nonce")?;
+        Ok(nonce > sent_nonce)
+    }
+}
+
+fn is_insufficient_funds(error: &TxSenderError) -> bool {
+    match error {
+        TxSenderError::Other(error) => format!("{error:#}").contains("insufficient funds"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use anyhow::anyhow;
+    use ethers::types::Eip1559TransactionRequest;
+    use rundler_provider::MockProvider;
+
+    use super::*;
+    use crate::sender::MockTransactionSender;
+
+    const PRIMARY: Address = Address::repeat_byte(1);
+    const BACKUP: Address = Address::repeat_byte(2);
+
+    fn given_signer(address: Address) -> MockTransactionSender {
+        let mut signer = MockTransactionSender::new();
+        signer.expect_address().return_const(address);
+        signer
+    }
+
+    fn expect_status(signer: &mut MockTransactionSender, status: fn() -> TxStatus) {
+        signer
+            .expect_get_transaction_status()
+            .returning(move |_| Box::pin(async move { Ok(status()) }));
+    }
+
+    fn expect_send(signer: &mut MockTransactionSender, nonce: u64) {
+        signer
+            .expect_send_transaction()
+            .withf(move |tx, _, _| tx.nonce() == Some(&U256::from(nonce)))
+            .times(1)
+            .returning(move |_, _, _| {
+                Box::pin(async move {
+                    Ok(SentTxInfo {
+                        nonce: U256::from(nonce),
+                        tx_hash: H256::random(),
+                    })
+                })
+            });
+    }
+
+    fn create_sender(
+        provider: MockProvider,
+        primary: MockTransactionSender,
+        backup: MockTransactionSender,
+        stuck_timeout: Duration,
+    ) -> MultiSignerTransactionSender<MockProvider, MockTransactionSender> {
+        MultiSignerTransactionSender::new(
+            Arc::new(provider),
+            vec![primary, backup],
+            MultiSignerSettings {
+                min_balance: U256::from(100),
+                stuck_timeout,
+            },
+        )
+        .unwrap()
+    }
+
+    async fn send(
+        sender: &MultiSignerTransactionSender<MockProvider, MockTransactionSender>,
+        nonce: u64,
+    ) -> Result<SentTxInfo> {
+        let tx = Eip1559TransactionRequest::new().nonce(nonce).into();
+        sender
+            .send_transaction(tx, GasFees::default(), &ExpectedStorage::default())
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_on_insufficient_funds_and_recovers() {
+        let primary_balance = Arc::new(AtomicU64::new(1000));
+        let mut provider = MockProvider::new();
+        let balance = Arc::clone(&primary_balance);
+        provider.expect_get_balance().returning(move |address, _| {
+            if address == PRIMARY {
+                Ok(balance.load(Ordering::SeqCst).into())
+            } else {
+                Ok(U256::from(1000))
+            }
+        });
+        provider
+            .expect_get_transaction_count()
+            .returning(|address| {
+                if address == PRIMARY {
+                    Ok(U256::from(3))
+                } else {
+                    Ok(U256::from(7))
+                }
+            });
+
+        let mut primary = given_signer(PRIMARY);
+        primary
+            .expect_send_transaction()
+            .times(1)
+            .returning(|_, _, _| {
+                Box::pin(async {
+                    Err(TxSenderError::Other(anyhow!(
+                        "insufficient funds for gas * price + value"
+                    )))
+                })
+            });
+        expect_send(&mut primary, 3);
+        let mut backup = given_signer(BACKUP);
+        expect_send(&mut backup, 7);
+        expect_status(&mut backup, || TxStatus::Mined { block_number: 1 });
+        let sender = create_sender(provider, primary, backup, Duration::from_secs(60));
+
+        // The primary's balance looks sufficient until it fails to send
+        assert!(send(&sender, 3).await.is_err());
+        assert_eq!(sender.address(), PRIMARY);
+
+        // Fails over to the backup at the backup's nonce
+        assert_eq!(send(&sender, 3).await.unwrap().nonce, U256::from(7));
+        assert_eq!(sender.address(), BACKUP);
+
+        // Returns to the primary at its own nonce once it is topped up
+        primary_balance.store(2000, Ordering::SeqCst);
+        assert_eq!(send(&sender, 8).await.unwrap().nonce, U256::from(3));
+        assert_eq!(sender.address(), PRIMARY);
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_on_low_balance() {
+        let mut provider = MockProvider::new();
+        provider.expect_get_balance().returning(|address, _| {
+            if address == PRIMARY {
+                Ok(U256::from(99))
+            } else {
+                Ok(U256::from(100))
+            }
+        });
+        provider
+            .expect_get_transaction_count()
+            .returning(|_| Ok(U256::from(7)));
+
+        let mut backup = given_signer(BACKUP);
+        expect_send(&mut backup, 7);
+        let sender = create_sender(
+            provider,
+            given_signer(PRIMARY),
+            backup,
+            Duration::from_secs(60),
+        );
+
+        send(&sender, 0).await.unwrap();
+        assert_eq!(sender.address(), BACKUP);
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_when_nonce_stuck() {
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::from(1000)));
+        provider
+            .expect_get_transaction_count()
+            .returning(|address| {
+                if address == PRIMARY {
+                    Ok(U256::from(3))
+                } else {
+                    Ok(U256::from(7))
+                }
+            });
+
+        let mut primary = given_signer(PRIMARY);
+        expect_send(&mut primary, 3);
+        expect_status(&mut primary, || TxStatus::Dropped);
+        let mut backup = given_signer(BACKUP);
+        expect_send(&mut backup, 7);
+        let sender = create_sender(provider, primary, backup, Duration::ZERO);
+
+        send(&sender, 3).await.unwrap();
+        assert_eq!(sender.address(), PRIMARY);
+
+        // The primary's transaction at nonce 3 dropped without mining
+        send(&sender, 3).await.unwrap();
+        assert_eq!(sender.address(), BACKUP);
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_when_pending_transaction_stuck() {
+        let mut provider = MockProvider::new();
+        provider
+            .expect_get_balance()
+            .returning(|_, _| Ok(U256::from(1000)));
+        provider
+            .expect_get_transaction_count()
+            .returning(|address| {
+                if address == PRIMARY {
+                    Ok(U256::from(3))
+                } else {
+                    Ok(U256::from(7))
+                }
+            });
+
+        let mut primary = given_signer(PRIMARY);
+        expect_send(&mut primary, 3);
+        expect_status(&mut primary, || TxStatus::Pending);
+        let mut backup = given_signer(BACKUP);
+        expect_send(&mut backup, 7);
+        let sender = create_sender(provider, primary, backup, Duration::ZERO);
+
+        send(&sender, 3).await.unwrap();
+        assert_eq!(sender.address(), PRIMARY);
+
+        // The primary's transaction at nonce 3 is still pending past the
+        // stuck timeout
+        assert_eq!(send(&sender, 3).await.unwrap().nonce, U256::from(7));
+        assert_eq!(sender.address(), BACKUP);
+    }
+}
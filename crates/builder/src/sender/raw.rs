@@ -22,6 +22,7 @@ use ethers::{
 };
 use ethers_signers::Signer;
 use rundler_sim::ExpectedStorage;
+use rundler_types::GasFees;
 
 use super::Result;
 use crate::sender::{fill_and_sign, SentTxInfo, TransactionSender, TxStatus};
@@ -47,9 +48,10 @@ where
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
+        gas_fees: GasFees,
         _expected_storage: &ExpectedStorage,
     ) -> Result<SentTxInfo> {
-        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx).await?;
+        let (raw_tx, nonce) = fill_and_sign(&self.provider, tx, gas_fees).await?;
 
         let tx_hash = self
             .provider
@@ -201,7 +201,10 @@ impl LocalBuilderServerRunner {
                                         Ok(ServerResponse::DebugSendBundleNow { hash: tx_hash, block_number })
                                     },
                                     SendBundleResult::NoOperationsInitially => {
-                                        Err(anyhow::anyhow!("no ops to send").into())
+                                        Err(BuilderServerError::NoOperations)
+                                    },
+                                    SendBundleResult::HeldForMoreOps { .. } => {
+                                        Err(anyhow::anyhow!("bundle held to wait for more operations").into())
                                     },
                                     SendBundleResult::NoOperationsAfterFeeIncreases { .. } => {
                                         Err(anyhow::anyhow!("bundle initially had operations, but after increasing gas fees it was empty").into())
@@ -245,3 +248,59 @@ enum ServerResponse {
     DebugSendBundleNow { hash: H256, block_number: u64 },
     DebugSetBundlingMode,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(send_bundle_requester: mpsc::Sender<SendBundleRequest>) -> LocalBuilderHandle {
+        let builder = LocalBuilderBuilder::new(10);
+        let handle = builder.get_handle();
+        builder.run(
+            Arc::new(AtomicBool::new(true)),
+            vec![send_bundle_requester],
+            vec![Address::random()],
+            CancellationToken::new(),
+        );
+        handle
+    }
+
+    #[tokio::test]
+    async fn test_debug_send_bundle_now() {
+        let (send_bundle_tx, mut send_bundle_rx) = mpsc::channel(10);
+        let handle = setup(send_bundle_tx);
+        let hash = H256::random();
+
+        let (result, _) = tokio::join!(handle.debug_send_bundle_now(), async {
+            let request: SendBundleRequest = send_bundle_rx.recv().await.unwrap();
+            request
+                .responder
+                .send(SendBundleResult::Success {
+                    block_number: 5,
+                    attempt_number: 0,
+                    tx_hash: hash,
+                })
+                .unwrap();
+        });
+
+        assert_eq!(result.unwrap(), (hash, 5));
+        // Exactly one bundle was requested
+        assert!(send_bundle_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_debug_send_bundle_now_no_operations() {
+        let (send_bundle_tx, mut send_bundle_rx) = mpsc::channel(10);
+        let handle = setup(send_bundle_tx);
+
+        let (result, _) = tokio::join!(handle.debug_send_bundle_now(), async {
+            let request: SendBundleRequest = send_bundle_rx.recv().await.unwrap();
+            request
+                .responder
+                .send(SendBundleResult::NoOperationsInitially)
+                .unwrap();
+        });
+
+        assert!(matches!(result, Err(BuilderServerError::NoOperations)));
+    }
+}
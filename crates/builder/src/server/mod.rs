@@ -30,6 +30,9 @@ pub enum BuilderServerError {
     /// Builder returned an unexpected response type for the given request
     #[error("Unexpected response from BuilderServer")]
     UnexpectedResponse,
+    /// The builder had no operations to bundle
+    #[error("No operations to bundle")]
+    NoOperations,
     /// Internal errors
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -46,6 +49,8 @@ pub trait BuilderServer: Send + Sync + 'static {
     async fn get_supported_entry_points(&self) -> BuilderResult<Vec<Address>>;
 
     /// Trigger the builder to send a bundle now, used for debugging.
+    /// Returns the bundle transaction hash and the block it was mined in, or
+    /// `BuilderServerError::NoOperations` if there was nothing to bundle.
     ///
     /// Bundling mode must be set to `Manual`, or this will error
     async fn debug_send_bundle_now(&self) -> BuilderResult<(H256, u64)>;
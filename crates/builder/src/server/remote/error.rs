@@ -13,7 +13,7 @@
 
 use rundler_task::grpc::protos::ConversionError;
 
-use super::protos::{builder_error, BuilderError as ProtoBuilderError};
+use super::protos::{builder_error, BuilderError as ProtoBuilderError, NoOperationsError};
 use crate::server::BuilderServerError;
 
 impl From<tonic::Status> for BuilderServerError {
@@ -36,6 +36,7 @@ impl TryFrom<ProtoBuilderError> for BuilderServerError {
             Some(builder_error::Error::Internal(e)) => {
                 Ok(BuilderServerError::Other(anyhow::anyhow!(e)))
             }
+            Some(builder_error::Error::NoOperations(_)) => Ok(BuilderServerError::NoOperations),
             None => Ok(BuilderServerError::Other(anyhow::anyhow!("Unknown error"))),
         }
     }
@@ -49,6 +50,9 @@ impl From<BuilderServerError> for ProtoBuilderError {
                     "Unexpected response".to_string(),
                 )),
             },
+            BuilderServerError::NoOperations => ProtoBuilderError {
+                error: Some(builder_error::Error::NoOperations(NoOperationsError {})),
+            },
             BuilderServerError::Other(e) => ProtoBuilderError {
                 error: Some(builder_error::Error::Internal(e.to_string())),
             },
@@ -22,17 +22,18 @@ use anyhow::{bail, Context};
 use async_trait::async_trait;
 use ethers::{
     providers::{JsonRpcClient, Provider},
-    types::{Address, H256},
+    types::{Address, H256, I256, U256},
 };
 use ethers_signers::Signer;
 use futures::future;
 use futures_util::TryFutureExt;
 use rundler_pool::PoolServer;
 use rundler_sim::{
-    MempoolConfig, PriorityFeeMode, SimulateValidationTracerImpl, SimulationSettings, SimulatorImpl,
+    gas::CalldataGasConstants, MempoolConfig, PriorityFeeMode, SimulateValidationTracerImpl,
+    SimulationSettings, SimulatorImpl,
 };
 use rundler_task::Task;
-use rundler_types::contracts::i_entry_point::IEntryPoint;
+use rundler_types::{contracts::i_entry_point::IEntryPoint, TransactionType};
 use rundler_utils::{emit::WithEntryPoint, eth, handle};
 use rusoto_core::Region;
 use tokio::{
@@ -41,13 +42,15 @@ use tokio::{
     time, try_join,
 };
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
     bundle_proposer::{self, BundleProposerImpl},
+    bundle_scorer::BundleScorer,
     bundle_sender::{self, BundleSender, BundleSenderImpl, SendBundleRequest},
     emit::BuilderEvent,
-    sender::TransactionSenderType,
+    gas_oracle::GasOracle,
+    sender::{MultiSignerSettings, MultiSignerTransactionSender, TransactionSenderType},
     server::{spawn_remote_builder_server, LocalBuilderBuilder},
     signer::{BundlerSigner, KmsSigner, LocalSigner},
     transaction_tracker::{self, TransactionTrackerImpl},
@@ -74,32 +77,100 @@ pub struct Args {
     pub redis_uri: String,
     /// Redis lease TTL in milliseconds
     pub redis_lock_ttl_millis: u64,
+    /// Private keys of backup signers, in priority order, to fail over to
+    /// when the primary signer is underfunded or stuck
+    pub backup_private_keys: Vec<String>,
+    /// Balance in wei below which a signer is skipped in favor of the next one
+    pub signer_min_balance: U256,
+    /// How long a signer's transaction may stay unmined at the same nonce
+    /// before the signer is considered stuck
+    pub signer_stuck_timeout: Duration,
     /// Chain ID
     pub chain_id: u64,
     /// Maximum bundle size in number of operations
     pub max_bundle_size: u64,
     /// Maximum bundle size in gas limit
     pub max_bundle_gas: u64,
+    /// Percentage increase applied to the handleOps gas estimate, capped at the block gas limit
+    pub handle_ops_gas_limit_increase_percent: u64,
     /// URL to submit bundles too
     pub submit_url: String,
+    /// URL of the provider used for read-only simulation calls
+    pub simulation_url: String,
+    /// Websocket URL of the node used to subscribe to pending transactions,
+    /// if any
+    pub node_ws: Option<String>,
     /// Percentage to add to the the network priority fee for the bundle priority fee
     pub bundle_priority_fee_overhead_percent: u64,
     /// Priority fee mode to use for operation priority fee minimums
     pub priority_fee_mode: PriorityFeeMode,
+    /// Whether to query the network for the max priority fee
+    pub use_dynamic_max_priority_fee: bool,
+    /// Max priority fee to use when the network is not queried
+    pub static_max_priority_fee: U256,
+    /// Minimum projected profit, in wei, for a bundle to be sent. If not set,
+    /// bundles are sent regardless of profit.
+    pub min_bundle_profit: Option<I256>,
+    /// Number of times a sender's ops are excluded for accessing another
+    /// sender in the bundle after which the sender's reputation is penalized.
+    /// Zero never penalizes the sender.
+    pub accessed_other_sender_penalty_threshold: u64,
+    /// Maximum number of ops in a bundle that use the same unstaked paymaster,
+    /// factory or aggregator. Zero does not limit them.
+    pub max_unstaked_entity_bundle_ops: u64,
+    /// Maximum length, in bytes, of an aggregator signature. Aggregators that
+    /// return longer signatures are left out of the bundle. Zero does not
+    /// limit the length.
+    pub max_aggregator_signature_length: u64,
+    /// Whether to simulate ops against the pending block instead of the latest
+    /// block, so that ops that are only valid after pending transactions are
+    /// included
+    pub simulate_against_pending: bool,
+    /// Minimum per op of the node's gas estimate for a bundle. Bundles with a
+    /// lower estimate are skipped, as they would run out of gas on chain. Zero
+    /// disables the check.
+    pub min_gas_estimate_per_op: u64,
+    /// Whether to drop ops with init code whose factory has no code or whose
+    /// sender is already deployed at the current head
+    pub check_init_code: bool,
+    /// Maximum percentage of the bundle size that ops sponsored by a single
+    /// paymaster can take up. Zero does not limit the share.
+    pub max_paymaster_bundle_percent: u64,
     /// Sender to be used by the builder
     pub sender_type: TransactionSenderType,
+    /// Type of transaction used to submit bundles
+    pub transaction_type: TransactionType,
     /// RPC node poll interval
     pub eth_poll_interval: Duration,
     /// Operation simulation settings
     pub sim_settings: SimulationSettings,
+    /// Calldata gas costs used in the static portion of the pre-verification gas
+    pub calldata_gas: CalldataGasConstants,
     /// Alt-mempool configs
     pub mempool_configs: HashMap<H256, MempoolConfig>,
     /// Maximum number of blocks to wait for a transaction to be mined
     pub max_blocks_to_wait_for_mine: u64,
     /// Percentage to increase the fees by when replacing a bundle transaction
     pub replacement_fee_percent_increase: u64,
+    /// Number of consecutive polls that must report a bundle transaction as
+    /// dropped before it is treated as dropped. Zero never treats it as dropped.
+    pub drop_confirmation_polls: u64,
     /// Maximum number of times to increase the fees when replacing a bundle transaction
     pub max_fee_increases: u64,
+    /// Number of consecutive bundle send failures after which bundling is paused.
+    /// Zero disables pausing.
+    pub circuit_breaker_failure_threshold: u64,
+    /// How long to pause bundling after repeated send failures
+    pub circuit_breaker_cooldown: Duration,
+    /// Maximum time to build a bundle. Bundle attempts that take longer are
+    /// abandoned and retried on the next block.
+    pub make_bundle_timeout: Duration,
+    /// Minimum number of ops to send a bundle. Smaller bundles wait for more
+    /// ops, up to `max_bundle_delay`, unless one of their ops is about to
+    /// expire. Zero or one sends every bundle immediately.
+    pub min_bundle_ops: usize,
+    /// Maximum time to hold a bundle with fewer than `min_bundle_ops` ops
+    pub max_bundle_delay: Duration,
     /// Address to bind the remote builder server to, if any. If none, no server is starter.
     pub remote_address: Option<SocketAddr>,
     /// Optional Bloxroute auth header
@@ -121,6 +192,8 @@ pub struct BuilderTask<P> {
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
     builder_builder: LocalBuilderBuilder,
     pool: P,
+    gas_oracle: Option<Arc<dyn GasOracle>>,
+    bundle_scorer: Option<Arc<dyn BundleScorer>>,
 }
 
 #[async_trait]
@@ -132,6 +205,8 @@ where
         info!("Mempool config: {:?}", self.args.mempool_configs);
 
         let provider = eth::new_provider(&self.args.rpc_url, Some(self.args.eth_poll_interval))?;
+        let simulation_provider =
+            eth::new_provider(&self.args.simulation_url, Some(self.args.eth_poll_interval))?;
         let manual_bundling_mode = Arc::new(AtomicBool::new(false));
 
         let mut sender_handles = vec![];
@@ -142,6 +217,7 @@ where
                     i + self.args.bundle_builder_index_offset,
                     Arc::clone(&manual_bundling_mode),
                     Arc::clone(&provider),
+                    Arc::clone(&simulation_provider),
                 )
                 .await?;
             sender_handles.push(spawn_guard);
@@ -210,9 +286,25 @@ where
             event_sender,
             builder_builder,
             pool,
+            gas_oracle: None,
+            bundle_scorer: None,
         }
     }
 
+    /// Prices bundles with a custom gas oracle instead of the default one based on
+    /// the network priority fee and base fee
+    pub fn with_gas_oracle(mut self, gas_oracle: Arc<dyn GasOracle>) -> Self {
+        self.gas_oracle = Some(gas_oracle);
+        self
+    }
+
+    /// Orders the candidate ops of each bundle by a custom scorer instead of
+    /// the pool's order by max fee
+    pub fn with_bundle_scorer(mut self, bundle_scorer: Arc<dyn BundleScorer>) -> Self {
+        self.bundle_scorer = Some(bundle_scorer);
+        self
+    }
+
     /// Convert this task into a boxed task
     pub fn boxed(self) -> Box<dyn Task> {
         Box::new(self)
@@ -223,6 +315,7 @@ where
         index: u64,
         manual_bundling_mode: Arc<AtomicBool>,
         provider: Arc<Provider<C>>,
+        simulation_provider: Arc<Provider<C>>,
     ) -> anyhow::Result<(
         JoinHandle<anyhow::Result<()>>,
         mpsc::Sender<SendBundleRequest>,
@@ -265,16 +358,38 @@ where
             chain_id: self.args.chain_id,
             max_bundle_size: self.args.max_bundle_size,
             max_bundle_gas: self.args.max_bundle_gas,
+            handle_ops_gas_limit_increase_percent: self.args.handle_ops_gas_limit_increase_percent,
             beneficiary,
             priority_fee_mode: self.args.priority_fee_mode,
             bundle_priority_fee_overhead_percent: self.args.bundle_priority_fee_overhead_percent,
+            use_dynamic_max_priority_fee: self.args.use_dynamic_max_priority_fee,
+            static_max_priority_fee: self.args.static_max_priority_fee,
+            min_bundle_profit: self.args.min_bundle_profit,
+            accessed_other_sender_penalty_threshold: self
+                .args
+                .accessed_other_sender_penalty_threshold,
+            max_unstaked_entity_bundle_ops: self.args.max_unstaked_entity_bundle_ops,
+            max_aggregator_signature_length: self.args.max_aggregator_signature_length,
+            simulate_against_pending: self.args.simulate_against_pending,
+            min_gas_estimate_per_op: self.args.min_gas_estimate_per_op,
+            check_init_code: self.args.check_init_code,
+            max_paymaster_bundle_percent: self.args.max_paymaster_bundle_percent,
+            calldata_gas: self.args.calldata_gas,
         };
 
         let entry_point = IEntryPoint::new(self.args.entry_point_address, Arc::clone(&provider));
-        let simulate_validation_tracer =
-            SimulateValidationTracerImpl::new(Arc::clone(&provider), entry_point.clone());
+        // Simulations are read-only, so they are routed to the simulation provider, leaving
+        // the primary provider for tracking and sending bundle transactions.
+        let simulation_entry_point = IEntryPoint::new(
+            self.args.entry_point_address,
+            Arc::clone(&simulation_provider),
+        );
+        let simulate_validation_tracer = SimulateValidationTracerImpl::new(
+            Arc::clone(&simulation_provider),
+            simulation_entry_point.clone(),
+        );
 	let simulator = SimulatorImpl::new(
-            Arc::clone(&provider),
+            Arc::clone(&simulation_provider),
             entry_point.address(),
             simulate_validation_tracer,
             self.args.sim_settings,
@@ -284,41 +399,100 @@ where
         let submit_provider =
             eth::new_provider(&self.args.submit_url, Some(self.args.eth_poll_interval))?;
 
-        let transaction_sender = self.args.sender_type.into_sender(
-            submit_provider,
+        let mut transaction_senders = vec![self.args.sender_type.into_sender(
+            Arc::clone(&submit_provider),
             signer,
             self.args.chain_id,
             self.args.eth_poll_interval,
             &self.args.bloxroute_auth_header,
+        )?];
+        for private_key in &self.args.backup_private_keys {
+            let backup_signer = BundlerSigner::Local(
+                LocalSigner::connect(
+                    Arc::clone(&provider),
+                    self.args.chain_id,
+                    private_key.to_owned(),
+                )
+                .await?,
+            );
+            info!("Using backup signer {:?}", backup_signer.address());
+            transaction_senders.push(self.args.sender_type.into_sender(
+                Arc::clone(&submit_provider),
+                backup_signer,
+                self.args.chain_id,
+                self.args.eth_poll_interval,
+                &self.args.bloxroute_auth_header,
+            )?);
+        }
+        let transaction_sender = MultiSignerTransactionSender::new(
+            Arc::clone(&provider),
+            transaction_senders,
+            MultiSignerSettings {
+                min_balance: self.args.signer_min_balance,
+                stuck_timeout: self.args.signer_stuck_timeout,
+            },
         )?;
 
         let tracker_settings = transaction_tracker::Settings {
             poll_interval: self.args.eth_poll_interval,
             max_blocks_to_wait_for_mine: self.args.max_blocks_to_wait_for_mine,
             replacement_fee_percent_increase: self.args.replacement_fee_percent_increase,
+            drop_confirmation_polls: self.args.drop_confirmation_polls,
+        };
+
+        let pending_transactions = match &self.args.node_ws {
+            Some(node_ws) => {
+                let ws_provider = eth::new_ws_provider(node_ws).await?;
+                match rundler_provider::Provider::subscribe_pending_transactions(&*ws_provider)
+                    .await
+                {
+                    Ok(stream) => stream,
+                    Err(error) => {
+                        warn!(
+                            "Failed to subscribe to pending transactions, polling instead: {error}"
+                        );
+                        None
+                    }
+                }
+            }
+            None => None,
         };
 
         let transaction_tracker = TransactionTrackerImpl::new(
             Arc::clone(&provider),
             transaction_sender,
             tracker_settings,
+            pending_transactions,
         )
         .await?;
 
         let builder_settings = bundle_sender::Settings {
             replacement_fee_percent_increase: self.args.replacement_fee_percent_increase,
             max_fee_increases: self.args.max_fee_increases,
+            transaction_type: self.args.transaction_type,
+            circuit_breaker_failure_threshold: self.args.circuit_breaker_failure_threshold,
+            circuit_breaker_cooldown: self.args.circuit_breaker_cooldown,
+            make_bundle_timeout: self.args.make_bundle_timeout,
+            min_bundle_ops: self.args.min_bundle_ops,
+            max_bundle_delay: self.args.max_bundle_delay,
         };
 
-        let proposer = BundleProposerImpl::new(
+        let mut proposer = BundleProposerImpl::new(
             index,
             self.pool.clone(),
             simulator,
-            entry_point.clone(),
+            simulation_entry_point,
             Arc::clone(&provider),
+            simulation_provider,
             proposer_settings,
             self.event_sender.clone(),
         );
+        if let Some(gas_oracle) = &self.gas_oracle {
+            proposer = proposer.with_gas_oracle(Arc::clone(gas_oracle));
+        }
+        if let Some(bundle_scorer) = &self.bundle_scorer {
+            proposer = proposer.with_bundle_scorer(Arc::clone(bundle_scorer));
+        }
         let builder = BundleSenderImpl::new(
             index,
             manual_bundling_mode.clone(),
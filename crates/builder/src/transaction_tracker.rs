@@ -11,12 +11,19 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
-use ethers::types::{transaction::eip2718::TypedTransaction, H256, U256};
-use rundler_provider::Provider;
+use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
+use futures_util::StreamExt;
+#[cfg(test)]
+use mockall::automock;
+use rundler_provider::{PendingTransactionStream, Provider};
 use rundler_sim::ExpectedStorage;
 use rundler_types::GasFees;
 use tokio::time;
@@ -33,19 +40,24 @@ use crate::sender::{TransactionSender, TxSenderError, TxStatus};
 /// until it returns a `TrackerUpdate` to indicate whether a transaction has
 /// succeeded (potentially not the most recent one) or whether circumstances
 /// have changed so that it is worth making another attempt.
+#[cfg_attr(test, automock)]
 #[async_trait]
 pub(crate) trait TransactionTracker: Send + Sync + 'static {
-    fn get_nonce_and_required_fees(&self) -> anyhow::Result<(U256, Option<GasFees>)>;
+    fn get_nonce_and_required_fees(&self) -> TrackerResult<(U256, Option<GasFees>)>;
 
     /// Sends the provided transaction and typically returns its transaction
     /// hash, but if the transaction failed to send because another transaction
     /// with the same nonce mined first, then returns information about that
     /// transaction instead.
+    ///
+    /// `bundle_hash` identifies the logical bundle carried by the transaction
+    /// (see `Bundle::hash`) and is used to detect re-submissions.
     async fn send_transaction(
         &self,
         tx: TypedTransaction,
         expected_stroage: &ExpectedStorage,
-    ) -> anyhow::Result<SendResult>;
+        bundle_hash: H256,
+    ) -> TrackerResult<SendResult>;
 
     /// Waits until one of the following occurs:
     ///
@@ -55,13 +67,27 @@ pub(crate) trait TransactionTracker: Send + Sync + 'static {
     ///    that a transaction from our account other than one of the ones we are
     ///    tracking has mined. This should not normally happen.
     /// 4. Several new blocks have passed.
-    async fn wait_for_update(&self) -> anyhow::Result<TrackerUpdate>;
+    async fn wait_for_update(&self) -> TrackerResult<TrackerUpdate>;
 
     /// Like `wait_for_update`, except it returns immediately if there is no
     /// update rather than waiting for several new blocks.
-    async fn check_for_update_now(&self) -> anyhow::Result<Option<TrackerUpdate>>;
+    async fn check_for_update_now(&self) -> TrackerResult<Option<TrackerUpdate>>;
+}
+
+/// Errors returned by the transaction tracker
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum TrackerError {
+    /// The tracker is in use by another call, e.g. one waiting for a
+    /// transaction to mine. Callers should back off and try again later.
+    #[error("tracker should not be called while waiting for a transaction")]
+    Busy,
+    /// Any other error
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
 }
 
+pub(crate) type TrackerResult<T> = Result<T, TrackerError>;
+
 pub(crate) enum SendResult {
     TxHash(H256),
     TrackerUpdate(TrackerUpdate),
@@ -105,10 +131,28 @@ where
     provider: Arc<P>,
     sender: T,
     settings: Settings,
+    // Address of the signer that sends the tracked transactions, which changes
+    // if the sender fails over to another signer
+    address: Address,
     nonce: U256,
     transactions: Vec<PendingTransaction>,
     has_dropped: bool,
+    // Consecutive polls that reported the latest transaction as dropped
+    drop_polls: u64,
     attempt_count: u64,
+    // Our transactions seen entering the node's mempool, recorded by a task
+    // draining the pending transaction subscription if there is one
+    mempool_watch: Arc<Mutex<MempoolWatch>>,
+}
+
+#[derive(Debug, Default)]
+struct MempoolWatch {
+    // Hashes of our transactions at the current nonce. Only these are
+    // recorded, so the sets stay bounded by the number of attempts.
+    watched: HashSet<H256>,
+    // Watched transactions seen entering the node's mempool since the last
+    // check for them
+    seen: HashSet<H256>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -116,11 +160,15 @@ pub(crate) struct Settings {
     pub(crate) poll_interval: Duration,
     pub(crate) max_blocks_to_wait_for_mine: u64,
     pub(crate) replacement_fee_percent_increase: u64,
+    /// Number of consecutive polls that must report the latest transaction as
+    /// dropped before it is treated as dropped, 0 to always treat it as pending
+    pub(crate) drop_confirmation_polls: u64,
 }
 
 #[derive(Clone, Copy, Debug)]
 struct PendingTransaction {
     tx_hash: H256,
+    bundle_hash: H256,
     gas_fees: GasFees,
     attempt_number: u64,
 }
@@ -131,7 +179,7 @@ where
     P: Provider,
     T: TransactionSender,
 {
-    fn get_nonce_and_required_fees(&self) -> anyhow::Result<(U256, Option<GasFees>)> {
+    fn get_nonce_and_required_fees(&self) -> TrackerResult<(U256, Option<GasFees>)> {
         Ok(self.inner()?.get_nonce_and_required_fees())
     }
 
@@ -139,16 +187,20 @@ where
         &self,
         tx: TypedTransaction,
         expected_storage: &ExpectedStorage,
-    ) -> anyhow::Result<SendResult> {
-        self.inner()?.send_transaction(tx, expected_storage).await
+        bundle_hash: H256,
+    ) -> TrackerResult<SendResult> {
+        Ok(self
+            .inner()?
+            .send_transaction(tx, expected_storage, bundle_hash)
+            .await?)
     }
 
-    async fn wait_for_update(&self) -> anyhow::Result<TrackerUpdate> {
-        self.inner()?.wait_for_update().await
+    async fn wait_for_update(&self) -> TrackerResult<TrackerUpdate> {
+        Ok(self.inner()?.wait_for_update().await?)
     }
 
-    async fn check_for_update_now(&self) -> anyhow::Result<Option<TrackerUpdate>> {
-        self.inner()?.check_for_update_now().await
+    async fn check_for_update_now(&self) -> TrackerResult<Option<TrackerUpdate>> {
+        Ok(self.inner()?.check_for_update_now().await?)
     }
 }
 
@@ -161,17 +213,18 @@ where
         provider: Arc<P>,
         sender: T,
         settings: Settings,
+        pending_transactions: Option<PendingTransactionStream>,
     ) -> anyhow::Result<Self> {
-        let inner = TransactionTrackerImplInner::new(provider, sender, settings).await?;
+        let inner =
+            TransactionTrackerImplInner::new(provider, sender, settings, pending_transactions)
+                .await?;
         Ok(Self(tokio::sync::Mutex::new(inner)))
     }
 
     fn inner(
         &self,
-    ) -> anyhow::Result<tokio::sync::MutexGuard<'_, TransactionTrackerImplInner<P, T>>> {
-        self.0
-            .try_lock()
-            .context("tracker should not be called while waiting for a transaction")
+    ) -> TrackerResult<tokio::sync::MutexGuard<'_, TransactionTrackerImplInner<P, T>>> {
+        self.0.try_lock().map_err(|_| TrackerError::Busy)
     }
 }
 
@@ -180,19 +233,35 @@ where
     P: Provider,
     T: TransactionSender,
 {
-    async fn new(provider: Arc<P>, sender: T, settings: Settings) -> anyhow::Result<Self> {
+    async fn new(
+        provider: Arc<P>,
+        sender: T,
+        settings: Settings,
+        pending_transactions: Option<PendingTransactionStream>,
+    ) -> anyhow::Result<Self> {
+        let address = sender.address();
         let nonce = provider
-            .get_transaction_count(sender.address())
+            .get_transaction_count(address)
             .await
             .unwrap_or(U256::zero());
+        let mempool_watch = Arc::new(Mutex::new(MempoolWatch::default()));
+        if let Some(pending_transactions) = pending_transactions {
+            tokio::spawn(watch_pending_transactions(
+                pending_transactions,
+                Arc::downgrade(&mempool_watch),
+            ));
+        }
         Ok(Self {
             provider,
             sender,
             settings,
+            address,
             nonce,
             transactions: vec![],
             has_dropped: false,
+            drop_polls: 0,
             attempt_count: 0,
+            mempool_watch,
         })
     }
 
@@ -212,11 +281,21 @@ where
         &mut self,
         tx: TypedTransaction,
         expected_storage: &ExpectedStorage,
+        bundle_hash: H256,
     ) -> anyhow::Result<SendResult> {
         self.validate_transaction(&tx)?;
+        if self.is_resubmission(bundle_hash) {
+            warn!(
+                "Re-submitting bundle {bundle_hash:?} which is already pending at nonce {:?}",
+                self.nonce
+            );
+        }
         let gas_fees = GasFees::from(&tx);
         println!("HC send_transaction will send tx {:?}", tx.clone());
-        let send_result = self.sender.send_transaction(tx, expected_storage).await;
+        let send_result = self
+            .sender
+            .send_transaction(tx, gas_fees, expected_storage)
+            .await;
         println!("HC send_transaction result {:?}", send_result);
         let sent_tx = match send_result {
             Ok(sent_tx) => sent_tx,
@@ -226,20 +305,43 @@ where
             }
         };
         info!(
-            "Sent transaction {:?} nonce: {:?}",
-            sent_tx.tx_hash, sent_tx.nonce
+            "Sent transaction {:?} nonce: {:?} bundle: {:?}",
+            sent_tx.tx_hash, sent_tx.nonce, bundle_hash
         );
+        // A sender only fails over to another signer when none of our
+        // transactions are pending, so track the new signer from its nonce
+        let address = self.sender.address();
+        if address != self.address {
+            info!("Tracking transactions from signer {address:?}");
+            self.address = address;
+            self.set_nonce_and_clear_state(sent_tx.nonce);
+        }
+        self.mempool_watch
+            .lock()
+            .unwrap()
+            .watched
+            .insert(sent_tx.tx_hash);
         self.transactions.push(PendingTransaction {
             tx_hash: sent_tx.tx_hash,
+            bundle_hash,
             gas_fees,
             attempt_number: self.attempt_count,
         });
         self.has_dropped = false;
+        self.drop_polls = 0;
         self.attempt_count += 1;
         self.update_metrics();
         Ok(SendResult::TxHash(sent_tx.tx_hash))
     }
 
+    /// Returns true if a transaction carrying the same logical bundle is
+    /// already pending at the current nonce.
+    fn is_resubmission(&self, bundle_hash: H256) -> bool {
+        self.transactions
+            .iter()
+            .any(|tx| tx.bundle_hash == bundle_hash)
+    }
+
     /// When we fail to send a transaction, it may be because another
     /// transaction has mined before it could be sent, invalidating the nonce.
     /// Thus, do one last check for an update before returning the error.
@@ -338,9 +440,33 @@ where
             .get_transaction_status(last_tx.tx_hash)
             .await
             .context("tracker should check for dropped transactions")?;
-            println!("HC check_for_update_now status {:?}", status);
+        println!("HC check_for_update_now status {:?}", status);
+        let in_node_mempool =
+            matches!(status, TxStatus::Dropped) && self.is_in_node_mempool(last_tx.tx_hash).await?;
         Ok(match status {
-            TxStatus::Pending | TxStatus::Dropped => None,
+            TxStatus::Pending => {
+                self.drop_polls = 0;
+                None
+            }
+            TxStatus::Dropped if in_node_mempool => {
+                // The sender lost track of the transaction but it is still
+                // waiting in the node's mempool
+                self.drop_polls = 0;
+                None
+            }
+            TxStatus::Dropped => {
+                // Providers can report spurious drops that recover on the next
+                // poll (#295), so only trust a drop seen on consecutive polls
+                self.drop_polls += 1;
+                if self.settings.drop_confirmation_polls > 0
+                    && self.drop_polls >= self.settings.drop_confirmation_polls
+                {
+                    self.has_dropped = true;
+                    Some(TrackerUpdate::LatestTxDropped { nonce: self.nonce })
+                } else {
+                    None
+                }
+            }
             TxStatus::Mined { block_number } => {
                 let nonce = self.nonce;
                 self.set_nonce_and_clear_state(nonce + 1);
@@ -353,25 +479,39 @@ where
                     gas_limit,
                     gas_used,
                 })
-            } // TODO(#295): dropped status is often incorrect, for now just assume its still pending
-              // TxStatus::Dropped => {
-              //     self.has_dropped = true;
-              //     Some(TrackerUpdate::LatestTxDropped { nonce: self.nonce })
-              // }
+            }
         })
     }
 
+    /// Returns true if the transaction is waiting in the node's mempool. The
+    /// node is always polled, as the subscription only reports transactions
+    /// entering the mempool and not leaving it. Without the txpool namespace,
+    /// a sighting by the subscription since the last check holds off the drop.
+    async fn is_in_node_mempool(&self, tx_hash: H256) -> anyhow::Result<bool> {
+        let seen = self.mempool_watch.lock().unwrap().seen.remove(&tx_hash);
+        match self.provider.txpool_contains(self.address, tx_hash).await {
+            Ok(contains) => Ok(contains),
+            Err(error) => {
+                // Otherwise rely on confirming the drop over several polls
+                warn!("Failed to check node mempool for transaction {tx_hash:?}: {error}");
+                Ok(seen)
+            }
+        }
+    }
+
     fn set_nonce_and_clear_state(&mut self, nonce: U256) {
         self.nonce = nonce;
         self.transactions.clear();
+        *self.mempool_watch.lock().unwrap() = MempoolWatch::default();
         self.has_dropped = false;
+        self.drop_polls = 0;
         self.attempt_count = 0;
         self.update_metrics();
     }
 
     async fn get_external_nonce(&self) -> anyhow::Result<U256> {
         self.provider
-            .get_transaction_count(self.sender.address())
+            .get_transaction_count(self.address)
             .await
             .context("tracker should load current nonce from provider")
     }
@@ -430,6 +570,25 @@ where
     }
 }
 
+/// Drains the pending transaction subscription as notifications arrive,
+/// recording which of our transactions enter the node's mempool, until the
+/// subscription ends or the tracker is dropped.
+async fn watch_pending_transactions(
+    mut pending_transactions: PendingTransactionStream,
+    mempool_watch: Weak<Mutex<MempoolWatch>>,
+) {
+    while let Some(tx_hash) = pending_transactions.next().await {
+        let Some(mempool_watch) = mempool_watch.upgrade() else {
+            return;
+        };
+        let mut mempool_watch = mempool_watch.lock().unwrap();
+        if mempool_watch.watched.contains(&tx_hash) {
+            mempool_watch.seen.insert(tx_hash);
+        }
+    }
+    warn!("Pending transaction subscription ended");
+}
+
 struct TransactionTrackerMetrics {}
 
 impl TransactionTrackerMetrics {
@@ -464,9 +623,12 @@ impl TransactionTrackerMetrics {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
 
-    use ethers::types::{Address, Eip1559TransactionRequest, Transaction, TransactionReceipt};
+    use ethers::types::{Eip1559TransactionRequest, Transaction, TransactionReceipt};
     use mockall::Sequence;
     use rundler_provider::MockProvider;
 
@@ -483,15 +645,24 @@ mod tests {
     async fn create_tracker(
         sender: MockTransactionSender,
         provider: MockProvider,
+    ) -> TransactionTrackerImpl<MockProvider, MockTransactionSender> {
+        create_subscribed_tracker(sender, provider, None).await
+    }
+
+    async fn create_subscribed_tracker(
+        sender: MockTransactionSender,
+        provider: MockProvider,
+        pending_transactions: Option<PendingTransactionStream>,
     ) -> TransactionTrackerImpl<MockProvider, MockTransactionSender> {
         let settings = Settings {
             poll_interval: Duration::from_secs(0),
             max_blocks_to_wait_for_mine: 3,
             replacement_fee_percent_increase: 5,
+            drop_confirmation_polls: 2,
         };
 
         let tracker: TransactionTrackerImpl<MockProvider, MockTransactionSender> =
-            TransactionTrackerImpl::new(Arc::new(provider), sender, settings)
+            TransactionTrackerImpl::new(Arc::new(provider), sender, settings, pending_transactions)
                 .await
                 .unwrap();
 
@@ -502,7 +673,7 @@ mod tests {
     async fn test_nonce_and_fees() {
         let (mut sender, mut provider) = create_base_config();
         sender.expect_address().return_const(Address::zero());
-        sender.expect_send_transaction().returning(move |_a, _b| {
+        sender.expect_send_transaction().returning(move |_, _, _| {
             Box::pin(async {
                 Ok(SentTxInfo {
                     nonce: U256::from(0),
@@ -524,7 +695,9 @@ mod tests {
         let exp = ExpectedStorage::default();
 
         // send dummy transaction
-        let _sent = tracker.send_transaction(tx.into(), &exp).await;
+        let _sent = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await;
         let nonce_and_fees = tracker.get_nonce_and_required_fees().unwrap();
 
         assert_eq!(
@@ -539,56 +712,148 @@ mod tests {
         );
     }
 
-    // TODO(#295): fix dropped status
-    // #[tokio::test]
-    // async fn test_nonce_and_fees_dropped() {
-    //     let (mut sender, mut provider) = create_base_config();
-    //     sender.expect_address().return_const(Address::zero());
+    #[tokio::test]
+    async fn test_busy_while_locked() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
 
-    //     sender
-    //         .expect_get_transaction_status()
-    //         .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
+        let tracker = create_tracker(sender, provider).await;
 
-    //     sender.expect_send_transaction().returning(move |_a, _b| {
-    //         Box::pin(async {
-    //             Ok(SentTxInfo {
-    //                 nonce: U256::from(0),
-    //                 tx_hash: H256::zero(),
-    //             })
-    //         })
-    //     });
+        // Simulate another call holding the tracker, e.g. while waiting for a mine
+        let _guard = tracker.0.try_lock().unwrap();
+        assert!(matches!(
+            tracker.get_nonce_and_required_fees(),
+            Err(TrackerError::Busy)
+        ));
+        assert!(matches!(
+            tracker.check_for_update_now().await,
+            Err(TrackerError::Busy)
+        ));
+    }
 
-    //     provider
-    //         .expect_get_transaction_count()
-    //         .returning(move |_a| Ok(U256::from(0)));
+    #[tokio::test]
+    async fn test_nonce_and_fees_dropped() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
 
-    //     provider
-    //         .expect_get_block_number()
-    //         .returning(move || Ok(1))
-    //         .times(1);
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
 
-    //     let tracker = create_tracker(sender, provider).await;
+        sender.expect_send_transaction().returning(move |_, _, _| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+        provider
+            .expect_txpool_contains()
+            .returning(move |_, _| Ok(false));
+
+        provider.expect_get_block_number().returning(move || Ok(1));
+
+        let tracker = create_tracker(sender, provider).await;
+
+        let tx = Eip1559TransactionRequest::new()
+            .nonce(0)
+            .gas(10000)
+            .max_fee_per_gas(10000);
+        let exp = ExpectedStorage::default();
+
+        // send dummy transaction
+        let _sent = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await;
+        let _tracker_update = tracker.wait_for_update().await.unwrap();
+
+        let nonce_and_fees = tracker.get_nonce_and_required_fees().unwrap();
+
+        assert_eq!((U256::from(0), None), nonce_and_fees);
+    }
+
+    #[tokio::test]
+    async fn test_transient_drop_treated_as_pending() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+
+        // The drop is never reported on two consecutive polls
+        let mut s = Sequence::new();
+        for dropped in [true, false, true] {
+            sender
+                .expect_get_transaction_status()
+                .times(1)
+                .in_sequence(&mut s)
+                .returning(move |_a| {
+                    Box::pin(async move {
+                        if dropped {
+                            Ok(TxStatus::Dropped)
+                        } else {
+                            Ok(TxStatus::Pending)
+                        }
+                    })
+                });
+        }
+
+        sender.expect_send_transaction().returning(move |_, _, _| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
 
-    //     let tx = Eip1559TransactionRequest::new()
-    //         .nonce(0)
-    //         .gas(10000)
-    //         .max_fee_per_gas(10000);
-    //     let exp = ExpectedStorage::default();
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+        provider
+            .expect_txpool_contains()
+            .returning(move |_, _| Ok(false));
 
-    //     // send dummy transaction
-    //     let _sent = tracker.send_transaction(tx.into(), &exp).await;
-    //     let _tracker_update = tracker.wait_for_update().await.unwrap();
+        let tracker = create_tracker(sender, provider).await;
 
-    //     let nonce_and_fees = tracker.get_nonce_and_required_fees().unwrap();
+        let tx = Eip1559TransactionRequest::new()
+            .nonce(0)
+            .gas(10000)
+            .max_fee_per_gas(10000);
+        let exp = ExpectedStorage::default();
 
-    //     assert_eq!((U256::from(0), None), nonce_and_fees);
-    // }
+        // send dummy transaction
+        let _sent = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await;
+        for _ in 0..3 {
+            assert!(tracker.check_for_update_now().await.unwrap().is_none());
+        }
+
+        // Still requires a fee increase to replace the pending transaction
+        let nonce_and_fees = tracker.get_nonce_and_required_fees().unwrap();
+        assert_eq!(
+            (
+                U256::from(0),
+                Some(GasFees {
+                    max_fee_per_gas: U256::from(10500),
+                    max_priority_fee_per_gas: U256::zero(),
+                })
+            ),
+            nonce_and_fees
+        );
+    }
 
     #[tokio::test]
     async fn test_send_transaction_without_nonce() {
         let (mut sender, mut provider) = create_base_config();
         sender.expect_address().return_const(Address::zero());
-        sender.expect_send_transaction().returning(move |_a, _b| {
+        sender.expect_send_transaction().returning(move |_, _, _| {
             Box::pin(async {
                 Ok(SentTxInfo {
                     nonce: U256::from(0),
@@ -605,7 +870,9 @@ mod tests {
 
         let tx = Eip1559TransactionRequest::new();
         let exp = ExpectedStorage::default();
-        let sent_transaction = tracker.send_transaction(tx.into(), &exp).await;
+        let sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await;
 
         assert!(sent_transaction.is_err());
     }
@@ -615,7 +882,7 @@ mod tests {
         let (mut sender, mut provider) = create_base_config();
 
         sender.expect_address().return_const(Address::zero());
-        sender.expect_send_transaction().returning(move |_a, _b| {
+        sender.expect_send_transaction().returning(move |_, _, _| {
             Box::pin(async {
                 Ok(SentTxInfo {
                     nonce: U256::from(0),
@@ -632,7 +899,9 @@ mod tests {
 
         let tx = Eip1559TransactionRequest::new().nonce(0);
         let exp = ExpectedStorage::default();
-        let sent_transaction = tracker.send_transaction(tx.into(), &exp).await;
+        let sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await;
 
         assert!(sent_transaction.is_err());
     }
@@ -641,7 +910,7 @@ mod tests {
     async fn test_send_transaction() {
         let (mut sender, mut provider) = create_base_config();
         sender.expect_address().return_const(Address::zero());
-        sender.expect_send_transaction().returning(move |_a, _b| {
+        sender.expect_send_transaction().returning(move |_, _, _| {
             Box::pin(async {
                 Ok(SentTxInfo {
                     nonce: U256::from(0),
@@ -658,7 +927,10 @@ mod tests {
 
         let tx = Eip1559TransactionRequest::new().nonce(0);
         let exp = ExpectedStorage::default();
-        let sent_transaction = tracker.send_transaction(tx.into(), &exp).await.unwrap();
+        let sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await
+            .unwrap();
 
         assert!(matches!(sent_transaction, SendResult::TxHash(..)));
     }
@@ -691,43 +963,173 @@ mod tests {
         ));
     }
 
-    // TODO(#295): fix dropped status
-    // #[tokio::test]
-    // async fn test_wait_for_update_dropped() {
-    //     let (mut sender, mut provider) = create_base_config();
-    //     sender.expect_address().return_const(Address::zero());
+    #[tokio::test]
+    async fn test_wait_for_update_dropped() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
 
-    //     sender
-    //         .expect_get_transaction_status()
-    //         .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
 
-    //     sender.expect_send_transaction().returning(move |_a, _b| {
-    //         Box::pin(async {
-    //             Ok(SentTxInfo {
-    //                 nonce: U256::from(0),
-    //                 tx_hash: H256::zero(),
-    //             })
-    //         })
-    //     });
+        sender.expect_send_transaction().returning(move |_, _, _| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
 
-    //     provider
-    //         .expect_get_transaction_count()
-    //         .returning(move |_a| Ok(U256::from(0)));
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+        provider
+            .expect_txpool_contains()
+            .returning(move |_, _| Ok(false));
 
-    //     provider.expect_get_block_number().returning(move || Ok(1));
+        provider.expect_get_block_number().returning(move || Ok(1));
 
-    //     let tracker = create_tracker(sender, provider).await;
+        let tracker = create_tracker(sender, provider).await;
 
-    //     let tx = Eip1559TransactionRequest::new().nonce(0);
-    //     let exp = ExpectedStorage::default();
-    //     let _sent_transaction = tracker.send_transaction(tx.into(), &exp).await.unwrap();
-    //     let tracker_update = tracker.wait_for_update().await.unwrap();
+        let tx = Eip1559TransactionRequest::new().nonce(0);
+        let exp = ExpectedStorage::default();
+        let _sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await
+            .unwrap();
+        let tracker_update = tracker.wait_for_update().await.unwrap();
 
-    //     assert!(matches!(
-    //         tracker_update,
-    //         TrackerUpdate::LatestTxDropped { .. }
-    //     ));
-    // }
+        assert!(matches!(
+            tracker_update,
+            TrackerUpdate::LatestTxDropped { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_but_in_txpool_treated_as_pending() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
+
+        sender.expect_send_transaction().returning(move |_, _, _| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+        // The node still has the transaction in its mempool
+        provider
+            .expect_txpool_contains()
+            .returning(move |_, _| Ok(true));
+
+        let tracker = create_tracker(sender, provider).await;
+
+        let tx = Eip1559TransactionRequest::new().nonce(0);
+        let exp = ExpectedStorage::default();
+        let _sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            assert!(tracker.check_for_update_now().await.unwrap().is_none());
+        }
+    }
+
+    fn create_dropped_tx_config() -> (MockTransactionSender, MockProvider) {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+
+        sender
+            .expect_get_transaction_status()
+            .returning(move |_a| Box::pin(async { Ok(TxStatus::Dropped) }));
+
+        sender.expect_send_transaction().returning(move |_, _, _| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+
+        (sender, provider)
+    }
+
+    #[tokio::test]
+    async fn test_dropped_but_seen_by_subscription_held_off_once() {
+        let (sender, mut provider) = create_dropped_tx_config();
+        // The node does not support the txpool namespace
+        provider
+            .expect_txpool_contains()
+            .returning(move |_, _| Err(anyhow::anyhow!("txpool not supported").into()));
+
+        let (pending_tx, pending_rx) = futures::channel::mpsc::unbounded();
+        let tracker = create_subscribed_tracker(sender, provider, Some(Box::pin(pending_rx))).await;
+
+        let tx = Eip1559TransactionRequest::new().nonce(0);
+        let exp = ExpectedStorage::default();
+        let _sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await
+            .unwrap();
+
+        // The subscription reports other transactions and ours entering the
+        // node's mempool
+        pending_tx.unbounded_send(H256::repeat_byte(1)).unwrap();
+        pending_tx.unbounded_send(H256::zero()).unwrap();
+        time::sleep(Duration::from_millis(10)).await;
+
+        // The sighting holds off the drop for one check only, so a
+        // transaction that really left the mempool is still treated as dropped
+        assert!(tracker.check_for_update_now().await.unwrap().is_none());
+        assert!(tracker.check_for_update_now().await.unwrap().is_none());
+        assert!(matches!(
+            tracker.check_for_update_now().await.unwrap(),
+            Some(TrackerUpdate::LatestTxDropped { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_and_seen_by_subscription_reconfirmed_by_polling() {
+        let (sender, mut provider) = create_dropped_tx_config();
+        // The node reports that the transaction has left its mempool
+        provider
+            .expect_txpool_contains()
+            .returning(move |_, _| Ok(false));
+
+        let (pending_tx, pending_rx) = futures::channel::mpsc::unbounded();
+        let tracker = create_subscribed_tracker(sender, provider, Some(Box::pin(pending_rx))).await;
+
+        let tx = Eip1559TransactionRequest::new().nonce(0);
+        let exp = ExpectedStorage::default();
+        let _sent_transaction = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await
+            .unwrap();
+
+        pending_tx.unbounded_send(H256::zero()).unwrap();
+        time::sleep(Duration::from_millis(10)).await;
+
+        assert!(tracker.check_for_update_now().await.unwrap().is_none());
+        assert!(matches!(
+            tracker.check_for_update_now().await.unwrap(),
+            Some(TrackerUpdate::LatestTxDropped { .. })
+        ));
+    }
 
     #[tokio::test]
     async fn test_wait_for_update_nonce_used() {
@@ -766,7 +1168,7 @@ mod tests {
             .expect_get_transaction_status()
             .returning(move |_a| Box::pin(async { Ok(TxStatus::Mined { block_number: 1 }) }));
 
-        sender.expect_send_transaction().returning(move |_a, _b| {
+        sender.expect_send_transaction().returning(move |_, _, _| {
             Box::pin(async {
                 Ok(SentTxInfo {
                     nonce: U256::from(0),
@@ -806,9 +1208,65 @@ mod tests {
         let exp = ExpectedStorage::default();
 
         // send dummy transaction
-        let _sent = tracker.send_transaction(tx.into(), &exp).await;
+        let _sent = tracker
+            .send_transaction(tx.into(), &exp, H256::zero())
+            .await;
         let tracker_update = tracker.wait_for_update().await.unwrap();
 
         assert!(matches!(tracker_update, TrackerUpdate::Mined { .. }));
     }
+
+    #[tokio::test]
+    async fn test_follows_sender_to_new_signer() {
+        let primary = Address::repeat_byte(1);
+        let backup = Address::repeat_byte(2);
+        let switched = Arc::new(AtomicBool::new(false));
+
+        let (mut sender, mut provider) = create_base_config();
+        let is_switched = Arc::clone(&switched);
+        sender.expect_address().returning(move || {
+            if is_switched.load(Ordering::SeqCst) {
+                backup
+            } else {
+                primary
+            }
+        });
+        // The sender fails over to the backup signer, at the backup's nonce
+        sender.expect_send_transaction().returning(move |_, _, _| {
+            switched.store(true, Ordering::SeqCst);
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(7),
+                    tx_hash: H256::random(),
+                })
+            })
+        });
+        sender
+            .expect_get_transaction_status()
+            .returning(|_| Box::pin(async { Ok(TxStatus::Pending) }));
+        provider
+            .expect_get_transaction_count()
+            .returning(move |address| {
+                if address == primary {
+                    Ok(U256::from(3))
+                } else {
+                    Ok(U256::from(7))
+                }
+            });
+
+        let tracker = create_tracker(sender, provider).await;
+        let tx = Eip1559TransactionRequest::new()
+            .nonce(3)
+            .gas(10000)
+            .max_fee_per_gas(10000);
+        tracker
+            .send_transaction(tx.into(), &ExpectedStorage::default(), H256::zero())
+            .await
+            .unwrap();
+
+        // The backup's nonce has not changed, so its transaction is pending
+        let (nonce, _) = tracker.get_nonce_and_required_fees().unwrap();
+        assert_eq!(nonce, U256::from(7));
+        assert!(tracker.check_for_update_now().await.unwrap().is_none());
+    }
 }
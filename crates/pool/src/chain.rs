@@ -54,6 +54,8 @@ pub(crate) struct Chain<P: Provider> {
     blocks: VecDeque<BlockSummary>,
     /// Semaphore to limit the number of concurrent `eth_getLogs` calls.
     load_ops_semaphore: Semaphore,
+    /// Semaphore to limit the number of concurrent `eth_getBlockByNumber` calls.
+    load_blocks_semaphore: Semaphore,
 }
 
 #[derive(Default, Debug, Eq, PartialEq)]
@@ -61,6 +63,8 @@ pub struct ChainUpdate {
     pub latest_block_number: u64,
     pub latest_block_hash: H256,
     pub latest_block_timestamp: Timestamp,
+    /// Base fee per gas of the latest block, if the chain supports EIP-1559
+    pub latest_base_fee_per_gas: Option<U256>,
     /// Blocks before this number are no longer tracked in this `Chain`, so no
     /// further updates related to them will be sent.
     pub earliest_remembered_block_number: u64,
@@ -74,6 +78,11 @@ pub struct ChainUpdate {
     /// Boolean to state if the most recent chain update had a reorg
     /// that was larger than the existing history that has been tracked
     pub reorg_larger_than_history: bool,
+    /// Boolean to state if the most recent chain update had a reorg that was
+    /// within the tracked history but deeper than the configured reset
+    /// threshold. Consumers should conservatively re-validate their state
+    /// rather than relying only on the unmined ops.
+    pub reorg_exceeds_reset_threshold: bool,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -84,13 +93,23 @@ pub struct MinedOp {
     pub nonce: U256,
     pub actual_gas_cost: U256,
     pub paymaster: Option<Address>,
+    pub tx_hash: H256,
+    pub block_number: u64,
+    /// Index of the operation's event among the logs of its block
+    pub log_index: u64,
+    pub block_timestamp: Timestamp,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct DepositInfo {
     pub address: Address,
     pub entrypoint: Address,
+    /// The account's total deposit after this deposit, as reported by the
+    /// `Deposited` event. This is not the amount deposited.
     pub amount: U256,
+    pub block_number: u64,
+    /// Index of the deposit's event among the logs of its block
+    pub log_index: u64,
 }
 
 impl MinedOp {
@@ -107,13 +126,20 @@ pub(crate) struct Settings {
     pub(crate) history_size: u64,
     pub(crate) poll_interval: Duration,
     pub(crate) entry_point_addresses: Vec<Address>,
+    /// Reorgs deeper than this many blocks (but within history) are flagged
+    /// as requiring a full re-validation. If `None`, no such flag is set.
+    pub(crate) reorg_reset_threshold: Option<u64>,
+    /// Maximum number of blocks to load concurrently by number when loading
+    /// chain history. If 1, blocks are loaded sequentially by parent hash.
+    pub(crate) max_load_blocks_concurrency: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 struct BlockSummary {
     number: u64,
     hash: H256,
     timestamp: Timestamp,
+    base_fee_per_gas: Option<U256>,
     parent_hash: H256,
     ops: Vec<MinedOp>,
     entity_deposits: Vec<DepositInfo>,
@@ -123,11 +149,13 @@ impl<P: Provider> Chain<P> {
     pub(crate) fn new(provider: Arc<P>, settings: Settings) -> Self {
         let history_size = settings.history_size as usize;
         assert!(history_size > 0, "history size should be positive");
+        let max_load_blocks_concurrency = settings.max_load_blocks_concurrency.max(1);
         Self {
             provider,
             settings,
             blocks: VecDeque::new(),
             load_ops_semaphore: Semaphore::new(MAX_LOAD_OPS_CONCURRENCY),
+            load_blocks_semaphore: Semaphore::new(max_load_blocks_concurrency),
         }
     }
 
@@ -225,7 +253,7 @@ impl<P: Provider> Chain<P> {
             .flat_map(|block| &block.entity_deposits)
             .copied()
             .collect();
-        Ok(self.new_update(0, mined_ops, vec![], entity_deposits, vec![], false))
+        Ok(self.new_update(0, mined_ops, vec![], entity_deposits, vec![], false, false))
     }
 
     /// Given a collection of blocks to add to the chain, whose numbers may
@@ -265,6 +293,11 @@ impl<P: Provider> Chain<P> {
             .collect();
 
         let is_reorg_larger_than_history = reorg_depth >= self.settings.history_size;
+        let is_reorg_exceeding_reset_threshold = !is_reorg_larger_than_history
+            && self
+                .settings
+                .reorg_reset_threshold
+                .is_some_and(|threshold| reorg_depth > threshold);
 
         for _ in 0..reorg_depth {
             self.blocks.pop_back();
@@ -287,6 +320,7 @@ impl<P: Provider> Chain<P> {
             entity_deposits,
             unmined_entity_deposits,
             is_reorg_larger_than_history,
+            is_reorg_exceeding_reset_threshold,
         )
     }
 
@@ -346,6 +380,17 @@ impl<P: Provider> Chain<P> {
         head: BlockSummary,
         min_block_number: u64,
     ) -> anyhow::Result<VecDeque<BlockSummary>> {
+        if self.settings.max_load_blocks_concurrency > 1 && head.number > min_block_number {
+            if let Some(mut blocks) = self
+                .load_blocks_by_number_no_ops(&head, min_block_number)
+                .await?
+            {
+                blocks.push_back(head);
+                return Ok(blocks);
+            }
+            warn!("Blocks loaded by number did not form a chain to head {:?}. Loading by parent hash instead.", head.hash);
+        }
+
         let mut blocks =
             VecDeque::with_capacity(head.number.saturating_sub(min_block_number) as usize + 1);
         blocks.push_front(head);
@@ -365,6 +410,51 @@ impl<P: Provider> Chain<P> {
         Ok(blocks)
     }
 
+    /// Loads the blocks from `min_block_number` up to, but not including, the
+    /// head concurrently by number. Returns `None` if any block is missing or
+    /// the loaded blocks don't form a parent-hash chain ending at the head, as
+    /// can happen if the chain reorgs while loading.
+    async fn load_blocks_by_number_no_ops(
+        &self,
+        head: &BlockSummary,
+        min_block_number: u64,
+    ) -> anyhow::Result<Option<VecDeque<BlockSummary>>> {
+        let future_blocks = (min_block_number..head.number).map(|number| async move {
+            let _permit = self
+                .load_blocks_semaphore
+                .acquire()
+                .await
+                .expect("semaphore should not be closed");
+            self.provider
+                .get_block(number)
+                .await
+                .context("should load block by number")
+        });
+        let loaded_blocks = future::try_join_all(future_blocks).await?;
+
+        let mut blocks = VecDeque::with_capacity(loaded_blocks.len() + 1);
+        for (number, block) in (min_block_number..).zip(loaded_blocks) {
+            let Some(block) = block else {
+                return Ok(None);
+            };
+            let block = BlockSummary::try_from_block_without_ops(block, Some(number))?;
+            if blocks
+                .back()
+                .is_some_and(|parent: &BlockSummary| parent.hash != block.parent_hash)
+            {
+                return Ok(None);
+            }
+            blocks.push_back(block);
+        }
+        if blocks
+            .back()
+            .is_some_and(|parent| parent.hash != head.parent_hash)
+        {
+            return Ok(None);
+        }
+        Ok(Some(blocks))
+    }
+
     async fn load_ops_into_block_summaries(
         &self,
         blocks: &mut VecDeque<BlockSummary>,
@@ -375,7 +465,7 @@ impl<P: Provider> Chain<P> {
         // tell which branch we read events from.
         let future_opses = blocks
             .iter()
-            .map(|block| self.load_ops_in_block_with_hash(block.hash));
+            .map(|block| self.load_ops_in_block_with_hash(block.hash, block.timestamp));
         let opses = future::try_join_all(future_opses)
             .await
             .context("should load ops for new blocks")?;
@@ -389,6 +479,7 @@ impl<P: Provider> Chain<P> {
     async fn load_ops_in_block_with_hash(
         &self,
         block_hash: H256,
+        block_timestamp: Timestamp,
     ) -> anyhow::Result<(Vec<MinedOp>, Vec<DepositInfo>)> {
         let _permit = self
             .load_ops_semaphore
@@ -411,12 +502,12 @@ impl<P: Provider> Chain<P> {
             .context("chain state should load user operation events")?;
 
         let deposits = self.load_entity_deposits(&logs);
-        let mined_ops = self.load_mined_ops(&logs);
+        let mined_ops = self.load_mined_ops(&logs, block_timestamp);
 
         Ok((mined_ops, deposits))
     }
 
-    fn load_mined_ops(&self, logs: &Vec<Log>) -> Vec<MinedOp> {
+    fn load_mined_ops(&self, logs: &Vec<Log>, block_timestamp: Timestamp) -> Vec<MinedOp> {
         let mut mined_ops = vec![];
         for log in logs {
             let entry_point = log.address;
@@ -434,6 +525,10 @@ impl<P: Provider> Chain<P> {
                     nonce: event.nonce,
                     actual_gas_cost: event.actual_gas_cost,
                     paymaster,
+                    tx_hash: log.transaction_hash.unwrap_or_default(),
+                    block_number: log.block_number.unwrap_or_default().as_u64(),
+                    log_index: log.log_index.unwrap_or_default().as_u64(),
+                    block_timestamp,
                 };
 
                 mined_ops.push(mined);
@@ -452,6 +547,8 @@ impl<P: Provider> Chain<P> {
                     entrypoint,
                     address: event.account,
                     amount: event.total_deposit,
+                    block_number: log.block_number.unwrap_or_default().as_u64(),
+                    log_index: log.log_index.unwrap_or_default().as_u64(),
                 };
 
                 deposits.push(info);
@@ -477,6 +574,7 @@ impl<P: Provider> Chain<P> {
         entity_deposits: Vec<DepositInfo>,
         unmined_entity_deposits: Vec<DepositInfo>,
         reorg_larger_than_history: bool,
+        reorg_exceeds_reset_threshold: bool,
     ) -> ChainUpdate {
         let latest_block = self
             .blocks
@@ -486,6 +584,7 @@ impl<P: Provider> Chain<P> {
             latest_block_number: latest_block.number,
             latest_block_hash: latest_block.hash,
             latest_block_timestamp: latest_block.timestamp,
+            latest_base_fee_per_gas: latest_block.base_fee_per_gas,
             earliest_remembered_block_number: self.blocks[0].number,
             reorg_depth,
             mined_ops,
@@ -493,6 +592,7 @@ impl<P: Provider> Chain<P> {
             entity_deposits,
             unmined_entity_deposits,
             reorg_larger_than_history,
+            reorg_exceeds_reset_threshold,
         }
     }
 }
@@ -525,6 +625,7 @@ impl BlockSummary {
                 .as_u64(),
             hash: block.hash.context("block hash should exist")?,
             timestamp: block.timestamp.as_u64().into(),
+            base_fee_per_gas: block.base_fee_per_gas,
             parent_hash: block.parent_hash,
             ops: Vec::new(),
             entity_deposits: Vec::new(),
@@ -648,6 +749,11 @@ mod tests {
             })
         }
 
+        fn get_block_by_number(&self, number: u64) -> Option<Block<H256>> {
+            let hash = self.blocks.read().get(number as usize)?.hash;
+            self.get_block_by_hash(hash)
+        }
+
         fn get_logs_by_block_hash(&self, block_hash: H256) -> Vec<Log> {
             let blocks = self.blocks.read();
             let block = blocks.iter().find(|block| block.hash == block_hash);
@@ -686,6 +792,7 @@ mod tests {
                 latest_block_number: 3,
                 latest_block_hash: hash(3),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 1,
                 reorg_depth: 0,
                 mined_ops: vec![fake_mined_op(103), fake_mined_op(104), fake_mined_op(105),],
@@ -693,6 +800,7 @@ mod tests {
                 entity_deposits: vec![],
                 unmined_entity_deposits: vec![],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -717,6 +825,7 @@ mod tests {
                 latest_block_number: 4,
                 latest_block_hash: hash(4),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 2,
                 reorg_depth: 0,
                 mined_ops: vec![fake_mined_op(106)],
@@ -724,6 +833,7 @@ mod tests {
                 entity_deposits: vec![],
                 unmined_entity_deposits: vec![],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -754,6 +864,7 @@ mod tests {
                 latest_block_number: 4,
                 latest_block_hash: hash(14),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 2,
                 reorg_depth: 1,
                 mined_ops: vec![fake_mined_op(112), fake_mined_op(113), fake_mined_op(114)],
@@ -761,6 +872,7 @@ mod tests {
                 entity_deposits: vec![],
                 unmined_entity_deposits: vec![fake_mined_deposit(Address::zero(), 0.into())],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -792,12 +904,14 @@ mod tests {
                 latest_block_number: 2,
                 latest_block_hash: hash(12),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 0,
                 reorg_depth: 2,
                 mined_ops: vec![fake_mined_op(111), fake_mined_op(112)],
                 unmined_ops: vec![fake_mined_op(101), fake_mined_op(102)],
                 unmined_entity_deposits: vec![fake_mined_deposit(addr(1), 0.into())],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -826,12 +940,14 @@ mod tests {
                 entity_deposits: vec![fake_mined_deposit(addr(1), 0.into())],
                 latest_block_hash: hash(11),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 0,
                 reorg_depth: 2,
                 mined_ops: vec![fake_mined_op(111)],
                 unmined_ops: vec![fake_mined_op(101), fake_mined_op(102)],
                 unmined_entity_deposits: vec![],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -861,12 +977,14 @@ mod tests {
                 latest_block_number: 3,
                 latest_block_hash: hash(13),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 1,
                 reorg_depth: 3,
                 mined_ops: vec![fake_mined_op(111), fake_mined_op(112), fake_mined_op(113)],
                 unmined_ops: vec![fake_mined_op(101), fake_mined_op(102), fake_mined_op(103)],
                 unmined_entity_deposits: vec![],
                 reorg_larger_than_history: true,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -893,6 +1011,7 @@ mod tests {
                 latest_block_number: 6,
                 latest_block_hash: hash(16),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 4,
                 reorg_depth: 0,
                 entity_deposits: vec![],
@@ -900,6 +1019,7 @@ mod tests {
                 unmined_ops: vec![],
                 unmined_entity_deposits: vec![],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
@@ -920,6 +1040,7 @@ mod tests {
                 latest_block_number: 1,
                 latest_block_hash: hash(1),
                 latest_block_timestamp: 0.into(),
+                latest_base_fee_per_gas: None,
                 earliest_remembered_block_number: 0,
                 reorg_depth: 0,
                 entity_deposits: vec![],
@@ -927,11 +1048,118 @@ mod tests {
                 unmined_ops: vec![],
                 unmined_entity_deposits: vec![],
                 reorg_larger_than_history: false,
+                reorg_exceeds_reset_threshold: false,
             }
         );
     }
 
+    #[tokio::test]
+    async fn test_reorg_reset_threshold() {
+        // (reorg depth, expected reorg_exceeds_reset_threshold) with a threshold of 1.
+        // A reorg of depth 3 is larger than the history, so it is only flagged as such.
+        let cases = [(1, false), (2, true), (3, false)];
+        for (depth, exceeds_threshold) in cases {
+            let update = sync_reorg_of_depth(depth, Some(1)).await;
+            assert_eq!(update.reorg_depth, depth);
+            assert_eq!(
+                update.reorg_exceeds_reset_threshold, exceeds_threshold,
+                "unexpected threshold flag for reorg depth {depth}"
+            );
+            assert_eq!(update.reorg_larger_than_history, depth >= HISTORY_SIZE);
+        }
+
+        // Without a threshold, a reorg within history is never flagged.
+        let update = sync_reorg_of_depth(2, None).await;
+        assert!(!update.reorg_exceeds_reset_threshold);
+    }
+
+    #[tokio::test]
+    async fn test_base_fee_propagated() {
+        let (mut chain, controller) = new_chain();
+        controller.set_blocks(vec![
+            MockBlock::new(hash(0), vec![], vec![]),
+            MockBlock::new(hash(1), vec![], vec![]),
+        ]);
+        let mut head = controller.get_head();
+        head.base_fee_per_gas = Some(U256::from(1_000_000_000));
+        let update = chain.sync_to_block(head).await.unwrap();
+        assert_eq!(
+            update.latest_base_fee_per_gas,
+            Some(U256::from(1_000_000_000))
+        );
+
+        // Blocks without a base fee, such as on pre-EIP-1559 chains, propagate `None`
+        controller
+            .get_blocks_mut()
+            .push(MockBlock::new(hash(2), vec![], vec![]));
+        let update = chain.sync_to_block(controller.get_head()).await.unwrap();
+        assert_eq!(update.latest_base_fee_per_gas, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_block_loading_matches_sequential() {
+        let blocks = vec![
+            MockBlock::new(hash(0), vec![hash(101)], vec![]),
+            MockBlock::new(hash(1), vec![hash(102)], vec![addr(1)]),
+            MockBlock::new(hash(2), vec![], vec![]),
+            MockBlock::new(hash(3), vec![hash(103), hash(104)], vec![]),
+        ];
+        let (mut sequential_chain, sequential_controller) = new_chain();
+        sequential_controller.set_blocks(blocks.clone());
+        let (mut concurrent_chain, concurrent_controller) =
+            new_chain_with_settings(None, HISTORY_SIZE as usize);
+        concurrent_controller.set_blocks(blocks);
+
+        let sequential_update = sequential_chain
+            .sync_to_block(sequential_controller.get_head())
+            .await
+            .unwrap();
+        let concurrent_update = concurrent_chain
+            .sync_to_block(concurrent_controller.get_head())
+            .await
+            .unwrap();
+
+        assert_eq!(concurrent_update, sequential_update);
+        assert_eq!(concurrent_chain.blocks, sequential_chain.blocks);
+        assert_eq!(concurrent_chain.blocks.len(), HISTORY_SIZE as usize);
+    }
+
+    /// Syncs a chain of four blocks, then replaces the top `depth` blocks and
+    /// returns the resulting update.
+    async fn sync_reorg_of_depth(depth: u64, reorg_reset_threshold: Option<u64>) -> ChainUpdate {
+        let (mut chain, controller) = new_chain_with_reorg_reset_threshold(reorg_reset_threshold);
+        controller.set_blocks(
+            (0..4)
+                .map(|i| MockBlock::new(hash(i), vec![hash(100 + i)], vec![]))
+                .collect(),
+        );
+        chain.sync_to_block(controller.get_head()).await.unwrap();
+        {
+            let mut blocks = controller.get_blocks_mut();
+            for _ in 0..depth {
+                blocks.pop();
+            }
+            for i in (4 - depth as u8)..4 {
+                blocks.push(MockBlock::new(hash(10 + i), vec![hash(110 + i)], vec![]));
+            }
+        }
+        chain.sync_to_block(controller.get_head()).await.unwrap()
+    }
+
     fn new_chain() -> (Chain<impl Provider>, ProviderController) {
+        new_chain_with_reorg_reset_threshold(None)
+    }
+
+    fn new_chain_with_reorg_reset_threshold(
+        reorg_reset_threshold: Option<u64>,
+    ) -> (Chain<impl Provider>, ProviderController) {
+        new_chain_with_settings(reorg_reset_threshold, 1)
+    }
+
+    fn new_chain_with_settings(
+        reorg_reset_threshold: Option<u64>,
+        max_load_blocks_concurrency: usize,
+    ) -> (Chain<impl Provider>, ProviderController) {
         let (provider, controller) = new_mock_provider();
         let chain = Chain::new(
             Arc::new(provider),
@@ -939,6 +1167,8 @@ mod tests {
                 history_size: HISTORY_SIZE,
                 poll_interval: Duration::from_secs(250), // Not used in tests.
                 entry_point_addresses: vec![ENTRY_POINT_ADDRESS],
+                reorg_reset_threshold,
+                max_load_blocks_concurrency,
             },
         );
         (chain, controller)
@@ -955,6 +1185,11 @@ mod tests {
             move |hash| Ok(controller.get_block_by_hash(hash))
         });
 
+        provider.expect_get_block::<u64>().returning({
+            let controller = controller.clone();
+            move |number| Ok(controller.get_block_by_number(number))
+        });
+
         provider.expect_get_logs().returning({
             let controller = controller.clone();
             move |filter| {
@@ -1015,6 +1250,10 @@ mod tests {
             nonce: U256::zero(),
             actual_gas_cost: U256::zero(),
             paymaster: None,
+            tx_hash: H256::zero(),
+            block_number: 0,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
         }
     }
 
@@ -1023,6 +1262,8 @@ mod tests {
             address,
             entrypoint: ENTRY_POINT_ADDRESS,
             amount,
+            block_number: 0,
+            log_index: 0,
         }
     }
 
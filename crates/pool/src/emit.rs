@@ -118,6 +118,33 @@ pub enum OpRemovalReason {
         /// Op was valid until this timestamp
         valid_until: Timestamp,
     },
+    /// Op was removed because its submission deadline passed before it was included
+    SubmissionDeadline {
+        /// Deadline given when the op was submitted
+        deadline: Timestamp,
+    },
+    /// Op was removed because it stayed in the pool longer than the op TTL
+    TtlExpired {
+        /// Block timestamp at which the op was added or last resubmitted
+        added_at: Timestamp,
+    },
+    /// Op was removed because it failed re-validation after a deep reorg
+    FailedRevalidation {
+        /// Block number at which the op was re-validated
+        block_number: u64,
+    },
+    /// Op was removed because builders reported it failing simulation too many
+    /// consecutive times
+    FailedSimulation {
+        /// Number of consecutive failed simulations
+        failures: u64,
+    },
+    /// Op was removed because builders reported its simulation erroring, other
+    /// than with validation rule violations, too many consecutive times
+    ErroredSimulation {
+        /// Number of consecutive errored simulations
+        errors: u64,
+    },
 }
 
 impl EntitySummary {
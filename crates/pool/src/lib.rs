@@ -26,14 +26,16 @@ pub use emit::OpPoolEvent as PoolEvent;
 
 mod mempool;
 pub use mempool::{
-    MempoolError, PoolConfig, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+    AdmissionFilter, EvictionPolicy, MempoolError, NoopAdmissionFilter, OpStatus, PoolConfig,
+    PoolOperation, Reputation, ReputationStatus, StakeStatus,
 };
 
 mod server;
 #[cfg(feature = "test-utils")]
 pub use server::MockPoolServer;
 pub use server::{
-    LocalPoolBuilder, LocalPoolHandle, PoolResult, PoolServer, PoolServerError, RemotePoolClient,
+    forwarded_ops_hash, LocalPoolBuilder, LocalPoolHandle, PoolResult, PoolServer, PoolServerError,
+    PoolSnapshot, PoolStats, RemotePoolClient, SNAPSHOT_VERSION,
 };
 
 mod task;
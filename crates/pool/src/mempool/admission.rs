@@ -0,0 +1,39 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::fmt::Debug;
+
+use rundler_types::UserOperation;
+use tonic::async_trait;
+
+/// Custom admission logic run on each user operation before it is validated
+/// and added to the mempool, e.g. to only accept known senders or paymasters.
+///
+/// Provide an implementation with `PoolTask::with_admission_filter`.
+#[async_trait]
+pub trait AdmissionFilter: Send + Sync + Debug + 'static {
+    /// Returns `Err` with the reason to reject the operation, or `Ok` to
+    /// continue with the standard validation
+    async fn check(&self, op: &UserOperation) -> Result<(), String>;
+}
+
+/// Admission filter that accepts every operation
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopAdmissionFilter;
+
+#[async_trait]
+impl AdmissionFilter for NoopAdmissionFilter {
+    async fn check(&self, _op: &UserOperation) -> Result<(), String> {
+        Ok(())
+    }
+}
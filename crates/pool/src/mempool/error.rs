@@ -13,7 +13,10 @@
 
 use std::mem;
 
-use ethers::{abi::Address, types::U256};
+use ethers::{
+    abi::Address,
+    types::{H256, U256},
+};
 use rundler_sim::{
     PrecheckError, PrecheckViolation, SimulationError, SimulationViolation, ViolationError,
 };
@@ -35,6 +38,10 @@ pub enum MempoolError {
     /// and the replacement operation has lower gas price.
     #[error("Replacement operation underpriced. Existing priority fee: {0}. Existing fee: {1}")]
     ReplacementUnderpriced(U256, U256),
+    /// The operation to be replaced is not in the pool, or does not share the
+    /// replacement operation's sender and nonce
+    #[error("Operation {0} not found for replacement")]
+    ReplacementTargetNotFound(H256),
     /// Max operations reached for unstaked sender [UREP-010] or unstaked non-sender entity [UREP-020]
     #[error("Max operations ({0}) reached for entity {1}")]
     MaxOperationsReached(usize, Address),
@@ -72,6 +79,16 @@ pub enum MempoolError {
     /// An unknown entry point was specified
     #[error("Unknown entry point {0}")]
     UnknownEntryPoint(Address),
+    /// The mempool is paused and not accepting new operations
+    #[error("Mempool paused")]
+    Paused,
+    /// Operation was rejected by the pool's admission filter
+    #[error("Operation rejected by admission filter: {0}")]
+    AdmissionRejected(String),
+    /// Operations were forwarded by a peer that is not trusted by this pool, or
+    /// that is banned for forwarding invalid operations
+    #[error("Operations forwarded by untrusted peer {0:?}")]
+    UntrustedPeer(Address),
 }
 
 impl From<SimulationError> for MempoolError {
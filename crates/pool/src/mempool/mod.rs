@@ -11,6 +11,9 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+mod admission;
+pub use admission::{AdmissionFilter, NoopAdmissionFilter};
+
 mod error;
 pub use error::MempoolError;
 
@@ -30,13 +33,15 @@ mod uo_pool;
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 use ethers::types::{Address, H256, U256};
 #[cfg(test)]
 use mockall::automock;
 use rundler_sim::{EntityInfos, MempoolConfig, PrecheckSettings, SimulationSettings};
-use rundler_types::{Entity, EntityType, EntityUpdate, UserOperation, ValidTimeRange};
+use rundler_types::{Entity, EntityType, EntityUpdate, Timestamp, UserOperation, ValidTimeRange};
+use strum::{Display, EnumString};
 use tonic::async_trait;
 pub(crate) use uo_pool::UoPool;
 
@@ -54,10 +59,30 @@ pub trait Mempool: Send + Sync + 'static {
     fn entry_point(&self) -> Address;
 
     /// Adds a user operation to the pool
+    ///
+    /// If a submission deadline is given, the operation is evicted from the pool
+    /// once the latest block timestamp passes it, regardless of its valid time range.
+    ///
+    /// A bundler tip is an off-chain payment the submitter commits to making to
+    /// the bundler on inclusion, which builders weigh when ordering operations.
     async fn add_operation(
         &self,
         origin: OperationOrigin,
         op: UserOperation,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+    ) -> MempoolResult<H256>;
+
+    /// Replaces the operation with hash `old_hash` with a fee-bumped operation
+    /// sharing its sender and nonce.
+    ///
+    /// The new operation is validated as in `add_operation` and swapped in only
+    /// if validation passes, otherwise the existing operation is left in place.
+    async fn replace_operation(
+        &self,
+        origin: OperationOrigin,
+        old_hash: H256,
+        op: UserOperation,
     ) -> MempoolResult<H256>;
 
     /// Removes a set of operations from the pool.
@@ -66,6 +91,9 @@ pub trait Mempool: Send + Sync + 'static {
     /// Updates the reputation of an entity.
     fn update_entity(&self, entity_update: EntityUpdate);
 
+    /// Removes all operations that reference the entity from the pool.
+    fn evict_entity_ops(&self, entity: Entity);
+
     /// Returns current paymaster balance
     async fn paymaster_balance(&self, paymaster: Address) -> ProviderResult<PaymasterMetadata>;
 
@@ -89,6 +117,19 @@ pub trait Mempool: Send + Sync + 'static {
     /// Looks up a user operation by hash, returns None if not found
     fn get_user_operation_by_hash(&self, hash: H256) -> Option<Arc<PoolOperation>>;
 
+    /// Returns the lifecycle status of a user operation
+    fn get_op_status(&self, hash: H256) -> OpStatus;
+
+    /// Records that the given operations were included in a bundle transaction
+    /// that has been sent but not yet mined
+    fn mark_submitted(&self, hashes: &[H256], tx_hash: H256);
+
+    /// Records that the given operations failed simulation while building a
+    /// bundle, `hashes` with validation rule violations and `error_hashes` with
+    /// other errors. Operations that have failed too many consecutive times are
+    /// removed.
+    fn record_simulation_failures(&self, hashes: &[H256], error_hashes: &[H256]);
+
     /// Debug methods
 
     /// Clears the mempool of UOs or reputation of all addresses
@@ -124,6 +165,11 @@ pub struct PoolConfig {
     pub min_replacement_fee_increase_percentage: u64,
     /// After this threshold is met, we will start to drop the worst userops from the mempool
     pub max_size_of_pool_bytes: usize,
+    /// The maximum number of operations the mempool can hold, 0 for no limit. When full,
+    /// operations are evicted according to `eviction_policy`
+    pub max_pool_size: usize,
+    /// How to choose which operation to evict when the mempool is full
+    pub eviction_policy: EvictionPolicy,
     /// Operations that are always banned from the mempool
     pub blocklist: Option<HashSet<Address>>,
     /// Operations that are always allowed in the mempool, regardless of reputation
@@ -143,6 +189,31 @@ pub struct PoolConfig {
     pub throttled_entity_mempool_count: u64,
     /// The maximum number of blocks a user operation with a throttled entity can stay in the mempool
     pub throttled_entity_live_blocks: u64,
+    /// The maximum amount of time an unmined user operation can stay in the mempool, measured
+    /// in block time from when it was added or last resubmitted. If not set, unmined operations
+    /// are never evicted for their age.
+    pub op_ttl: Option<Duration>,
+    /// Half-life of the reputation ops seen and ops included counts. If not set,
+    /// the counts decay by 1/24 every hour as defined by the spec.
+    pub reputation_half_life: Option<Duration>,
+    /// Number of consecutive times a builder can report that an operation failed
+    /// simulation with validation rule violations before it is removed
+    pub max_simulation_failures: u64,
+    /// Number of consecutive times a builder can report that an operation's
+    /// simulation errored for another reason before it is removed, 0 to never
+    /// remove operations for these errors
+    pub max_simulation_errors: u64,
+}
+
+/// Policy used to choose which operation to evict when the mempool is full
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, Eq, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum EvictionPolicy {
+    /// Evict the operation with the lowest gas price
+    #[default]
+    LowestFee,
+    /// Evict the operation that was submitted to the mempool first
+    Oldest,
 }
 
 /// Stake status structure
@@ -162,6 +233,29 @@ pub struct StakeInfo {
     pub unstake_delay_sec: u32,
 }
 
+/// Lifecycle status of a user operation, as seen by the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpStatus {
+    /// The pool has no record of the operation
+    NotFound,
+    /// The operation is in the pool waiting to be bundled
+    Pending,
+    /// The operation is in a bundle transaction that has been sent but not yet mined
+    Submitted {
+        /// Hash of the bundle transaction
+        tx_hash: H256,
+    },
+    /// The operation was mined
+    Mined {
+        /// Hash of the transaction that included the operation
+        tx_hash: H256,
+        /// Number of the block that included the operation
+        block_number: u64,
+    },
+    /// The operation was removed from the pool without being mined
+    Dropped,
+}
+
 /// Origin of an operation.
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code)] // TODO(danc): remove once implemented
@@ -173,6 +267,8 @@ pub enum OperationOrigin {
     /// The operation was returned to the pool when the block it was in was
     /// reorged away.
     ReturnedAfterReorg,
+    /// The operation was forwarded by the trusted peer bundler with this address.
+    Peer(Address),
 }
 
 /// A user operation with additional metadata from validation.
@@ -198,6 +294,12 @@ pub struct PoolOperation {
     pub account_is_staked: bool,
     /// Staking information about all the entities.
     pub entity_infos: EntityInfos,
+    /// Optional deadline after which the operation is evicted from the pool
+    /// without being included.
+    pub submission_deadline: Option<Timestamp>,
+    /// Off-chain payment committed to the bundler for including the operation,
+    /// on top of its gas fees. Zero if there is no tip.
+    pub bundler_tip: U256,
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Copy)]
@@ -212,6 +314,12 @@ pub struct PaymasterMetadata {
 }
 
 impl PoolOperation {
+    /// Returns the hash of the user operation for the entry point it was
+    /// submitted to.
+    pub fn op_hash(&self, chain_id: u64) -> H256 {
+        self.uo.op_hash(self.entry_point, chain_id)
+    }
+
     /// Returns true if the operation contains the given entity.
     pub fn contains_entity(&self, entity: &Entity) -> bool {
         if let Some(e) = self.entity_infos.get(entity.kind) {
@@ -281,6 +389,22 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_op_hash_uses_entry_point() {
+        let entry_point = Address::random();
+        let po = PoolOperation {
+            uo: UserOperation {
+                sender: Address::random(),
+                ..Default::default()
+            },
+            entry_point,
+            ..Default::default()
+        };
+
+        assert_eq!(po.op_hash(1337), po.uo.op_hash(entry_point, 1337));
+        assert_ne!(po.op_hash(1337), po.uo.op_hash(Address::random(), 1337));
+    }
+
     #[test]
     fn test_entities() {
         let sender = Address::random();
@@ -321,6 +445,8 @@ mod tests {
                     is_staked: false,
                 }),
             },
+            submission_deadline: None,
+            bundler_tip: U256::zero(),
         };
 
         assert!(po.requires_stake(EntityType::Account));
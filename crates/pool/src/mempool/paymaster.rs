@@ -90,25 +90,13 @@ impl PaymasterTracker {
         keys
     }
 
-    pub(crate) fn update_paymaster_balance_after_deposit_reorg(
+    pub(crate) fn set_paymaster_balance_from_deposit(
         &mut self,
         paymaster: Address,
-        deposit_amount: U256,
+        deposit_total: U256,
     ) {
         if let Some(paymaster_balance) = self.paymaster_balances.get_mut(&paymaster) {
-            paymaster_balance.confirmed =
-                paymaster_balance.confirmed.saturating_sub(deposit_amount);
-        }
-    }
-
-    pub(crate) fn update_paymaster_balance_from_deposit(
-        &mut self,
-        paymaster: Address,
-        deposit_amount: U256,
-    ) {
-        if let Some(paymaster_balance) = self.paymaster_balances.get_mut(&paymaster) {
-            paymaster_balance.confirmed =
-                paymaster_balance.confirmed.saturating_add(deposit_amount);
+            paymaster_balance.confirmed = deposit_total;
         }
     }
 
@@ -303,6 +291,8 @@ mod tests {
             account_is_staked: true,
             entity_infos: EntityInfos::default(),
             sim_block_number: 0,
+            submission_deadline: None,
+            bundler_tip: U256::zero(),
         }
     }
 
@@ -15,6 +15,7 @@ use std::{
     cmp::Ordering,
     collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 use anyhow::Context;
@@ -24,14 +25,14 @@ use ethers::{
 };
 use rundler_types::{Entity, EntityType, Timestamp, UserOperation, UserOperationId};
 use rundler_utils::math;
-use tracing::info;
+use tracing::{info, warn};
 
 use super::{
     entity_tracker::EntityCounter,
     error::{MempoolError, MempoolResult},
     paymaster::PaymasterTracker,
     size::SizeTracker,
-    PaymasterMetadata, PoolConfig, PoolOperation,
+    EvictionPolicy, PaymasterMetadata, PoolConfig, PoolOperation,
 };
 use crate::chain::{DepositInfo, MinedOp};
 
@@ -40,9 +41,12 @@ pub(crate) struct PoolInnerConfig {
     entry_point: Address,
     chain_id: u64,
     max_size_of_pool_bytes: usize,
+    max_pool_size: usize,
+    eviction_policy: EvictionPolicy,
     min_replacement_fee_increase_percentage: u64,
     throttled_entity_mempool_count: u64,
     throttled_entity_live_blocks: u64,
+    op_ttl: Option<Duration>,
 }
 
 impl From<PoolConfig> for PoolInnerConfig {
@@ -51,9 +55,12 @@ impl From<PoolConfig> for PoolInnerConfig {
             entry_point: config.entry_point,
             chain_id: config.chain_id,
             max_size_of_pool_bytes: config.max_size_of_pool_bytes,
+            max_pool_size: config.max_pool_size,
+            eviction_policy: config.eviction_policy,
             min_replacement_fee_increase_percentage: config.min_replacement_fee_increase_percentage,
             throttled_entity_mempool_count: config.throttled_entity_mempool_count,
             throttled_entity_live_blocks: config.throttled_entity_live_blocks,
+            op_ttl: config.op_ttl,
         }
     }
 }
@@ -86,6 +93,9 @@ pub(crate) struct PoolInner {
     pool_size: SizeTracker,
     /// keeps track of the size of the removed cache in bytes
     cache_size: SizeTracker,
+    /// Timestamp of the latest block seen by the pool, used as the entry time of new operations.
+    /// `None` until the pool sees its first block.
+    block_timestamp: Option<Timestamp>,
 }
 
 impl PoolInner {
@@ -102,6 +112,7 @@ impl PoolInner {
             submission_id: 0,
             pool_size: SizeTracker::default(),
             cache_size: SizeTracker::default(),
+            block_timestamp: None,
         }
     }
 
@@ -144,7 +155,40 @@ impl PoolInner {
         paymaster_meta: Option<PaymasterMetadata>,
     ) -> MempoolResult<H256> {
         println!("HC pool add_operation {:?}", op);
-        let ret = self.add_operation_internal(Arc::new(op), None, paymaster_meta);
+        let ret = self.add_operation_internal(Arc::new(op), None, None, None, paymaster_meta);
+        self.update_metrics();
+        ret
+    }
+
+    /// Swaps the operation with hash `old_hash` for `op`, which must share its
+    /// sender and nonce. If `op` cannot be added, the old operation is restored.
+    pub(crate) fn replace_operation(
+        &mut self,
+        old_hash: H256,
+        op: PoolOperation,
+        paymaster_meta: Option<PaymasterMetadata>,
+    ) -> MempoolResult<H256> {
+        let old = match self.by_hash.get(&old_hash) {
+            Some(old) if old.uo().id() == op.uo.id() => old.clone(),
+            _ => return Err(MempoolError::ReplacementTargetNotFound(old_hash)),
+        };
+
+        let ret = self.add_operation_internal(Arc::new(op), None, None, None, paymaster_meta);
+        if ret.is_err() && !self.by_hash.contains_key(&old_hash) {
+            let paymaster_meta = old
+                .uo()
+                .paymaster()
+                .and_then(|paymaster| self.paymaster_metadata(paymaster));
+            if let Err(e) = self.add_operation_internal(
+                old.po,
+                Some(old.submission_id),
+                Some(old.added_at),
+                Some(old.entered_at),
+                paymaster_meta,
+            ) {
+                warn!("Failed to restore replaced operation {old_hash:?}: {e:?}");
+            }
+        }
         self.update_metrics();
         ret
     }
@@ -185,6 +229,120 @@ impl PoolInner {
         expired
     }
 
+    /// Removes all operations whose submission deadline is before `now`, returning the
+    /// hashes of the removed operations and their deadlines.
+    ///
+    /// NOTE: This method is O(n) where n is the number of operations in the pool.
+    /// It should be called sparingly (e.g. when a block is mined).
+    pub(crate) fn remove_past_submission_deadline(
+        &mut self,
+        now: Timestamp,
+    ) -> Vec<(H256, Timestamp)> {
+        let mut past_deadline = Vec::new();
+        for (hash, op) in &self.by_hash {
+            if let Some(deadline) = op.po.submission_deadline {
+                if deadline < now {
+                    past_deadline.push((*hash, deadline));
+                }
+            }
+        }
+
+        for (hash, _) in &past_deadline {
+            self.remove_operation_by_hash(*hash);
+        }
+
+        past_deadline
+    }
+
+    /// Sets the timestamp of the latest block, which is recorded as the entry time of
+    /// operations added afterwards.
+    ///
+    /// Operations added before the first block is seen have no known entry time, so
+    /// they are treated as entering the pool at this block rather than at timestamp 0.
+    pub(crate) fn set_block_timestamp(&mut self, block_timestamp: Timestamp) {
+        if self.block_timestamp.is_none() {
+            let hashes: Vec<_> = self.by_hash.keys().copied().collect();
+            for hash in hashes {
+                self.update_operation(hash, |op| {
+                    op.added_at = block_timestamp;
+                    op.entered_at = block_timestamp;
+                });
+            }
+        }
+        self.block_timestamp = Some(block_timestamp);
+    }
+
+    /// Removes all operations that entered the pool more than the configured TTL before
+    /// `now`, returning the hashes of the removed operations and their entry times.
+    /// Removes nothing if no TTL is configured.
+    ///
+    /// NOTE: This method is O(n) where n is the number of operations in the pool.
+    /// It should be called sparingly (e.g. when a block is mined).
+    pub(crate) fn remove_stale(&mut self, now: Timestamp) -> Vec<(H256, Timestamp)> {
+        let Some(ttl) = self.config.op_ttl.map(|ttl| ttl.as_secs()) else {
+            return vec![];
+        };
+        let mut stale = Vec::new();
+        for (hash, op) in &self.by_hash {
+            let age = now
+                .seconds_since_epoch()
+                .saturating_sub(op.added_at.seconds_since_epoch());
+            if age > ttl {
+                stale.push((*hash, op.added_at));
+            }
+        }
+
+        for (hash, _) in &stale {
+            self.remove_operation_by_hash(*hash);
+        }
+
+        stale
+    }
+
+    /// Resets the entry time of an operation to the latest block timestamp, so that
+    /// a resubmitted operation restarts its TTL.
+    pub(crate) fn refresh_operation(&mut self, hash: H256) {
+        let block_timestamp = self.block_timestamp.unwrap_or_default();
+        self.update_operation(hash, |op| op.added_at = block_timestamp);
+    }
+
+    /// Records a failed simulation of an operation. Returns the number of
+    /// consecutive failures, or `None` if the operation is not in the pool.
+    pub(crate) fn record_simulation_failure(&mut self, hash: H256) -> Option<u64> {
+        self.update_operation(hash, |op| op.simulation_failures += 1);
+        self.by_hash.get(&hash).map(|op| op.simulation_failures)
+    }
+
+    /// Records a simulation of an operation that errored for a reason other
+    /// than a validation rule violation. Returns the number of consecutive
+    /// errors, or `None` if the operation is not in the pool.
+    pub(crate) fn record_simulation_error(&mut self, hash: H256) -> Option<u64> {
+        self.update_operation(hash, |op| op.simulation_errors += 1);
+        self.by_hash.get(&hash).map(|op| op.simulation_errors)
+    }
+
+    /// Resets the failed and errored simulation counts of an operation, once a
+    /// builder was able to include it in a bundle.
+    pub(crate) fn reset_simulation_failures(&mut self, hash: H256) {
+        self.update_operation(hash, |op| {
+            op.simulation_failures = 0;
+            op.simulation_errors = 0;
+        });
+    }
+
+    /// Applies `update` to the operation with the given hash in every index. `update`
+    /// must not change the fields the operation is ordered by.
+    fn update_operation(&mut self, hash: H256, update: impl FnOnce(&mut OrderedPoolOperation)) {
+        let Some(op) = self.by_hash.get_mut(&hash) else {
+            return;
+        };
+        update(op);
+
+        let op = op.clone();
+        self.by_id.insert(op.uo().id(), op.clone());
+        self.best.replace(op);
+    }
+
     pub(crate) fn address_count(&self, address: &Address) -> usize {
         if let Some(entity) = self.count_by_address.get(address) {
             return entity.total();
@@ -269,6 +427,17 @@ impl PoolInner {
         ret
     }
 
+    /// Returns the time between a mined operation first entering the pool and
+    /// `mined_at`, or `None` if the operation is not in the mined cache.
+    pub(crate) fn inclusion_latency(&self, hash: H256, mined_at: Timestamp) -> Option<Duration> {
+        let (op, _) = self.mined_at_block_number_by_hash.get(&hash)?;
+        Some(Duration::from_secs(
+            mined_at
+                .seconds_since_epoch()
+                .saturating_sub(op.entered_at.seconds_since_epoch()),
+        ))
+    }
+
     pub(crate) fn unmine_operation(&mut self, mined_op: &MinedOp) -> Option<Arc<PoolOperation>> {
         let hash = mined_op.hash;
         let (op, block_number) = self.mined_at_block_number_by_hash.remove(&hash)?;
@@ -356,23 +525,31 @@ impl PoolInner {
         self.paymaster_balances.paymaster_exists(paymaster)
     }
 
-    pub(crate) fn update_paymaster_balances_after_update(
-        &mut self,
-        deposits: &Vec<DepositInfo>,
-        unmined_entity_deposits: &Vec<DepositInfo>,
-    ) {
-        for deposit in deposits {
-            self.paymaster_balances
-                .update_paymaster_balance_from_deposit(deposit.address, deposit.amount)
-        }
+    /// Sets the confirmed balance of the paymaster to the total carried by its
+    /// deposit event. Ops mined before the deposit are already accounted for in
+    /// the total, so deposits must be applied in order with mined ops.
+    pub(crate) fn set_paymaster_balance_from_deposit(&mut self, deposit: &DepositInfo) {
+        self.paymaster_balances
+            .set_paymaster_balance_from_deposit(deposit.address, deposit.amount)
+    }
 
-        for unmined_deposit in unmined_entity_deposits {
-            self.paymaster_balances
-                .update_paymaster_balance_after_deposit_reorg(
-                    unmined_deposit.address,
-                    unmined_deposit.amount,
-                )
-        }
+    /// Returns the paymasters with unmined deposits and no deposit on the new chain,
+    /// whose confirmed balance is no longer known and must be reloaded.
+    pub(crate) fn paymasters_with_unknown_balances(
+        &self,
+        deposits: &[DepositInfo],
+        unmined_entity_deposits: &[DepositInfo],
+    ) -> Vec<Address> {
+        unmined_entity_deposits
+            .iter()
+            .map(|d| d.address)
+            .filter(|address| {
+                self.paymaster_balances.paymaster_exists(*address)
+                    && !deposits.iter().any(|d| d.address == *address)
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
     }
 
     pub(crate) fn clear(&mut self) {
@@ -391,23 +568,33 @@ impl PoolInner {
     fn enforce_size(&mut self) -> anyhow::Result<Vec<H256>> {
         let mut removed = Vec::new();
 
-        while self.pool_size > self.config.max_size_of_pool_bytes {
-            if let Some(worst) = self.best.pop_last() {
-                let hash = worst
-                    .uo()
-                    .op_hash(self.config.entry_point, self.config.chain_id);
-
-                let _ = self
-                    .remove_operation_internal(hash, None)
-                    .context("should have removed the worst operation")?;
-
-                removed.push(hash);
-            }
+        while self.is_over_capacity() {
+            let worst = match self.config.eviction_policy {
+                EvictionPolicy::LowestFee => self.best.last(),
+                EvictionPolicy::Oldest => self.best.iter().min_by_key(|op| op.submission_id),
+            };
+            let Some(worst) = worst else {
+                break;
+            };
+            let hash = worst
+                .uo()
+                .op_hash(self.config.entry_point, self.config.chain_id);
+
+            let _ = self
+                .remove_operation_internal(hash, None)
+                .context("should have removed the worst operation")?;
+
+            removed.push(hash);
         }
 
         Ok(removed)
     }
 
+    fn is_over_capacity(&self) -> bool {
+        self.pool_size > self.config.max_size_of_pool_bytes
+            || (self.config.max_pool_size > 0 && self.by_hash.len() > self.config.max_pool_size)
+    }
+
     fn put_back_unmined_operation(
         &mut self,
         op: OrderedPoolOperation,
@@ -421,13 +608,21 @@ impl PoolInner {
             paymaster_meta = self.paymaster_metadata(paymaster);
         }
 
-        self.add_operation_internal(op.po, Some(op.submission_id), paymaster_meta)
+        self.add_operation_internal(
+            op.po,
+            Some(op.submission_id),
+            Some(op.added_at),
+            Some(op.entered_at),
+            paymaster_meta,
+        )
     }
 
     fn add_operation_internal(
         &mut self,
         op: Arc<PoolOperation>,
         submission_id: Option<u64>,
+        added_at: Option<Timestamp>,
+        entered_at: Option<Timestamp>,
         paymaster_meta: Option<PaymasterMetadata>,
     ) -> MempoolResult<H256> {
         // Check if operation already known or replacing an existing operation
@@ -442,9 +637,14 @@ impl PoolInner {
                 .add_or_update_balance(&op, &paymaster_meta)?;
         }
 
+        let block_timestamp = self.block_timestamp.unwrap_or_default();
         let pool_op = OrderedPoolOperation {
             po: op,
             submission_id: submission_id.unwrap_or_else(|| self.next_submission_id()),
+            added_at: added_at.unwrap_or(block_timestamp),
+            entered_at: entered_at.unwrap_or(block_timestamp),
+            simulation_failures: 0,
+            simulation_errors: 0,
         };
 
         // update counts
@@ -550,6 +750,14 @@ impl PoolInner {
 struct OrderedPoolOperation {
     po: Arc<PoolOperation>,
     submission_id: u64,
+    added_at: Timestamp,
+    /// Block timestamp at which the operation first entered the pool. Unlike
+    /// `added_at`, this is not reset when the operation is resubmitted.
+    entered_at: Timestamp,
+    /// Consecutive failed simulations reported by builders
+    simulation_failures: u64,
+    /// Consecutive errored simulations reported by builders
+    simulation_errors: u64,
 }
 
 impl OrderedPoolOperation {
@@ -735,6 +943,10 @@ mod tests {
             entry_point: pool.config.entry_point,
             sender,
             nonce: U256::from(nonce),
+            tx_hash: H256::zero(),
+            block_number: 0,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
         };
 
         pool.mine_operation(&mined_op, 1);
@@ -767,6 +979,10 @@ mod tests {
             entry_point: pool.config.entry_point,
             sender,
             nonce: U256::from(nonce),
+            tx_hash: H256::zero(),
+            block_number: 0,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
         };
 
         pool.mine_operation(&mined_op, 1);
@@ -776,6 +992,69 @@ mod tests {
         assert!(pool.best.is_empty());
     }
 
+    #[test]
+    fn unmine_op_preserves_entry_timestamp() {
+        let mut pool = PoolInner::new(conf());
+        let sender = Address::random();
+        let op = create_op(sender, 0, 1);
+        let hash = op.uo.op_hash(pool.config.entry_point, pool.config.chain_id);
+
+        pool.set_block_timestamp(Timestamp::from(100));
+        pool.add_operation(op, None).unwrap();
+
+        let mined_op = MinedOp {
+            paymaster: None,
+            actual_gas_cost: U256::zero(),
+            hash,
+            entry_point: pool.config.entry_point,
+            sender,
+            nonce: U256::zero(),
+            tx_hash: H256::zero(),
+            block_number: 1,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
+        };
+        pool.set_block_timestamp(Timestamp::from(200));
+        pool.mine_operation(&mined_op, 1);
+        assert!(pool.by_hash.is_empty());
+
+        // Reorged out at a later block
+        pool.set_block_timestamp(Timestamp::from(300));
+        assert!(pool.unmine_operation(&mined_op).is_some());
+
+        let op = &pool.by_hash[&hash];
+        assert_eq!(op.added_at, Timestamp::from(100));
+        assert_eq!(op.entered_at, Timestamp::from(100));
+        assert!(pool.mined_at_block_number_by_hash.is_empty());
+    }
+
+    #[test]
+    fn unmine_op_after_forgotten_is_not_put_back() {
+        let mut pool = PoolInner::new(conf());
+        let sender = Address::random();
+        let op = create_op(sender, 0, 1);
+        let hash = op.uo.op_hash(pool.config.entry_point, pool.config.chain_id);
+        pool.add_operation(op, None).unwrap();
+
+        let mined_op = MinedOp {
+            paymaster: None,
+            actual_gas_cost: U256::zero(),
+            hash,
+            entry_point: pool.config.entry_point,
+            sender,
+            nonce: U256::zero(),
+            tx_hash: H256::zero(),
+            block_number: 1,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
+        };
+        pool.mine_operation(&mined_op, 1);
+        pool.forget_mined_operations_before_block(2);
+
+        assert!(pool.unmine_operation(&mined_op).is_none());
+        assert!(pool.by_hash.is_empty());
+    }
+
     #[test]
     fn remove_aggregator() {
         let mut pool = PoolInner::new(conf());
@@ -907,6 +1186,70 @@ mod tests {
         assert!(result.is_ok(), "{:?}", result.err());
     }
 
+    #[test]
+    fn pool_at_capacity_admits_higher_fee() {
+        let mut args = conf();
+        args.max_pool_size = 3;
+        let mut pool = PoolInner::new(args);
+        let mut hashes = vec![];
+        for i in 0..3 {
+            let op = create_op(Address::random(), 0, i + 1);
+            hashes.push(pool.add_operation(op, None).unwrap());
+        }
+
+        let op = create_op(Address::random(), 0, 10);
+        let hash = pool.add_operation(op, None).unwrap();
+
+        assert_eq!(pool.by_hash.len(), 3);
+        assert!(pool.by_hash.contains_key(&hash));
+        // lowest fee op should have been evicted
+        assert!(!pool.by_hash.contains_key(&hashes[0]));
+        assert!(pool.by_hash.contains_key(&hashes[1]));
+        assert!(pool.by_hash.contains_key(&hashes[2]));
+    }
+
+    #[test]
+    fn pool_at_capacity_rejects_lower_fee() {
+        let mut args = conf();
+        args.max_pool_size = 3;
+        let mut pool = PoolInner::new(args);
+        let mut hashes = vec![];
+        for i in 0..3 {
+            let op = create_op(Address::random(), 0, i + 2);
+            hashes.push(pool.add_operation(op, None).unwrap());
+        }
+
+        let op = create_op(Address::random(), 0, 1);
+        let result = pool.add_operation(op, None);
+
+        assert!(matches!(result, Err(MempoolError::DiscardedOnInsert)));
+        assert_eq!(pool.by_hash.len(), 3);
+        for hash in hashes {
+            assert!(pool.by_hash.contains_key(&hash));
+        }
+    }
+
+    #[test]
+    fn pool_at_capacity_evicts_oldest() {
+        let mut args = conf();
+        args.max_pool_size = 3;
+        args.eviction_policy = EvictionPolicy::Oldest;
+        let mut pool = PoolInner::new(args);
+        let mut hashes = vec![];
+        for i in 0..3 {
+            let op = create_op(Address::random(), 0, 10 - i);
+            hashes.push(pool.add_operation(op, None).unwrap());
+        }
+
+        // a lower fee op is still admitted, evicting the first submitted op
+        let op = create_op(Address::random(), 0, 1);
+        let hash = pool.add_operation(op, None).unwrap();
+
+        assert_eq!(pool.by_hash.len(), 3);
+        assert!(pool.by_hash.contains_key(&hash));
+        assert!(!pool.by_hash.contains_key(&hashes[0]));
+    }
+
     #[test]
     fn replace_op_underpriced() {
         let mut pool = PoolInner::new(conf());
@@ -932,7 +1275,11 @@ mod tests {
             pool.pool_size,
             OrderedPoolOperation {
                 po: Arc::new(po1),
-                submission_id: 0
+                submission_id: 0,
+                added_at: Timestamp::default(),
+                entered_at: Timestamp::default(),
+                simulation_failures: 0,
+                simulation_errors: 0,
             }
             .mem_size()
         );
@@ -970,7 +1317,11 @@ mod tests {
             pool.pool_size,
             OrderedPoolOperation {
                 po: Arc::new(po2),
-                submission_id: 0
+                submission_id: 0,
+                added_at: Timestamp::default(),
+                entered_at: Timestamp::default(),
+                simulation_failures: 0,
+                simulation_errors: 0,
             }
             .mem_size()
         );
@@ -1030,14 +1381,165 @@ mod tests {
         assert!(res.contains(&(po3.uo.op_hash(conf.entry_point, conf.chain_id), 9.into())));
     }
 
+    #[test]
+    fn test_remove_stale() {
+        let conf = conf();
+        let mut pool = PoolInner::new(conf.clone());
+        pool.set_block_timestamp(100.into());
+        let po1 = create_op(Address::random(), 0, 10);
+        let _ = pool.add_operation(po1.clone(), None).unwrap();
+
+        pool.set_block_timestamp(150.into());
+        let po2 = create_op(Address::random(), 0, 10);
+        let _ = pool.add_operation(po2.clone(), None).unwrap();
+
+        // po1 is exactly at its TTL, so nothing is evicted yet
+        assert!(pool.remove_stale(160.into()).is_empty());
+
+        let res = pool.remove_stale(161.into());
+        assert_eq!(
+            res,
+            vec![(
+                po1.uo.op_hash(conf.entry_point, conf.chain_id),
+                Timestamp::from(100)
+            )]
+        );
+        check_map_entry(
+            pool.by_hash
+                .get(&po2.uo.op_hash(conf.entry_point, conf.chain_id)),
+            Some(&po2),
+        );
+    }
+
+    #[test]
+    fn test_remove_stale_without_ttl() {
+        let mut pool = PoolInner::new(PoolInnerConfig {
+            op_ttl: None,
+            ..conf()
+        });
+        pool.set_block_timestamp(100.into());
+        let _ = pool
+            .add_operation(create_op(Address::random(), 0, 10), None)
+            .unwrap();
+
+        assert!(pool.remove_stale(1_000_000.into()).is_empty());
+        assert_eq!(pool.by_hash.len(), 1);
+    }
+
+    #[test]
+    fn test_ops_added_before_first_block_enter_at_first_block() {
+        let conf = conf();
+        let mut pool = PoolInner::new(conf.clone());
+        let po1 = create_op(Address::random(), 0, 10);
+        let hash = pool.add_operation(po1, None).unwrap();
+
+        // the first block is long past the TTL counted from timestamp 0
+        pool.set_block_timestamp(1_000.into());
+        assert!(pool.remove_stale(1_000.into()).is_empty());
+        assert_eq!(pool.by_hash[&hash].entered_at, Timestamp::from(1_000));
+
+        // later blocks don't move the entry time
+        pool.set_block_timestamp(1_010.into());
+        assert_eq!(
+            pool.remove_stale(1_061.into()),
+            vec![(hash, Timestamp::from(1_000))]
+        );
+    }
+
+    #[test]
+    fn test_refresh_resets_ttl() {
+        let conf = conf();
+        let mut pool = PoolInner::new(conf.clone());
+        pool.set_block_timestamp(100.into());
+        let po1 = create_op(Address::random(), 0, 10);
+        let hash = pool.add_operation(po1.clone(), None).unwrap();
+
+        pool.set_block_timestamp(150.into());
+        pool.refresh_operation(hash);
+
+        assert!(pool.remove_stale(161.into()).is_empty());
+        assert_eq!(
+            pool.remove_stale(211.into()),
+            vec![(hash, Timestamp::from(150))]
+        );
+    }
+
+    #[test]
+    fn test_inclusion_latency() {
+        let conf = conf();
+        let mut pool = PoolInner::new(conf.clone());
+        pool.set_block_timestamp(100.into());
+        let sender = Address::random();
+        let po1 = create_op(sender, 0, 10);
+        let hash = pool.add_operation(po1, None).unwrap();
+
+        // resubmitting the op does not reset its entry time
+        pool.set_block_timestamp(130.into());
+        pool.refresh_operation(hash);
+        assert_eq!(pool.inclusion_latency(hash, 142.into()), None);
+
+        let mined_op = MinedOp {
+            paymaster: None,
+            actual_gas_cost: U256::zero(),
+            hash,
+            entry_point: conf.entry_point,
+            sender,
+            nonce: U256::zero(),
+            tx_hash: H256::zero(),
+            block_number: 1,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
+        };
+        pool.mine_operation(&mined_op, 1);
+
+        assert_eq!(
+            pool.inclusion_latency(hash, 142.into()),
+            Some(Duration::from_secs(42))
+        );
+    }
+
+    #[test]
+    fn replacement_fee_bump_threshold() {
+        let sender = Address::random();
+        let config = PoolInnerConfig {
+            min_replacement_fee_increase_percentage: 25,
+            ..conf()
+        };
+
+        // just below the required bump
+        let mut pool = PoolInner::new(config.clone());
+        pool.add_operation(create_op(sender, 0, 100), None).unwrap();
+        assert!(matches!(
+            pool.add_operation(create_op(sender, 0, 124), None),
+            Err(MempoolError::ReplacementUnderpriced(_, _))
+        ));
+
+        // exactly at the required bump
+        let mut pool = PoolInner::new(config.clone());
+        pool.add_operation(create_op(sender, 0, 100), None).unwrap();
+        pool.add_operation(create_op(sender, 0, 125), None).unwrap();
+        assert_eq!(pool.by_hash.len(), 1);
+
+        // above the required bump
+        let mut pool = PoolInner::new(config);
+        pool.add_operation(create_op(sender, 0, 100), None).unwrap();
+        let replacement = create_op(sender, 0, 150);
+        let hash = pool.add_operation(replacement.clone(), None).unwrap();
+        assert_eq!(pool.by_hash.len(), 1);
+        check_map_entry(pool.by_hash.get(&hash), Some(&replacement));
+    }
+
     fn conf() -> PoolInnerConfig {
         PoolInnerConfig {
             entry_point: Address::random(),
             chain_id: 1,
             min_replacement_fee_increase_percentage: 10,
             max_size_of_pool_bytes: 20 * mem_size_of_ordered_pool_op(),
+            max_pool_size: 0,
+            eviction_policy: EvictionPolicy::LowestFee,
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
+            op_ttl: Some(Duration::from_secs(60)),
         }
     }
 
@@ -1045,6 +1547,10 @@ mod tests {
         OrderedPoolOperation {
             po: Arc::new(create_op(Address::random(), 1, 1)),
             submission_id: 1,
+            added_at: Timestamp::default(),
+            entered_at: Timestamp::default(),
+            simulation_failures: 0,
+            simulation_errors: 0,
         }
         .mem_size()
     }
@@ -23,6 +23,9 @@ use parking_lot::RwLock;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use tokio::time::interval;
 
+/// How often reputation counts are decayed
+const REPUTATION_UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 /// Reputation status for an entity
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ReputationStatus {
@@ -135,7 +138,7 @@ impl HourlyMovingAverageReputation {
 
     // run the reputation hourly update job
     pub(crate) async fn run(&self) {
-        let mut tick = interval(Duration::from_secs(60 * 60));
+        let mut tick = interval(REPUTATION_UPDATE_INTERVAL);
         loop {
             tick.tick().await;
             self.reputation.write().hourly_update();
@@ -205,6 +208,9 @@ pub(crate) struct ReputationParams {
     inclusion_rate_factor: u64,
     throttling_slack: u64,
     ban_slack: u64,
+    // If set, counts decay continuously with this half-life instead of by
+    // 1/24 every hour
+    decay_half_life: Option<Duration>,
 }
 
 impl Default for ReputationParams {
@@ -217,6 +223,7 @@ impl Default for ReputationParams {
             inclusion_rate_factor: 10,
             throttling_slack: 10,
             ban_slack: 50,
+            decay_half_life: None,
         }
     }
 }
@@ -233,6 +240,15 @@ impl ReputationParams {
             ..Self::default()
         }
     }
+
+    /// Decays the ops seen and ops included counts with the given half-life,
+    /// rather than the spec's hourly moving average, if set.
+    pub(crate) fn with_decay_half_life(self, decay_half_life: Option<Duration>) -> Self {
+        Self {
+            decay_half_life,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -335,14 +351,29 @@ impl AddressReputation {
     }
 
     fn hourly_update(&mut self) {
-        for count in self.counts.values_mut() {
-            count.ops_seen -= count.ops_seen / 24;
-            count.ops_included -= count.ops_included / 24;
+        match self.params.decay_half_life {
+            Some(half_life) => self.decay(REPUTATION_UPDATE_INTERVAL, half_life),
+            None => {
+                for count in self.counts.values_mut() {
+                    count.ops_seen -= count.ops_seen / 24;
+                    count.ops_included -= count.ops_included / 24;
+                }
+            }
         }
         self.counts
             .retain(|_, count| count.ops_seen > 0 || count.ops_included > 0);
     }
 
+    /// Decays the counts by the fraction that `half_life` leaves after
+    /// `elapsed`. Counts are rounded down so that they reach zero.
+    fn decay(&mut self, elapsed: Duration, half_life: Duration) {
+        let remaining = 0.5_f64.powf(elapsed.as_secs_f64() / half_life.as_secs_f64());
+        for count in self.counts.values_mut() {
+            count.ops_seen = (count.ops_seen as f64 * remaining) as u64;
+            count.ops_included = (count.ops_included as f64 * remaining) as u64;
+        }
+    }
+
     fn clear(&mut self) {
         self.counts.clear();
     }
@@ -447,6 +478,47 @@ mod tests {
         assert_eq!(counts.ops_included, 1000 - 1000 / 24);
     }
 
+    #[test]
+    fn hourly_update_with_half_life() {
+        let addr = Address::random();
+        let mut reputation = AddressReputation::new(
+            ReputationParams::bundler_default()
+                .with_decay_half_life(Some(Duration::from_secs(60 * 60))),
+        );
+        reputation.set_reputation(addr, 1024, 512);
+
+        reputation.hourly_update();
+        let counts = reputation.counts.get(&addr).unwrap();
+        assert_eq!(counts.ops_seen, 512);
+        assert_eq!(counts.ops_included, 256);
+
+        // Counts keep halving until they age out entirely
+        for _ in 0..9 {
+            reputation.hourly_update();
+        }
+        let counts = reputation.counts.get(&addr).unwrap();
+        assert_eq!(counts.ops_seen, 1);
+        assert_eq!(counts.ops_included, 0);
+
+        reputation.hourly_update();
+        assert!(reputation.counts.get(&addr).is_none());
+    }
+
+    #[test]
+    fn decay_with_longer_half_life() {
+        let addr = Address::random();
+        let mut reputation = AddressReputation::new(ReputationParams::bundler_default());
+        reputation.set_reputation(addr, 1000, 100);
+
+        reputation.decay(
+            Duration::from_secs(6 * 60 * 60),
+            Duration::from_secs(3 * 60 * 60),
+        );
+        let counts = reputation.counts.get(&addr).unwrap();
+        assert_eq!(counts.ops_seen, 250);
+        assert_eq!(counts.ops_included, 25);
+    }
+
     #[test]
     fn test_blocklist() {
         let addr = Address::random();
@@ -11,33 +11,48 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
 use ethers::{
     types::{Address, H256, U256},
     utils::format_units,
 };
+use futures::{stream, StreamExt};
 use itertools::Itertools;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use rundler_provider::{EntryPoint, PaymasterHelper, ProviderResult};
-use rundler_sim::{Prechecker, Simulator};
-use rundler_types::{Entity, EntityUpdate, EntityUpdateType, UserOperation};
+use rundler_sim::{Prechecker, SimulationError, Simulator, ViolationError};
+use rundler_types::{Entity, EntityUpdate, EntityUpdateType, Timestamp, UserOperation};
 use rundler_utils::emit::WithEntryPoint;
 use tokio::sync::broadcast;
 use tonic::async_trait;
 use tracing::info;
 
 use super::{
+    admission::{AdmissionFilter, NoopAdmissionFilter},
     error::{MempoolError, MempoolResult},
     pool::PoolInner,
     reputation::{Reputation, ReputationManager, ReputationStatus},
-    Mempool, OperationOrigin, PaymasterMetadata, PoolConfig, PoolOperation, StakeInfo, StakeStatus,
+    Mempool, OpStatus, OperationOrigin, PaymasterMetadata, PoolConfig, PoolOperation, StakeInfo,
+    StakeStatus,
 };
 use crate::{
     chain::{ChainUpdate, DepositInfo},
     emit::{EntityReputation, EntityStatus, EntitySummary, OpPoolEvent, OpRemovalReason},
 };
 
+/// Maximum number of operation statuses remembered after the operations are
+/// submitted, mined, or dropped
+const MAX_OP_STATUS_HISTORY: usize = 10_000;
+
+/// Maximum number of operations simulated concurrently when re-validating the
+/// pool
+const MAX_CONCURRENT_REVALIDATIONS: usize = 32;
+
 /// User Operation Mempool
 ///
 /// Wrapper around a pool object that implements thread-safety
@@ -53,11 +68,13 @@ pub(crate) struct UoPool<
     config: PoolConfig,
     reputation: Arc<R>,
     state: RwLock<UoPoolState>,
+    op_statuses: Mutex<OpStatusHistory>,
     event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
     prechecker: P,
     simulator: S,
     entry_point: E,
     paymaster_helper: PH,
+    admission_filter: Arc<dyn AdmissionFilter>,
 }
 
 struct UoPoolState {
@@ -66,6 +83,36 @@ struct UoPoolState {
     block_number: u64,
 }
 
+/// Statuses of operations that were submitted, mined, or dropped, keeping only
+/// the most recently recorded ones
+#[derive(Default)]
+struct OpStatusHistory {
+    statuses: HashMap<H256, OpStatus>,
+    order: VecDeque<H256>,
+}
+
+impl OpStatusHistory {
+    fn get(&self, hash: H256) -> Option<OpStatus> {
+        self.statuses.get(&hash).copied()
+    }
+
+    fn set(&mut self, hash: H256, status: OpStatus) {
+        if self.statuses.insert(hash, status).is_none() {
+            self.order.push_back(hash);
+            if self.order.len() > MAX_OP_STATUS_HISTORY {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.statuses.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.statuses.clear();
+        self.order.clear();
+    }
+}
+
 impl<R, P, S, E, PH> UoPool<R, P, S, E, PH>
 where
     R: ReputationManager,
@@ -91,15 +138,30 @@ where
                 throttled_ops: HashSet::new(),
                 block_number: 0,
             }),
+            op_statuses: Mutex::new(OpStatusHistory::default()),
             event_sender,
             prechecker,
             simulator,
             entry_point,
             paymaster_helper,
+            admission_filter: Arc::new(NoopAdmissionFilter),
         }
     }
 
+    /// Runs `admission_filter` on each new operation before standard validation
+    pub(crate) fn with_admission_filter(
+        mut self,
+        admission_filter: Arc<dyn AdmissionFilter>,
+    ) -> Self {
+        self.admission_filter = admission_filter;
+        self
+    }
+
     fn emit(&self, event: OpPoolEvent) {
+        // every removal other than mining is reported through this event
+        if let OpPoolEvent::RemovedOp { op_hash, .. } = &event {
+            self.op_statuses.lock().set(*op_hash, OpStatus::Dropped);
+        }
         let _ = self.event_sender.send(WithEntryPoint {
             entry_point: self.config.entry_point,
             event,
@@ -137,195 +199,88 @@ where
         UoPoolMetrics::increment_removed_operations(count, self.config.entry_point);
         UoPoolMetrics::increment_removed_entities(self.config.entry_point);
     }
-}
 
-#[async_trait]
-impl<R, P, S, E, PH> Mempool for UoPool<R, P, S, E, PH>
-where
-    R: ReputationManager,
-    P: Prechecker,
-    S: Simulator,
-    E: EntryPoint,
-    PH: PaymasterHelper,
-{
-    async fn on_chain_update(&self, update: &ChainUpdate) {
-        {
-            let deduped_ops = update.deduped_ops();
-            let mined_ops = deduped_ops
-                .mined_ops
-                .iter()
-                .filter(|op| op.entry_point == self.config.entry_point);
+    async fn reload_confirmed_paymaster_balances(
+        &self,
+        paymaster_addresses: Vec<Address>,
+    ) -> MempoolResult<()> {
+        let balances = self
+            .paymaster_helper
+            .get_balances(paymaster_addresses.clone())
+            .await?;
 
-            let deposits: Vec<DepositInfo> = update
-                .entity_deposits
-                .iter()
-                .filter(|d| d.entrypoint == self.config.entry_point)
-                .cloned()
-                .collect();
+        self.state
+            .write()
+            .pool
+            .set_confirmed_paymaster_balances(&paymaster_addresses, &balances);
 
-            let unmined_entity_deposits: Vec<DepositInfo> = update
-                .unmined_entity_deposits
-                .iter()
-                .filter(|d| d.entrypoint == self.config.entry_point)
-                .cloned()
-                .collect();
+        Ok(())
+    }
 
-            let unmined_ops = deduped_ops
-                .unmined_ops
-                .iter()
-                .filter(|op| op.entry_point == self.config.entry_point);
-            let mut mined_op_count = 0;
-            let mut unmined_op_count = 0;
+    /// Re-simulates every operation in the pool and removes the ones that no
+    /// longer pass validation.
+    async fn revalidate_operations(&self, block_number: u64) {
+        let ops: Vec<_> = self.state.read().pool.best_operations().collect();
+        let results: Vec<_> = stream::iter(&ops)
+            .map(|op| {
+                self.simulator
+                    .simulate_validation(op.uo.clone(), None, None)
+            })
+            .buffered(MAX_CONCURRENT_REVALIDATIONS)
+            .collect()
+            .await;
 
-            if update.reorg_larger_than_history {
-                let _ = self.reset_confirmed_paymaster_balances().await;
-            }
+        let invalid_hashes: Vec<_> = ops
+            .iter()
+            .zip(results)
+            .filter(|(_, result)| result.is_err())
+            .map(|(op, _)| op.uo.op_hash(self.config.entry_point, self.config.chain_id))
+            .collect();
 
+        let mut removed_hashes = vec![];
+        {
             let mut state = self.state.write();
-            state
-                .pool
-                .update_paymaster_balances_after_update(&deposits, &unmined_entity_deposits);
-
-            for op in mined_ops {
-                if op.entry_point != self.config.entry_point {
-                    continue;
-                }
-
-                // Remove throttled ops that were included in the block
-                state.throttled_ops.remove(&op.hash);
-
-                if let Some(op) = state.pool.mine_operation(op, update.latest_block_number) {
-                    // Only account for an entity once
-                    for entity_addr in op.entities().map(|e| e.address).unique() {
-                        self.reputation.add_included(entity_addr);
-                    }
-                    mined_op_count += 1;
+            for hash in invalid_hashes {
+                if state.pool.remove_operation_by_hash(hash).is_some() {
+                    state.throttled_ops.remove(&hash);
+                    removed_hashes.push(hash);
                 }
             }
-            for op in unmined_ops {
-                if op.entry_point != self.config.entry_point {
-                    continue;
-                }
+        }
 
-                if let Some(op) = state.pool.unmine_operation(op) {
-                    // Only account for an entity once
-                    for entity_addr in op.entities().map(|e| e.address).unique() {
-                        self.reputation.remove_included(entity_addr);
-                    }
-                    unmined_op_count += 1;
-                }
-            }
-            if mined_op_count > 0 {
-                info!(
-                "{mined_op_count} op(s) mined on entry point {:?} when advancing to block with number {}, hash {:?}.",
-                self.config.entry_point,
-                update.latest_block_number,
-                update.latest_block_hash,
-            );
-            }
-            if unmined_op_count > 0 {
-                info!(
-                "{unmined_op_count} op(s) unmined in reorg on entry point {:?} when advancing to block with number {}, hash {:?}.",
-                self.config.entry_point,
-                update.latest_block_number,
-                update.latest_block_hash,
-            );
-            }
-            UoPoolMetrics::update_ops_seen(
-                mined_op_count as isize - unmined_op_count as isize,
+        if !removed_hashes.is_empty() {
+            info!(
+                "{} op(s) failed re-validation after reorg on entry point {:?} at block {block_number}.",
+                removed_hashes.len(),
                 self.config.entry_point,
             );
-            UoPoolMetrics::increment_unmined_operations(unmined_op_count, self.config.entry_point);
-
-            state
-                .pool
-                .forget_mined_operations_before_block(update.earliest_remembered_block_number);
-            // Remove throttled ops that are too old
-            let mut to_remove = HashSet::new();
-            for hash in state.throttled_ops.iter() {
-                let block_seen = state
-                    .pool
-                    .get_operation_by_hash(*hash)
-                    .map(|po| po.sim_block_number);
-                if let Some(block) = block_seen {
-                    if update.latest_block_number - block > self.config.throttled_entity_live_blocks
-                    {
-                        to_remove.insert((*hash, block));
-                    }
-                }
-            }
-            for (hash, added_at_block) in to_remove {
-                state.pool.remove_operation_by_hash(hash);
-                state.throttled_ops.remove(&hash);
-                self.emit(OpPoolEvent::RemovedOp {
-                    op_hash: hash,
-                    reason: OpRemovalReason::ThrottledAndOld {
-                        added_at_block_number: added_at_block,
-                        current_block_number: update.latest_block_number,
-                    },
-                })
-            }
-
-            // expire old UOs
-            let expired = state.pool.remove_expired(update.latest_block_timestamp);
-            for (hash, until) in expired {
-                self.emit(OpPoolEvent::RemovedOp {
-                    op_hash: hash,
-                    reason: OpRemovalReason::Expired { valid_until: until },
-                })
-            }
-
-            state.block_number = update.latest_block_number;
         }
-
-        // update required bundle fees and update metrics
-        if let Ok((bundle_fees, base_fee)) = self.prechecker.update_fees().await {
-            let max_fee = match format_units(bundle_fees.max_fee_per_gas, "gwei") {
-                Ok(s) => s.parse::<f64>().unwrap_or_default(),
-                Err(_) => 0.0,
-            };
-            UoPoolMetrics::current_max_fee_gwei(max_fee);
-
-            let max_priority_fee = match format_units(bundle_fees.max_priority_fee_per_gas, "gwei")
-            {
-                Ok(s) => s.parse::<f64>().unwrap_or_default(),
-                Err(_) => 0.0,
-            };
-            UoPoolMetrics::current_max_priority_fee_gwei(max_priority_fee);
-
-            let base_fee = match format_units(base_fee, "gwei") {
-                Ok(s) => s.parse::<f64>().unwrap_or_default(),
-                Err(_) => 0.0,
-            };
-            UoPoolMetrics::current_base_fee(base_fee);
+        UoPoolMetrics::increment_removed_operations(removed_hashes.len(), self.config.entry_point);
+        for op_hash in removed_hashes {
+            self.emit(OpPoolEvent::RemovedOp {
+                op_hash,
+                reason: OpRemovalReason::FailedRevalidation { block_number },
+            })
         }
     }
 
-    fn entry_point(&self) -> Address {
-        self.config.entry_point
-    }
-
-    async fn reset_confirmed_paymaster_balances(&self) -> MempoolResult<()> {
-        let paymaster_addresses = self.state.read().pool.paymaster_addresses();
-
-        let balances = self
-            .paymaster_helper
-            .get_balances(paymaster_addresses.clone())
-            .await?;
-
-        self.state
-            .write()
-            .pool
-            .set_confirmed_paymaster_balances(&paymaster_addresses, &balances);
-
-        Ok(())
-    }
-
-    async fn add_operation(
+    /// Validates and adds an operation to the pool. If `replaces` is set, the
+    /// operation must replace the pool operation with that hash, which is
+    /// swapped out atomically once validation passes.
+    async fn add_operation_internal(
         &self,
         origin: OperationOrigin,
         op: UserOperation,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+        replaces: Option<H256>,
     ) -> MempoolResult<H256> {
+        // Run custom admission logic before any standard validation
+        self.admission_filter
+            .check(&op)
+            .await
+            .map_err(MempoolError::AdmissionRejected)?;
+
         // TODO(danc) aggregator reputation is not implemented
         // TODO(danc) catch ops with aggregators prior to simulation and reject
 
@@ -364,7 +319,18 @@ where
 
         // Check if op is already known or replacing another, and if so, ensure its fees are high enough
         // do this before simulation to save resources
-        let replacement = self.state.read().pool.check_replacement(&op)?;
+        let replacement = self.state.read().pool.check_replacement(&op);
+        if let Err(MempoolError::OperationAlreadyKnown) = replacement {
+            // Resubmitting a known op restarts its TTL
+            let hash = op.op_hash(self.config.entry_point, self.config.chain_id);
+            self.state.write().pool.refresh_operation(hash);
+        }
+        let replacement = replacement?;
+        if let Some(old_hash) = replaces {
+            if replacement != Some(old_hash) {
+                return Err(MempoolError::ReplacementTargetNotFound(old_hash));
+            }
+        }
         // Check if op violates the STO-040 spec rule
         self.state.read().pool.check_multiple_roles_violation(&op)?;
 
@@ -388,7 +354,11 @@ where
         let sim_result = self
             .simulator
             .simulate_validation(op.clone(), None, None)
-            .await?;
+            .await
+            .map_err(|error| {
+                UoPoolMetrics::increment_simulation_violations(&error, self.config.entry_point);
+                error
+            })?;
 
         // No aggregators supported for now
         if let Some(agg) = &sim_result.aggregator {
@@ -413,6 +383,8 @@ where
             entities_needing_stake: sim_result.entities_needing_stake,
             account_is_staked: sim_result.account_is_staked,
             entity_infos: sim_result.entity_infos,
+            submission_deadline,
+            bundler_tip: bundler_tip.unwrap_or_default(),
         };
 
         // Check sender count in mempool. If sender has too many operations, must be staked
@@ -446,9 +418,20 @@ where
         // Add op to pool
         let hash = {
             let mut state = self.state.write();
-            let hash = state
-                .pool
-                .add_operation(pool_op.clone(), paymaster_metadata)?;
+            let hash = match replaces {
+                Some(old_hash) => {
+                    let hash = state.pool.replace_operation(
+                        old_hash,
+                        pool_op.clone(),
+                        paymaster_metadata,
+                    )?;
+                    state.throttled_ops.remove(&old_hash);
+                    hash
+                }
+                None => state
+                    .pool
+                    .add_operation(pool_op.clone(), paymaster_metadata)?,
+            };
             if throttled {
                 state.throttled_ops.insert(hash);
             }
@@ -483,31 +466,319 @@ where
 
         Ok(hash)
     }
+}
 
-    fn remove_operations(&self, hashes: &[H256]) {
-        let mut count = 0;
-        let mut removed_hashes = vec![];
-        {
-            let mut state = self.state.write();
-            for hash in hashes {
-                if state.pool.remove_operation_by_hash(*hash).is_some() {
-                    count += 1;
-                    removed_hashes.push(*hash);
-                }
-            }
-        }
+#[async_trait]
+impl<R, P, S, E, PH> Mempool for UoPool<R, P, S, E, PH>
+where
+    R: ReputationManager,
+    P: Prechecker,
+    S: Simulator,
+    E: EntryPoint,
+    PH: PaymasterHelper,
+{
+    async fn on_chain_update(&self, update: &ChainUpdate) {
+        let unknown_paymaster_balances = {
+            let deduped_ops = update.deduped_ops();
+            let mut mined_ops: Vec<_> = deduped_ops
+                .mined_ops
+                .iter()
+                .filter(|op| op.entry_point == self.config.entry_point)
+                .collect();
+            mined_ops.sort_by_key(|op| (op.block_number, op.log_index));
 
-        for hash in removed_hashes {
-            self.emit(OpPoolEvent::RemovedOp {
-                op_hash: hash,
-                reason: OpRemovalReason::Requested,
-            })
-        }
-        UoPoolMetrics::increment_removed_operations(count, self.config.entry_point);
-    }
+            let mut deposits: Vec<DepositInfo> = update
+                .entity_deposits
+                .iter()
+                .filter(|d| d.entrypoint == self.config.entry_point)
+                .cloned()
+                .collect();
+            deposits.sort_by_key(|d| (d.block_number, d.log_index));
 
-    fn update_entity(&self, update: EntityUpdate) {
-        let entity = update.entity;
+            let unmined_entity_deposits: Vec<DepositInfo> = update
+                .unmined_entity_deposits
+                .iter()
+                .filter(|d| d.entrypoint == self.config.entry_point)
+                .cloned()
+                .collect();
+
+            let unmined_ops = deduped_ops
+                .unmined_ops
+                .iter()
+                .filter(|op| op.entry_point == self.config.entry_point);
+            let mut mined_op_count = 0;
+            let mut unmined_op_count = 0;
+
+            if update.reorg_larger_than_history || update.reorg_exceeds_reset_threshold {
+                let _ = self.reset_confirmed_paymaster_balances().await;
+            }
+
+            let mut state = self.state.write();
+            let unknown_paymaster_balances = state
+                .pool
+                .paymasters_with_unknown_balances(&deposits, &unmined_entity_deposits);
+
+            // Deposit events carry the paymaster's new total, which already
+            // accounts for ops mined before them, so deposits and mined ops are
+            // applied in block and log order
+            let mut pending_deposits = deposits.iter().peekable();
+            for op in mined_ops {
+                while let Some(deposit) = pending_deposits
+                    .next_if(|d| (d.block_number, d.log_index) < (op.block_number, op.log_index))
+                {
+                    state.pool.set_paymaster_balance_from_deposit(deposit);
+                }
+
+                // Remove throttled ops that were included in the block
+                state.throttled_ops.remove(&op.hash);
+                self.op_statuses.lock().set(
+                    op.hash,
+                    OpStatus::Mined {
+                        tx_hash: op.tx_hash,
+                        block_number: op.block_number,
+                    },
+                );
+
+                let (hash, mined_at) = (op.hash, op.block_timestamp);
+                if let Some(op) = state.pool.mine_operation(op, update.latest_block_number) {
+                    if let Some(latency) = state.pool.inclusion_latency(hash, mined_at) {
+                        UoPoolMetrics::record_inclusion_latency(latency, self.config.entry_point);
+                    }
+                    // Only account for an entity once
+                    for entity_addr in op.entities().map(|e| e.address).unique() {
+                        self.reputation.add_included(entity_addr);
+                    }
+                    mined_op_count += 1;
+                }
+            }
+            for deposit in pending_deposits {
+                state.pool.set_paymaster_balance_from_deposit(deposit);
+            }
+            for op in unmined_ops {
+                if op.entry_point != self.config.entry_point {
+                    continue;
+                }
+
+                // The op is pending again if it was put back in the pool, and
+                // dropped otherwise
+                self.op_statuses.lock().set(op.hash, OpStatus::Dropped);
+
+                if let Some(op) = state.pool.unmine_operation(op) {
+                    // Only account for an entity once
+                    for entity_addr in op.entities().map(|e| e.address).unique() {
+                        self.reputation.remove_included(entity_addr);
+                    }
+                    unmined_op_count += 1;
+                }
+            }
+            if mined_op_count > 0 {
+                info!(
+                "{mined_op_count} op(s) mined on entry point {:?} when advancing to block with number {}, hash {:?}.",
+                self.config.entry_point,
+                update.latest_block_number,
+                update.latest_block_hash,
+            );
+            }
+            if unmined_op_count > 0 {
+                info!(
+                "{unmined_op_count} op(s) unmined in reorg on entry point {:?} when advancing to block with number {}, hash {:?}.",
+                self.config.entry_point,
+                update.latest_block_number,
+                update.latest_block_hash,
+            );
+            }
+            UoPoolMetrics::update_ops_seen(
+                mined_op_count as isize - unmined_op_count as isize,
+                self.config.entry_point,
+            );
+            UoPoolMetrics::increment_unmined_operations(unmined_op_count, self.config.entry_point);
+
+            state
+                .pool
+                .forget_mined_operations_before_block(update.earliest_remembered_block_number);
+            // Remove throttled ops that are too old
+            let mut to_remove = HashSet::new();
+            for hash in state.throttled_ops.iter() {
+                let block_seen = state
+                    .pool
+                    .get_operation_by_hash(*hash)
+                    .map(|po| po.sim_block_number);
+                if let Some(block) = block_seen {
+                    if update.latest_block_number - block > self.config.throttled_entity_live_blocks
+                    {
+                        to_remove.insert((*hash, block));
+                    }
+                }
+            }
+            for (hash, added_at_block) in to_remove {
+                state.pool.remove_operation_by_hash(hash);
+                state.throttled_ops.remove(&hash);
+                self.emit(OpPoolEvent::RemovedOp {
+                    op_hash: hash,
+                    reason: OpRemovalReason::ThrottledAndOld {
+                        added_at_block_number: added_at_block,
+                        current_block_number: update.latest_block_number,
+                    },
+                })
+            }
+
+            // expire old UOs
+            let expired = state.pool.remove_expired(update.latest_block_timestamp);
+            for (hash, until) in expired {
+                self.emit(OpPoolEvent::RemovedOp {
+                    op_hash: hash,
+                    reason: OpRemovalReason::Expired { valid_until: until },
+                })
+            }
+
+            // evict UOs whose submission deadline has passed
+            let past_deadline = state
+                .pool
+                .remove_past_submission_deadline(update.latest_block_timestamp);
+            for (hash, deadline) in past_deadline {
+                state.throttled_ops.remove(&hash);
+                self.emit(OpPoolEvent::RemovedOp {
+                    op_hash: hash,
+                    reason: OpRemovalReason::SubmissionDeadline { deadline },
+                })
+            }
+
+            // evict UOs that have been in the pool longer than the TTL
+            state
+                .pool
+                .set_block_timestamp(update.latest_block_timestamp);
+            let stale = state.pool.remove_stale(update.latest_block_timestamp);
+            UoPoolMetrics::increment_ttl_evicted_operations(stale.len(), self.config.entry_point);
+            for (hash, added_at) in stale {
+                state.throttled_ops.remove(&hash);
+                self.emit(OpPoolEvent::RemovedOp {
+                    op_hash: hash,
+                    reason: OpRemovalReason::TtlExpired { added_at },
+                })
+            }
+
+            state.block_number = update.latest_block_number;
+            unknown_paymaster_balances
+        };
+
+        // unmined deposits only carry totals, so reload the balances they leave unknown
+        if !unknown_paymaster_balances.is_empty() {
+            let _ = self
+                .reload_confirmed_paymaster_balances(unknown_paymaster_balances)
+                .await;
+        }
+
+        // a deep reorg may have invalidated ops that were never mined, so conservatively
+        // re-validate the whole pool rather than trusting the unmined ops alone
+        if update.reorg_larger_than_history || update.reorg_exceeds_reset_threshold {
+            self.revalidate_operations(update.latest_block_number).await;
+        }
+
+        // update required bundle fees and update metrics
+        if let Ok((bundle_fees, base_fee)) = self.prechecker.update_fees().await {
+            let max_fee = match format_units(bundle_fees.max_fee_per_gas, "gwei") {
+                Ok(s) => s.parse::<f64>().unwrap_or_default(),
+                Err(_) => 0.0,
+            };
+            UoPoolMetrics::current_max_fee_gwei(max_fee);
+
+            let max_priority_fee = match format_units(bundle_fees.max_priority_fee_per_gas, "gwei")
+            {
+                Ok(s) => s.parse::<f64>().unwrap_or_default(),
+                Err(_) => 0.0,
+            };
+            UoPoolMetrics::current_max_priority_fee_gwei(max_priority_fee);
+
+            let base_fee = match format_units(base_fee, "gwei") {
+                Ok(s) => s.parse::<f64>().unwrap_or_default(),
+                Err(_) => 0.0,
+            };
+            UoPoolMetrics::current_base_fee(base_fee);
+        }
+    }
+
+    fn entry_point(&self) -> Address {
+        self.config.entry_point
+    }
+
+    async fn reset_confirmed_paymaster_balances(&self) -> MempoolResult<()> {
+        let paymaster_addresses = self.state.read().pool.paymaster_addresses();
+        self.reload_confirmed_paymaster_balances(paymaster_addresses)
+            .await
+    }
+
+    async fn add_operation(
+        &self,
+        origin: OperationOrigin,
+        op: UserOperation,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+    ) -> MempoolResult<H256> {
+        let OperationOrigin::Peer(peer) = origin else {
+            return self
+                .add_operation_internal(origin, op, submission_deadline, bundler_tip, None)
+                .await;
+        };
+
+        // A peer that keeps forwarding invalid operations is banned like an entity
+        if self.reputation.status(peer) == ReputationStatus::Banned {
+            return Err(MempoolError::UntrustedPeer(peer));
+        }
+        let result = self
+            .add_operation_internal(origin, op, submission_deadline, bundler_tip, None)
+            .await;
+        if let Err(MempoolError::PrecheckViolation(_) | MempoolError::SimulationViolation(_)) =
+            &result
+        {
+            self.reputation.add_seen(peer);
+        }
+        result
+    }
+
+    async fn replace_operation(
+        &self,
+        origin: OperationOrigin,
+        old_hash: H256,
+        op: UserOperation,
+    ) -> MempoolResult<H256> {
+        // The replacement inherits the submission deadline and bundler tip of the
+        // operation it replaces
+        let (submission_deadline, bundler_tip) = self
+            .state
+            .read()
+            .pool
+            .get_operation_by_hash(old_hash)
+            .map_or((None, None), |op| {
+                (op.submission_deadline, Some(op.bundler_tip))
+            });
+        self.add_operation_internal(origin, op, submission_deadline, bundler_tip, Some(old_hash))
+            .await
+    }
+
+    fn remove_operations(&self, hashes: &[H256]) {
+        let mut count = 0;
+        let mut removed_hashes = vec![];
+        {
+            let mut state = self.state.write();
+            for hash in hashes {
+                if state.pool.remove_operation_by_hash(*hash).is_some() {
+                    count += 1;
+                    removed_hashes.push(*hash);
+                }
+            }
+        }
+
+        for hash in removed_hashes {
+            self.emit(OpPoolEvent::RemovedOp {
+                op_hash: hash,
+                reason: OpRemovalReason::Requested,
+            })
+        }
+        UoPoolMetrics::increment_removed_operations(count, self.config.entry_point);
+    }
+
+    fn update_entity(&self, update: EntityUpdate) {
+        let entity = update.entity;
         match update.update_type {
             EntityUpdateType::UnstakedInvalidation => {
                 self.reputation.handle_urep_030_penalty(entity.address);
@@ -522,6 +793,10 @@ where
         }
     }
 
+    fn evict_entity_ops(&self, entity: Entity) {
+        self.remove_entity(entity);
+    }
+
     async fn paymaster_balance(&self, paymaster: Address) -> ProviderResult<PaymasterMetadata> {
         if self.state.read().pool.paymaster_exists(paymaster) {
             let meta = self
@@ -582,9 +857,68 @@ where
         self.state.read().pool.get_operation_by_hash(hash)
     }
 
+    fn get_op_status(&self, hash: H256) -> OpStatus {
+        let in_pool = self.state.read().pool.get_operation_by_hash(hash).is_some();
+        let recorded = self.op_statuses.lock().get(hash);
+        match recorded {
+            Some(OpStatus::Submitted { tx_hash }) if in_pool => OpStatus::Submitted { tx_hash },
+            _ if in_pool => OpStatus::Pending,
+            Some(status @ (OpStatus::Mined { .. } | OpStatus::Dropped)) => status,
+            _ => OpStatus::NotFound,
+        }
+    }
+
+    fn mark_submitted(&self, hashes: &[H256], tx_hash: H256) {
+        let mut state = self.state.write();
+        let mut op_statuses = self.op_statuses.lock();
+        for hash in hashes {
+            if state.pool.get_operation_by_hash(*hash).is_some() {
+                // The op simulated successfully to be included in the bundle
+                state.pool.reset_simulation_failures(*hash);
+                op_statuses.set(*hash, OpStatus::Submitted { tx_hash });
+            }
+        }
+    }
+
+    fn record_simulation_failures(&self, hashes: &[H256], error_hashes: &[H256]) {
+        let mut removed = vec![];
+        {
+            let mut state = self.state.write();
+            for hash in hashes {
+                let Some(failures) = state.pool.record_simulation_failure(*hash) else {
+                    continue;
+                };
+                if failures >= self.config.max_simulation_failures
+                    && state.pool.remove_operation_by_hash(*hash).is_some()
+                {
+                    state.throttled_ops.remove(hash);
+                    removed.push((*hash, OpRemovalReason::FailedSimulation { failures }));
+                }
+            }
+            for hash in error_hashes {
+                let Some(errors) = state.pool.record_simulation_error(*hash) else {
+                    continue;
+                };
+                if self.config.max_simulation_errors != 0
+                    && errors >= self.config.max_simulation_errors
+                    && state.pool.remove_operation_by_hash(*hash).is_some()
+                {
+                    state.throttled_ops.remove(hash);
+                    removed.push((*hash, OpRemovalReason::ErroredSimulation { errors }));
+                }
+            }
+        }
+
+        UoPoolMetrics::increment_removed_operations(removed.len(), self.config.entry_point);
+        for (op_hash, reason) in removed {
+            self.emit(OpPoolEvent::RemovedOp { op_hash, reason })
+        }
+    }
+
     fn clear_state(&self, clear_mempool: bool, clear_reputation: bool) {
         if clear_mempool {
-            self.state.write().pool.clear()
+            self.state.write().pool.clear();
+            self.op_statuses.lock().clear();
         }
         if clear_reputation {
             self.reputation.clear()
@@ -622,7 +956,23 @@ where
 
     fn set_reputation(&self, address: Address, ops_seen: u64, ops_included: u64) {
         self.reputation
-            .set_reputation(address, ops_seen, ops_included)
+            .set_reputation(address, ops_seen, ops_included);
+
+        // An override that bans the address also evicts its queued operations
+        if self.reputation.status(address) == ReputationStatus::Banned {
+            let entities = self
+                .state
+                .read()
+                .pool
+                .best_operations()
+                .flat_map(|op| op.entities().collect::<Vec<_>>())
+                .filter(|entity| entity.address == address)
+                .unique()
+                .collect::<Vec<_>>();
+            for entity in entities {
+                self.remove_entity(entity);
+            }
+        }
     }
 }
 
@@ -641,6 +991,14 @@ impl UoPoolMetrics {
         metrics::counter!("op_pool_removed_operations", num_ops as u64, "entrypoint" => entry_point.to_string());
     }
 
+    fn increment_ttl_evicted_operations(num_ops: usize, entry_point: Address) {
+        metrics::counter!("op_pool_ttl_evicted_operations", num_ops as u64, "entrypoint" => entry_point.to_string());
+    }
+
+    fn record_inclusion_latency(latency: Duration, entry_point: Address) {
+        metrics::histogram!("op_pool_inclusion_latency_seconds", latency.as_secs_f64(), "entrypoint" => entry_point.to_string());
+    }
+
     fn increment_removed_entities(entry_point: Address) {
         metrics::increment_counter!("op_pool_removed_entities", "entrypoint" => entry_point.to_string());
     }
@@ -656,23 +1014,43 @@ impl UoPoolMetrics {
     fn current_base_fee(fee: f64) {
         metrics::gauge!("op_pool_current_base_fee", fee);
     }
+
+    fn increment_simulation_violations(error: &SimulationError, entry_point: Address) {
+        let ViolationError::Violations(violations) = &error.violation_error else {
+            return;
+        };
+        for violation in violations {
+            metrics::increment_counter!("op_pool_simulation_violations", "entrypoint" => entry_point.to_string(), "violation" => violation.category());
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            OnceLock,
+        },
+        time::Duration,
+    };
 
     use ethers::types::{Bytes, H160};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
     use rundler_provider::{MockEntryPoint, MockPaymasterHelper};
     use rundler_sim::{
         EntityInfo, EntityInfos, MockPrechecker, MockSimulator, PrecheckError, PrecheckSettings,
         PrecheckViolation, SimulationError, SimulationResult, SimulationSettings,
         SimulationViolation, ViolationError,
     };
-    use rundler_types::{DepositInfo, EntityType, GasFees, ValidTimeRange};
+    use rundler_types::{DepositInfo, EntityType, GasFees, StorageSlot, ValidTimeRange};
 
     use super::*;
-    use crate::chain::MinedOp;
+    use crate::{
+        chain::{self, MinedOp},
+        mempool::EvictionPolicy,
+    };
 
     const THROTTLE_SLACK: u64 = 5;
     const BAN_SLACK: u64 = 10;
@@ -685,7 +1063,7 @@ mod tests {
         let pool = create_pool(ops);
 
         let hash = pool
-            .add_operation(OperationOrigin::Local, op.op)
+            .add_operation(OperationOrigin::Local, op.op, None, None)
             .await
             .unwrap();
         check_ops(pool.best_operations(1, 0).unwrap(), uos);
@@ -706,7 +1084,7 @@ mod tests {
         let mut hashes = vec![];
         for op in &uos {
             let hash = pool
-                .add_operation(OperationOrigin::Local, op.clone())
+                .add_operation(OperationOrigin::Local, op.clone(), None, None)
                 .await
                 .unwrap();
             hashes.push(hash);
@@ -728,7 +1106,7 @@ mod tests {
 
         for op in &uos {
             let _ = pool
-                .add_operation(OperationOrigin::Local, op.clone())
+                .add_operation(OperationOrigin::Local, op.clone(), None, None)
                 .await
                 .unwrap();
         }
@@ -751,6 +1129,7 @@ mod tests {
             latest_block_number: 1,
             latest_block_hash: H256::random(),
             latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
             earliest_remembered_block_number: 0,
             reorg_depth: 0,
             mined_ops: vec![MinedOp {
@@ -760,11 +1139,16 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                tx_hash: H256::zero(),
+                block_number: 0,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
             }],
             unmined_ops: vec![],
             entity_deposits: vec![],
             unmined_entity_deposits: vec![],
             reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
         })
         .await;
 
@@ -805,6 +1189,7 @@ mod tests {
             latest_block_number: 1,
             latest_block_hash: H256::random(),
             latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
             earliest_remembered_block_number: 0,
             reorg_depth: 0,
             mined_ops: vec![MinedOp {
@@ -814,11 +1199,16 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: 10.into(),
                 paymaster: Some(paymaster),
+                tx_hash: H256::zero(),
+                block_number: 0,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
             }],
             unmined_ops: vec![],
             entity_deposits: vec![],
             unmined_entity_deposits: vec![],
             reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
         })
         .await;
 
@@ -839,6 +1229,7 @@ mod tests {
             latest_block_number: 1,
             latest_block_hash: H256::random(),
             latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
             earliest_remembered_block_number: 0,
             reorg_depth: 0,
             mined_ops: vec![],
@@ -849,10 +1240,15 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: 10.into(),
                 paymaster: None,
+                tx_hash: H256::zero(),
+                block_number: 0,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
             }],
             entity_deposits: vec![],
             unmined_entity_deposits: vec![],
             reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
         })
         .await;
 
@@ -867,6 +1263,207 @@ mod tests {
         check_ops(pool.best_operations(3, 0).unwrap(), uos);
     }
 
+    #[tokio::test]
+    async fn chain_update_deposit_mine_unmine() {
+        let paymaster = Address::random();
+        let (pool, _) =
+            create_pool_insert_ops(vec![create_op(Address::random(), 0, 3, Some(paymaster))]).await;
+        let deposit = |amount: u64| chain::DepositInfo {
+            address: paymaster,
+            entrypoint: pool.config.entry_point,
+            amount: amount.into(),
+            block_number: 1,
+            log_index: 0,
+        };
+        let confirmed_balance = || {
+            pool.state
+                .read()
+                .pool
+                .paymaster_metadata(paymaster)
+                .unwrap()
+                .confirmed_balance
+        };
+        assert_eq!(confirmed_balance(), 1000.into());
+
+        // deposits carry the new total, so the last one is the balance
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: H256::random(),
+            latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
+            earliest_remembered_block_number: 0,
+            reorg_depth: 0,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_deposits: vec![deposit(1100), deposit(1250)],
+            unmined_entity_deposits: vec![],
+            reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
+        })
+        .await;
+        assert_eq!(confirmed_balance(), 1250.into());
+
+        // unmining them reloads the balance from the chain
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: H256::random(),
+            latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
+            earliest_remembered_block_number: 0,
+            reorg_depth: 1,
+            mined_ops: vec![],
+            unmined_ops: vec![],
+            entity_deposits: vec![],
+            unmined_entity_deposits: vec![deposit(1100), deposit(1250)],
+            reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
+        })
+        .await;
+        assert_eq!(confirmed_balance(), 1000.into());
+    }
+
+    #[tokio::test]
+    async fn chain_update_applies_deposits_and_mined_ops_in_log_order() {
+        let paymaster = Address::random();
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 3, Some(paymaster)),
+            create_op(Address::random(), 0, 2, Some(paymaster)),
+        ])
+        .await;
+        let mined_op = |i: usize, log_index: u64| MinedOp {
+            entry_point: pool.config.entry_point,
+            hash: uos[i].op_hash(pool.config.entry_point, 1),
+            sender: uos[i].sender,
+            nonce: uos[i].nonce,
+            actual_gas_cost: 10.into(),
+            paymaster: Some(paymaster),
+            tx_hash: H256::zero(),
+            block_number: 1,
+            log_index,
+            block_timestamp: Timestamp::default(),
+        };
+        let confirmed_balance = || {
+            pool.state
+                .read()
+                .pool
+                .paymaster_metadata(paymaster)
+                .unwrap()
+                .confirmed_balance
+        };
+
+        // The deposit's total already accounts for the op mined before it,
+        // but not for the one mined after it
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_hash: H256::random(),
+            mined_ops: vec![mined_op(1, 2), mined_op(0, 0)],
+            entity_deposits: vec![chain::DepositInfo {
+                address: paymaster,
+                entrypoint: pool.config.entry_point,
+                amount: 1490.into(),
+                block_number: 1,
+                log_index: 1,
+            }],
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(confirmed_balance(), 1480.into());
+    }
+
+    #[tokio::test]
+    async fn chain_update_mine_unmine_remine() {
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+        ])
+        .await;
+        let mined_op = MinedOp {
+            entry_point: pool.config.entry_point,
+            hash: uos[0].op_hash(pool.config.entry_point, 1),
+            sender: uos[0].sender,
+            nonce: uos[0].nonce,
+            actual_gas_cost: U256::zero(),
+            paymaster: None,
+            tx_hash: H256::zero(),
+            block_number: 0,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
+        };
+
+        // Mined, unmined in a reorg, then mined again in the new chain
+        for (number, mined_ops, unmined_ops) in [
+            (1, vec![mined_op], vec![]),
+            (2, vec![], vec![mined_op]),
+            (3, vec![mined_op], vec![]),
+        ] {
+            pool.on_chain_update(&ChainUpdate {
+                latest_block_number: number,
+                latest_block_hash: H256::random(),
+                mined_ops,
+                unmined_ops,
+                ..Default::default()
+            })
+            .await;
+        }
+
+        check_ops(pool.best_operations(2, 0).unwrap(), uos[1..].to_vec());
+    }
+
+    #[tokio::test]
+    async fn inclusion_latency_metric() {
+        let snapshotter = metrics_snapshotter().await;
+        let (pool, uos) =
+            create_pool_insert_ops(vec![create_op(Address::random(), 0, 3, None)]).await;
+
+        // The op enters the pool at the first block
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 1,
+            latest_block_timestamp: 100.into(),
+            ..Default::default()
+        })
+        .await;
+        // and is mined in a block before the latest one
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 3,
+            latest_block_timestamp: 200.into(),
+            mined_ops: vec![MinedOp {
+                entry_point: pool.config.entry_point,
+                hash: uos[0].op_hash(pool.config.entry_point, 1),
+                sender: uos[0].sender,
+                nonce: uos[0].nonce,
+                actual_gas_cost: U256::zero(),
+                paymaster: None,
+                tx_hash: H256::zero(),
+                block_number: 2,
+                log_index: 0,
+                block_timestamp: 142.into(),
+            }],
+            ..Default::default()
+        })
+        .await;
+
+        let entry_point = pool.config.entry_point.to_string();
+        let latencies: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| {
+                key.key().name() == "op_pool_inclusion_latency_seconds"
+                    && key
+                        .key()
+                        .labels()
+                        .any(|label| label.key() == "entrypoint" && label.value() == entry_point)
+            })
+            .filter_map(|(_, _, _, value)| match value {
+                DebugValue::Histogram(values) => Some(values),
+                _ => None,
+            })
+            .flatten()
+            .map(|value| value.0)
+            .collect();
+        assert_eq!(latencies, vec![42.0]);
+    }
+
     #[tokio::test]
     async fn chain_update_wrong_ep() {
         let (pool, uos) = create_pool_insert_ops(vec![
@@ -881,6 +1478,7 @@ mod tests {
             latest_block_number: 1,
             latest_block_hash: H256::random(),
             latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
             earliest_remembered_block_number: 0,
             reorg_depth: 0,
             mined_ops: vec![MinedOp {
@@ -890,17 +1488,120 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                tx_hash: H256::zero(),
+                block_number: 0,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
             }],
             unmined_ops: vec![],
             entity_deposits: vec![],
             unmined_entity_deposits: vec![],
             reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
         })
         .await;
 
         check_ops(pool.best_operations(3, 0).unwrap(), uos);
     }
 
+    #[tokio::test]
+    async fn test_removes_op_after_repeated_simulation_failures() {
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+        ])
+        .await;
+        let hashes: Vec<_> = uos
+            .iter()
+            .map(|uo| uo.op_hash(pool.config.entry_point, 1))
+            .collect();
+
+        // Failures are counted per op until it is included in a bundle
+        pool.record_simulation_failures(&hashes, &[]);
+        pool.record_simulation_failures(&hashes, &[]);
+        pool.mark_submitted(&hashes[1..], H256::random());
+        check_ops(pool.best_operations(2, 0).unwrap(), uos.clone());
+
+        // The op is removed once it fails the maximum number of consecutive times
+        pool.record_simulation_failures(&hashes, &[]);
+        check_ops(pool.best_operations(2, 0).unwrap(), vec![uos[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_removes_op_after_repeated_simulation_errors() {
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+        ])
+        .await;
+        let hashes: Vec<_> = uos
+            .iter()
+            .map(|uo| uo.op_hash(pool.config.entry_point, 1))
+            .collect();
+
+        // Errors short of the maximum are treated as transient
+        for _ in 0..4 {
+            pool.record_simulation_failures(&[], &hashes);
+        }
+        pool.mark_submitted(&hashes[1..], H256::random());
+        check_ops(pool.best_operations(2, 0).unwrap(), uos.clone());
+
+        // The op is removed once it errors the maximum number of consecutive times
+        pool.record_simulation_failures(&[], &hashes);
+        check_ops(pool.best_operations(2, 0).unwrap(), vec![uos[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_op_status_transitions() {
+        let (pool, uos) = create_pool_insert_ops(vec![
+            create_op(Address::random(), 0, 3, None),
+            create_op(Address::random(), 0, 2, None),
+        ])
+        .await;
+        let hashes: Vec<_> = uos
+            .iter()
+            .map(|uo| uo.op_hash(pool.config.entry_point, 1))
+            .collect();
+        assert_eq!(pool.get_op_status(H256::random()), OpStatus::NotFound);
+        assert_eq!(pool.get_op_status(hashes[0]), OpStatus::Pending);
+
+        let tx_hash = H256::random();
+        pool.mark_submitted(&hashes[..1], tx_hash);
+        assert_eq!(
+            pool.get_op_status(hashes[0]),
+            OpStatus::Submitted { tx_hash }
+        );
+        assert_eq!(pool.get_op_status(hashes[1]), OpStatus::Pending);
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_number: 5,
+            mined_ops: vec![MinedOp {
+                entry_point: pool.config.entry_point,
+                hash: hashes[0],
+                sender: uos[0].sender,
+                nonce: uos[0].nonce,
+                actual_gas_cost: U256::zero(),
+                paymaster: None,
+                tx_hash,
+                block_number: 5,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
+            }],
+            ..ChainUpdate::default()
+        })
+        .await;
+        assert_eq!(
+            pool.get_op_status(hashes[0]),
+            OpStatus::Mined {
+                tx_hash,
+                block_number: 5
+            }
+        );
+
+        pool.remove_operations(&hashes[1..]);
+        assert_eq!(pool.get_op_status(hashes[1]), OpStatus::Dropped);
+    }
+
     #[tokio::test]
     async fn test_account_reputation() {
         let address = Address::random();
@@ -923,6 +1624,7 @@ mod tests {
             latest_block_number: 1,
             latest_block_hash: H256::random(),
             latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
             earliest_remembered_block_number: 0,
             reorg_depth: 0,
             mined_ops: vec![MinedOp {
@@ -932,11 +1634,16 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                tx_hash: H256::zero(),
+                block_number: 0,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
             }],
             unmined_ops: vec![],
             entity_deposits: vec![],
             unmined_entity_deposits: vec![],
             reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
         })
         .await;
 
@@ -961,7 +1668,7 @@ mod tests {
 
         // Ops 0 through 3 should be included
         for uo in uos.iter().take(4) {
-            pool.add_operation(OperationOrigin::Local, uo.clone())
+            pool.add_operation(OperationOrigin::Local, uo.clone(), None, None)
                 .await
                 .unwrap();
         }
@@ -978,7 +1685,7 @@ mod tests {
 
         // Second op should be throttled
         let ret = pool
-            .add_operation(OperationOrigin::Local, uos[4].clone())
+            .add_operation(OperationOrigin::Local, uos[4].clone(), None, None)
             .await;
 
         assert!(ret.is_err());
@@ -995,6 +1702,7 @@ mod tests {
             latest_block_number: 1,
             latest_block_hash: H256::random(),
             latest_block_timestamp: 0.into(),
+            latest_base_fee_per_gas: None,
             earliest_remembered_block_number: 0,
             reorg_depth: 0,
             mined_ops: vec![MinedOp {
@@ -1004,16 +1712,21 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                tx_hash: H256::zero(),
+                block_number: 0,
+                log_index: 0,
+                block_timestamp: Timestamp::default(),
             }],
             entity_deposits: vec![],
             unmined_ops: vec![],
             unmined_entity_deposits: vec![],
             reorg_larger_than_history: false,
+            reorg_exceeds_reset_threshold: false,
         })
         .await;
 
         // Second op should be included
-        pool.add_operation(OperationOrigin::Local, uos[4].clone())
+        pool.add_operation(OperationOrigin::Local, uos[4].clone(), None, None)
             .await
             .unwrap();
         check_ops(
@@ -1038,7 +1751,9 @@ mod tests {
         pool.set_reputation(address, 1 + BAN_SLACK, 0);
 
         // First op should be banned
-        let ret = pool.add_operation(OperationOrigin::Local, uo.clone()).await;
+        let ret = pool
+            .add_operation(OperationOrigin::Local, uo.clone(), None, None)
+            .await;
         assert!(ret.is_err());
         match ret.unwrap_err() {
             MempoolError::EntityThrottled(entity) => {
@@ -1049,6 +1764,75 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_banned_paymaster_ops_evicted() {
+        let paymaster = Address::random();
+        let other_paymaster = Address::random();
+
+        let mut ops = vec![
+            create_op(Address::random(), 0, 3, Some(paymaster)),
+            create_op(Address::random(), 0, 2, Some(paymaster)),
+            create_op(Address::random(), 0, 1, Some(other_paymaster)),
+        ];
+        for op in &mut ops {
+            op.op.call_gas_limit = 10.into();
+            op.op.verification_gas_limit = 10.into();
+            op.op.pre_verification_gas = 10.into();
+            op.op.max_fee_per_gas = 1.into();
+        }
+        let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
+
+        // The paymasters must be pool entities for their ops to be evicted
+        let mut simulator = MockSimulator::new();
+        simulator
+            .expect_simulate_validation()
+            .returning(|op, _, _| {
+                Ok(SimulationResult {
+                    block_number: Some(0),
+                    entity_infos: EntityInfos {
+                        sender: EntityInfo {
+                            address: op.sender,
+                            is_staked: false,
+                        },
+                        paymaster: op.paymaster().map(|address| EntityInfo {
+                            address,
+                            is_staked: true,
+                        }),
+                        ..EntityInfos::default()
+                    },
+                    ..SimulationResult::default()
+                })
+            });
+        let pool = create_pool_with_simulator(ops, simulator);
+        for uo in &uos {
+            pool.add_operation(OperationOrigin::Local, uo.clone(), None, None)
+                .await
+                .unwrap();
+        }
+        check_ops(pool.best_operations(3, 0).unwrap(), uos.clone());
+
+        // Past ban slack
+        pool.set_reputation(paymaster, 1 + BAN_SLACK, 0);
+
+        check_ops(pool.best_operations(3, 0).unwrap(), vec![uos[2].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_evict_entity_ops() {
+        let sender = Address::random();
+        let ops = vec![
+            create_op(sender, 0, 3, None),
+            create_op(sender, 1, 2, None),
+            create_op(Address::random(), 0, 1, None),
+        ];
+        let (pool, uos) = create_pool_insert_ops(ops).await;
+        assert_eq!(pool.best_operations(3, 0).unwrap().len(), 3);
+
+        pool.evict_entity_ops(Entity::account(sender));
+
+        check_ops(pool.best_operations(3, 0).unwrap(), vec![uos[2].clone()]);
+    }
+
     #[tokio::test]
     async fn test_paymaster_balance_insufficient() {
         let paymaster = Address::random();
@@ -1062,7 +1846,7 @@ mod tests {
         let pool = create_pool(vec![op]);
 
         let ret = pool
-            .add_operation(OperationOrigin::Local, uo.clone())
+            .add_operation(OperationOrigin::Local, uo.clone(), None, None)
             .await
             .unwrap_err();
 
@@ -1082,13 +1866,43 @@ mod tests {
         let ops = vec![op.clone()];
         let pool = create_pool(ops);
 
-        match pool.add_operation(OperationOrigin::Local, op.op).await {
+        match pool
+            .add_operation(OperationOrigin::Local, op.op, None, None)
+            .await
+        {
             Err(MempoolError::PrecheckViolation(PrecheckViolation::InitCodeTooShort(_))) => {}
             _ => panic!("Expected InitCodeTooShort error"),
         }
         assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn peer_banned_after_invalid_ops() {
+        let op = create_op_with_errors(
+            Address::random(),
+            0,
+            0,
+            Some(PrecheckViolation::InitCodeTooShort(0)),
+            None,
+            false,
+        );
+        let pool = create_pool(vec![op.clone()]);
+        let peer = Address::random();
+
+        for _ in 0..=BAN_SLACK {
+            let res = pool
+                .add_operation(OperationOrigin::Peer(peer), op.op.clone(), None, None)
+                .await;
+            assert!(matches!(res, Err(MempoolError::PrecheckViolation(_))));
+        }
+        assert_eq!(pool.reputation.status(peer), ReputationStatus::Banned);
+
+        let res = pool
+            .add_operation(OperationOrigin::Peer(peer), op.op, None, None)
+            .await;
+        assert!(matches!(res, Err(MempoolError::UntrustedPeer(p)) if p == peer));
+    }
+
     #[tokio::test]
     async fn simulation_error() {
         let op = create_op_with_errors(
@@ -1102,25 +1916,82 @@ mod tests {
         let ops = vec![op.clone()];
         let pool = create_pool(ops);
 
-        match pool.add_operation(OperationOrigin::Local, op.op).await {
+        match pool
+            .add_operation(OperationOrigin::Local, op.op, None, None)
+            .await
+        {
             Err(MempoolError::SimulationViolation(SimulationViolation::DidNotRevert)) => {}
             _ => panic!("Expected DidNotRevert error"),
         }
         assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn simulation_violation_metrics() {
+        let snapshotter = metrics_snapshotter().await;
+
+        let mut entry_points = vec![];
+        for violation in [
+            SimulationViolation::InvalidSignature,
+            SimulationViolation::InvalidStorageAccess(
+                Entity::account(Address::random()),
+                StorageSlot {
+                    address: Address::random(),
+                    slot: U256::zero(),
+                },
+            ),
+        ] {
+            let op = create_op_with_errors(Address::random(), 0, 0, None, Some(violation), false);
+            let pool = create_pool(vec![op.clone()]);
+            assert!(pool
+                .add_operation(OperationOrigin::Local, op.op, None, None)
+                .await
+                .is_err());
+            entry_points.push(pool.config.entry_point);
+        }
+
+        let counters = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| key.key().name() == "op_pool_simulation_violations")
+            .filter_map(|(key, _, _, value)| {
+                let DebugValue::Counter(count) = value else {
+                    return None;
+                };
+                let label = |name: &str| {
+                    key.key()
+                        .labels()
+                        .find(|label| label.key() == name)
+                        .map(|label| label.value().to_string())
+                        .unwrap()
+                };
+                Some(((label("entrypoint"), label("violation")), count))
+            })
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(
+            counters.get(&(entry_points[0].to_string(), "signature".to_string())),
+            Some(&1)
+        );
+        assert_eq!(
+            counters.get(&(entry_points[1].to_string(), "storage_access".to_string())),
+            Some(&1)
+        );
+    }
+
     #[tokio::test]
     async fn test_already_known() {
         let op = create_op(Address::random(), 0, 0, None);
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
             .await
             .unwrap();
 
         let err = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
             .await
             .unwrap_err();
         assert!(matches!(err, MempoolError::OperationAlreadyKnown));
@@ -1134,7 +2005,7 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
             .await
             .unwrap();
 
@@ -1142,7 +2013,7 @@ mod tests {
         replacement.max_fee_per_gas = replacement.max_fee_per_gas + 1;
 
         let err = pool
-            .add_operation(OperationOrigin::Local, replacement)
+            .add_operation(OperationOrigin::Local, replacement, None, None)
             .await
             .unwrap_err();
 
@@ -1188,7 +2059,7 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
             .await
             .unwrap();
 
@@ -1196,7 +2067,7 @@ mod tests {
         replacement.max_fee_per_gas = replacement.max_fee_per_gas + 1;
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, replacement.clone())
+            .add_operation(OperationOrigin::Local, replacement.clone(), None, None)
             .await
             .unwrap();
 
@@ -1216,6 +2087,126 @@ mod tests {
         assert_eq!(rep[0].ops_included, 0);
     }
 
+    #[tokio::test]
+    async fn test_replace_op() {
+        let op = create_op(Address::random(), 0, 100, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        let old_hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap();
+
+        let mut replacement = op.op.clone();
+        replacement.max_fee_per_gas = 110.into();
+
+        let hash = pool
+            .replace_operation(OperationOrigin::Local, old_hash, replacement.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            hash,
+            replacement.op_hash(pool.config.entry_point, pool.config.chain_id)
+        );
+        assert!(pool.get_user_operation_by_hash(old_hash).is_none());
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![replacement]);
+    }
+
+    #[tokio::test]
+    async fn test_replace_op_underpriced() {
+        let op = create_op(Address::random(), 0, 100, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        let old_hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap();
+
+        let mut replacement = op.op.clone();
+        replacement.max_fee_per_gas = 105.into();
+
+        let err = pool
+            .replace_operation(OperationOrigin::Local, old_hash, replacement)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, MempoolError::ReplacementUnderpriced(_, _)));
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+    }
+
+    #[tokio::test]
+    async fn test_replace_op_failed_validation_keeps_old_op() {
+        let op = create_op(Address::random(), 0, 100, None);
+        let replacement = create_op_with_errors(
+            op.op.sender,
+            0,
+            110,
+            None,
+            Some(SimulationViolation::DidNotRevert),
+            false,
+        );
+        let pool = create_pool(vec![replacement.clone()]);
+
+        // Add the old op directly, the simulator only knows the failing replacement
+        let old_hash = pool
+            .state
+            .write()
+            .pool
+            .add_operation(
+                PoolOperation {
+                    uo: op.op.clone(),
+                    ..PoolOperation::default()
+                },
+                None,
+            )
+            .unwrap();
+
+        let err = pool
+            .replace_operation(OperationOrigin::Local, old_hash, replacement.op)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, MempoolError::SimulationViolation(_)));
+        assert!(pool.get_user_operation_by_hash(old_hash).is_some());
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
+    }
+
+    #[derive(Debug)]
+    struct RejectSender(Address);
+
+    #[async_trait]
+    impl AdmissionFilter for RejectSender {
+        async fn check(&self, op: &UserOperation) -> Result<(), String> {
+            if op.sender == self.0 {
+                Err("sender not allowed".to_string())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admission_filter() {
+        let rejected = create_op(Address::random(), 0, 0, None);
+        let admitted = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![admitted.clone()])
+            .with_admission_filter(Arc::new(RejectSender(rejected.op.sender)));
+
+        let err = pool
+            .add_operation(OperationOrigin::Local, rejected.op, None, None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, MempoolError::AdmissionRejected(reason) if reason == "sender not allowed")
+        );
+
+        pool.add_operation(OperationOrigin::Local, admitted.op.clone(), None, None)
+            .await
+            .unwrap();
+        check_ops(pool.best_operations(2, 0).unwrap(), vec![admitted.op]);
+    }
+
     #[tokio::test]
     async fn test_expiry() {
         let mut op = create_op(Address::random(), 0, 0, None);
@@ -1226,10 +2217,40 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap();
+
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 11.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_submission_deadline_evicts_before_valid_until() {
+        let mut op = create_op(Address::random(), 0, 0, None);
+        op.valid_time_range = ValidTimeRange {
+            valid_after: 0.into(),
+            valid_until: 100.into(),
+        };
+        let pool = create_pool(vec![op.clone()]);
+
+        let _ = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), Some(10.into()), None)
             .await
             .unwrap();
 
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 10.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
         check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
 
         pool.on_chain_update(&ChainUpdate {
@@ -1237,17 +2258,166 @@ mod tests {
             ..ChainUpdate::default()
         })
         .await;
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_eviction() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![op.clone()]);
+        pool.on_chain_update(&ChainUpdate::default()).await;
+
+        let _ = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 60.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 61.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_counts_from_first_block() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        // added before the pool has seen a block
+        let _ = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 1_000.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 1_061.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_ttl_reset_on_resubmission() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        let _ = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap();
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 50.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+
+        let err = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MempoolError::OperationAlreadyKnown));
+
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 100.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op.clone()]);
 
+        pool.on_chain_update(&ChainUpdate {
+            latest_block_timestamp: 111.into(),
+            ..ChainUpdate::default()
+        })
+        .await;
         check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn test_reorg_reset_threshold_revalidates_pool() {
+        let ops = vec![
+            create_op(Address::random(), 0, 2, None),
+            create_op(Address::random(), 0, 1, None),
+        ];
+        let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
+        let invalid_sender = uos[0].sender;
+        let reorged = Arc::new(AtomicBool::new(false));
+
+        let mut simulator = MockSimulator::new();
+        let sim_reorged = Arc::clone(&reorged);
+        simulator
+            .expect_simulate_validation()
+            .returning(move |op, _, _| {
+                if sim_reorged.load(Ordering::Relaxed) && op.sender == invalid_sender {
+                    Err(SimulationError {
+                        violation_error: ViolationError::Violations(vec![
+                            SimulationViolation::DidNotRevert,
+                        ]),
+                        entity_infos: None,
+                    })
+                } else {
+                    Ok(SimulationResult {
+                        block_number: Some(0),
+                        entity_infos: EntityInfos {
+                            sender: EntityInfo {
+                                address: op.sender,
+                                is_staked: false,
+                            },
+                            ..EntityInfos::default()
+                        },
+                        ..SimulationResult::default()
+                    })
+                }
+            });
+        let pool = create_pool_with_simulator(ops, simulator);
+        for uo in &uos {
+            pool.add_operation(OperationOrigin::Local, uo.clone(), None, None)
+                .await
+                .unwrap();
+        }
+        reorged.store(true, Ordering::Relaxed);
+
+        // a reorg within the threshold only touches the unmined ops
+        pool.on_chain_update(&ChainUpdate {
+            reorg_depth: 1,
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(2, 0).unwrap(), uos.clone());
+
+        pool.on_chain_update(&ChainUpdate {
+            reorg_depth: 5,
+            reorg_exceeds_reset_threshold: true,
+            ..ChainUpdate::default()
+        })
+        .await;
+        check_ops(pool.best_operations(2, 0).unwrap(), vec![uos[1].clone()]);
+    }
+
     #[tokio::test]
     async fn test_get_user_op_by_hash() {
         let op = create_op(Address::random(), 0, 0, None);
         let pool = create_pool(vec![op.clone()]);
 
         let hash = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
             .await
             .unwrap();
 
@@ -1261,7 +2431,7 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None, None)
             .await
             .unwrap();
 
@@ -1279,12 +2449,12 @@ mod tests {
         let pool = create_pool(ops.clone());
 
         for op in ops.iter().take(4) {
-            pool.add_operation(OperationOrigin::Local, op.op.clone())
+            pool.add_operation(OperationOrigin::Local, op.op.clone(), None, None)
                 .await
                 .unwrap();
         }
         assert!(pool
-            .add_operation(OperationOrigin::Local, ops[4].op.clone())
+            .add_operation(OperationOrigin::Local, ops[4].op.clone(), None, None)
             .await
             .is_err());
     }
@@ -1298,6 +2468,22 @@ mod tests {
         staked: bool,
     }
 
+    // The metrics recorder is global, so it is installed once and shared by
+    // all tests. Snapshots drain histograms, so tests that read metrics hold
+    // the lock, and tell their metrics apart by the pool's entry point.
+    async fn metrics_snapshotter() -> tokio::sync::MutexGuard<'static, Snapshotter> {
+        static SNAPSHOTTER: OnceLock<tokio::sync::Mutex<Snapshotter>> = OnceLock::new();
+        SNAPSHOTTER
+            .get_or_init(|| {
+                let recorder = DebuggingRecorder::new();
+                let snapshotter = recorder.snapshotter();
+                recorder.install().unwrap();
+                tokio::sync::Mutex::new(snapshotter)
+            })
+            .lock()
+            .await
+    }
+
     fn create_pool(
         ops: Vec<OpWithErrors>,
     ) -> UoPool<
@@ -1307,8 +2493,47 @@ mod tests {
         impl EntryPoint,
         impl PaymasterHelper,
     > {
-        let reputation = Arc::new(MockReputationManager::new(THROTTLE_SLACK, BAN_SLACK));
         let mut simulator = MockSimulator::new();
+        for op in ops.clone() {
+            simulator
+                .expect_simulate_validation()
+                .returning(move |_, _, _| {
+                    if let Some(error) = &op.simulation_error {
+                        Err(SimulationError {
+                            violation_error: ViolationError::Violations(vec![error.clone()]),
+                            entity_infos: None,
+                        })
+                    } else {
+                        Ok(SimulationResult {
+                            account_is_staked: op.staked,
+                            block_number: Some(0),
+                            valid_time_range: op.valid_time_range,
+                            entity_infos: EntityInfos {
+                                sender: EntityInfo {
+                                    address: op.op.sender,
+                                    is_staked: false,
+                                },
+                                ..EntityInfos::default()
+                            },
+                            ..SimulationResult::default()
+                        })
+                    }
+                });
+        }
+        create_pool_with_simulator(ops, simulator)
+    }
+
+    fn create_pool_with_simulator(
+        ops: Vec<OpWithErrors>,
+        simulator: MockSimulator,
+    ) -> UoPool<
+        impl ReputationManager,
+        impl Prechecker,
+        impl Simulator,
+        impl EntryPoint,
+        impl PaymasterHelper,
+    > {
+        let reputation = Arc::new(MockReputationManager::new(THROTTLE_SLACK, BAN_SLACK));
         let mut prechecker = MockPrechecker::new();
         let mut entrypoint = MockEntryPoint::new();
         let mut paymaster_helper = MockPaymasterHelper::new();
@@ -1332,6 +2557,10 @@ mod tests {
             })
         });
 
+        paymaster_helper
+            .expect_get_balances()
+            .returning(|addresses| Ok(vec![U256::from(1000); addresses.len()]));
+
         entrypoint
             .expect_balance_of()
             .returning(|_, _| Ok(U256::from(1000)));
@@ -1344,30 +2573,6 @@ mod tests {
                     Ok(())
                 }
             });
-            simulator
-                .expect_simulate_validation()
-                .returning(move |_, _, _| {
-                    if let Some(error) = &op.simulation_error {
-                        Err(SimulationError {
-                            violation_error: ViolationError::Violations(vec![error.clone()]),
-                            entity_infos: None,
-                        })
-                    } else {
-                        Ok(SimulationResult {
-                            account_is_staked: op.staked,
-                            block_number: Some(0),
-                            valid_time_range: op.valid_time_range,
-                            entity_infos: EntityInfos {
-                                sender: EntityInfo {
-                                    address: op.op.sender,
-                                    is_staked: false,
-                                },
-                                ..EntityInfos::default()
-                            },
-                            ..SimulationResult::default()
-                        })
-                    }
-                });
         }
 
         let args = PoolConfig {
@@ -1375,6 +2580,8 @@ mod tests {
             chain_id: 1,
             min_replacement_fee_increase_percentage: 10,
             max_size_of_pool_bytes: 10000,
+            max_pool_size: 0,
+            eviction_policy: EvictionPolicy::LowestFee,
             blocklist: None,
             allowlist: None,
             precheck_settings: PrecheckSettings::default(),
@@ -1384,6 +2591,10 @@ mod tests {
             same_sender_mempool_count: 4,
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
+            op_ttl: Some(Duration::from_secs(60)),
+            reputation_half_life: None,
+            max_simulation_failures: 3,
+            max_simulation_errors: 5,
         };
         let (event_sender, _) = broadcast::channel(4);
 
@@ -1413,7 +2624,9 @@ mod tests {
         let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
         let pool = create_pool(ops);
         for op in &uos {
-            let _ = pool.add_operation(OperationOrigin::Local, op.clone()).await;
+            let _ = pool
+                .add_operation(OperationOrigin::Local, op.clone(), None, None)
+                .await;
         }
         (pool, uos)
     }
@@ -11,14 +11,19 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, pin::Pin, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
 use async_stream::stream;
 use async_trait::async_trait;
-use ethers::types::{Address, H256};
+use ethers::types::{Address, Bytes, Signature, H256, U256};
 use futures_util::Stream;
 use rundler_task::server::{HealthCheck, ServerStatus};
-use rundler_types::{EntityUpdate, UserOperation};
+use rundler_types::{Entity, EntityUpdate, Timestamp, UserOperation};
 use tokio::{
     sync::{broadcast, mpsc, oneshot},
     task::JoinHandle,
@@ -29,17 +34,23 @@ use tracing::error;
 use super::{PoolResult, PoolServerError};
 use crate::{
     chain::ChainUpdate,
-    mempool::{Mempool, MempoolError, OperationOrigin, PoolOperation, StakeStatus},
-    server::{NewHead, PoolServer, Reputation},
+    mempool::{Mempool, MempoolError, OpStatus, OperationOrigin, PoolOperation, StakeStatus},
+    server::{forwarded_ops_hash, NewHead, PoolServer, PoolSnapshot, PoolStats, Reputation},
     ReputationStatus,
 };
 
+/// Forwarded batches may not be valid for longer than this, which bounds how long
+/// accepted batches are remembered to reject replays
+const MAX_FORWARDED_BATCH_VALIDITY: Duration = Duration::from_secs(300);
+
 /// Local pool server builder
 #[derive(Debug)]
 pub struct LocalPoolBuilder {
     req_sender: mpsc::Sender<ServerRequest>,
     req_receiver: mpsc::Receiver<ServerRequest>,
     block_sender: broadcast::Sender<NewHead>,
+    chain_id: u64,
+    trusted_peers: HashSet<Address>,
 }
 
 impl LocalPoolBuilder {
@@ -51,9 +62,19 @@ impl LocalPoolBuilder {
             req_sender,
             req_receiver,
             block_sender,
+            chain_id: 0,
+            trusted_peers: HashSet::new(),
         }
     }
 
+    /// Accept operations forwarded for `chain_id` by peer bundlers with these
+    /// signing addresses. By default no peers are trusted.
+    pub fn with_trusted_peers(mut self, chain_id: u64, trusted_peers: HashSet<Address>) -> Self {
+        self.chain_id = chain_id;
+        self.trusted_peers = trusted_peers;
+        self
+    }
+
     /// Get a handle to the local pool server that can be used to make requests
     pub fn get_handle(&self) -> LocalPoolHandle {
         LocalPoolHandle {
@@ -73,6 +94,8 @@ impl LocalPoolBuilder {
             self.block_sender,
             mempools,
             chain_updates,
+            self.chain_id,
+            self.trusted_peers,
         );
         tokio::spawn(async move { runner.run(shutdown_token).await })
     }
@@ -91,6 +114,11 @@ struct LocalPoolServerRunner<M> {
     block_sender: broadcast::Sender<NewHead>,
     mempools: HashMap<Address, Arc<M>>,
     chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+    chain_id: u64,
+    trusted_peers: HashSet<Address>,
+    // Hashes of the forwarded batches accepted so far, with their expiry
+    forwarded_batches: HashMap<H256, Timestamp>,
+    paused: bool,
 }
 
 impl LocalPoolHandle {
@@ -119,11 +147,19 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
-    async fn add_op(&self, entry_point: Address, op: UserOperation) -> PoolResult<H256> {
+    async fn add_op(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+    ) -> PoolResult<H256> {
         let req = ServerRequestKind::AddOp {
             entry_point,
             op,
             origin: OperationOrigin::Local,
+            submission_deadline,
+            bundler_tip,
         };
         let resp = self.send(req).await?;
         match resp {
@@ -132,6 +168,45 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn replace_op(
+        &self,
+        entry_point: Address,
+        old_hash: H256,
+        op: UserOperation,
+    ) -> PoolResult<H256> {
+        let req = ServerRequestKind::ReplaceOp {
+            entry_point,
+            old_hash,
+            op,
+            origin: OperationOrigin::Local,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::ReplaceOp { hash } => Ok(hash),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn forward_ops(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperation>,
+        valid_until: Timestamp,
+        signature: Bytes,
+    ) -> PoolResult<Vec<H256>> {
+        let req = ServerRequestKind::ForwardOps {
+            entry_point,
+            ops,
+            valid_until,
+            signature,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::ForwardOps { hashes } => Ok(hashes),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn get_ops(
         &self,
         entry_point: Address,
@@ -159,6 +234,15 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn get_op_status(&self, hash: H256) -> PoolResult<OpStatus> {
+        let req = ServerRequestKind::GetOpStatus { hash };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpStatus { status } => Ok(status),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<H256>) -> PoolResult<()> {
         let req = ServerRequestKind::RemoveOps { entry_point, ops };
         let resp = self.send(req).await?;
@@ -168,6 +252,42 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn mark_ops_submitted(
+        &self,
+        entry_point: Address,
+        hashes: Vec<H256>,
+        tx_hash: H256,
+    ) -> PoolResult<()> {
+        let req = ServerRequestKind::MarkOpsSubmitted {
+            entry_point,
+            hashes,
+            tx_hash,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::MarkOpsSubmitted => Ok(()),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn record_simulation_failures(
+        &self,
+        entry_point: Address,
+        hashes: Vec<H256>,
+        error_hashes: Vec<H256>,
+    ) -> PoolResult<()> {
+        let req = ServerRequestKind::RecordSimulationFailures {
+            entry_point,
+            hashes,
+            error_hashes,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::RecordSimulationFailures => Ok(()),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn update_entities(
         &self,
         entry_point: Address,
@@ -184,14 +304,28 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn evict_entity_ops(&self, entry_point: Address, entity: Entity) -> PoolResult<()> {
+        let req = ServerRequestKind::EvictEntityOps {
+            entry_point,
+            entity,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::EvictEntityOps => Ok(()),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn debug_clear_state(
         &self,
         clear_mempool: bool,
         clear_reputation: bool,
+        entry_point: Option<Address>,
     ) -> Result<(), PoolServerError> {
         let req = ServerRequestKind::DebugClearState {
             clear_mempool,
             clear_reputation,
+            entry_point,
         };
         let resp = self.send(req).await?;
         match resp {
@@ -266,6 +400,42 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn export_snapshot(&self) -> PoolResult<PoolSnapshot> {
+        let req = ServerRequestKind::ExportSnapshot;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::ExportSnapshot { snapshot } => Ok(snapshot),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn import_snapshot(&self, snapshot: Vec<u8>) -> PoolResult<Vec<H256>> {
+        let req = ServerRequestKind::ImportSnapshot { snapshot };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::ImportSnapshot { hashes } => Ok(hashes),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn get_stats(&self) -> PoolResult<PoolStats> {
+        let req = ServerRequestKind::GetStats;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetStats { stats } => Ok(stats),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn set_paused(&self, paused: bool) -> PoolResult<()> {
+        let req = ServerRequestKind::SetPaused { paused };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::SetPaused => Ok(()),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn subscribe_new_heads(&self) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>> {
         let req = ServerRequestKind::SubscribeNewHeads;
         let resp = self.send(req).await?;
@@ -313,12 +483,18 @@ where
         block_sender: broadcast::Sender<NewHead>,
         mempools: HashMap<Address, Arc<M>>,
         chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+        chain_id: u64,
+        trusted_peers: HashSet<Address>,
     ) -> Self {
         Self {
             req_receiver,
             block_sender,
             mempools,
             chain_updates,
+            chain_id,
+            trusted_peers,
+            forwarded_batches: HashMap::new(),
+            paused: false,
         }
     }
 
@@ -328,6 +504,44 @@ where
         })
     }
 
+    /// Returns the trusted peer that signed a batch of forwarded operations, and
+    /// records the batch so that it can't be replayed before it expires
+    fn authenticate_peer(
+        &mut self,
+        entry_point: Address,
+        ops: &[UserOperation],
+        valid_until: Timestamp,
+        signature: &[u8],
+    ) -> PoolResult<Address> {
+        let now = Timestamp::now();
+        if valid_until < now {
+            return Err(anyhow::anyhow!("forwarded ops expired at {valid_until}").into());
+        }
+        if valid_until > now + MAX_FORWARDED_BATCH_VALIDITY {
+            return Err(anyhow::anyhow!(
+                "forwarded ops valid until {valid_until}, which is too far in the future"
+            )
+            .into());
+        }
+
+        let hash = forwarded_ops_hash(self.chain_id, entry_point, ops, valid_until);
+        let signature = Signature::try_from(signature)
+            .map_err(|e| anyhow::anyhow!("invalid peer signature: {e}"))?;
+        let peer = signature
+            .recover(hash.as_bytes())
+            .map_err(|e| anyhow::anyhow!("should recover peer from signature: {e}"))?;
+        if !self.trusted_peers.contains(&peer) {
+            return Err(MempoolError::UntrustedPeer(peer).into());
+        }
+
+        self.forwarded_batches
+            .retain(|_, batch_valid_until| *batch_valid_until >= now);
+        if self.forwarded_batches.insert(hash, valid_until).is_some() {
+            return Err(anyhow::anyhow!("forwarded ops {hash:?} already received").into());
+        }
+        Ok(peer)
+    }
+
     fn get_ops(
         &self,
         entry_point: Address,
@@ -351,12 +565,44 @@ where
         Ok(None)
     }
 
+    fn get_op_status(&self, hash: H256) -> PoolResult<OpStatus> {
+        for mempool in self.mempools.values() {
+            let status = mempool.get_op_status(hash);
+            if status != OpStatus::NotFound {
+                return Ok(status);
+            }
+        }
+        Ok(OpStatus::NotFound)
+    }
+
     fn remove_ops(&self, entry_point: Address, ops: &[H256]) -> PoolResult<()> {
         let mempool = self.get_pool(entry_point)?;
         mempool.remove_operations(ops);
         Ok(())
     }
 
+    fn mark_ops_submitted(
+        &self,
+        entry_point: Address,
+        hashes: &[H256],
+        tx_hash: H256,
+    ) -> PoolResult<()> {
+        let mempool = self.get_pool(entry_point)?;
+        mempool.mark_submitted(hashes, tx_hash);
+        Ok(())
+    }
+
+    fn record_simulation_failures(
+        &self,
+        entry_point: Address,
+        hashes: &[H256],
+        error_hashes: &[H256],
+    ) -> PoolResult<()> {
+        let mempool = self.get_pool(entry_point)?;
+        mempool.record_simulation_failures(hashes, error_hashes);
+        Ok(())
+    }
+
     fn update_entities<'a>(
         &self,
         entry_point: Address,
@@ -369,7 +615,23 @@ where
         Ok(())
     }
 
-    fn debug_clear_state(&self, clear_mempool: bool, clear_reputation: bool) -> PoolResult<()> {
+    fn evict_entity_ops(&self, entry_point: Address, entity: Entity) -> PoolResult<()> {
+        let mempool = self.get_pool(entry_point)?;
+        mempool.evict_entity_ops(entity);
+        Ok(())
+    }
+
+    fn debug_clear_state(
+        &self,
+        clear_mempool: bool,
+        clear_reputation: bool,
+        entry_point: Option<Address>,
+    ) -> PoolResult<()> {
+        if let Some(entry_point) = entry_point {
+            self.get_pool(entry_point)?
+                .clear_state(clear_mempool, clear_reputation);
+            return Ok(());
+        }
         for mempool in self.mempools.values() {
             mempool.clear_state(clear_mempool, clear_reputation);
         }
@@ -411,6 +673,61 @@ where
         Ok(mempool.get_reputation_status(address))
     }
 
+    fn export_snapshot(&self) -> PoolResult<PoolSnapshot> {
+        let ops = self
+            .mempools
+            .values()
+            .flat_map(|mempool| mempool.all_operations(usize::MAX))
+            .map(|op| (*op).clone())
+            .collect();
+        Ok(PoolSnapshot::new(ops))
+    }
+
+    fn get_stats(&self) -> PoolStats {
+        let mut stats = PoolStats::default();
+        for (&entry_point, mempool) in &self.mempools {
+            let ops = mempool.all_operations(usize::MAX);
+            stats.total_ops += ops.len();
+            *stats.ops_by_entry_point.entry(entry_point).or_default() += ops.len();
+            for op in ops {
+                *stats.ops_by_sender.entry(op.uo.sender).or_default() += 1;
+                if let Some(paymaster) = op.uo.paymaster() {
+                    *stats.ops_by_paymaster.entry(paymaster).or_default() += 1;
+                }
+                if let Some(factory) = op.uo.factory() {
+                    *stats.ops_by_factory.entry(factory).or_default() += 1;
+                }
+            }
+        }
+        stats
+    }
+
+    fn snapshot_ops_by_pool(
+        &self,
+        snapshot: &[u8],
+    ) -> PoolResult<Vec<(Arc<M>, UserOperation, Option<Timestamp>, U256)>> {
+        let snapshot = PoolSnapshot::decode(snapshot)?;
+        Ok(snapshot
+            .ops
+            .into_iter()
+            .filter_map(|op| match self.get_pool(op.entry_point) {
+                Ok(mempool) => Some((
+                    Arc::clone(mempool),
+                    op.uo,
+                    op.submission_deadline,
+                    op.bundler_tip,
+                )),
+                Err(_) => {
+                    tracing::warn!(
+                        "Skipping snapshot operation for unsupported entry point {:?}",
+                        op.entry_point
+                    );
+                    None
+                }
+            })
+            .collect())
+    }
+
     async fn run(&mut self, shutdown_token: CancellationToken) -> anyhow::Result<()> {
         loop {
             tokio::select! {
@@ -419,20 +736,38 @@ where
                 }
                 chain_update = self.chain_updates.recv() => {
                     if let Ok(chain_update) = chain_update {
+                        // If the pool has fallen behind, drain the buffered updates. They are
+                        // all applied in order, so that an op mined, unmined and mined again
+                        // ends up mined, but listeners are only notified of the latest head.
+                        let mut chain_updates = vec![chain_update];
+                        loop {
+                            match self.chain_updates.try_recv() {
+                                Ok(later) => chain_updates.push(later),
+                                Err(broadcast::error::TryRecvError::Lagged(count)) => {
+                                    error!("Pool server lagged behind by {count} chain updates");
+                                }
+                                Err(_) => break,
+                            }
+                        }
+
                         // Update each mempool before notifying listeners of the chain update
                         // This allows the mempools to update their state before the listeners
                         // pull information from the mempool.
                         // For example, a bundle builder listening for a new block to kick off
                         // its bundle building process will want to be able to query the mempool
                         // and only receive operations that have not yet been mined.
-                        for mempool in self.mempools.values() {
-                            mempool.on_chain_update(&chain_update).await;
+                        for chain_update in &chain_updates {
+                            for mempool in self.mempools.values() {
+                                mempool.on_chain_update(chain_update).await;
+                            }
                         }
 
-                        let _ = self.block_sender.send(NewHead {
-                            block_hash: chain_update.latest_block_hash,
-                            block_number: chain_update.latest_block_number,
-                        });
+                        if let Some(latest) = chain_updates.last() {
+                            let _ = self.block_sender.send(NewHead {
+                                block_hash: latest.latest_block_hash,
+                                block_number: latest.latest_block_number,
+                            });
+                        }
                     }
                 }
                 Some(req) = self.req_receiver.recv() => {
@@ -442,12 +777,15 @@ where
                                 entry_points: self.mempools.keys().copied().collect()
                             })
                         },
-                        ServerRequestKind::AddOp { entry_point, op, origin } => {
+                        ServerRequestKind::AddOp { .. } if self.paused => {
+                            Err(MempoolError::Paused.into())
+                        },
+                        ServerRequestKind::AddOp { entry_point, op, origin, submission_deadline, bundler_tip } => {
                             match self.get_pool(entry_point) {
                                 Ok(mempool) => {
                                     let mempool = Arc::clone(mempool);
                                     tokio::spawn(async move {
-                                        let resp = match mempool.add_operation(origin, op).await {
+                                        let resp = match mempool.add_operation(origin, op, submission_deadline, bundler_tip).await {
                                             Ok(hash) => Ok(ServerResponse::AddOp { hash }),
                                             Err(e) => Err(e.into()),
                                         };
@@ -460,6 +798,59 @@ where
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::ReplaceOp { .. } if self.paused => {
+                            Err(MempoolError::Paused.into())
+                        },
+                        ServerRequestKind::ReplaceOp { entry_point, old_hash, op, origin } => {
+                            match self.get_pool(entry_point) {
+                                Ok(mempool) => {
+                                    let mempool = Arc::clone(mempool);
+                                    tokio::spawn(async move {
+                                        let resp = match mempool.replace_operation(origin, old_hash, op).await {
+                                            Ok(hash) => Ok(ServerResponse::ReplaceOp { hash }),
+                                            Err(e) => Err(e.into()),
+                                        };
+                                        if let Err(e) = req.response.send(resp) {
+                                            tracing::error!("Failed to send response: {:?}", e);
+                                        }
+                                    });
+                                    continue;
+                                },
+                                Err(e) => Err(e),
+                            }
+                        },
+                        ServerRequestKind::ForwardOps { .. } if self.paused => {
+                            Err(MempoolError::Paused.into())
+                        },
+                        ServerRequestKind::ForwardOps { entry_point, ops, valid_until, signature } => {
+                            let peer_and_pool = self
+                                .get_pool(entry_point)
+                                .map(Arc::clone)
+                                .and_then(|mempool| {
+                                    self.authenticate_peer(entry_point, &ops, valid_until, &signature)
+                                        .map(|peer| (peer, mempool))
+                                });
+                            match peer_and_pool {
+                                Ok((peer, mempool)) => {
+                                    tokio::spawn(async move {
+                                        // Add sequentially so that operations from the same
+                                        // sender keep their relative order
+                                        let mut hashes = vec![];
+                                        for op in ops {
+                                            match mempool.add_operation(OperationOrigin::Peer(peer), op, None, None).await {
+                                                Ok(hash) => hashes.push(hash),
+                                                Err(e) => tracing::warn!("Failed to add operation forwarded by peer {peer:?}: {e:?}"),
+                                            }
+                                        }
+                                        if let Err(e) = req.response.send(Ok(ServerResponse::ForwardOps { hashes })) {
+                                            tracing::error!("Failed to send response: {:?}", e);
+                                        }
+                                    });
+                                    continue;
+                                },
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::GetOps { entry_point, max_ops, shard_index } => {
                             match self.get_ops(entry_point, max_ops, shard_index) {
                                 Ok(ops) => Ok(ServerResponse::GetOps { ops }),
@@ -472,20 +863,44 @@ where
                                 Err(e) => Err(e),
                             }
                         }
+                        ServerRequestKind::GetOpStatus { hash } => {
+                            match self.get_op_status(hash) {
+                                Ok(status) => Ok(ServerResponse::GetOpStatus { status }),
+                                Err(e) => Err(e),
+                            }
+                        }
                         ServerRequestKind::RemoveOps { entry_point, ops } => {
                             match self.remove_ops(entry_point, &ops) {
                                 Ok(_) => Ok(ServerResponse::RemoveOps),
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::MarkOpsSubmitted { entry_point, hashes, tx_hash } => {
+                            match self.mark_ops_submitted(entry_point, &hashes, tx_hash) {
+                                Ok(_) => Ok(ServerResponse::MarkOpsSubmitted),
+                                Err(e) => Err(e),
+                            }
+                        },
+                        ServerRequestKind::RecordSimulationFailures { entry_point, hashes, error_hashes } => {
+                            match self.record_simulation_failures(entry_point, &hashes, &error_hashes) {
+                                Ok(_) => Ok(ServerResponse::RecordSimulationFailures),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::UpdateEntities { entry_point, entity_updates } => {
                             match self.update_entities(entry_point, &entity_updates) {
                                 Ok(_) => Ok(ServerResponse::UpdateEntities),
                                 Err(e) => Err(e),
                             }
                         },
-                        ServerRequestKind::DebugClearState { clear_mempool, clear_reputation } => {
-                            match self.debug_clear_state(clear_mempool, clear_reputation) {
+                        ServerRequestKind::EvictEntityOps { entry_point, entity } => {
+                            match self.evict_entity_ops(entry_point, entity) {
+                                Ok(_) => Ok(ServerResponse::EvictEntityOps),
+                                Err(e) => Err(e),
+                            }
+                        },
+                        ServerRequestKind::DebugClearState { clear_mempool, clear_reputation, entry_point } => {
+                            match self.debug_clear_state(clear_mempool, clear_reputation, entry_point) {
                                 Ok(_) => Ok(ServerResponse::DebugClearState),
                                 Err(e) => Err(e),
                             }
@@ -532,6 +947,41 @@ where
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::ExportSnapshot => {
+                            match self.export_snapshot() {
+                                Ok(snapshot) => Ok(ServerResponse::ExportSnapshot { snapshot }),
+                                Err(e) => Err(e),
+                            }
+                        },
+                        ServerRequestKind::GetStats => {
+                            Ok(ServerResponse::GetStats { stats: self.get_stats() })
+                        },
+                        ServerRequestKind::SetPaused { paused } => {
+                            self.paused = paused;
+                            Ok(ServerResponse::SetPaused)
+                        },
+                        ServerRequestKind::ImportSnapshot { snapshot } => {
+                            match self.snapshot_ops_by_pool(&snapshot) {
+                                Ok(ops) => {
+                                    tokio::spawn(async move {
+                                        // Re-add sequentially so that operations from the same
+                                        // sender keep their relative order
+                                        let mut hashes = vec![];
+                                        for (mempool, op, submission_deadline, bundler_tip) in ops {
+                                            match mempool.add_operation(OperationOrigin::Local, op, submission_deadline, Some(bundler_tip)).await {
+                                                Ok(hash) => hashes.push(hash),
+                                                Err(e) => tracing::warn!("Failed to re-add snapshot operation: {e:?}"),
+                                            }
+                                        }
+                                        if let Err(e) = req.response.send(Ok(ServerResponse::ImportSnapshot { hashes })) {
+                                            tracing::error!("Failed to send response: {:?}", e);
+                                        }
+                                    });
+                                    continue;
+                                },
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::SubscribeNewHeads => {
                             Ok(ServerResponse::SubscribeNewHeads { new_heads: self.block_sender.subscribe() } )
                         }
@@ -560,6 +1010,20 @@ enum ServerRequestKind {
         entry_point: Address,
         op: UserOperation,
         origin: OperationOrigin,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+    },
+    ReplaceOp {
+        entry_point: Address,
+        old_hash: H256,
+        op: UserOperation,
+        origin: OperationOrigin,
+    },
+    ForwardOps {
+        entry_point: Address,
+        ops: Vec<UserOperation>,
+        valid_until: Timestamp,
+        signature: Bytes,
     },
     GetOps {
         entry_point: Address,
@@ -569,17 +1033,35 @@ enum ServerRequestKind {
     GetOpByHash {
         hash: H256,
     },
+    GetOpStatus {
+        hash: H256,
+    },
     RemoveOps {
         entry_point: Address,
         ops: Vec<H256>,
     },
+    MarkOpsSubmitted {
+        entry_point: Address,
+        hashes: Vec<H256>,
+        tx_hash: H256,
+    },
+    RecordSimulationFailures {
+        entry_point: Address,
+        hashes: Vec<H256>,
+        error_hashes: Vec<H256>,
+    },
     UpdateEntities {
         entry_point: Address,
         entity_updates: Vec<EntityUpdate>,
     },
+    EvictEntityOps {
+        entry_point: Address,
+        entity: Entity,
+    },
     DebugClearState {
         clear_mempool: bool,
         clear_reputation: bool,
+        entry_point: Option<Address>,
     },
     DebugDumpMempool {
         entry_point: Address,
@@ -599,6 +1081,14 @@ enum ServerRequestKind {
         entry_point: Address,
         address: Address,
     },
+    ExportSnapshot,
+    ImportSnapshot {
+        snapshot: Vec<u8>,
+    },
+    GetStats,
+    SetPaused {
+        paused: bool,
+    },
     SubscribeNewHeads,
 }
 
@@ -610,14 +1100,26 @@ enum ServerResponse {
     AddOp {
         hash: H256,
     },
+    ReplaceOp {
+        hash: H256,
+    },
+    ForwardOps {
+        hashes: Vec<H256>,
+    },
     GetOps {
         ops: Vec<PoolOperation>,
     },
     GetOpByHash {
         op: Option<PoolOperation>,
     },
+    GetOpStatus {
+        status: OpStatus,
+    },
     RemoveOps,
+    MarkOpsSubmitted,
+    RecordSimulationFailures,
     UpdateEntities,
+    EvictEntityOps,
     DebugClearState,
     DebugDumpMempool {
         ops: Vec<PoolOperation>,
@@ -632,6 +1134,16 @@ enum ServerResponse {
     GetStakeStatus {
         status: StakeStatus,
     },
+    ExportSnapshot {
+        snapshot: PoolSnapshot,
+    },
+    ImportSnapshot {
+        hashes: Vec<H256>,
+    },
+    GetStats {
+        stats: PoolStats,
+    },
+    SetPaused,
     SubscribeNewHeads {
         new_heads: broadcast::Receiver<NewHead>,
     },
@@ -641,10 +1153,16 @@ enum ServerResponse {
 mod tests {
     use std::{iter::zip, sync::Arc};
 
+    use ethers::signers::{LocalWallet, Signer};
     use futures_util::StreamExt;
 
     use super::*;
-    use crate::{chain::ChainUpdate, mempool::MockMempool};
+    use crate::{
+        chain::{ChainUpdate, MinedOp},
+        mempool::MockMempool,
+    };
+
+    const CHAIN_ID: u64 = 1;
 
     #[tokio::test]
     async fn test_add_op() {
@@ -652,14 +1170,46 @@ mod tests {
         let hash0 = H256::random();
         mock_pool
             .expect_add_operation()
-            .returning(move |_, _| Ok(hash0));
+            .returning(move |_, _, _, _| Ok(hash0));
+
+        let ep = Address::random();
+        let state = setup(HashMap::from([(ep, Arc::new(mock_pool))]));
+
+        let hash1 = state
+            .handle
+            .add_op(ep, UserOperation::default(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(hash0, hash1);
+    }
+
+    #[tokio::test]
+    async fn test_add_op_rejected_while_paused() {
+        let mut mock_pool = MockMempool::new();
+        let hash0 = H256::random();
+        mock_pool
+            .expect_add_operation()
+            .times(1)
+            .returning(move |_, _, _, _| Ok(hash0));
 
         let ep = Address::random();
         let state = setup(HashMap::from([(ep, Arc::new(mock_pool))]));
 
+        state.handle.set_paused(true).await.unwrap();
+        let err = state
+            .handle
+            .add_op(ep, UserOperation::default(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            PoolServerError::MempoolError(MempoolError::Paused)
+        ));
+
+        state.handle.set_paused(false).await.unwrap();
         let hash1 = state
             .handle
-            .add_op(ep, UserOperation::default())
+            .add_op(ep, UserOperation::default(), None, None)
             .await
             .unwrap();
         assert_eq!(hash0, hash1);
@@ -691,6 +1241,65 @@ mod tests {
         assert_eq!(number, new_block.block_number);
     }
 
+    #[tokio::test]
+    async fn test_buffered_chain_updates_applied_in_order() {
+        let ep = Address::random();
+        let op = MinedOp {
+            hash: H256::random(),
+            entry_point: ep,
+            sender: Address::random(),
+            nonce: U256::zero(),
+            actual_gas_cost: U256::zero(),
+            paymaster: None,
+            tx_hash: H256::zero(),
+            block_number: 0,
+            log_index: 0,
+            block_timestamp: Timestamp::default(),
+        };
+
+        // Buffer updates that mine an op, unmine it in a reorg, and mine it again
+        let updates = [
+            (1, 0, vec![op], vec![]),
+            (2, 1, vec![], vec![op]),
+            (3, 0, vec![op], vec![]),
+        ];
+
+        // Every update is applied, in order
+        let mut mock_pool = MockMempool::new();
+        let mut seq = mockall::Sequence::new();
+        for (number, _, mined_ops, unmined_ops) in updates.clone() {
+            mock_pool
+                .expect_on_chain_update()
+                .withf(move |update| {
+                    update.latest_block_number == number
+                        && update.mined_ops == mined_ops
+                        && update.unmined_ops == unmined_ops
+                })
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(|_| ());
+        }
+        let state = setup(HashMap::from([(ep, Arc::new(mock_pool))]));
+        let mut sub = state.handle.subscribe_new_heads().await.unwrap();
+
+        for (number, reorg_depth, mined_ops, unmined_ops) in updates {
+            state
+                .chain_update_tx
+                .send(Arc::new(ChainUpdate {
+                    latest_block_number: number,
+                    reorg_depth,
+                    mined_ops,
+                    unmined_ops,
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+
+        // Only the latest head is sent to listeners
+        let new_block = sub.next().await.unwrap();
+        assert_eq!(new_block.block_number, 3);
+    }
+
     #[tokio::test]
     async fn test_get_supported_entry_points() {
         let mut eps0 = vec![Address::random(), Address::random(), Address::random()];
@@ -718,13 +1327,13 @@ mod tests {
         let hashes = [h0, h1, h2];
         pools[0]
             .expect_add_operation()
-            .returning(move |_, _| Ok(h0));
+            .returning(move |_, _, _, _| Ok(h0));
         pools[1]
             .expect_add_operation()
-            .returning(move |_, _| Ok(h1));
+            .returning(move |_, _, _, _| Ok(h1));
         pools[2]
             .expect_add_operation()
-            .returning(move |_, _| Ok(h2));
+            .returning(move |_, _, _, _| Ok(h2));
 
         let state = setup(
             zip(eps.iter(), pools.into_iter())
@@ -737,13 +1346,283 @@ mod tests {
                 *hash,
                 state
                     .handle
-                    .add_op(*ep, UserOperation::default())
+                    .add_op(*ep, UserOperation::default(), None, None)
                     .await
                     .unwrap()
             );
         }
     }
 
+    #[tokio::test]
+    async fn test_get_op_status_checks_each_entry_point() {
+        let mined_hash = H256::random();
+        let tx_hash = H256::random();
+        let mut pool0 = MockMempool::new();
+        pool0
+            .expect_get_op_status()
+            .returning(|_| OpStatus::NotFound);
+        let mut pool1 = MockMempool::new();
+        pool1.expect_get_op_status().returning(move |hash| {
+            if hash == mined_hash {
+                OpStatus::Mined {
+                    tx_hash,
+                    block_number: 7,
+                }
+            } else {
+                OpStatus::NotFound
+            }
+        });
+
+        let state = setup(HashMap::from([
+            (Address::random(), Arc::new(pool0)),
+            (Address::random(), Arc::new(pool1)),
+        ]));
+
+        assert_eq!(
+            state.handle.get_op_status(mined_hash).await.unwrap(),
+            OpStatus::Mined {
+                tx_hash,
+                block_number: 7
+            }
+        );
+        assert_eq!(
+            state.handle.get_op_status(H256::random()).await.unwrap(),
+            OpStatus::NotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let ep = Address::random();
+        let ops: Vec<_> = (0..3u64)
+            .map(|i| PoolOperation {
+                uo: UserOperation {
+                    sender: Address::random(),
+                    nonce: i.into(),
+                    ..Default::default()
+                },
+                entry_point: ep,
+                ..Default::default()
+            })
+            .collect();
+
+        let mut populated_pool = MockMempool::new();
+        let pool_ops: Vec<_> = ops.iter().cloned().map(Arc::new).collect();
+        populated_pool
+            .expect_all_operations()
+            .returning(move |_| pool_ops.clone());
+        let state = setup(HashMap::from([(ep, Arc::new(populated_pool))]));
+        let snapshot = state.handle.export_snapshot().await.unwrap();
+        assert_eq!(snapshot.ops, ops);
+
+        let imported = Arc::new(parking_lot::Mutex::new(vec![]));
+        let mut fresh_pool = MockMempool::new();
+        let imported_clone = Arc::clone(&imported);
+        fresh_pool
+            .expect_add_operation()
+            .returning(move |_, op, _, _| {
+                let hash = op.op_hash(ep, 1);
+                imported_clone.lock().push(op);
+                Ok(hash)
+            });
+        let state = setup(HashMap::from([(ep, Arc::new(fresh_pool))]));
+        let hashes = state
+            .handle
+            .import_snapshot(snapshot.encode())
+            .await
+            .unwrap();
+
+        let expected_uos: Vec<_> = ops.iter().map(|op| op.uo.clone()).collect();
+        let expected_hashes: Vec<_> = expected_uos.iter().map(|uo| uo.op_hash(ep, 1)).collect();
+        assert_eq!(*imported.lock(), expected_uos);
+        assert_eq!(hashes, expected_hashes);
+    }
+
+    #[tokio::test]
+    async fn test_get_stats() {
+        let ep = Address::random();
+        let paymasters = [Address::random(), Address::random()];
+        let senders = [Address::random(), Address::random(), Address::random()];
+        let ops: Vec<_> = [
+            (senders[0], paymasters[0]),
+            (senders[1], paymasters[0]),
+            (senders[2], paymasters[1]),
+        ]
+        .into_iter()
+        .map(|(sender, paymaster)| {
+            Arc::new(PoolOperation {
+                uo: UserOperation {
+                    sender,
+                    paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+                    ..Default::default()
+                },
+                entry_point: ep,
+                ..Default::default()
+            })
+        })
+        .collect();
+
+        let mut pool = MockMempool::new();
+        pool.expect_all_operations().returning(move |_| ops.clone());
+        let state = setup(HashMap::from([(ep, Arc::new(pool))]));
+
+        let stats = state.handle.get_stats().await.unwrap();
+        assert_eq!(stats.total_ops, 3);
+        assert_eq!(stats.ops_by_entry_point, HashMap::from([(ep, 3)]));
+        assert_eq!(
+            stats.ops_by_paymaster,
+            HashMap::from([(paymasters[0], 2), (paymasters[1], 1)])
+        );
+        assert_eq!(
+            stats.ops_by_sender,
+            senders.iter().map(|s| (*s, 1)).collect::<HashMap<_, _>>()
+        );
+        assert!(stats.ops_by_factory.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_debug_clear_state_of_one_entry_point() {
+        let mut cleared_pool = MockMempool::new();
+        cleared_pool
+            .expect_clear_state()
+            .withf(|clear_mempool, clear_reputation| *clear_mempool && *clear_reputation)
+            .times(1)
+            .return_const(());
+        let mut other_pool = MockMempool::new();
+        other_pool.expect_clear_state().never();
+
+        let eps = [Address::random(), Address::random()];
+        let state = setup(HashMap::from([
+            (eps[0], Arc::new(cleared_pool)),
+            (eps[1], Arc::new(other_pool)),
+        ]));
+
+        state
+            .handle
+            .debug_clear_state(true, true, Some(eps[0]))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_evict_entity_ops() {
+        let paymaster = Entity::paymaster(Address::random());
+        let mut mock_pool = MockMempool::new();
+        mock_pool
+            .expect_evict_entity_ops()
+            .withf(move |entity| *entity == paymaster)
+            .times(1)
+            .return_const(());
+
+        let ep = Address::random();
+        let state = setup(HashMap::from([(ep, Arc::new(mock_pool))]));
+
+        state.handle.evict_entity_ops(ep, paymaster).await.unwrap();
+        assert!(state
+            .handle
+            .evict_entity_ops(Address::random(), paymaster)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_forward_ops_from_trusted_peer() {
+        let peer = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        let peer_address = peer.address();
+        let mut mock_pool = MockMempool::new();
+        let hash0 = H256::random();
+        mock_pool
+            .expect_add_operation()
+            .withf(move |origin, _, _, _| matches!(origin, OperationOrigin::Peer(p) if *p == peer_address))
+            .times(1)
+            .returning(move |_, _, _, _| Ok(hash0));
+
+        let ep = Address::random();
+        let state = setup_with_trusted_peers(
+            HashMap::from([(ep, Arc::new(mock_pool))]),
+            HashSet::from([peer_address]),
+        );
+
+        let ops = vec![UserOperation::default()];
+        let valid_until = Timestamp::now() + Duration::from_secs(60);
+        let signature = sign_forwarded_ops(&peer, ep, &ops, valid_until).await;
+        let hashes = state
+            .handle
+            .forward_ops(ep, ops.clone(), valid_until, signature.clone())
+            .await
+            .unwrap();
+        assert_eq!(hashes, vec![hash0]);
+
+        // the same batch is not accepted twice
+        let result = state
+            .handle
+            .forward_ops(ep, ops, valid_until, signature)
+            .await;
+        assert!(matches!(result, Err(PoolServerError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn test_forward_ops_from_untrusted_peer() {
+        let trusted = LocalWallet::from_bytes(&[1; 32]).unwrap().address();
+        let peer = LocalWallet::from_bytes(&[2; 32]).unwrap();
+        let mut mock_pool = MockMempool::new();
+        mock_pool.expect_add_operation().never();
+
+        let ep = Address::random();
+        let state = setup_with_trusted_peers(
+            HashMap::from([(ep, Arc::new(mock_pool))]),
+            HashSet::from([trusted]),
+        );
+
+        let ops = vec![UserOperation::default()];
+        let valid_until = Timestamp::now() + Duration::from_secs(60);
+        let signature = sign_forwarded_ops(&peer, ep, &ops, valid_until).await;
+        let result = state
+            .handle
+            .forward_ops(ep, ops, valid_until, signature)
+            .await;
+        assert!(matches!(
+            result,
+            Err(PoolServerError::MempoolError(MempoolError::UntrustedPeer(address))) if address == peer.address()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forward_ops_expired() {
+        let peer = LocalWallet::from_bytes(&[1; 32]).unwrap();
+        let mut mock_pool = MockMempool::new();
+        mock_pool.expect_add_operation().never();
+
+        let ep = Address::random();
+        let state = setup_with_trusted_peers(
+            HashMap::from([(ep, Arc::new(mock_pool))]),
+            HashSet::from([peer.address()]),
+        );
+
+        let ops = vec![UserOperation::default()];
+        let valid_until = Timestamp::now() - Duration::from_secs(60);
+        let signature = sign_forwarded_ops(&peer, ep, &ops, valid_until).await;
+        let result = state
+            .handle
+            .forward_ops(ep, ops, valid_until, signature)
+            .await;
+        assert!(matches!(result, Err(PoolServerError::Other(_))));
+    }
+
+    async fn sign_forwarded_ops(
+        peer: &LocalWallet,
+        entry_point: Address,
+        ops: &[UserOperation],
+        valid_until: Timestamp,
+    ) -> Bytes {
+        let hash = forwarded_ops_hash(CHAIN_ID, entry_point, ops, valid_until);
+        peer.sign_message(hash.as_bytes())
+            .await
+            .unwrap()
+            .to_vec()
+            .into()
+    }
+
     struct State {
         handle: LocalPoolHandle,
         chain_update_tx: broadcast::Sender<Arc<ChainUpdate>>,
@@ -751,7 +1630,14 @@ mod tests {
     }
 
     fn setup(pools: HashMap<Address, Arc<MockMempool>>) -> State {
-        let builder = LocalPoolBuilder::new(10, 10);
+        setup_with_trusted_peers(pools, HashSet::new())
+    }
+
+    fn setup_with_trusted_peers(
+        pools: HashMap<Address, Arc<MockMempool>>,
+        trusted_peers: HashSet<Address>,
+    ) -> State {
+        let builder = LocalPoolBuilder::new(10, 10).with_trusted_peers(CHAIN_ID, trusted_peers);
         let handle = builder.get_handle();
         let (tx, rx) = broadcast::channel(10);
         let run_handle = builder.run(pools, rx, CancellationToken::new());
@@ -14,28 +14,57 @@
 mod error;
 mod local;
 mod remote;
+mod snapshot;
 
-use std::pin::Pin;
+use std::{collections::HashMap, pin::Pin};
 
 use async_trait::async_trait;
 pub use error::PoolServerError;
-use ethers::types::{Address, H256};
+use ethers::{
+    abi::AbiEncode,
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
+};
 use futures_util::Stream;
 pub use local::{LocalPoolBuilder, LocalPoolHandle};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 pub(crate) use remote::spawn_remote_mempool_server;
 pub use remote::RemotePoolClient;
-use rundler_types::{EntityUpdate, UserOperation};
+use rundler_types::{Entity, EntityUpdate, Timestamp, UserOperation};
+pub use snapshot::{PoolSnapshot, SNAPSHOT_VERSION};
 
 use crate::{
-    mempool::{PoolOperation, Reputation, StakeStatus},
+    mempool::{OpStatus, PoolOperation, Reputation, StakeStatus},
     ReputationStatus,
 };
 
 /// Result type for pool server operations.
 pub type PoolResult<T> = std::result::Result<T, PoolServerError>;
 
+/// Hash of a batch of operations forwarded to a peer's pool. The forwarding
+/// bundler signs this hash as a message with its peer key.
+///
+/// The hash commits to the chain id and an expiry so that a captured batch can't
+/// be replayed on another chain or after it expires.
+pub fn forwarded_ops_hash(
+    chain_id: u64,
+    entry_point: Address,
+    ops: &[UserOperation],
+    valid_until: Timestamp,
+) -> H256 {
+    keccak256(
+        (
+            U256::from(chain_id),
+            entry_point,
+            ops.to_vec(),
+            U256::from(valid_until.seconds_since_epoch()),
+        )
+            .encode(),
+    )
+    .into()
+}
+
 #[derive(Clone, Debug)]
 pub struct NewHead {
     pub block_hash: H256,
@@ -51,6 +80,21 @@ impl Default for NewHead {
     }
 }
 
+/// Statistics about the operations currently in the pool
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Total number of operations across all entry points
+    pub total_ops: usize,
+    /// Number of operations per entry point
+    pub ops_by_entry_point: HashMap<Address, usize>,
+    /// Number of operations per paymaster
+    pub ops_by_paymaster: HashMap<Address, usize>,
+    /// Number of operations per factory
+    pub ops_by_factory: HashMap<Address, usize>,
+    /// Number of operations per sender
+    pub ops_by_sender: HashMap<Address, usize>,
+}
+
 /// Pool server trait
 #[cfg_attr(feature = "test-utils", automock)]
 #[async_trait]
@@ -58,8 +102,41 @@ pub trait PoolServer: Send + Sync + 'static {
     /// Get the supported entry points of the pool
     async fn get_supported_entry_points(&self) -> PoolResult<Vec<Address>>;
 
-    /// Add an operation to the pool
-    async fn add_op(&self, entry_point: Address, op: UserOperation) -> PoolResult<H256>;
+    /// Add an operation to the pool, optionally with a deadline after which it is
+    /// evicted from the pool without being included, and with an off-chain tip
+    /// committed to the bundler for including it
+    async fn add_op(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+    ) -> PoolResult<H256>;
+
+    /// Replace the operation with hash `old_hash` with a fee-bumped operation that
+    /// shares its sender and nonce. The swap happens only if the new operation
+    /// passes validation, otherwise the old operation remains in the pool.
+    async fn replace_op(
+        &self,
+        entry_point: Address,
+        old_hash: H256,
+        op: UserOperation,
+    ) -> PoolResult<H256>;
+
+    /// Add operations forwarded by a trusted peer bundler. `signature` is the
+    /// peer's signature over `forwarded_ops_hash(chain_id, entry_point, &ops, valid_until)`,
+    /// and the recovered signer must be one of the pool's trusted peers. A batch
+    /// is only accepted once, and only before `valid_until`.
+    ///
+    /// Operations are added with the peer as their origin. Operations that fail
+    /// validation are skipped. Returns the hashes of the operations that were added.
+    async fn forward_ops(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperation>,
+        valid_until: Timestamp,
+        signature: Bytes,
+    ) -> PoolResult<Vec<H256>>;
 
     /// Get operations from the pool
     async fn get_ops(
@@ -74,9 +151,33 @@ pub trait PoolServer: Send + Sync + 'static {
     /// Returns None if the operation is not found
     async fn get_op_by_hash(&self, hash: H256) -> PoolResult<Option<PoolOperation>>;
 
+    /// Get the status of an operation by hash
+    /// Checks each entry point in order until the operation is known
+    /// Returns `OpStatus::NotFound` if no entry point knows of the operation
+    async fn get_op_status(&self, hash: H256) -> PoolResult<OpStatus>;
+
     /// Remove operations from the pool by hash
     async fn remove_ops(&self, entry_point: Address, ops: Vec<H256>) -> PoolResult<()>;
 
+    /// Mark operations as submitted to the chain in the given transaction
+    async fn mark_ops_submitted(
+        &self,
+        entry_point: Address,
+        hashes: Vec<H256>,
+        tx_hash: H256,
+    ) -> PoolResult<()>;
+
+    /// Record that operations failed simulation while building a bundle,
+    /// `hashes` with validation rule violations and `error_hashes` with other
+    /// errors. The pool removes operations that fail too many consecutive
+    /// times.
+    async fn record_simulation_failures(
+        &self,
+        entry_point: Address,
+        hashes: Vec<H256>,
+        error_hashes: Vec<H256>,
+    ) -> PoolResult<()>;
+
     /// Update operations associated with entities from the pool
     async fn update_entities(
         &self,
@@ -84,17 +185,23 @@ pub trait PoolServer: Send + Sync + 'static {
         entities: Vec<EntityUpdate>,
     ) -> PoolResult<()>;
 
+    /// Remove all operations that reference the entity from the pool, used
+    /// when the entity is banned
+    async fn evict_entity_ops(&self, entry_point: Address, entity: Entity) -> PoolResult<()>;
+
     /// Subscribe to new chain heads from the pool.
     ///
     /// The pool will notify the subscriber when a new chain head is received, and the pool
     /// has processed all operations up to that head.
     async fn subscribe_new_heads(&self) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>>;
 
-    /// Clear the pool state, used for debug methods
+    /// Clear the pool state of the given entry point, or of all entry points
+    /// if `None`, used for debug methods
     async fn debug_clear_state(
         &self,
         clear_mempool: bool,
         clear_reputation: bool,
+        entry_point: Option<Address>,
     ) -> PoolResult<()>;
 
     /// Dump all operations in the pool, used for debug methods
@@ -123,4 +230,22 @@ pub trait PoolServer: Send + Sync + 'static {
 
     /// Dump reputations for entities, used for debug methods
     async fn debug_dump_reputation(&self, entry_point: Address) -> PoolResult<Vec<Reputation>>;
+
+    /// Export a snapshot of all operations in the pool across all entry points
+    async fn export_snapshot(&self) -> PoolResult<PoolSnapshot>;
+
+    /// Re-add the operations from a serialized snapshot to the pool
+    ///
+    /// Each operation is re-validated before being added. Operations that fail
+    /// validation or target an unsupported entry point are skipped. Returns the
+    /// hashes of the operations that were added.
+    async fn import_snapshot(&self, snapshot: Vec<u8>) -> PoolResult<Vec<H256>>;
+
+    /// Get the number of operations in the pool, in total and grouped by entry
+    /// point, paymaster, factory and sender
+    async fn get_stats(&self) -> PoolResult<PoolStats>;
+
+    /// Pause or resume the pool. While paused, `add_op` rejects new operations,
+    /// but operations already in the pool continue to be returned by `get_ops`
+    async fn set_paused(&self, paused: bool) -> PoolResult<()>;
 }
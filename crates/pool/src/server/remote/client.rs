@@ -13,18 +13,20 @@
 
 use std::{pin::Pin, str::FromStr};
 
-use ethers::types::{Address, H256};
+use anyhow::Context;
+use ethers::types::{Address, Bytes, H256, U256};
 use futures_util::Stream;
 use rundler_task::{
-    grpc::protos::{from_bytes, ConversionError},
+    grpc::protos::{from_bytes, to_le_bytes, ConversionError},
     server::{HealthCheck, ServerStatus},
 };
-use rundler_types::{EntityUpdate, UserOperation};
+use rundler_types::{Entity, EntityUpdate, Timestamp, UserOperation};
 use rundler_utils::retry::{self, UnlimitedRetryOpts};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tonic::{
     async_trait,
+    codec::CompressionEncoding,
     transport::{Channel, Uri},
 };
 use tonic_health::{
@@ -34,16 +36,22 @@ use tonic_health::{
 
 use super::protos::{
     self, add_op_response, debug_clear_state_response, debug_dump_mempool_response,
-    debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
-    get_ops_response, get_reputation_status_response, get_stake_status_response,
-    op_pool_client::OpPoolClient, remove_ops_response, update_entities_response, AddOpRequest,
-    DebugClearStateRequest, DebugDumpMempoolRequest, DebugDumpReputationRequest,
-    DebugSetReputationRequest, GetOpsRequest, GetReputationStatusRequest, GetStakeStatusRequest,
-    RemoveOpsRequest, SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, UpdateEntitiesRequest,
+    debug_dump_reputation_response, debug_set_reputation_response, evict_entity_ops_response,
+    export_snapshot_response, forward_ops_response, get_op_by_hash_response,
+    get_op_status_response, get_ops_response, get_reputation_status_response,
+    get_stake_status_response, get_stats_response, import_snapshot_response,
+    mark_ops_submitted_response, op_pool_client::OpPoolClient, record_simulation_failures_response,
+    remove_ops_response, replace_op_response, set_paused_response, update_entities_response,
+    AddOpRequest, DebugClearStateRequest, DebugDumpMempoolRequest, DebugDumpReputationRequest,
+    DebugSetReputationRequest, EvictEntityOpsRequest, ExportSnapshotRequest, ForwardOpsRequest,
+    GetOpStatusRequest, GetOpsRequest, GetReputationStatusRequest, GetStakeStatusRequest,
+    GetStatsRequest, ImportSnapshotRequest, MarkOpsSubmittedRequest,
+    RecordSimulationFailuresRequest, RemoveOpsRequest, ReplaceOpRequest, SetPausedRequest,
+    SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, UpdateEntitiesRequest,
 };
 use crate::{
-    mempool::{PoolOperation, Reputation, StakeStatus},
-    server::{error::PoolServerError, NewHead, PoolResult, PoolServer},
+    mempool::{OpStatus, PoolOperation, Reputation, StakeStatus},
+    server::{error::PoolServerError, NewHead, PoolResult, PoolServer, PoolSnapshot, PoolStats},
     ReputationStatus,
 };
 
@@ -59,7 +67,21 @@ pub struct RemotePoolClient {
 impl RemotePoolClient {
     /// Connect to a remote pool server, returning a client for submitting requests.
     pub async fn connect(url: String) -> anyhow::Result<Self> {
-        let op_pool_client = OpPoolClient::connect(url.clone()).await?;
+        Self::connect_with_compression(url, false).await
+    }
+
+    /// Connect to a remote pool server, optionally gzip compressing requests and
+    /// accepting gzip compressed responses.
+    ///
+    /// The server must also be started with compression enabled to accept
+    /// compressed requests.
+    pub async fn connect_with_compression(url: String, compression: bool) -> anyhow::Result<Self> {
+        let mut op_pool_client = OpPoolClient::connect(url.clone()).await?;
+        if compression {
+            op_pool_client = op_pool_client
+                .send_compressed(CompressionEncoding::Gzip)
+                .accept_compressed(CompressionEncoding::Gzip);
+        }
         let op_pool_health =
             HealthClient::new(Channel::builder(Uri::from_str(&url)?).connect().await?);
         Ok(Self {
@@ -135,13 +157,22 @@ impl PoolServer for RemotePoolClient {
             .collect::<Result<_, ConversionError>>()?)
     }
 
-    async fn add_op(&self, entry_point: Address, op: UserOperation) -> PoolResult<H256> {
+    async fn add_op(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
+    ) -> PoolResult<H256> {
         let res = self
             .op_pool_client
             .clone()
             .add_op(AddOpRequest {
                 entry_point: entry_point.as_bytes().to_vec(),
                 op: Some(protos::UserOperation::from(&op)),
+                submission_deadline: submission_deadline
+                    .map_or(0, |deadline| deadline.seconds_since_epoch()),
+                bundler_tip: bundler_tip.map_or(vec![], to_le_bytes),
             })
             .await?
             .into_inner()
@@ -156,6 +187,66 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn replace_op(
+        &self,
+        entry_point: Address,
+        old_hash: H256,
+        op: UserOperation,
+    ) -> PoolResult<H256> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .replace_op(ReplaceOpRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                old_hash: old_hash.as_bytes().to_vec(),
+                op: Some(protos::UserOperation::from(&op)),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(replace_op_response::Result::Success(s)) => Ok(H256::from_slice(&s.hash)),
+            Some(replace_op_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn forward_ops(
+        &self,
+        entry_point: Address,
+        ops: Vec<UserOperation>,
+        valid_until: Timestamp,
+        signature: Bytes,
+    ) -> PoolResult<Vec<H256>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .forward_ops(ForwardOpsRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                ops: ops.iter().map(protos::UserOperation::from).collect(),
+                valid_until: valid_until.seconds_since_epoch(),
+                signature: signature.to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(forward_ops_response::Result::Success(s)) => Ok(s
+                .hashes
+                .into_iter()
+                .map(|h| from_bytes(&h))
+                .collect::<Result<_, ConversionError>>()?),
+            Some(forward_ops_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn get_ops(
         &self,
         entry_point: Address,
@@ -215,6 +306,28 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn get_op_status(&self, hash: H256) -> PoolResult<OpStatus> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_op_status(GetOpStatusRequest {
+                hash: hash.as_bytes().to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_op_status_response::Result::Success(s)) => {
+                Ok(s.status.unwrap_or_default().try_into()?)
+            }
+            Some(get_op_status_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<H256>) -> PoolResult<()> {
         let res = self
             .op_pool_client
@@ -236,6 +349,63 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn mark_ops_submitted(
+        &self,
+        entry_point: Address,
+        hashes: Vec<H256>,
+        tx_hash: H256,
+    ) -> PoolResult<()> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .mark_ops_submitted(MarkOpsSubmittedRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                hashes: hashes.into_iter().map(|h| h.as_bytes().to_vec()).collect(),
+                tx_hash: tx_hash.as_bytes().to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(mark_ops_submitted_response::Result::Success(_)) => Ok(()),
+            Some(mark_ops_submitted_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn record_simulation_failures(
+        &self,
+        entry_point: Address,
+        hashes: Vec<H256>,
+        error_hashes: Vec<H256>,
+    ) -> PoolResult<()> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .record_simulation_failures(RecordSimulationFailuresRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                hashes: hashes.into_iter().map(|h| h.as_bytes().to_vec()).collect(),
+                error_hashes: error_hashes
+                    .into_iter()
+                    .map(|h| h.as_bytes().to_vec())
+                    .collect(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(record_simulation_failures_response::Result::Success(_)) => Ok(()),
+            Some(record_simulation_failures_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn update_entities(
         &self,
         entry_point: Address,
@@ -264,10 +434,32 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn evict_entity_ops(&self, entry_point: Address, entity: Entity) -> PoolResult<()> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .evict_entity_ops(EvictEntityOpsRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                entity: Some(protos::Entity::from(&entity)),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(evict_entity_ops_response::Result::Success(_)) => Ok(()),
+            Some(evict_entity_ops_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn debug_clear_state(
         &self,
         clear_mempool: bool,
         clear_reputation: bool,
+        entry_point: Option<Address>,
     ) -> PoolResult<()> {
         let res = self
             .op_pool_client
@@ -275,6 +467,9 @@ impl PoolServer for RemotePoolClient {
             .debug_clear_state(DebugClearStateRequest {
                 clear_mempool,
                 clear_reputation,
+                entry_point: entry_point
+                    .map(|ep| ep.as_bytes().to_vec())
+                    .unwrap_or_default(),
             })
             .await?
             .into_inner()
@@ -421,6 +616,88 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn export_snapshot(&self) -> PoolResult<PoolSnapshot> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .export_snapshot(ExportSnapshotRequest {})
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(export_snapshot_response::Result::Success(s)) => Ok(s
+                .snapshot
+                .context("should have received snapshot from op pool")?
+                .try_into()?),
+            Some(export_snapshot_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn import_snapshot(&self, snapshot: Vec<u8>) -> PoolResult<Vec<H256>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .import_snapshot(ImportSnapshotRequest { snapshot })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(import_snapshot_response::Result::Success(s)) => Ok(s
+                .hashes
+                .into_iter()
+                .map(|h| from_bytes(&h))
+                .collect::<Result<_, ConversionError>>()?),
+            Some(import_snapshot_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn get_stats(&self) -> PoolResult<PoolStats> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_stats(GetStatsRequest {})
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_stats_response::Result::Success(s)) => Ok(s
+                .stats
+                .context("should have received stats from op pool")?
+                .try_into()?),
+            Some(get_stats_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn set_paused(&self, paused: bool) -> PoolResult<()> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .set_paused(SetPausedRequest { paused })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(set_paused_response::Result::Success(_)) => Ok(()),
+            Some(set_paused_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn subscribe_new_heads(&self) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>> {
         let (tx, rx) = mpsc::unbounded_channel();
         let client = self.op_pool_client.clone();
@@ -19,20 +19,21 @@ use rundler_types::StorageSlot;
 
 use super::protos::{
     mempool_error, precheck_violation_error, simulation_violation_error,
-    AccessedUndeployedContract, AggregatorValidationFailed, AssociatedStorageIsAlternateSender,
-    CallGasLimitTooLow, CallHadValue, CalledBannedEntryPointMethod, CodeHashChanged, DidNotRevert,
-    DiscardedOnInsertError, Entity, EntityThrottledError, EntityType, ExistingSenderWithInitCode,
-    FactoryCalledCreate2Twice, FactoryIsNotContract, InitCodeTooShort, InvalidSignature,
-    InvalidStorageAccess, MaxFeePerGasTooLow, MaxOperationsReachedError,
-    MaxPriorityFeePerGasTooLow, MempoolError as ProtoMempoolError, MultipleRolesViolation,
-    NotStaked, OperationAlreadyKnownError, OutOfGas, PaymasterBalanceTooLow,
-    PaymasterDepositTooLow, PaymasterIsNotContract, PaymasterTooShort, PreVerificationGasTooLow,
-    PrecheckViolationError as ProtoPrecheckViolationError, ReplacementUnderpricedError,
-    SenderAddressUsedAsAlternateEntity, SenderFundsTooLow, SenderIsNotContractAndNoInitCode,
-    SimulationViolationError as ProtoSimulationViolationError, TotalGasLimitTooHigh,
-    UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError, UnstakedAggregator,
-    UnstakedPaymasterContext, UnsupportedAggregatorError, UsedForbiddenOpcode,
-    UsedForbiddenPrecompile, VerificationGasLimitTooHigh, WrongNumberOfPhases,
+    AccessedUndeployedContract, AdmissionRejectedError, AggregatorValidationFailed,
+    AssociatedStorageIsAlternateSender, CallGasLimitTooLow, CallHadValue,
+    CalledBannedEntryPointMethod, CodeHashChanged, DidNotRevert, DiscardedOnInsertError, Entity,
+    EntityThrottledError, EntityType, ExistingSenderWithInitCode, FactoryCalledCreate2Twice,
+    FactoryIsNotContract, InitCodeTooShort, InvalidSignature, InvalidStorageAccess,
+    MaxFeePerGasTooLow, MaxOperationsReachedError, MaxPriorityFeePerGasTooLow,
+    MempoolError as ProtoMempoolError, MempoolPausedError, MultipleRolesViolation, NotStaked,
+    OperationAlreadyKnownError, OutOfGas, PaymasterBalanceTooLow, PaymasterDepositTooLow,
+    PaymasterIsNotContract, PaymasterTooShort, PreVerificationGasTooLow,
+    PrecheckViolationError as ProtoPrecheckViolationError, ReplacementTargetNotFoundError,
+    ReplacementUnderpricedError, SenderAddressUsedAsAlternateEntity, SenderFundsTooLow,
+    SenderIsNotContractAndNoInitCode, SimulationViolationError as ProtoSimulationViolationError,
+    TotalGasLimitTooHigh, UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError,
+    UnstakedAggregator, UnstakedPaymasterContext, UnsupportedAggregatorError, UntrustedPeerError,
+    UsedForbiddenOpcode, UsedForbiddenPrecompile, VerificationGasLimitTooHigh, WrongNumberOfPhases,
 };
 use crate::{mempool::MempoolError, server::error::PoolServerError};
 
@@ -109,6 +110,16 @@ impl TryFrom<ProtoMempoolError> for MempoolError {
             Some(mempool_error::Error::UnknownEntryPoint(e)) => {
                 MempoolError::UnknownEntryPoint(from_bytes(&e.entry_point)?)
             }
+            Some(mempool_error::Error::MempoolPaused(_)) => MempoolError::Paused,
+            Some(mempool_error::Error::ReplacementTargetNotFound(e)) => {
+                MempoolError::ReplacementTargetNotFound(from_bytes(&e.hash)?)
+            }
+            Some(mempool_error::Error::AdmissionRejected(e)) => {
+                MempoolError::AdmissionRejected(e.reason)
+            }
+            Some(mempool_error::Error::UntrustedPeer(e)) => {
+                MempoolError::UntrustedPeer(from_bytes(&e.peer)?)
+            }
             _ => bail!("unknown proto mempool error"),
         })
     }
@@ -202,6 +213,26 @@ impl From<MempoolError> for ProtoMempoolError {
                     },
                 )),
             },
+            MempoolError::Paused => ProtoMempoolError {
+                error: Some(mempool_error::Error::MempoolPaused(MempoolPausedError {})),
+            },
+            MempoolError::ReplacementTargetNotFound(hash) => ProtoMempoolError {
+                error: Some(mempool_error::Error::ReplacementTargetNotFound(
+                    ReplacementTargetNotFoundError {
+                        hash: hash.as_bytes().to_vec(),
+                    },
+                )),
+            },
+            MempoolError::AdmissionRejected(reason) => ProtoMempoolError {
+                error: Some(mempool_error::Error::AdmissionRejected(
+                    AdmissionRejectedError { reason },
+                )),
+            },
+            MempoolError::UntrustedPeer(peer) => ProtoMempoolError {
+                error: Some(mempool_error::Error::UntrustedPeer(UntrustedPeerError {
+                    peer: peer.as_bytes().to_vec(),
+                })),
+            },
         }
     }
 }
@@ -14,8 +14,67 @@
 mod client;
 mod error;
 #[allow(non_snake_case, unreachable_pub, clippy::large_enum_variant)]
-mod protos;
+pub(super) mod protos;
 mod server;
 
 pub use client::*;
 pub(crate) use server::spawn_remote_mempool_server;
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::TcpListener, sync::Arc};
+
+    use ethers::types::{Address, Bytes, H256};
+    use rundler_task::server::connect_with_retries;
+    use rundler_types::UserOperation;
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::{mempool::MockMempool, server::LocalPoolBuilder, PoolServer};
+
+    #[tokio::test]
+    async fn test_compressed_add_op_round_trip() {
+        let hash = H256::random();
+        let op = UserOperation {
+            call_data: Bytes::from(vec![1; 4096]),
+            ..Default::default()
+        };
+        let expected_op = op.clone();
+        let mut mock_pool = MockMempool::new();
+        mock_pool
+            .expect_add_operation()
+            .times(1)
+            .withf(move |_, op, _, _| *op == expected_op)
+            .returning(move |_, _, _, _| Ok(hash));
+
+        let ep = Address::random();
+        let builder = LocalPoolBuilder::new(10, 10);
+        let pool_handle = builder.get_handle();
+        let (_chain_update_tx, chain_update_rx) = broadcast::channel(10);
+        let shutdown_token = CancellationToken::new();
+        let _run_handle = builder.run(
+            HashMap::from([(ep, Arc::new(mock_pool))]),
+            chain_update_rx,
+            shutdown_token.clone(),
+        );
+
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let _server_handle =
+            spawn_remote_mempool_server(1, pool_handle, addr, true, shutdown_token.clone())
+                .await
+                .unwrap();
+
+        let client = connect_with_retries("op pool", &format!("http://{addr}"), |url| {
+            RemotePoolClient::connect_with_compression(url, true)
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(client.add_op(ep, op, None, None).await.unwrap(), hash);
+        shutdown_token.cancel();
+    }
+}
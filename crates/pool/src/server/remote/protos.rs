@@ -11,8 +11,10 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::collections::HashMap;
+
 use anyhow::{anyhow, Context};
-use ethers::types::{Address, H256};
+use ethers::types::{Address, H256, U256};
 use rundler_task::grpc::protos::{from_bytes, to_le_bytes, ConversionError};
 use rundler_types::{
     Entity as RundlerEntity, EntityType as RundlerEntityType, EntityUpdate as RundlerEntityUpdate,
@@ -22,10 +24,13 @@ use rundler_types::{
 
 use crate::{
     mempool::{
-        PoolOperation, Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
-        StakeInfo as RundlerStakeInfo, StakeStatus as RundlerStakeStatus,
+        OpStatus as PoolOpStatus, PoolOperation, Reputation as PoolReputation,
+        ReputationStatus as PoolReputationStatus, StakeInfo as RundlerStakeInfo,
+        StakeStatus as RundlerStakeStatus,
+    },
+    server::{
+        NewHead as PoolNewHead, PoolSnapshot as RundlerPoolSnapshot, PoolStats as RundlerPoolStats,
     },
-    server::NewHead as PoolNewHead,
 };
 
 tonic::include_proto!("op_pool");
@@ -245,6 +250,46 @@ impl From<RundlerStakeStatus> for StakeStatus {
     }
 }
 
+impl From<PoolOpStatus> for OpStatus {
+    fn from(status: PoolOpStatus) -> Self {
+        let (kind, tx_hash, block_number) = match status {
+            PoolOpStatus::NotFound => (OpStatusKind::NotFound, None, 0),
+            PoolOpStatus::Pending => (OpStatusKind::Pending, None, 0),
+            PoolOpStatus::Submitted { tx_hash } => (OpStatusKind::Submitted, Some(tx_hash), 0),
+            PoolOpStatus::Mined {
+                tx_hash,
+                block_number,
+            } => (OpStatusKind::Mined, Some(tx_hash), block_number),
+            PoolOpStatus::Dropped => (OpStatusKind::Dropped, None, 0),
+        };
+        OpStatus {
+            kind: kind.into(),
+            tx_hash: tx_hash.map_or(vec![], |h| h.as_bytes().to_vec()),
+            block_number,
+        }
+    }
+}
+
+impl TryFrom<OpStatus> for PoolOpStatus {
+    type Error = ConversionError;
+
+    fn try_from(status: OpStatus) -> Result<Self, Self::Error> {
+        match status.kind {
+            x if x == OpStatusKind::NotFound as i32 => Ok(Self::NotFound),
+            x if x == OpStatusKind::Pending as i32 => Ok(Self::Pending),
+            x if x == OpStatusKind::Submitted as i32 => Ok(Self::Submitted {
+                tx_hash: from_bytes(&status.tx_hash)?,
+            }),
+            x if x == OpStatusKind::Mined as i32 => Ok(Self::Mined {
+                tx_hash: from_bytes(&status.tx_hash)?,
+                block_number: status.block_number,
+            }),
+            x if x == OpStatusKind::Dropped as i32 => Ok(Self::Dropped),
+            _ => Err(ConversionError::InvalidEnumValue(status.kind)),
+        }
+    }
+}
+
 impl From<&PoolOperation> for MempoolOp {
     fn from(op: &PoolOperation) -> Self {
         MempoolOp {
@@ -261,6 +306,10 @@ impl From<&PoolOperation> for MempoolOp {
                 .map(|e| EntityType::from(*e).into())
                 .collect(),
             account_is_staked: op.account_is_staked,
+            submission_deadline: op
+                .submission_deadline
+                .map_or(0, |deadline| deadline.seconds_since_epoch()),
+            bundler_tip: to_le_bytes(op.bundler_tip),
         }
     }
 }
@@ -282,8 +331,8 @@ impl TryFrom<MempoolOp> for PoolOperation {
 
         let valid_time_range = ValidTimeRange::new(op.valid_after.into(), op.valid_until.into());
 
-        let expected_code_hash = H256::from_slice(&op.expected_code_hash);
-        let sim_block_hash = H256::from_slice(&op.sim_block_hash);
+        let expected_code_hash: H256 = from_bytes(&op.expected_code_hash)?;
+        let sim_block_hash: H256 = from_bytes(&op.sim_block_hash)?;
         let entities_needing_stake = op
             .entities_needing_stake
             .into_iter()
@@ -305,6 +354,82 @@ impl TryFrom<MempoolOp> for PoolOperation {
             sim_block_number: 0,
             account_is_staked: op.account_is_staked,
             entity_infos: rundler_sim::EntityInfos::default(),
+            submission_deadline: (op.submission_deadline != 0)
+                .then(|| op.submission_deadline.into()),
+            bundler_tip: if op.bundler_tip.is_empty() {
+                U256::zero()
+            } else {
+                from_bytes(&op.bundler_tip)?
+            },
+        })
+    }
+}
+
+impl From<&RundlerPoolSnapshot> for PoolSnapshot {
+    fn from(snapshot: &RundlerPoolSnapshot) -> Self {
+        PoolSnapshot {
+            version: snapshot.version,
+            ops: snapshot.ops.iter().map(MempoolOp::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<PoolSnapshot> for RundlerPoolSnapshot {
+    type Error = anyhow::Error;
+
+    fn try_from(snapshot: PoolSnapshot) -> Result<Self, Self::Error> {
+        Ok(RundlerPoolSnapshot {
+            version: snapshot.version,
+            ops: snapshot
+                .ops
+                .into_iter()
+                .map(PoolOperation::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+fn to_address_counts(counts: &HashMap<Address, usize>) -> Vec<AddressCount> {
+    counts
+        .iter()
+        .map(|(address, &count)| AddressCount {
+            address: address.0.to_vec(),
+            count: count as u64,
+        })
+        .collect()
+}
+
+fn from_address_counts(
+    counts: Vec<AddressCount>,
+) -> Result<HashMap<Address, usize>, ConversionError> {
+    counts
+        .into_iter()
+        .map(|c| Ok((from_bytes(&c.address)?, c.count as usize)))
+        .collect()
+}
+
+impl From<&RundlerPoolStats> for PoolStats {
+    fn from(stats: &RundlerPoolStats) -> Self {
+        PoolStats {
+            total_ops: stats.total_ops as u64,
+            ops_by_entry_point: to_address_counts(&stats.ops_by_entry_point),
+            ops_by_paymaster: to_address_counts(&stats.ops_by_paymaster),
+            ops_by_factory: to_address_counts(&stats.ops_by_factory),
+            ops_by_sender: to_address_counts(&stats.ops_by_sender),
+        }
+    }
+}
+
+impl TryFrom<PoolStats> for RundlerPoolStats {
+    type Error = ConversionError;
+
+    fn try_from(stats: PoolStats) -> Result<Self, Self::Error> {
+        Ok(RundlerPoolStats {
+            total_ops: stats.total_ops as usize,
+            ops_by_entry_point: from_address_counts(stats.ops_by_entry_point)?,
+            ops_by_paymaster: from_address_counts(stats.ops_by_paymaster)?,
+            ops_by_factory: from_address_counts(stats.ops_by_factory)?,
+            ops_by_sender: from_address_counts(stats.ops_by_sender)?,
         })
     }
 }
@@ -328,3 +453,102 @@ impl From<PoolNewHead> for NewHead {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Bytes;
+
+    use super::*;
+
+    fn complex_op() -> RundlerUserOperation {
+        RundlerUserOperation {
+            sender: Address::random(),
+            nonce: U256::MAX,
+            init_code: Bytes::from(vec![1; 52]),
+            call_data: Bytes::from(vec![2; 100]),
+            call_gas_limit: 100_000.into(),
+            verification_gas_limit: 200_000.into(),
+            pre_verification_gas: 50_000.into(),
+            max_fee_per_gas: 3_000_000_000_u64.into(),
+            max_priority_fee_per_gas: 1_000_000_000.into(),
+            paymaster_and_data: Bytes::from(vec![3; 84]),
+            signature: Bytes::from(vec![4; 65]),
+        }
+    }
+
+    #[test]
+    fn test_user_operation_round_trip() {
+        let op = complex_op();
+        let converted = RundlerUserOperation::try_from(UserOperation::from(&op)).unwrap();
+        assert_eq!(converted, op);
+    }
+
+    #[test]
+    fn test_user_operation_round_trip_empty_fields() {
+        let op = RundlerUserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let proto = UserOperation::from(&op);
+        assert!(proto.init_code.is_empty());
+        assert!(proto.paymaster_and_data.is_empty());
+        assert!(proto.signature.is_empty());
+
+        let converted = RundlerUserOperation::try_from(proto).unwrap();
+        assert_eq!(converted, op);
+    }
+
+    #[test]
+    fn test_user_operation_invalid_sender() {
+        let proto = UserOperation {
+            sender: vec![1; 19],
+            ..UserOperation::from(&complex_op())
+        };
+        assert!(RundlerUserOperation::try_from(proto).is_err());
+    }
+
+    #[test]
+    fn test_mempool_op_round_trip() {
+        // The simulation block number and entity infos are not sent over the wire
+        let op = PoolOperation {
+            uo: complex_op(),
+            entry_point: Address::random(),
+            aggregator: Some(Address::random()),
+            valid_time_range: ValidTimeRange::new(100.into(), 200.into()),
+            expected_code_hash: H256::random(),
+            sim_block_hash: H256::random(),
+            entities_needing_stake: vec![RundlerEntityType::Paymaster, RundlerEntityType::Factory],
+            account_is_staked: true,
+            submission_deadline: Some(300.into()),
+            bundler_tip: 1_000.into(),
+            ..Default::default()
+        };
+        let converted = PoolOperation::try_from(MempoolOp::from(&op)).unwrap();
+        assert_eq!(converted, op);
+    }
+
+    #[test]
+    fn test_mempool_op_round_trip_defaults() {
+        let op = PoolOperation::default();
+        let converted = PoolOperation::try_from(MempoolOp::from(&op)).unwrap();
+        assert_eq!(converted, op);
+    }
+
+    #[test]
+    fn test_mempool_op_missing_user_op() {
+        let proto = MempoolOp {
+            uo: None,
+            ..MempoolOp::from(&PoolOperation::default())
+        };
+        assert!(PoolOperation::try_from(proto).is_err());
+    }
+
+    #[test]
+    fn test_mempool_op_invalid_code_hash() {
+        let proto = MempoolOp {
+            expected_code_hash: vec![1; 31],
+            ..MempoolOp::from(&PoolOperation::default())
+        };
+        assert!(PoolOperation::try_from(proto).is_err());
+    }
+}
@@ -23,29 +23,41 @@ use async_trait::async_trait;
 use ethers::types::{Address, H256};
 use futures_util::StreamExt;
 use rundler_task::grpc::{metrics::GrpcMetricsLayer, protos::from_bytes};
-use rundler_types::EntityUpdate;
+use rundler_types::{Entity, EntityUpdate, Timestamp, UserOperation};
 use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tokio_util::sync::CancellationToken;
-use tonic::{transport::Server, Request, Response, Result, Status};
+use tonic::{codec::CompressionEncoding, transport::Server, Request, Response, Result, Status};
 
 use super::protos::{
     add_op_response, debug_clear_state_response, debug_dump_mempool_response,
-    debug_dump_reputation_response, debug_set_reputation_response, get_op_by_hash_response,
-    get_ops_response, get_reputation_status_response, get_stake_status_response,
+    debug_dump_reputation_response, debug_set_reputation_response, evict_entity_ops_response,
+    export_snapshot_response, forward_ops_response, get_op_by_hash_response,
+    get_op_status_response, get_ops_response, get_reputation_status_response,
+    get_stake_status_response, get_stats_response, import_snapshot_response,
+    mark_ops_submitted_response,
     op_pool_server::{OpPool, OpPoolServer},
-    remove_ops_response, update_entities_response, AddOpRequest, AddOpResponse, AddOpSuccess,
+    record_simulation_failures_response, remove_ops_response, replace_op_response,
+    set_paused_response, update_entities_response, AddOpRequest, AddOpResponse, AddOpSuccess,
     DebugClearStateRequest, DebugClearStateResponse, DebugClearStateSuccess,
     DebugDumpMempoolRequest, DebugDumpMempoolResponse, DebugDumpMempoolSuccess,
     DebugDumpReputationRequest, DebugDumpReputationResponse, DebugDumpReputationSuccess,
     DebugSetReputationRequest, DebugSetReputationResponse, DebugSetReputationSuccess,
-    GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess, GetOpsRequest, GetOpsResponse,
+    EvictEntityOpsRequest, EvictEntityOpsResponse, EvictEntityOpsSuccess, ExportSnapshotRequest,
+    ExportSnapshotResponse, ExportSnapshotSuccess, ForwardOpsRequest, ForwardOpsResponse,
+    ForwardOpsSuccess, GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess,
+    GetOpStatusRequest, GetOpStatusResponse, GetOpStatusSuccess, GetOpsRequest, GetOpsResponse,
     GetOpsSuccess, GetReputationStatusRequest, GetReputationStatusResponse,
     GetReputationStatusSuccess, GetStakeStatusRequest, GetStakeStatusResponse,
-    GetStakeStatusSuccess, GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse,
-    MempoolOp, RemoveOpsRequest, RemoveOpsResponse, RemoveOpsSuccess, SubscribeNewHeadsRequest,
-    SubscribeNewHeadsResponse, UpdateEntitiesRequest, UpdateEntitiesResponse,
-    UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
+    GetStakeStatusSuccess, GetStatsRequest, GetStatsResponse, GetStatsSuccess,
+    GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse, ImportSnapshotRequest,
+    ImportSnapshotResponse, ImportSnapshotSuccess, MarkOpsSubmittedRequest,
+    MarkOpsSubmittedResponse, MarkOpsSubmittedSuccess, MempoolOp, PoolSnapshot, PoolStats,
+    RecordSimulationFailuresRequest, RecordSimulationFailuresResponse,
+    RecordSimulationFailuresSuccess, RemoveOpsRequest, RemoveOpsResponse, RemoveOpsSuccess,
+    ReplaceOpRequest, ReplaceOpResponse, ReplaceOpSuccess, SetPausedRequest, SetPausedResponse,
+    SetPausedSuccess, SubscribeNewHeadsRequest, SubscribeNewHeadsResponse, UpdateEntitiesRequest,
+    UpdateEntitiesResponse, UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
 };
 use crate::{
     mempool::Reputation,
@@ -58,11 +70,17 @@ pub(crate) async fn spawn_remote_mempool_server(
     chain_id: u64,
     local_pool: LocalPoolHandle,
     addr: SocketAddr,
+    compression: bool,
     shutdown_token: CancellationToken,
 ) -> anyhow::Result<JoinHandle<anyhow::Result<()>>> {
     // gRPC server
     let pool_impl = OpPoolImpl::new(chain_id, local_pool);
-    let op_pool_server = OpPoolServer::new(pool_impl);
+    let mut op_pool_server = OpPoolServer::new(pool_impl);
+    if compression {
+        op_pool_server = op_pool_server
+            .accept_compressed(CompressionEncoding::Gzip)
+            .send_compressed(CompressionEncoding::Gzip);
+    }
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(OP_POOL_FILE_DESCRIPTOR_SET)
         .build()?;
@@ -146,7 +164,22 @@ impl OpPool for OpPoolImpl {
             Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
         })?;
 
-        let resp = match self.local_pool.add_op(ep, uo).await {
+        let submission_deadline =
+            (req.submission_deadline != 0).then(|| Timestamp::from(req.submission_deadline));
+        let bundler_tip = if req.bundler_tip.is_empty() {
+            None
+        } else {
+            Some(
+                from_bytes(&req.bundler_tip)
+                    .map_err(|e| Status::invalid_argument(format!("Invalid bundler tip: {e}")))?,
+            )
+        };
+
+        let resp = match self
+            .local_pool
+            .add_op(ep, uo, submission_deadline, bundler_tip)
+            .await
+        {
             Ok(hash) => AddOpResponse {
                 result: Some(add_op_response::Result::Success(AddOpSuccess {
                     hash: hash.as_bytes().to_vec(),
@@ -160,6 +193,70 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn replace_op(
+        &self,
+        request: Request<ReplaceOpRequest>,
+    ) -> Result<Response<ReplaceOpResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let old_hash = from_bytes(&req.old_hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid hash in ReplaceOpRequest: {e}"))
+        })?;
+
+        let proto_op = req
+            .op
+            .ok_or_else(|| Status::invalid_argument("Operation is required in ReplaceOpRequest"))?;
+        let uo = proto_op.try_into().map_err(|e| {
+            Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
+        })?;
+
+        let resp = match self.local_pool.replace_op(ep, old_hash, uo).await {
+            Ok(hash) => ReplaceOpResponse {
+                result: Some(replace_op_response::Result::Success(ReplaceOpSuccess {
+                    hash: hash.as_bytes().to_vec(),
+                })),
+            },
+            Err(error) => ReplaceOpResponse {
+                result: Some(replace_op_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn forward_ops(
+        &self,
+        request: Request<ForwardOpsRequest>,
+    ) -> Result<Response<ForwardOpsResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let ops = req
+            .ops
+            .into_iter()
+            .map(|op| op.try_into())
+            .collect::<std::result::Result<Vec<UserOperation>, _>>()
+            .map_err(|e| {
+                Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
+            })?;
+
+        let resp = match self
+            .local_pool
+            .forward_ops(ep, ops, req.valid_until.into(), req.signature.into())
+            .await
+        {
+            Ok(hashes) => ForwardOpsResponse {
+                result: Some(forward_ops_response::Result::Success(ForwardOpsSuccess {
+                    hashes: hashes.into_iter().map(|h| h.as_bytes().to_vec()).collect(),
+                })),
+            },
+            Err(error) => ForwardOpsResponse {
+                result: Some(forward_ops_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn get_ops(&self, request: Request<GetOpsRequest>) -> Result<Response<GetOpsResponse>> {
         let req = request.into_inner();
         let ep = self.get_entry_point(&req.entry_point)?;
@@ -208,6 +305,32 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_op_status(
+        &self,
+        request: Request<GetOpStatusRequest>,
+    ) -> Result<Response<GetOpStatusResponse>> {
+        let req = request.into_inner();
+
+        let hash = from_bytes(&req.hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid hash in GetOpStatusRequest: {e}"))
+        })?;
+
+        let resp = match self.local_pool.get_op_status(hash).await {
+            Ok(status) => GetOpStatusResponse {
+                result: Some(get_op_status_response::Result::Success(
+                    GetOpStatusSuccess {
+                        status: Some(status.into()),
+                    },
+                )),
+            },
+            Err(error) => GetOpStatusResponse {
+                result: Some(get_op_status_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn remove_ops(
         &self,
         request: Request<RemoveOpsRequest>,
@@ -238,6 +361,86 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn mark_ops_submitted(
+        &self,
+        request: Request<MarkOpsSubmittedRequest>,
+    ) -> Result<Response<MarkOpsSubmittedResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let hashes: Vec<H256> = req
+            .hashes
+            .into_iter()
+            .map(|h| {
+                if h.len() != 32 {
+                    return Err(Status::invalid_argument("Hash must be 32 bytes long"));
+                }
+                Ok(H256::from_slice(&h))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let tx_hash = from_bytes(&req.tx_hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid tx hash in MarkOpsSubmittedRequest: {e}"))
+        })?;
+
+        let resp = match self
+            .local_pool
+            .mark_ops_submitted(ep, hashes, tx_hash)
+            .await
+        {
+            Ok(_) => MarkOpsSubmittedResponse {
+                result: Some(mark_ops_submitted_response::Result::Success(
+                    MarkOpsSubmittedSuccess {},
+                )),
+            },
+            Err(error) => MarkOpsSubmittedResponse {
+                result: Some(mark_ops_submitted_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn record_simulation_failures(
+        &self,
+        request: Request<RecordSimulationFailuresRequest>,
+    ) -> Result<Response<RecordSimulationFailuresResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let parse_hashes = |hashes: Vec<Vec<u8>>| {
+            hashes
+                .into_iter()
+                .map(|h| {
+                    if h.len() != 32 {
+                        return Err(Status::invalid_argument("Hash must be 32 bytes long"));
+                    }
+                    Ok(H256::from_slice(&h))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let hashes = parse_hashes(req.hashes)?;
+        let error_hashes = parse_hashes(req.error_hashes)?;
+
+        let resp = match self
+            .local_pool
+            .record_simulation_failures(ep, hashes, error_hashes)
+            .await
+        {
+            Ok(_) => RecordSimulationFailuresResponse {
+                result: Some(record_simulation_failures_response::Result::Success(
+                    RecordSimulationFailuresSuccess {},
+                )),
+            },
+            Err(error) => RecordSimulationFailuresResponse {
+                result: Some(record_simulation_failures_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn update_entities(
         &self,
         request: Request<UpdateEntitiesRequest>,
@@ -267,14 +470,46 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn evict_entity_ops(
+        &self,
+        request: Request<EvictEntityOpsRequest>,
+    ) -> Result<Response<EvictEntityOpsResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let entity: Entity = req
+            .entity
+            .as_ref()
+            .ok_or_else(|| Status::invalid_argument("Entity is required in EvictEntityOpsRequest"))?
+            .try_into()
+            .map_err(|e| Status::invalid_argument(format!("Invalid entity: {e}")))?;
+
+        let resp = match self.local_pool.evict_entity_ops(ep, entity).await {
+            Ok(_) => EvictEntityOpsResponse {
+                result: Some(evict_entity_ops_response::Result::Success(
+                    EvictEntityOpsSuccess {},
+                )),
+            },
+            Err(error) => EvictEntityOpsResponse {
+                result: Some(evict_entity_ops_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn debug_clear_state(
         &self,
         request: Request<DebugClearStateRequest>,
     ) -> Result<Response<DebugClearStateResponse>> {
         let req = request.into_inner();
+        let entry_point = if req.entry_point.is_empty() {
+            None
+        } else {
+            Some(self.get_entry_point(&req.entry_point)?)
+        };
         let resp = match self
             .local_pool
-            .debug_clear_state(req.clear_mempool, req.clear_reputation)
+            .debug_clear_state(req.clear_mempool, req.clear_reputation, entry_point)
             .await
         {
             Ok(_) => DebugClearStateResponse {
@@ -431,6 +666,83 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn export_snapshot(
+        &self,
+        _request: Request<ExportSnapshotRequest>,
+    ) -> Result<Response<ExportSnapshotResponse>> {
+        let resp = match self.local_pool.export_snapshot().await {
+            Ok(snapshot) => ExportSnapshotResponse {
+                result: Some(export_snapshot_response::Result::Success(
+                    ExportSnapshotSuccess {
+                        snapshot: Some(PoolSnapshot::from(&snapshot)),
+                    },
+                )),
+            },
+            Err(error) => ExportSnapshotResponse {
+                result: Some(export_snapshot_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn import_snapshot(
+        &self,
+        request: Request<ImportSnapshotRequest>,
+    ) -> Result<Response<ImportSnapshotResponse>> {
+        let req = request.into_inner();
+
+        let resp = match self.local_pool.import_snapshot(req.snapshot).await {
+            Ok(hashes) => ImportSnapshotResponse {
+                result: Some(import_snapshot_response::Result::Success(
+                    ImportSnapshotSuccess {
+                        hashes: hashes.into_iter().map(|h| h.as_bytes().to_vec()).collect(),
+                    },
+                )),
+            },
+            Err(error) => ImportSnapshotResponse {
+                result: Some(import_snapshot_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn get_stats(
+        &self,
+        _request: Request<GetStatsRequest>,
+    ) -> Result<Response<GetStatsResponse>> {
+        let resp = match self.local_pool.get_stats().await {
+            Ok(stats) => GetStatsResponse {
+                result: Some(get_stats_response::Result::Success(GetStatsSuccess {
+                    stats: Some(PoolStats::from(&stats)),
+                })),
+            },
+            Err(error) => GetStatsResponse {
+                result: Some(get_stats_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn set_paused(
+        &self,
+        request: Request<SetPausedRequest>,
+    ) -> Result<Response<SetPausedResponse>> {
+        let req = request.into_inner();
+        let resp = match self.local_pool.set_paused(req.paused).await {
+            Ok(_) => SetPausedResponse {
+                result: Some(set_paused_response::Result::Success(SetPausedSuccess {})),
+            },
+            Err(error) => SetPausedResponse {
+                result: Some(set_paused_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     type SubscribeNewHeadsStream = UnboundedReceiverStream<Result<SubscribeNewHeadsResponse>>;
 
     async fn subscribe_new_heads(
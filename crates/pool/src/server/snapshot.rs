@@ -0,0 +1,93 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use anyhow::Context;
+use prost::Message;
+
+use super::{remote::protos, PoolResult, PoolServerError};
+use crate::mempool::PoolOperation;
+
+/// Current version of the pool snapshot format.
+///
+/// Bump this whenever the encoding of a snapshot changes in a way that older
+/// bundlers could not read.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// A snapshot of the operations in the pool, used to carry pending operations
+/// across a restart of the bundler.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    /// Version of the snapshot format
+    pub version: u32,
+    /// Operations in the pool when the snapshot was taken
+    pub ops: Vec<PoolOperation>,
+}
+
+impl PoolSnapshot {
+    /// Create a snapshot of the given operations at the current format version
+    pub fn new(ops: Vec<PoolOperation>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            ops,
+        }
+    }
+
+    /// Serialize the snapshot to bytes
+    pub fn encode(&self) -> Vec<u8> {
+        protos::PoolSnapshot::from(self).encode_to_vec()
+    }
+
+    /// Deserialize a snapshot from bytes, failing if the format version is not supported
+    pub fn decode(bytes: &[u8]) -> PoolResult<Self> {
+        let snapshot =
+            protos::PoolSnapshot::decode(bytes).context("should decode pool snapshot")?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(PoolServerError::Other(anyhow::anyhow!(
+                "unsupported pool snapshot version {}, expected {SNAPSHOT_VERSION}",
+                snapshot.version
+            )));
+        }
+        Ok(snapshot.try_into()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::UserOperation;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let snapshot = PoolSnapshot::new(vec![PoolOperation {
+            uo: UserOperation {
+                nonce: 1.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }]);
+
+        let decoded = PoolSnapshot::decode(&snapshot.encode()).unwrap();
+        assert_eq!(snapshot, decoded);
+    }
+
+    #[test]
+    fn test_decode_unsupported_version() {
+        let snapshot = PoolSnapshot {
+            version: SNAPSHOT_VERSION + 1,
+            ops: vec![],
+        };
+
+        assert!(PoolSnapshot::decode(&snapshot.encode()).is_err());
+    }
+}
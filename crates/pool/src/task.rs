@@ -11,11 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
-use ethers::providers::Middleware;
+use ethers::{providers::Middleware, types::Address};
 use rundler_provider::{EntryPoint, PaymasterHelper, Provider};
 use rundler_sim::{
     Prechecker, PrecheckerImpl, SimulateValidationTracerImpl, Simulator, SimulatorImpl,
@@ -28,7 +33,10 @@ use rundler_utils::{emit::WithEntryPoint, eth, handle};
 use tokio::{sync::broadcast, try_join};
 use tokio_util::sync::CancellationToken;
 
-use super::mempool::{HourlyMovingAverageReputation, PoolConfig, ReputationParams};
+use super::mempool::{
+    AdmissionFilter, HourlyMovingAverageReputation, NoopAdmissionFilter, PoolConfig,
+    ReputationParams,
+};
 use crate::{
     chain::{self, Chain},
     emit::OpPoolEvent,
@@ -47,13 +55,23 @@ pub struct Args {
     pub chain_id: u64,
     /// Number of blocks to keep in the chain history.
     pub chain_history_size: u64,
+    /// Reorgs deeper than this many blocks trigger a full mempool re-validation.
+    /// If not provided, only reorgs larger than the chain history do.
+    pub reorg_reset_threshold: Option<u64>,
+    /// Maximum number of blocks to load concurrently when loading chain history.
+    pub chain_load_blocks_concurrency: usize,
     /// Pool configurations.
     pub pool_configs: Vec<PoolConfig>,
     /// Address to bind the remote mempool server to, if any.
     /// If not provided, a server will not be started.
     pub remote_address: Option<SocketAddr>,
+    /// Whether the remote mempool server gzip compresses its gRPC payloads.
+    pub grpc_compression: bool,
     /// Channel capacity for the chain update channel.
     pub chain_update_channel_capacity: usize,
+    /// Signing addresses of the peer bundlers allowed to forward operations
+    /// to this pool.
+    pub trusted_peers: HashSet<Address>,
 }
 
 /// Mempool task.
@@ -62,6 +80,7 @@ pub struct PoolTask {
     args: Args,
     event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
     pool_builder: LocalPoolBuilder,
+    admission_filter: Arc<dyn AdmissionFilter>,
 }
 
 #[async_trait]
@@ -81,6 +100,8 @@ impl Task for PoolTask {
                 .iter()
                 .map(|config| config.entry_point)
                 .collect(),
+            reorg_reset_threshold: self.args.reorg_reset_threshold,
+            max_load_blocks_concurrency: self.args.chain_load_blocks_concurrency,
         };
         let provider = eth::new_provider(&self.args.http_url, Some(self.args.http_poll_interval))?;
         let chain = Chain::new(provider.clone(), chain_settings);
@@ -90,23 +111,34 @@ impl Task for PoolTask {
         // create mempools
         let mut mempools = HashMap::new();
         for pool_config in &self.args.pool_configs {
-            let pool =
-                PoolTask::create_mempool(pool_config, self.event_sender.clone(), provider.clone())
-                    .await
-                    .context("should have created mempool")?;
+            let pool = PoolTask::create_mempool(
+                pool_config,
+                self.event_sender.clone(),
+                provider.clone(),
+                Arc::clone(&self.admission_filter),
+            )
+            .await
+            .context("should have created mempool")?;
 
             mempools.insert(pool_config.entry_point, Arc::new(pool));
         }
 
         let pool_handle = self.pool_builder.get_handle();
-        let pool_runner_handle =
-            self.pool_builder
-                .run(mempools, update_sender.subscribe(), shutdown_token.clone());
+        let pool_runner_handle = self
+            .pool_builder
+            .with_trusted_peers(self.args.chain_id, self.args.trusted_peers.clone())
+            .run(mempools, update_sender.subscribe(), shutdown_token.clone());
 
         let remote_handle = match self.args.remote_address {
             Some(addr) => {
-                spawn_remote_mempool_server(self.args.chain_id, pool_handle, addr, shutdown_token)
-                    .await?
+                spawn_remote_mempool_server(
+                    self.args.chain_id,
+                    pool_handle,
+                    addr,
+                    self.args.grpc_compression,
+                    shutdown_token,
+                )
+                .await?
             }
             None => tokio::spawn(async { Ok(()) }),
         };
@@ -141,9 +173,17 @@ impl PoolTask {
             args,
             event_sender,
             pool_builder,
+            admission_filter: Arc::new(NoopAdmissionFilter),
         }
     }
 
+    /// Runs custom admission logic on each new user operation before the
+    /// standard validation. By default every operation is admitted.
+    pub fn with_admission_filter(mut self, admission_filter: Arc<dyn AdmissionFilter>) -> Self {
+        self.admission_filter = admission_filter;
+        self
+    }
+
     /// Convert this task into a boxed task.
     pub fn boxed(self) -> Box<dyn Task> {
         Box::new(self)
@@ -153,6 +193,7 @@ impl PoolTask {
         pool_config: &PoolConfig,
         event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
         provider: Arc<P>,
+        admission_filter: Arc<dyn AdmissionFilter>,
     ) -> anyhow::Result<
         UoPool<
             HourlyMovingAverageReputation,
@@ -164,7 +205,8 @@ impl PoolTask {
     > {
         // Reputation manager
         let reputation = Arc::new(HourlyMovingAverageReputation::new(
-            ReputationParams::bundler_default(),
+            ReputationParams::bundler_default()
+                .with_decay_half_life(pool_config.reputation_half_life),
             pool_config.blocklist.clone(),
             pool_config.allowlist.clone(),
         ));
@@ -199,6 +241,7 @@ impl PoolTask {
             simulator,
             i_entry_point,
             paymaster_helper,
-        ))
+        )
+        .with_admission_filter(admission_filter))
     }
 }
@@ -20,7 +20,7 @@ use ethers::{
     providers::{spoof, Middleware, RawCall},
     types::{
         transaction::eip2718::TypedTransaction, Address, BlockId, Bytes, Eip1559TransactionRequest,
-        H256, U256,
+        TransactionRequest, H256, U256,
     },
 };
 
@@ -29,10 +29,13 @@ use rundler_types::hybrid_compute;
 
 use rundler_types::{
     contracts::{
-        i_entry_point::{ExecutionResult, FailedOp, IEntryPoint, SignatureValidationFailed},
+        i_entry_point::{
+            ExecutionResult, FailedOp, IEntryPoint, SenderAddressResult,
+            SignatureValidationFailed, ValidationResult, ValidationResultWithAggregation,
+        },
         shared_types::UserOpsPerAggregator,
     },
-    GasFees, UserOperation,
+    GasFees, TransactionType, UserOperation,
 };
 use rundler_utils::eth::{self, ContractRevertError};
 
@@ -73,13 +76,17 @@ where
         ops_per_aggregator: Vec<UserOpsPerAggregator>,
         beneficiary: Address,
         gas: U256,
+        block_id: Option<BlockId>,
     ) -> anyhow::Result<HandleOpsOut> {
 
         println!("HC entry_point call_handle_ops 1, len {:?} gas {:?}", ops_per_aggregator[0].user_ops.len(), gas);
 
-	let result = get_handle_ops_call(self, ops_per_aggregator.clone(), beneficiary, gas)
-            .call()
-            .await;
+        let call = get_handle_ops_call(self, ops_per_aggregator.clone(), beneficiary, gas);
+        let call = match block_id {
+            Some(bid) => call.block(bid),
+            None => call,
+        };
+        let result = call.call().await;
         println!("HC entry_point call_handle_ops 2 result{:?}", result);
         let error = match result {
             Ok(()) => return Ok(HandleOpsOut::Success),
@@ -105,10 +112,30 @@ where
             if error.to_string().contains("return data out of bounds") {
                 return Ok(HandleOpsOut::PostOpRevert);
             }
+            if let Some(out) = decode_unhandled_handle_ops_revert(revert_data) {
+                return Ok(out);
+            }
         }
         Err(error)?
     }
 
+    async fn estimate_handle_ops_gas(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator>,
+        beneficiary: Address,
+        gas: U256,
+        block_id: Option<BlockId>,
+    ) -> anyhow::Result<U256> {
+        let call = get_handle_ops_call(self, ops_per_aggregator, beneficiary, gas);
+        let call = match block_id {
+            Some(bid) => call.block(bid),
+            None => call,
+        };
+        call.estimate_gas()
+            .await
+            .context("should estimate handle ops gas")
+    }
+
     async fn balance_of(
         &self,
         address: Address,
@@ -128,7 +155,7 @@ where
         op: UserOperation,
         target: Address,
         target_call_data: Bytes,
-        block_hash: H256,
+        block_id: BlockId,
         gas: U256,
         spoofed_state: &spoof::State,
     ) -> anyhow::Result<Result<ExecutionResult, String>> {
@@ -136,7 +163,7 @@ where
 
 	let contract_error = self
             .simulate_handle_op(op, target, target_call_data)
-            .block(block_hash)
+            .block(block_id)
             .gas(gas)
             .call_raw()
             .state(spoofed_state)
@@ -155,17 +182,21 @@ where
         beneficiary: Address,
         gas: U256,
         gas_fees: GasFees,
+        transaction_type: TransactionType,
     ) -> TypedTransaction {
 
         println!("HC starting get_send_bundle_transaction, len {} gas {:?} maxfees {:?}", ops_per_aggregator[0].user_ops.len(), gas, gas_fees);
 
-        let tx: Eip1559TransactionRequest =
-            get_handle_ops_call(self, ops_per_aggregator, beneficiary, gas)
-                .tx
-                .into();
-        tx.max_fee_per_gas(gas_fees.max_fee_per_gas)
-            .max_priority_fee_per_gas(gas_fees.max_priority_fee_per_gas)
-            .into()
+        let tx = get_handle_ops_call(self, ops_per_aggregator, beneficiary, gas).tx;
+        match transaction_type {
+            TransactionType::Eip1559 => Eip1559TransactionRequest::from(tx)
+                .max_fee_per_gas(gas_fees.max_fee_per_gas)
+                .max_priority_fee_per_gas(gas_fees.max_priority_fee_per_gas)
+                .into(),
+            TransactionType::Legacy => TransactionRequest::from(tx)
+                .gas_price(gas_fees.max_fee_per_gas)
+                .into(),
+        }
     }
 
     fn decode_simulate_handle_ops_revert(
@@ -187,6 +218,29 @@ where
         }
     }
 
+    async fn get_user_op_hash(&self, op: UserOperation) -> anyhow::Result<H256> {
+        let hash = IEntryPoint::get_user_op_hash(self, op.clone())
+            .call()
+            .await
+            .map(H256::from)
+            .context("entry point should return user op hash")?;
+        // In test builds, check that the local hash agrees with the deployed contract
+        #[cfg(test)]
+        {
+            let chain_id = self
+                .client()
+                .get_chainid()
+                .await
+                .context("should get chain id")?;
+            assert_eq!(
+                hash,
+                op.op_hash(self.deref().address(), chain_id.as_u64()),
+                "local user op hash should match the entry point's"
+            );
+        }
+        Ok(hash)
+    }
+
     async fn get_nonce(&self, address: Address, key: ::ethers::core::types::U256) -> Result<::ethers::core::types::U256, String> {
         let ret = IEntryPoint::get_nonce(self, address, key).await;
         Ok(ret.unwrap())
@@ -209,3 +263,174 @@ fn get_handle_ops_call<M: Middleware>(
         };
     call.gas(gas)
 }
+
+/// Describes handle ops revert data that is not a `FailedOp` or `SignatureValidationFailed`,
+/// so that reverts the builder can't act on are still diagnosable.
+///
+/// Returns `None` if the revert data is too short to carry a selector.
+fn decode_unhandled_handle_ops_revert(revert_data: &[u8]) -> Option<HandleOpsOut> {
+    let selector: [u8; 4] = revert_data.get(..4)?.try_into().ok()?;
+    let description = if let Ok(err) = ContractRevertError::decode(revert_data) {
+        format!("revert reason: {}", err.reason)
+    } else if let Ok(result) = SenderAddressResult::decode(revert_data) {
+        format!("SenderAddressResult({:?})", result.sender)
+    } else if ExecutionResult::decode(revert_data).is_ok() {
+        "ExecutionResult".to_string()
+    } else if ValidationResult::decode(revert_data).is_ok() {
+        "ValidationResult".to_string()
+    } else if ValidationResultWithAggregation::decode(revert_data).is_ok() {
+        "ValidationResultWithAggregation".to_string()
+    } else {
+        return Some(HandleOpsOut::Reverted(selector));
+    };
+    Some(HandleOpsOut::EntryPointError(description))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::{self, Token},
+        contract::EthError,
+        providers::Provider,
+        types::BlockNumber,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_decode_known_handle_ops_revert() {
+        let sender = Address::random();
+        let revert_data = [
+            SenderAddressResult::selector().as_slice(),
+            &abi::encode(&[Token::Address(sender)]),
+        ]
+        .concat();
+
+        let out = decode_unhandled_handle_ops_revert(&revert_data).unwrap();
+        let HandleOpsOut::EntryPointError(description) = out else {
+            panic!("should decode a known entry point error, got {out:?}");
+        };
+        assert_eq!(description, format!("SenderAddressResult({sender:?})"));
+    }
+
+    #[test]
+    fn test_decode_unknown_handle_ops_revert() {
+        let revert_data = [0xde, 0xad, 0xbe, 0xef, 0x00, 0x01];
+        let out = decode_unhandled_handle_ops_revert(&revert_data).unwrap();
+        assert!(matches!(
+            out,
+            HandleOpsOut::Reverted([0xde, 0xad, 0xbe, 0xef])
+        ));
+
+        // Too short to carry a selector
+        assert!(decode_unhandled_handle_ops_revert(&[0xde, 0xad]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_call_spoofed_simulate_op_uses_block_number() {
+        let (provider, mock) = Provider::mocked();
+        let entry_point = IEntryPoint::new(Address::random(), Arc::new(provider));
+        let op = UserOperation::default();
+        let block_id = BlockId::Number(BlockNumber::Number(100.into()));
+        let gas = U256::from(1_000_000);
+        let spoofed_state = spoof::State::default();
+
+        // The mock has no responses queued, so the call fails after recording the request
+        let _ = entry_point
+            .call_spoofed_simulate_op(
+                op.clone(),
+                Address::zero(),
+                Bytes::new(),
+                block_id,
+                gas,
+                &spoofed_state,
+            )
+            .await;
+
+        let tx = entry_point
+            .simulate_handle_op(op, Address::zero(), Bytes::new())
+            .gas(gas)
+            .tx;
+        mock.assert_request("eth_call", (tx, block_id, spoofed_state))
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_user_op_hash_matches_local_hash() {
+        // Hash generated by the entry point contract's getUserOpHash() for a
+        // zeroed user operation, as in the rundler_types op hash tests
+        let entry_point_address: Address = "0x66a15edcc3b50a663e72f1457ffd49b9ae284ddc"
+            .parse()
+            .unwrap();
+        let contract_hash: H256 =
+            "0xdca97c3b49558ab360659f6ead939773be8bf26631e61bb17045bb70dc983b2d"
+                .parse()
+                .unwrap();
+        let chain_id = 1337;
+        let op = UserOperation::default();
+
+        let (provider, mock) = Provider::mocked();
+        let entry_point = IEntryPoint::new(entry_point_address, Arc::new(provider));
+        // Responses are served last in, first out
+        mock.push(U256::from(chain_id)).unwrap();
+        mock.push(Bytes::from(contract_hash.as_bytes().to_vec()))
+            .unwrap();
+
+        let hash = EntryPoint::get_user_op_hash(&entry_point, op.clone())
+            .await
+            .unwrap();
+        assert_eq!(hash, contract_hash);
+        assert_eq!(hash, op.op_hash(entry_point_address, chain_id));
+
+        let tx = IEntryPoint::get_user_op_hash(&entry_point, op).tx;
+        mock.assert_request("eth_call", (tx, "latest")).unwrap();
+    }
+
+    #[test]
+    fn test_send_bundle_transaction_types() {
+        let (provider, _) = Provider::mocked();
+        let entry_point = IEntryPoint::new(Address::random(), Arc::new(provider));
+        let bundle = vec![UserOpsPerAggregator {
+            user_ops: vec![UserOperation::default()],
+            ..Default::default()
+        }];
+        let beneficiary = Address::random();
+        let gas = U256::from(1_000_000);
+        let gas_fees = GasFees {
+            max_fee_per_gas: 100.into(),
+            max_priority_fee_per_gas: 10.into(),
+        };
+
+        let eip1559_tx = entry_point.get_send_bundle_transaction(
+            bundle.clone(),
+            beneficiary,
+            gas,
+            gas_fees,
+            TransactionType::Eip1559,
+        );
+        let legacy_tx = entry_point.get_send_bundle_transaction(
+            bundle,
+            beneficiary,
+            gas,
+            gas_fees,
+            TransactionType::Legacy,
+        );
+
+        let TypedTransaction::Eip1559(eip1559) = &eip1559_tx else {
+            panic!("should build an EIP-1559 transaction");
+        };
+        assert_eq!(eip1559.max_fee_per_gas, Some(gas_fees.max_fee_per_gas));
+        assert_eq!(
+            eip1559.max_priority_fee_per_gas,
+            Some(gas_fees.max_priority_fee_per_gas)
+        );
+        let TypedTransaction::Legacy(legacy) = &legacy_tx else {
+            panic!("should build a legacy transaction");
+        };
+        assert_eq!(legacy.gas_price, Some(gas_fees.max_fee_per_gas));
+
+        assert_eq!(eip1559_tx.to(), legacy_tx.to());
+        assert_eq!(eip1559_tx.data(), legacy_tx.data());
+        assert_eq!(eip1559_tx.gas(), legacy_tx.gas());
+    }
+}
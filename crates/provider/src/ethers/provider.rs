@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Debug, sync::Arc};
+use std::{any::Any, collections::HashMap, fmt::Debug, sync::Arc};
 
 use anyhow::Context;
 use ethers::{
@@ -19,7 +19,7 @@ use ethers::{
     prelude::ContractError as EthersContractError,
     providers::{
         JsonRpcClient, Middleware, Provider as EthersProvider,
-        ProviderError as EthersProviderError, RawCall,
+        ProviderError as EthersProviderError, PubsubClient, RawCall, Ws,
     },
     types::{
         spoof, transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
@@ -28,6 +28,7 @@ use ethers::{
         H256, U256, U64,
     },
 };
+use futures_util::{future, StreamExt};
 use rundler_types::{
     contracts::{
         gas_price_oracle::GasPriceOracle, i_aggregator::IAggregator, i_entry_point::IEntryPoint,
@@ -37,7 +38,10 @@ use rundler_types::{
 };
 use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{AggregatorOut, AggregatorSimOut, Provider, ProviderError, ProviderResult};
+use crate::{
+    AggregatorOut, AggregatorSimOut, PendingTransactionStream, Provider, ProviderError,
+    ProviderResult,
+};
 
 const ARBITRUM_NITRO_NODE_INTERFACE_ADDRESS: Address = H160([
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xc8,
@@ -105,6 +109,38 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::get_transaction(self, transaction_hash).await?)
     }
 
+    async fn txpool_contains(&self, from: Address, tx_hash: H256) -> ProviderResult<bool> {
+        // Only fetch the sender's transactions rather than the whole mempool.
+        // The result maps "pending" and "queued" to transactions by nonce.
+        let content: HashMap<String, HashMap<String, Transaction>> =
+            EthersProvider::request(self, "txpool_contentFrom", [from]).await?;
+        Ok(content
+            .values()
+            .flat_map(|txs| txs.values())
+            .any(|tx| tx.hash == tx_hash))
+    }
+
+    async fn subscribe_pending_transactions(
+        &self,
+    ) -> ProviderResult<Option<PendingTransactionStream>> {
+        // Only websocket connections support subscriptions
+        let Some(ws) = (AsRef::<C>::as_ref(self) as &dyn Any).downcast_ref::<Ws>() else {
+            return Ok(None);
+        };
+        let id: U256 =
+            EthersProvider::request(self, "eth_subscribe", ["newPendingTransactions"]).await?;
+        let notifications = ws
+            .subscribe(id)
+            .context("should subscribe to pending transactions")?;
+        // The stream holds a handle to the connection so that it stays open
+        // for as long as the stream is in use
+        let ws = ws.clone();
+        Ok(Some(Box::pin(notifications.filter_map(move |hash| {
+            let _connection = &ws;
+            future::ready(serde_json::from_str(hash.get()).ok())
+        }))))
+    }
+
     async fn get_transaction_receipt<T: Send + Sync + Into<TxHash> + 'static>(
         &self,
         transaction_hash: T,
@@ -166,6 +202,14 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(self.request("eth_maxPriorityFeePerGas", ()).await?)
     }
 
+    async fn get_block_gas_limit(&self, block_hash: H256) -> ProviderResult<U256> {
+        Ok(Middleware::get_block(self, block_hash)
+            .await
+            .context("should load block to get gas limit")?
+            .context("block should exist to get gas limit")?
+            .gas_limit)
+    }
+
     async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
         Ok(Middleware::get_logs(self, filter).await?)
     }
@@ -288,3 +332,62 @@ impl<M: Middleware> From<EthersContractError<M>> for ProviderError {
         ProviderError::ContractError(e.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_latest_block_hash_and_number_uses_one_call() {
+        let (provider, mock) = EthersProvider::mocked();
+        let hash = H256::random();
+        mock.push(Block::<H256> {
+            hash: Some(hash),
+            number: Some(100.into()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let (latest_hash, latest_number) = Provider::get_latest_block_hash_and_number(&provider)
+            .await
+            .unwrap();
+        assert_eq!(latest_hash, hash);
+        assert_eq!(latest_number, U64::from(100));
+
+        // Both values came from a single request for the latest block
+        mock.assert_request("eth_getBlockByNumber", ("latest", false))
+            .unwrap();
+        assert!(mock
+            .assert_request("eth_getBlockByNumber", ("latest", false))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_txpool_contains() {
+        let (provider, mock) = EthersProvider::mocked();
+        let from = Address::random();
+        let tx_hash = H256::random();
+        let content = serde_json::json!({
+            "pending": {
+                "4": Transaction {
+                    hash: tx_hash,
+                    from,
+                    nonce: 4.into(),
+                    ..Default::default()
+                },
+            },
+            "queued": {},
+        });
+        // Responses are served last in, first out
+        mock.push(content.clone()).unwrap();
+        mock.push(content).unwrap();
+
+        assert!(Provider::txpool_contains(&provider, from, tx_hash)
+            .await
+            .unwrap());
+        assert!(!Provider::txpool_contains(&provider, from, H256::random())
+            .await
+            .unwrap());
+        mock.assert_request("txpool_contentFrom", [from]).unwrap();
+    }
+}
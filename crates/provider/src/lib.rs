@@ -25,8 +25,8 @@ mod ethers;
 
 mod traits;
 pub use traits::{
-    AggregatorOut, AggregatorSimOut, EntryPoint, HandleOpsOut, PaymasterHelper, Provider,
-    ProviderError, ProviderResult, StakeManager, NonceManager,
+    AggregatorOut, AggregatorSimOut, EntryPoint, HandleOpsOut, PaymasterHelper,
+    PendingTransactionStream, Provider, ProviderError, ProviderResult, StakeManager, NonceManager,
 };
 #[cfg(any(test, feature = "test-utils"))]
 pub use traits::{MockEntryPoint, MockPaymasterHelper, MockProvider, MockStakeManager, MockNonceManager};
@@ -18,7 +18,7 @@ use ethers::types::{
 use mockall::automock;
 use rundler_types::{
     contracts::{i_entry_point::ExecutionResult, shared_types::UserOpsPerAggregator},
-    GasFees, UserOperation,
+    GasFees, TransactionType, UserOperation,
 };
 
 /// Result of an entry point handle ops call
@@ -33,6 +33,12 @@ pub enum HandleOpsOut {
     /// Call failed due to a bug in the 0.6 entry point contract https://github.com/eth-infinitism/account-abstraction/pull/325.
     /// Special handling is required to remove the offending operation from the bundle.
     PostOpRevert,
+    /// Call failed with an entry point error that handle ops is not expected to
+    /// revert with, or with a revert reason string, described by `String`
+    EntryPointError(String),
+    /// Call failed with revert data that does not decode to any known error,
+    /// carrying the 4-byte selector of the revert data
+    Reverted([u8; 4]),
 }
 
 /// Trait for interacting with an entry point contract.
@@ -44,14 +50,27 @@ pub trait EntryPoint: Send + Sync + 'static {
     /// Get the address of the entry point contract
     fn address(&self) -> Address;
 
-    /// Call the entry point contract's `handleOps` function
+    /// Call the entry point contract's `handleOps` function, against the
+    /// given block or the latest block if `None`
     async fn call_handle_ops(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator>,
         beneficiary: Address,
         gas: U256,
+        block_id: Option<BlockId>,
     ) -> anyhow::Result<HandleOpsOut>;
 
+    /// Estimate the gas used by the entry point contract's `handleOps`
+    /// function with `eth_estimateGas`, capped at `gas`, against the given
+    /// block or the latest block if `None`
+    async fn estimate_handle_ops_gas(
+        &self,
+        ops_per_aggregator: Vec<UserOpsPerAggregator>,
+        beneficiary: Address,
+        gas: U256,
+        block_id: Option<BlockId>,
+    ) -> anyhow::Result<U256>;
+
     /// Get the balance of an address
     async fn balance_of(&self, address: Address, block_id: Option<BlockId>)
         -> anyhow::Result<U256>;
@@ -64,24 +83,28 @@ pub trait EntryPoint: Send + Sync + 'static {
     ) -> anyhow::Result<TypedTransaction>;
 
     /// Call the entry point contract's `simulateHandleOps` function
-    /// with a spoofed state
+    /// with a spoofed state at the given block, by hash or by number
     async fn call_spoofed_simulate_op(
         &self,
         op: UserOperation,
         target: Address,
         target_call_data: Bytes,
-        block_hash: H256,
+        block_id: BlockId,
         gas: U256,
         spoofed_state: &spoof::State,
     ) -> anyhow::Result<Result<ExecutionResult, String>>;
 
     /// Construct the transaction to send a bundle of operations to the entry point contract
+    ///
+    /// The gas fees are applied according to `transaction_type`, legacy transactions use the
+    /// max fee as their gas price.
     fn get_send_bundle_transaction(
         &self,
         ops_per_aggregator: Vec<UserOpsPerAggregator>,
         beneficiary: Address,
         gas: U256,
         gas_fees: GasFees,
+        transaction_type: TransactionType,
     ) -> TypedTransaction;
 
     /// Decode the revert data from a call to `simulateHandleOps`
@@ -90,6 +113,12 @@ pub trait EntryPoint: Send + Sync + 'static {
         revert_data: Bytes,
     ) -> Result<ExecutionResult, String>;
 
+    /// Call the entry point contract's `getUserOpHash` function
+    ///
+    /// Used to cross-check the locally computed operation hash against the one
+    /// computed by the deployed contract.
+    async fn get_user_op_hash(&self, op: UserOperation) -> anyhow::Result<H256>;
+
     /// Get the AA nonce for an account. Used for Hybrid Compute
     async fn get_nonce(&self, address: Address, key: ::ethers::core::types::U256) -> Result<::ethers::core::types::U256, String>;
 }
@@ -24,7 +24,9 @@ pub use entry_point::{EntryPoint, HandleOpsOut};
 mod provider;
 #[cfg(feature = "test-utils")]
 pub use provider::MockProvider;
-pub use provider::{AggregatorOut, AggregatorSimOut, Provider, ProviderResult};
+pub use provider::{
+    AggregatorOut, AggregatorSimOut, PendingTransactionStream, Provider, ProviderResult,
+};
 
 mod stake_manager;
 #[cfg(feature = "test-utils")]
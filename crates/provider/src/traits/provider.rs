@@ -13,13 +13,14 @@
 
 //! Trait for interacting with chain data and contracts.
 
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, pin::Pin, sync::Arc};
 
 use ethers::types::{
     spoof, transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
     FeeHistory, Filter, GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace, Log,
     Transaction, TransactionReceipt, TxHash, H256, U256, U64,
 };
+use futures_util::Stream;
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 use rundler_types::UserOperation;
@@ -50,6 +51,9 @@ pub enum AggregatorOut {
 /// Result of a provider method call
 pub type ProviderResult<T> = Result<T, ProviderError>;
 
+/// Stream of the hashes of transactions entering the node's mempool
+pub type PendingTransactionStream = Pin<Box<dyn Stream<Item = H256> + Send>>;
+
 /// Trait for interacting with chain data and contracts.
 #[cfg_attr(feature = "test-utils", automock)]
 #[async_trait::async_trait]
@@ -94,6 +98,18 @@ pub trait Provider: Send + Sync + Debug + 'static {
         tx: T,
     ) -> ProviderResult<Option<Transaction>>;
 
+    /// Returns true if the transaction sent by `from` is waiting in the node's
+    /// mempool, as opposed to having been mined, dropped, or never seen by the
+    /// node. Requires the node's `txpool` namespace.
+    async fn txpool_contains(&self, from: Address, tx_hash: H256) -> ProviderResult<bool>;
+
+    /// Subscribes to the hashes of transactions entering the node's mempool.
+    /// Returns `None` if the transport does not support subscriptions, in
+    /// which case callers should poll instead.
+    async fn subscribe_pending_transactions(
+        &self,
+    ) -> ProviderResult<Option<PendingTransactionStream>>;
+
     /// Get transaction receipt by hash
     async fn get_transaction_receipt<T: Send + Sync + Into<TxHash> + 'static>(
         &self,
@@ -124,6 +140,9 @@ pub trait Provider: Send + Sync + Debug + 'static {
     /// Get the max fee per gas as reported by the node's RPC
     async fn get_max_priority_fee(&self) -> ProviderResult<U256>;
 
+    /// Get the gas limit from the header of the block with the given hash
+    async fn get_block_gas_limit(&self, block_hash: H256) -> ProviderResult<U256>;
+
     /// Get the code at an address
     async fn get_code(&self, address: Address, block_hash: Option<H256>) -> ProviderResult<Bytes>;
 
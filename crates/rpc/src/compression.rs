@@ -0,0 +1,83 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use futures_util::stream;
+use hyper::{body::HttpBody, Body, Response};
+use tower_http::compression::CompressionBody;
+
+/// Converts a response from `tower_http`'s `CompressionLayer` back into a
+/// plain `hyper` response, as the JSON-RPC server can't serve the compressed
+/// body type directly.
+pub(crate) fn into_hyper_body(response: Response<CompressionBody<Body>>) -> Response<Body> {
+    response.map(|body| {
+        Body::wrap_stream(stream::unfold(Box::pin(body), |mut body| async move {
+            body.data().await.map(|chunk| (chunk, body))
+        }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::{
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+        Request,
+    };
+    use tower::{service_fn, ServiceBuilder, ServiceExt};
+    use tower_http::compression::CompressionLayer;
+
+    use super::*;
+
+    fn large_response() -> String {
+        let ops = vec![r#"{"sender":"0x0000000000000000000000000000000000000001"}"#; 1000];
+        format!(r#"{{"jsonrpc":"2.0","id":1,"result":[{}]}}"#, ops.join(","))
+    }
+
+    async fn call(accept_encoding: Option<&str>) -> Response<Body> {
+        let service = ServiceBuilder::new()
+            .map_response(into_hyper_body)
+            .layer(CompressionLayer::new())
+            .service(service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from(large_response())))
+            }));
+        let mut request = Request::post("/");
+        if let Some(accept_encoding) = accept_encoding {
+            request = request.header(ACCEPT_ENCODING, accept_encoding);
+        }
+        service
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compresses_when_accepted() {
+        let response = call(Some("gzip")).await;
+        assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        // gzip magic number
+        assert_eq!(body[..2], [0x1f, 0x8b]);
+        assert!(body.len() < large_response().len());
+    }
+
+    #[tokio::test]
+    async fn test_uncompressed_when_not_accepted() {
+        let response = call(None).await;
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, large_response());
+    }
+}
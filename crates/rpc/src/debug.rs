@@ -15,7 +15,7 @@ use async_trait::async_trait;
 use ethers::types::{Address, H256};
 use futures_util::StreamExt;
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
-use rundler_builder::{BuilderServer, BundlingMode};
+use rundler_builder::{BuilderServer, BuilderServerError, BundlingMode};
 use rundler_pool::PoolServer;
 
 use crate::{
@@ -28,9 +28,10 @@ use crate::{
 /// Debug API
 #[rpc(client, server, namespace = "debug")]
 pub trait DebugApi {
-    /// Clears the state of the pool.
+    /// Clears the mempool and reputations of the given entry point, or of all
+    /// entry points if not provided.
     #[method(name = "bundler_clearState")]
-    async fn bundler_clear_state(&self) -> RpcResult<String>;
+    async fn bundler_clear_state(&self, entry_point: Option<Address>) -> RpcResult<String>;
 
     /// Clears the state of the mempool without affect reputations.
     #[method(name = "bundler_clearMempool")]
@@ -40,11 +41,12 @@ pub trait DebugApi {
     #[method(name = "bundler_dumpMempool")]
     async fn bundler_dump_mempool(&self, entry_point: Address) -> RpcResult<Vec<RpcUserOperation>>;
 
-    /// Triggers the builder to send a bundle now
+    /// Triggers the builder to send a bundle now, returning its transaction
+    /// hash, or null if there were no operations to bundle.
     ///
     /// Note that the bundling mode must be set to `Manual` else this will fail.
     #[method(name = "bundler_sendBundleNow")]
-    async fn bundler_send_bundle_now(&self) -> RpcResult<H256>;
+    async fn bundler_send_bundle_now(&self) -> RpcResult<Option<H256>>;
 
     /// Sets the bundling mode.
     #[method(name = "bundler_setBundlingMode")]
@@ -91,10 +93,10 @@ where
     P: PoolServer,
     B: BuilderServer,
 {
-    async fn bundler_clear_state(&self) -> RpcResult<String> {
+    async fn bundler_clear_state(&self, entry_point: Option<Address>) -> RpcResult<String> {
         let _ = self
             .pool
-            .debug_clear_state(true, true)
+            .debug_clear_state(true, true, entry_point)
             .await
             .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
 
@@ -104,7 +106,7 @@ where
     async fn bundler_clear_mempool(&self) -> RpcResult<String> {
         let _ = self
             .pool
-            .debug_clear_state(true, false)
+            .debug_clear_state(true, false, None)
             .await
             .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
 
@@ -122,18 +124,18 @@ where
             .collect::<Vec<RpcUserOperation>>())
     }
 
-    async fn bundler_send_bundle_now(&self) -> RpcResult<H256> {
+    async fn bundler_send_bundle_now(&self) -> RpcResult<Option<H256>> {
         let mut new_heads = self
             .pool
             .subscribe_new_heads()
             .await
             .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
 
-        let (tx, block_number) = self
-            .builder
-            .debug_send_bundle_now()
-            .await
-            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+        let (tx, block_number) = match self.builder.debug_send_bundle_now().await {
+            Ok(sent) => sent,
+            Err(BuilderServerError::NoOperations) => return Ok(None),
+            Err(e) => return Err(rpc_err(INTERNAL_ERROR_CODE, e.to_string())),
+        };
 
         // After the bundle is sent, we need to make sure that the mempool
         // has processes the same block that the transaction was mined on.
@@ -152,7 +154,7 @@ where
             }
         }
 
-        Ok(tx)
+        Ok(Some(tx))
     }
 
     async fn bundler_set_bundling_mode(&self, mode: BundlingMode) -> RpcResult<String> {
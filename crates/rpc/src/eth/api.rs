@@ -12,26 +12,28 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     sync::Arc,
 };
 
 use anyhow::Context;
 use ethers::{
-    abi::{AbiDecode, RawLog},
+    abi::{self, AbiDecode, RawLog, Token},
     prelude::EthEvent,
     types::{
-        spoof, Address, Bytes, Filter, GethDebugBuiltInTracerType, GethDebugTracerType,
-        GethDebugTracingOptions, GethTrace, GethTraceFrame, Log, TransactionReceipt, H256, U256,
+        spoof, transaction::eip2718::TypedTransaction, Address, Bytes, Eip1559TransactionRequest,
+        Filter, GethDebugBuiltInTracerType, GethDebugTracerType, GethDebugTracingOptions,
+        GethTrace, GethTraceFrame, Log, Signature, TransactionReceipt, H256, U256,
         U64,BigEndianHash,
     },
-    utils::{to_checksum, hex},
+    utils::{hash_message, to_checksum, hex},
 };
-use rundler_pool::PoolServer;
+use futures_util::future;
+use rundler_pool::{OpStatus, PoolServer};
 use rundler_provider::{EntryPoint, Provider };
 use rundler_sim::{
-    EstimationSettings, FeeEstimator, GasEstimate, GasEstimationError, GasEstimator,
-    GasEstimatorImpl, PrecheckSettings, UserOperationOptionalGas,
+    gas::CalldataGasConstants, EstimationSettings, FeeEstimator, GasEstimate, GasEstimationError,
+    GasEstimator, GasEstimatorImpl, PrecheckSettings, PrecheckViolation, UserOperationOptionalGas,
 };
 use rundler_types::{
     contracts::i_entry_point::{
@@ -39,13 +41,16 @@ use rundler_types::{
     },
     contracts::hc_helper::{HCHelper as HH2},
     contracts::simple_account::SimpleAccount,
-    UserOperation,
+    Timestamp, UserOperation,
 };
 use rundler_utils::{eth::log_to_raw_log, log::LogOnError};
 use tracing::Level;
 
-use super::error::{EthResult, EthRpcError, ExecutionRevertedWithBytesData};
-use crate::types::{RichUserOperation, RpcUserOperation, UserOperationReceipt};
+use super::error::{EthResult, EthRpcError};
+use crate::types::{
+    RichUserOperation, RpcUserOperation, RpcUserOperationStatus, SendUserOperationBatchResult,
+    UserOperationReceipt,
+};
 
 use rundler_types::hybrid_compute;
 //use ethers::types::BigEndianHash;
@@ -61,6 +66,26 @@ use rundler_utils::eth;
 pub struct Settings {
     /// The number of blocks to look back for user operation events
     pub user_operation_event_block_distance: Option<u64>,
+    /// Senders allowed to submit operations. If empty, all senders are allowed.
+    pub allowed_senders: HashSet<Address>,
+    /// Senders that are not allowed to submit operations
+    pub denied_senders: HashSet<Address>,
+    /// Senders whose bundler tips are accepted, as they pay them under an
+    /// off-chain agreement with the operator. Tips are unenforced, so
+    /// operations from other senders that declare a tip are rejected.
+    pub bundler_tip_senders: HashSet<Address>,
+    /// Operations with a higher verification gas limit are rejected. If `None`,
+    /// there is no limit.
+    pub max_verification_gas: Option<U256>,
+    /// Whether to return a partial receipt, with the bundle transaction hash and
+    /// no block fields, for operations in a bundle that has been sent but not
+    /// yet mined. Otherwise no receipt is returned until the bundle is mined.
+    pub partial_receipts: bool,
+    /// Whether to reject operations whose signature recovers to an address
+    /// other than the `owner()` of the sender, as happens when an op is signed
+    /// for another chain. Only suitable when accounts use a plain ECDSA
+    /// signature by their owner, since other signature schemes are rejected too.
+    pub check_signature_chain: bool,
     /// HybridCompute info
     pub hc: hybrid_compute::HcCfg,
 }
@@ -72,6 +97,12 @@ impl Settings {
     ) -> Self {
         Self {
             user_operation_event_block_distance: block_distance,
+            allowed_senders: HashSet::new(),
+            denied_senders: HashSet::new(),
+            bundler_tip_senders: HashSet::new(),
+            max_verification_gas: None,
+            partial_receipts: false,
+            check_signature_chain: false,
 	    hc: hybrid_compute::HC_CONFIG.lock().unwrap().clone(),
         }
     }
@@ -110,6 +141,17 @@ where
 // hope for the best. Unused gas will be refunded.
 const VG_PAD:i32 = 20000;
 
+/// Prefix of the message a sender signs (EIP-191) to cancel a pending user
+/// operation. The user operation hash is appended to it.
+const CANCEL_USER_OPERATION_PREFIX: &[u8] = b"Cancel user operation: ";
+/// `bytes4(keccak256("owner()"))`
+const OWNER_SELECTOR: [u8; 4] = [0x8d, 0xa5, 0xcb, 0x5b];
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`
+const ERC1271_IS_VALID_SIGNATURE_SELECTOR: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+/// Highest address in the range reserved for precompiles. Senders at or below
+/// it, including the zero address, can't be accounts.
+const MAX_RESERVED_SENDER: u64 = 0xff;
+
 #[derive(Debug)]
 pub(crate) struct EthApi<P, E, PS> where E: EntryPoint {
     contexts_by_entry_point: HashMap<Address, EntryPointContext<P, E>>,
@@ -152,6 +194,8 @@ where
                             chain_id,
                             precheck_settings.priority_fee_mode,
                             precheck_settings.bundle_priority_fee_overhead_percent,
+                            precheck_settings.use_dynamic_max_priority_fee,
+                            precheck_settings.static_max_priority_fee,
                         ),
                     ),
                 )
@@ -171,6 +215,8 @@ where
         &self,
         op: RpcUserOperation,
         entry_point: Address,
+        submission_deadline: Option<Timestamp>,
+        bundler_tip: Option<U256>,
     ) -> EthResult<H256> {
         if !self.contexts_by_entry_point.contains_key(&entry_point) {
             return Err(EthRpcError::InvalidParams(
@@ -178,13 +224,204 @@ where
             ));
         }
 	println!("HC send_user_operation {:?}", op);
+        let op: UserOperation = op.into();
+        op.validate_structure()
+            .map_err(|e| EthRpcError::InvalidParams(e.to_string()))?;
+        check_sender_not_reserved(op.sender, entry_point)?;
+        self.check_sender_allowed(op.sender)?;
+        self.check_bundler_tip_allowed(op.sender, bundler_tip)?;
+        if self.settings.check_signature_chain {
+            self.check_signed_for_chain(&op, entry_point).await?;
+        }
         self.pool
-            .add_op(entry_point, op.into())
+            .add_op(entry_point, op, submission_deadline, bundler_tip)
             .await
             .map_err(EthRpcError::from)
             .log_on_error_level(Level::DEBUG, "failed to add op to the mempool")
     }
 
+    pub(crate) async fn send_user_operation_batch(
+        &self,
+        ops: Vec<(RpcUserOperation, Address)>,
+    ) -> EthResult<Vec<SendUserOperationBatchResult>> {
+        let results = future::join_all(
+            ops.into_iter()
+                .map(|(op, entry_point)| self.send_user_operation(op, entry_point, None, None)),
+        )
+        .await;
+        Ok(results
+            .into_iter()
+            .map(|result| match result {
+                Ok(hash) => SendUserOperationBatchResult {
+                    user_op_hash: Some(hash),
+                    error: None,
+                },
+                Err(error) => SendUserOperationBatchResult {
+                    user_op_hash: None,
+                    error: Some(error.into()),
+                },
+            })
+            .collect())
+    }
+
+    /// Rejects ops from senders that are denied, or that are missing from a
+    /// non-empty allowlist.
+    fn check_sender_allowed(&self, sender: Address) -> EthResult<()> {
+        let allowed = &self.settings.allowed_senders;
+        if self.settings.denied_senders.contains(&sender)
+            || (!allowed.is_empty() && !allowed.contains(&sender))
+        {
+            return Err(EthRpcError::SenderNotAllowed(sender));
+        }
+        Ok(())
+    }
+
+    /// Rejects bundler tips from senders that are not trusted to pay them, as
+    /// nothing on chain enforces the payment and an untrusted tip would let
+    /// any sender jump the bundle order for free.
+    fn check_bundler_tip_allowed(
+        &self,
+        sender: Address,
+        bundler_tip: Option<U256>,
+    ) -> EthResult<()> {
+        match bundler_tip {
+            Some(tip) if !tip.is_zero() && !self.settings.bundler_tip_senders.contains(&sender) => {
+                Err(EthRpcError::InvalidParams(format!(
+                    "bundler tips are not accepted from sender {sender:?}"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects ops with a verification gas limit above the configured maximum,
+    /// as these can be expensive to simulate. Sent ops are already checked by
+    /// the pool's precheck, so this is only needed before estimation.
+    fn check_verification_gas_limit(&self, verification_gas_limit: U256) -> EthResult<()> {
+        match self.settings.max_verification_gas {
+            Some(max) if verification_gas_limit > max => Err(
+                PrecheckViolation::VerificationGasLimitTooHigh(verification_gas_limit, max).into(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rejects ops that appear to have been signed for a different chain.
+    ///
+    /// The op hash commits to the chain id, so an ECDSA signature over the hash
+    /// for another chain recovers to an address other than the account owner.
+    /// This is only detectable for accounts with a plain ECDSA signature that
+    /// expose `owner()`, so it only runs when enabled in the settings.
+    async fn check_signed_for_chain(
+        &self,
+        op: &UserOperation,
+        entry_point: Address,
+    ) -> EthResult<()> {
+        let Some(signer) = op.recover_signer(entry_point, self.chain_id) else {
+            return Ok(());
+        };
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(op.sender)
+            .data(OWNER_SELECTOR.to_vec())
+            .into();
+        let owner = match self.provider.call(&tx, None, &spoof::State::default()).await {
+            Ok(ret) if ret.len() == 32 => Address::from_slice(&ret[12..]),
+            // undeployed or not an owned account, can't tell
+            _ => return Ok(()),
+        };
+
+        if owner != signer {
+            return Err(EthRpcError::InvalidChainId(self.chain_id));
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn cancel_user_operation(
+        &self,
+        hash: H256,
+        signature: Bytes,
+    ) -> EthResult<bool> {
+        if hash == H256::zero() {
+            return Err(EthRpcError::InvalidParams(
+                "Missing/invalid userOpHash".to_string(),
+            ));
+        }
+
+        // an op that has already landed on chain can't be cancelled
+        let mined = self
+            .get_user_operation_event_by_hash(hash)
+            .await
+            .log_on_error("should have successfully queried for user op events by hash")?;
+        if mined.is_some() {
+            return Err(EthRpcError::InvalidParams(
+                "user operation has already been mined".to_string(),
+            ));
+        }
+
+        let Some(op) = self
+            .pool
+            .get_op_by_hash(hash)
+            .await
+            .map_err(EthRpcError::from)?
+        else {
+            return Err(EthRpcError::InvalidParams(
+                "user operation not found in the mempool".to_string(),
+            ));
+        };
+
+        if !self
+            .is_valid_cancellation_signature(op.uo.sender, hash, &signature)
+            .await
+        {
+            return Err(EthRpcError::InvalidCancellationSignature(op.uo.sender));
+        }
+
+        self.pool
+            .remove_ops(op.entry_point, vec![hash])
+            .await
+            .map_err(EthRpcError::from)?;
+        Ok(true)
+    }
+
+    /// Checks that `signature` over the cancellation message for `hash` was
+    /// produced by `sender`, either directly as an EOA or through the sender
+    /// contract's ERC-1271 `isValidSignature`.
+    async fn is_valid_cancellation_signature(
+        &self,
+        sender: Address,
+        hash: H256,
+        signature: &Bytes,
+    ) -> bool {
+        let message = cancellation_message(hash);
+        if let Ok(sig) = Signature::try_from(signature.as_ref()) {
+            if sig
+                .recover(message.clone())
+                .is_ok_and(|signer| signer == sender)
+            {
+                return true;
+            }
+        }
+
+        let call_data = [
+            ERC1271_IS_VALID_SIGNATURE_SELECTOR.as_slice(),
+            &abi::encode(&[
+                Token::FixedBytes(hash_message(&message).as_bytes().to_vec()),
+                Token::Bytes(signature.to_vec()),
+            ]),
+        ]
+        .concat();
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(sender)
+            .data(call_data)
+            .into();
+        // a sender that doesn't implement ERC-1271 reverts, which is a rejection
+        match self.provider.call(&tx, None, &spoof::State::default()).await {
+            Ok(ret) => ret.len() >= 4 && ret[..4] == ERC1271_IS_VALID_SIGNATURE_SELECTOR,
+            Err(_) => false,
+        }
+    }
+
     // Verify that the trigger string came from the HCHelper contract
     async fn hc_verify_trigger(
         &self,
@@ -233,6 +470,8 @@ where
               max_call_gas: 0,
               max_simulate_handle_ops_gas: 0,
               validation_estimation_gas_fee: 0,
+              pre_verification_gas_breakdown: false,
+              calldata_gas: CalldataGasConstants::default(),
         };
         let hh = op.clone().into_user_operation(&es).op_hc_hash();
 	println!("HC api.rs hh {:?}", hh);
@@ -428,6 +667,9 @@ where
 	        pre_verification_gas: needed_pvg,
 	        verification_gas_limit: r3.verification_gas_limit,
 	        call_gas_limit: r3.call_gas_limit,
+	        // The breakdown no longer matches the offchain-adjusted pre verification gas
+	        breakdown: None,
+	        ..r3
 	    });
 	} else {
             return result2;
@@ -448,6 +690,9 @@ where
                     "supplied entry_point address is not a known entry point".to_string(),
                 )
             })?;
+        if let Some(verification_gas_limit) = op.verification_gas_limit {
+            self.check_verification_gas_limit(verification_gas_limit)?;
+        }
 
 	//println!("HC api.rs Before estimate_gas {:?}", op);
         let mut result = context
@@ -480,19 +725,47 @@ where
                 pre_verification_gas: estimate.pre_verification_gas,
 	        verification_gas_limit: estimate.verification_gas_limit + VG_PAD,
 	        call_gas_limit: estimate.call_gas_limit,
+                ..estimate
             }),
-            Err(GasEstimationError::RevertInValidation(message)) => {
-                Err(EthRpcError::EntryPointValidationRejected(message))?
-            }
-            Err(GasEstimationError::RevertInCallWithMessage(message)) => {
-                Err(EthRpcError::ExecutionReverted(message))?
-            }
-            Err(GasEstimationError::RevertInCallWithBytes(b)) => {
-                Err(EthRpcError::ExecutionRevertedWithBytes(
-                    ExecutionRevertedWithBytesData { revert_data: b },
-                ))?
-            }
-            Err(GasEstimationError::Other(error)) => Err(error)?,
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub(crate) async fn estimate_pre_verification_gas(
+        &self,
+        op: UserOperationOptionalGas,
+        entry_point: Address,
+    ) -> EthResult<U256> {
+        let context = self
+            .contexts_by_entry_point
+            .get(&entry_point)
+            .ok_or_else(|| {
+                EthRpcError::InvalidParams(
+                    "supplied entry_point address is not a known entry point".to_string(),
+                )
+            })?;
+
+        // Hybrid compute ops also pay for their offchain ops, which is only known
+        // once a full estimate has simulated the offchain response
+        let es = EstimationSettings {
+            max_verification_gas: 0,
+            max_call_gas: 0,
+            max_simulate_handle_ops_gas: 0,
+            validation_estimation_gas_fee: 0,
+            pre_verification_gas_breakdown: false,
+            calldata_gas: CalldataGasConstants::default(),
+        };
+        let hc_pvg = hybrid_compute::hc_get_pvg(op.clone().into_user_operation(&es).op_hc_hash());
+
+        match context
+            .gas_estimator
+            .estimate_pre_verification_gas(op, None)
+            .await
+        {
+            Ok(pre_verification_gas) => Ok(hc_pvg.map_or(pre_verification_gas, |hc_pvg| {
+                pre_verification_gas.max(hc_pvg)
+            })),
+            Err(error) => Err(error.into()),
         }
     }
 
@@ -529,6 +802,24 @@ where
         }
     }
 
+    pub(crate) async fn get_user_operation_status(
+        &self,
+        hash: H256,
+    ) -> EthResult<RpcUserOperationStatus> {
+        if hash == H256::zero() {
+            return Err(EthRpcError::InvalidParams(
+                "Missing/invalid userOpHash".to_string(),
+            ));
+        }
+
+        let status = self
+            .pool
+            .get_op_status(hash)
+            .await
+            .map_err(EthRpcError::from)?;
+        Ok(status.into())
+    }
+
     pub(crate) async fn get_user_operation_receipt(
         &self,
         hash: H256,
@@ -545,7 +836,12 @@ where
             .await
             .context("should have fetched user ops by hash")?;
 
-        let Some(log) = log else { return Ok(None) };
+        let Some(log) = log else {
+            if self.settings.partial_receipts {
+                return self.get_submitted_user_operation_receipt(hash).await;
+            }
+            return Ok(None);
+        };
         let entry_point = log.address;
 
         // If the event is found, get the TX receipt
@@ -587,13 +883,66 @@ where
             paymaster: uo_event.paymaster.into(),
             actual_gas_cost: uo_event.actual_gas_cost,
             actual_gas_used: uo_event.actual_gas_used,
-            success: uo_event.success,
+            effective_gas_price: effective_gas_price(
+                uo_event.actual_gas_cost,
+                uo_event.actual_gas_used,
+            ),
+            success: Some(uo_event.success),
             logs: filtered_logs,
             receipt: tx_receipt,
             reason,
         }))
     }
 
+    /// Returns a partial receipt for an operation in a bundle transaction that
+    /// has been sent but not yet mined, or `None` if it is in no such bundle.
+    /// The receipt has the bundle transaction hash and no block fields, logs
+    /// or gas usage.
+    async fn get_submitted_user_operation_receipt(
+        &self,
+        hash: H256,
+    ) -> EthResult<Option<UserOperationReceipt>> {
+        let status = self
+            .pool
+            .get_op_status(hash)
+            .await
+            .map_err(EthRpcError::from)?;
+        let OpStatus::Submitted { tx_hash } = status else {
+            return Ok(None);
+        };
+        // Ops left out of a fee-bumped replacement bundle still point at the
+        // replaced transaction, which the node no longer knows about
+        if self.provider.get_transaction(tx_hash).await?.is_none() {
+            return Ok(None);
+        }
+        let Some(op) = self
+            .pool
+            .get_op_by_hash(hash)
+            .await
+            .map_err(EthRpcError::from)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(UserOperationReceipt {
+            user_op_hash: hash,
+            entry_point: op.entry_point.into(),
+            sender: op.uo.sender.into(),
+            nonce: op.uo.nonce,
+            paymaster: op.uo.paymaster().unwrap_or_default().into(),
+            actual_gas_cost: U256::zero(),
+            actual_gas_used: U256::zero(),
+            effective_gas_price: None,
+            success: None,
+            reason: String::new(),
+            logs: vec![],
+            receipt: TransactionReceipt {
+                transaction_hash: tx_hash,
+                ..Default::default()
+            },
+        }))
+    }
+
     pub(crate) async fn supported_entry_points(&self) -> EthResult<Vec<String>> {
         Ok(self
             .contexts_by_entry_point
@@ -654,12 +1003,8 @@ where
         Ok(Some(RichUserOperation {
             user_operation: user_operation.into(),
             entry_point: event.address.into(),
-            block_number: Some(
-                tx.block_number
-                    .map(|n| U256::from(n.as_u64()))
-                    .unwrap_or_default(),
-            ),
-            block_hash: Some(tx.block_hash.unwrap_or_default()),
+            block_number: tx.block_number.map(|n| U256::from(n.as_u64())),
+            block_hash: tx.block_hash,
             transaction_hash: Some(transaction_hash),
         }))
     }
@@ -854,15 +1199,35 @@ where
     }
 }
 
+/// The message a sender signs to cancel the pending user operation with the given hash.
+fn cancellation_message(hash: H256) -> Vec<u8> {
+    [CANCEL_USER_OPERATION_PREFIX, hash.as_bytes()].concat()
+}
+
+/// The gas price a user operation effectively paid, or `None` if it used no gas.
+fn effective_gas_price(actual_gas_cost: U256, actual_gas_used: U256) -> Option<U256> {
+    actual_gas_cost.checked_div(actual_gas_used)
+}
+
+/// Rejects ops whose sender is the entry point, the zero address or a
+/// precompile, which would otherwise only fail during simulation.
+fn check_sender_not_reserved(sender: Address, entry_point: Address) -> EthResult<()> {
+    if sender == entry_point || sender <= Address::from_low_u64_be(MAX_RESERVED_SENDER) {
+        return Err(EthRpcError::InvalidSender(sender));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use ethers::{
         abi::AbiEncode,
+        signers::{LocalWallet, Signer},
         types::{Log, Transaction, TransactionReceipt},
         utils::keccak256,
     };
-    use mockall::predicate::eq;
-    use rundler_pool::{MockPoolServer, PoolOperation};
+    use mockall::{predicate::eq, Sequence};
+    use rundler_pool::{MempoolError, MockPoolServer, OpStatus, PoolOperation};
     use rundler_provider::{MockEntryPoint, MockProvider};
     use rundler_sim::PriorityFeeMode;
     use rundler_types::contracts::i_entry_point::HandleOpsCall;
@@ -1107,6 +1472,66 @@ mod tests {
         assert_eq!(res, Some(ro));
     }
 
+    #[tokio::test]
+    async fn test_get_user_op_by_hash_mined_still_in_pool() {
+        let ep = Address::random();
+        let uo = UserOperation::default();
+        let hash = uo.op_hash(ep, 1);
+        let block_number = 1000;
+        let block_hash = H256::random();
+
+        // The pool may not have processed the block containing the op yet
+        let po = PoolOperation {
+            uo: uo.clone(),
+            entry_point: ep,
+            ..Default::default()
+        };
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(Some(po.clone())));
+
+        let mut provider = MockProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(1000));
+
+        let tx_data: Bytes = IEntryPointCalls::HandleOps(HandleOpsCall {
+            beneficiary: Address::zero(),
+            ops: vec![uo.clone()],
+        })
+        .encode()
+        .into();
+        let tx = Transaction {
+            to: Some(ep),
+            input: tx_data,
+            block_number: Some(block_number.into()),
+            block_hash: Some(block_hash),
+            ..Default::default()
+        };
+        let tx_hash = tx.hash();
+        let log = Log {
+            address: ep,
+            transaction_hash: Some(tx_hash),
+            ..Default::default()
+        };
+
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![log.clone()]));
+        provider
+            .expect_get_transaction()
+            .with(eq(tx_hash))
+            .returning(move |_| Ok(Some(tx.clone())));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(provider, entry_point, pool);
+        let res = api.get_user_operation_by_hash(hash).await.unwrap().unwrap();
+        assert_eq!(res.transaction_hash, Some(tx_hash));
+        assert_eq!(res.block_hash, Some(block_hash));
+        assert_eq!(res.block_number, Some(block_number.into()));
+    }
+
     #[tokio::test]
     async fn test_get_user_op_by_hash_not_found() {
         let ep = Address::random();
@@ -1131,6 +1556,558 @@ mod tests {
         assert_eq!(res, None);
     }
 
+    #[tokio::test]
+    async fn test_get_user_op_status() {
+        let hash = H256::random();
+        let tx_hash = H256::random();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_status()
+            .with(eq(hash))
+            .times(1)
+            .returning(move |_| {
+                Ok(OpStatus::Mined {
+                    tx_hash,
+                    block_number: 42,
+                })
+            });
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(Address::random);
+
+        let api = create_api(MockProvider::default(), entry_point, pool);
+        let res = api.get_user_operation_status(hash).await.unwrap();
+        assert_eq!(
+            res,
+            RpcUserOperationStatus::Mined {
+                transaction_hash: tx_hash,
+                block_number: 42,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_user_op_receipt_partial_while_submitted() {
+        let ep = Address::random();
+        let uo = UserOperation {
+            sender: Address::random(),
+            nonce: 7.into(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        let tx_hash = H256::random();
+        let po = PoolOperation {
+            uo: uo.clone(),
+            entry_point: ep,
+            ..Default::default()
+        };
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_status()
+            .with(eq(hash))
+            .returning(move |_| Ok(OpStatus::Submitted { tx_hash }));
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(Some(po.clone())));
+
+        let mut provider = MockProvider::default();
+        provider.expect_get_logs().returning(move |_| Ok(vec![]));
+        provider.expect_get_block_number().returning(|| Ok(1000));
+        // The bundle transaction is replaced by a fee bump that leaves the op out
+        let mut seq = Sequence::new();
+        provider
+            .expect_get_transaction()
+            .with(eq(tx_hash))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(Some(Transaction::default())));
+        provider
+            .expect_get_transaction()
+            .with(eq(tx_hash))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Ok(None));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(provider, entry_point, pool);
+        let res = api.get_user_operation_receipt(hash).await.unwrap();
+        assert!(res.is_none());
+
+        api.settings.partial_receipts = true;
+        let res = api.get_user_operation_receipt(hash).await.unwrap().unwrap();
+        assert_eq!(res.user_op_hash, hash);
+        assert_eq!(res.sender, uo.sender.into());
+        assert_eq!(res.nonce, uo.nonce);
+        assert_eq!(res.success, None);
+        assert_eq!(res.receipt.transaction_hash, tx_hash);
+        assert_eq!(res.receipt.block_hash, None);
+        assert_eq!(res.receipt.block_number, None);
+        // The partial receipt leaves out the success flag
+        let json = serde_json::to_value(&res).unwrap();
+        assert!(json.get("success").is_none());
+
+        let res = api.get_user_operation_receipt(hash).await.unwrap();
+        assert!(res.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_user_op() {
+        let ep = Address::random();
+        let wallet = given_wallet(SENDER_KEY);
+        let uo = UserOperation {
+            sender: wallet.address(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        let signature = wallet
+            .sign_message(cancellation_message(hash))
+            .await
+            .unwrap();
+
+        let po = PoolOperation {
+            uo,
+            entry_point: ep,
+            ..Default::default()
+        };
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(Some(po.clone())));
+        pool.expect_remove_ops()
+            .with(eq(ep), eq(vec![hash]))
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let mut provider = MockProvider::default();
+        provider.expect_get_logs().returning(move |_| Ok(vec![]));
+        provider.expect_get_block_number().returning(|| Ok(1000));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(provider, entry_point, pool);
+        let res = api
+            .cancel_user_operation(hash, signature.to_vec().into())
+            .await
+            .unwrap();
+        assert!(res);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_user_op_wrong_signer() {
+        let ep = Address::random();
+        let uo = UserOperation {
+            sender: given_wallet(SENDER_KEY).address(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        let signature = given_wallet(OTHER_KEY)
+            .sign_message(cancellation_message(hash))
+            .await
+            .unwrap();
+
+        let po = PoolOperation {
+            uo,
+            entry_point: ep,
+            ..Default::default()
+        };
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(Some(po.clone())));
+        pool.expect_remove_ops().times(0);
+
+        let mut provider = MockProvider::default();
+        provider.expect_get_logs().returning(move |_| Ok(vec![]));
+        provider.expect_get_block_number().returning(|| Ok(1000));
+        // sender is not an ERC-1271 contract either
+        provider
+            .expect_call()
+            .returning(|_, _, _| Ok(Bytes::default()));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(provider, entry_point, pool);
+        let res = api
+            .cancel_user_operation(hash, signature.to_vec().into())
+            .await;
+        assert!(matches!(
+            res,
+            Err(EthRpcError::InvalidCancellationSignature(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_user_op_mined() {
+        let ep = Address::random();
+        let wallet = given_wallet(SENDER_KEY);
+        let uo = UserOperation {
+            sender: wallet.address(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        let signature = wallet
+            .sign_message(cancellation_message(hash))
+            .await
+            .unwrap();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_remove_ops().times(0);
+
+        let log = Log {
+            address: ep,
+            transaction_hash: Some(H256::random()),
+            ..Default::default()
+        };
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![log.clone()]));
+        provider.expect_get_block_number().returning(|| Ok(1000));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(provider, entry_point, pool);
+        let res = api
+            .cancel_user_operation(hash, signature.to_vec().into())
+            .await;
+        assert!(matches!(res, Err(EthRpcError::InvalidParams(_))));
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_signed_for_chain() {
+        let ep = Address::random();
+        let owner = given_wallet(SENDER_KEY);
+        let mut uo = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        uo.signature = owner.sign_message(hash).await.unwrap().to_vec().into();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op()
+            .times(1)
+            .returning(move |_, _, _, _| Ok(hash));
+
+        let mut provider = MockProvider::default();
+        let owner_address = owner.address();
+        provider.expect_call().returning(move |_, _, _| {
+            Ok(H256::from(owner_address).as_bytes().to_vec().into())
+        });
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(provider, entry_point, pool);
+        api.settings.check_signature_chain = true;
+        let res = api
+            .send_user_operation(uo.into(), ep, None, None)
+            .await
+            .unwrap();
+        assert_eq!(res, hash);
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_signed_for_other_chain() {
+        let ep = Address::random();
+        let owner = given_wallet(SENDER_KEY);
+        let mut uo = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        // the api is configured for chain 1
+        let other_chain_hash = uo.op_hash(ep, 5);
+        uo.signature = owner
+            .sign_message(other_chain_hash)
+            .await
+            .unwrap()
+            .to_vec()
+            .into();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op().times(0);
+
+        let mut provider = MockProvider::default();
+        let owner_address = owner.address();
+        provider.expect_call().returning(move |_, _, _| {
+            Ok(H256::from(owner_address).as_bytes().to_vec().into())
+        });
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(provider, entry_point, pool);
+        api.settings.check_signature_chain = true;
+        let res = api.send_user_operation(uo.into(), ep, None, None).await;
+        assert!(matches!(res, Err(EthRpcError::InvalidChainId(1))));
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_skips_chain_check_by_default() {
+        let ep = Address::random();
+        let owner = given_wallet(SENDER_KEY);
+        let mut uo = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        uo.signature = owner
+            .sign_message(uo.op_hash(ep, 5))
+            .await
+            .unwrap()
+            .to_vec()
+            .into();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op()
+            .times(1)
+            .returning(move |_, _, _, _| Ok(hash));
+
+        let mut provider = MockProvider::default();
+        provider.expect_call().times(0);
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(provider, entry_point, pool);
+        let res = api
+            .send_user_operation(uo.into(), ep, None, None)
+            .await
+            .unwrap();
+        assert_eq!(res, hash);
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_batch() {
+        let ep = Address::random();
+        let valid = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let known = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let valid_hash = valid.op_hash(ep, 1);
+        let known_sender = known.sender;
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op().times(2).returning(move |_, op, _, _| {
+            if op.sender == known_sender {
+                Err(MempoolError::OperationAlreadyKnown.into())
+            } else {
+                Ok(valid_hash)
+            }
+        });
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(MockProvider::default(), entry_point, pool);
+        let res = api
+            .send_user_operation_batch(vec![
+                (valid.clone().into(), ep),
+                (valid.into(), Address::random()),
+                (known.into(), ep),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].user_op_hash, Some(valid_hash));
+        assert!(res[0].error.is_none());
+        assert_eq!(res[1].user_op_hash, None);
+        assert_eq!(
+            res[1].error.as_ref().unwrap().message(),
+            "supplied entry point addr is not a known entry point"
+        );
+        assert_eq!(res[2].user_op_hash, None);
+        assert_eq!(res[2].error.as_ref().unwrap().message(), "already known");
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        assert_eq!(
+            effective_gas_price(U256::from(2_100_000), U256::from(21_000)),
+            Some(U256::from(100))
+        );
+        assert_eq!(
+            effective_gas_price(U256::from(2_100_000), U256::zero()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_default_allows_all_senders() {
+        let res = send_user_op_from(Address::random(), vec![], vec![], 1).await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_sender_allowlist() {
+        let allowed = Address::random();
+        let res = send_user_op_from(allowed, vec![allowed], vec![], 1).await;
+        assert!(res.is_ok());
+
+        let other = Address::random();
+        let res = send_user_op_from(other, vec![allowed], vec![], 0).await;
+        assert!(matches!(res, Err(EthRpcError::SenderNotAllowed(s)) if s == other));
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_bundler_tip_senders() {
+        let ep = Address::random();
+        let tipper = Address::random();
+        let other = Address::random();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op()
+            .times(3)
+            .returning(move |_, _, _, _| Ok(H256::random()));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(MockProvider::default(), entry_point, pool);
+        api.settings.bundler_tip_senders = HashSet::from([tipper]);
+        let op_from = |sender| UserOperation {
+            sender,
+            ..Default::default()
+        };
+
+        let res = api
+            .send_user_operation(op_from(tipper).into(), ep, None, Some(1_000.into()))
+            .await;
+        assert!(res.is_ok());
+
+        // other senders may not declare a tip, but may send ops without one
+        let res = api
+            .send_user_operation(op_from(other).into(), ep, None, Some(1_000.into()))
+            .await;
+        assert!(matches!(res, Err(EthRpcError::InvalidParams(_))));
+        let res = api
+            .send_user_operation(op_from(other).into(), ep, None, Some(U256::zero()))
+            .await;
+        assert!(res.is_ok());
+        let res = api
+            .send_user_operation(op_from(other).into(), ep, None, None)
+            .await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_sender_denylist() {
+        let denied = Address::random();
+        let res = send_user_op_from(denied, vec![], vec![denied], 0).await;
+        assert!(matches!(res, Err(EthRpcError::SenderNotAllowed(s)) if s == denied));
+
+        // the denylist takes precedence over the allowlist
+        let res = send_user_op_from(denied, vec![denied], vec![denied], 0).await;
+        assert!(matches!(res, Err(EthRpcError::SenderNotAllowed(s)) if s == denied));
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_rejects_entry_point_sender() {
+        let ep = Address::random();
+        let uo = UserOperation {
+            sender: ep,
+            ..Default::default()
+        };
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op().times(0);
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let api = create_api(MockProvider::default(), entry_point, pool);
+        let res = api.send_user_operation(uo.into(), ep, None, None).await;
+        assert!(matches!(res, Err(EthRpcError::InvalidSender(s)) if s == ep));
+    }
+
+    #[tokio::test]
+    async fn test_send_user_op_rejects_reserved_senders() {
+        let res = send_user_op_from(Address::zero(), vec![], vec![], 0).await;
+        assert!(matches!(res, Err(EthRpcError::InvalidSender(s)) if s.is_zero()));
+
+        let ecrecover = Address::from_low_u64_be(1);
+        let res = send_user_op_from(ecrecover, vec![], vec![], 0).await;
+        assert!(matches!(res, Err(EthRpcError::InvalidSender(s)) if s == ecrecover));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_user_op_gas_rejects_over_max_verification_gas() {
+        let ep = Address::random();
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(
+            MockProvider::default(),
+            entry_point,
+            MockPoolServer::default(),
+        );
+        api.settings.max_verification_gas = Some(1_000.into());
+
+        let op = UserOperationOptionalGas {
+            sender: Address::zero(),
+            nonce: U256::zero(),
+            init_code: Bytes::new(),
+            call_data: Bytes::new(),
+            call_gas_limit: None,
+            verification_gas_limit: Some(1_001.into()),
+            pre_verification_gas: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            paymaster_and_data: Bytes::new(),
+            signature: Bytes::new(),
+        };
+        let res = api.estimate_user_operation_gas(op, ep, None).await;
+        assert!(matches!(
+            res,
+            Err(EthRpcError::PrecheckFailed(
+                PrecheckViolation::VerificationGasLimitTooHigh(_, _)
+            ))
+        ));
+    }
+
+    async fn send_user_op_from(
+        sender: Address,
+        allowed_senders: Vec<Address>,
+        denied_senders: Vec<Address>,
+        expected_pool_calls: usize,
+    ) -> EthResult<H256> {
+        let ep = Address::random();
+        let uo = UserOperation {
+            sender,
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_add_op()
+            .times(expected_pool_calls)
+            .returning(move |_, _, _, _| Ok(hash));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(MockProvider::default(), entry_point, pool);
+        api.settings.allowed_senders = allowed_senders.into_iter().collect();
+        api.settings.denied_senders = denied_senders.into_iter().collect();
+        api.send_user_operation(uo.into(), ep, None, None).await
+    }
+
+    const SENDER_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const OTHER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn given_wallet(key: &str) -> LocalWallet {
+        key.parse().unwrap()
+    }
+
     fn given_log(topic_0: &str, topic_1: &str) -> Log {
         Log {
             topics: vec![
@@ -1166,12 +2143,16 @@ mod tests {
                     max_call_gas: 1_000_000,
                     max_simulate_handle_ops_gas: 1_000_000,
                     validation_estimation_gas_fee: 1_000_000_000_000,
+                    pre_verification_gas_breakdown: false,
+                    calldata_gas: CalldataGasConstants::default(),
                 },
                 FeeEstimator::new(
                     Arc::clone(&provider),
                     1,
                     PriorityFeeMode::BaseFeePercent(0),
                     0,
+                    true,
+                    U256::zero(),
                 ),
             ),
         );
@@ -18,7 +18,7 @@ use jsonrpsee::types::{
 };
 use rundler_pool::{MempoolError, PoolServerError};
 use rundler_provider::ProviderError;
-use rundler_sim::{PrecheckViolation, SimulationViolation};
+use rundler_sim::{GasEstimationError, PrecheckViolation, SimulationViolation};
 use rundler_types::{Entity, EntityType, Timestamp};
 use serde::Serialize;
 
@@ -71,13 +71,13 @@ pub enum EthRpcError {
     SenderAddressUsedAsAlternateEntity(Address),
     /// Opcode violation
     #[error("{0} uses banned opcode: {1:?}")]
-    OpcodeViolation(EntityType, Opcode),
+    OpcodeViolation(EntityType, Opcode, SimulationViolationData),
     /// Used for other simulation violations that map to Opcode Violations
     #[error("{0}")]
-    OpcodeViolationMap(SimulationViolation),
+    OpcodeViolationMap(SimulationViolation, SimulationViolationData),
     /// Invalid storage access, maps to Opcode Violation
     #[error("{0} accesses inaccessible storage at address: {1:?} slot: {2:#032x}")]
-    InvalidStorageAccess(EntityType, Address, U256),
+    InvalidStorageAccess(EntityType, Address, U256, SimulationViolationData),
     /// Operation is out of time range
     #[error("operation is out of time range")]
     OutOfTimeRange(OutOfTimeRangeData),
@@ -118,6 +118,18 @@ pub enum EthRpcError {
     ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData),
     #[error("operation rejected by mempool: {0}")]
     OperationRejected(String),
+    /// Operation signature was produced for a different chain
+    #[error("user operation signature is not valid for chain id {0}, it may have been signed for another chain")]
+    InvalidChainId(u64),
+    /// Cancellation was not signed by the sender of the operation
+    #[error("cancellation signature is not valid for sender {0:?}")]
+    InvalidCancellationSignature(Address),
+    /// Sender is denied or not in the allowlist of this bundler
+    #[error("sender {0:?} is not allowed to submit user operations to this bundler")]
+    SenderNotAllowed(Address),
+    /// Sender is the entry point, the zero address or a precompile
+    #[error("sender {0:?} is a reserved address and cannot be a user operation sender")]
+    InvalidSender(Address),
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -166,6 +178,82 @@ impl StakeTooLowData {
     }
 }
 
+/// The ERC-4337 validation rule broken by an operation, along with the
+/// offending entity, returned in the error data of opcode violations
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "rule", rename_all = "camelCase")]
+pub enum SimulationViolationData {
+    #[serde(rename_all = "camelCase")]
+    BannedOpcode {
+        entity: Entity,
+        contract: Address,
+        opcode: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    BannedPrecompile {
+        entity: Entity,
+        contract: Address,
+        precompile: Address,
+    },
+    #[serde(rename_all = "camelCase")]
+    UndeployedContract { entity: Entity, address: Address },
+    #[serde(rename_all = "camelCase")]
+    FactoryCalledCreate2Twice { factory: Address },
+    #[serde(rename_all = "camelCase")]
+    InvalidStorageAccess {
+        entity: Entity,
+        address: Address,
+        slot: U256,
+    },
+    #[serde(rename_all = "camelCase")]
+    BannedEntryPointMethod { entity: Entity },
+    #[serde(rename_all = "camelCase")]
+    CallHadValue { entity: Entity },
+}
+
+impl SimulationViolationData {
+    /// Returns the structured data for violations of the opcode and storage
+    /// rules, or `None` for other violations
+    fn from_violation(violation: &SimulationViolation) -> Option<Self> {
+        let data = match violation {
+            SimulationViolation::UsedForbiddenOpcode(entity, contract, opcode) => {
+                Self::BannedOpcode {
+                    entity: *entity,
+                    contract: *contract,
+                    opcode: opcode.to_string(),
+                }
+            }
+            SimulationViolation::UsedForbiddenPrecompile(entity, contract, precompile) => {
+                Self::BannedPrecompile {
+                    entity: *entity,
+                    contract: *contract,
+                    precompile: *precompile,
+                }
+            }
+            SimulationViolation::AccessedUndeployedContract(entity, address) => {
+                Self::UndeployedContract {
+                    entity: *entity,
+                    address: *address,
+                }
+            }
+            SimulationViolation::FactoryCalledCreate2Twice(factory) => {
+                Self::FactoryCalledCreate2Twice { factory: *factory }
+            }
+            SimulationViolation::InvalidStorageAccess(entity, slot) => Self::InvalidStorageAccess {
+                entity: *entity,
+                address: slot.address,
+                slot: slot.slot,
+            },
+            SimulationViolation::CalledBannedEntryPointMethod(entity) => {
+                Self::BannedEntryPointMethod { entity: *entity }
+            }
+            SimulationViolation::CallHadValue(entity) => Self::CallHadValue { entity: *entity },
+            _ => return None,
+        };
+        Some(data)
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReplacementUnderpricedData {
@@ -216,6 +304,9 @@ impl From<MempoolError> for EthRpcError {
                     current_max_fee: fee,
                 })
             }
+            MempoolError::ReplacementTargetNotFound(hash) => {
+                EthRpcError::InvalidParams(format!("operation {hash:?} not found for replacement"))
+            }
             MempoolError::MaxOperationsReached(count, address) => {
                 EthRpcError::MaxOperationsReached(count, address)
             }
@@ -243,6 +334,11 @@ impl From<MempoolError> for EthRpcError {
             MempoolError::UnknownEntryPoint(a) => {
                 EthRpcError::EntryPointValidationRejected(format!("unknown entry point: {}", a))
             }
+            MempoolError::Paused => EthRpcError::OperationRejected("mempool paused".to_owned()),
+            MempoolError::AdmissionRejected(reason) => EthRpcError::OperationRejected(reason),
+            MempoolError::UntrustedPeer(peer) => {
+                EthRpcError::OperationRejected(format!("untrusted peer {peer:?}"))
+            }
         }
     }
 }
@@ -253,8 +349,48 @@ impl From<PrecheckViolation> for EthRpcError {
     }
 }
 
+impl From<GasEstimationError> for EthRpcError {
+    fn from(value: GasEstimationError) -> Self {
+        match value {
+            GasEstimationError::RevertInValidation(message) => {
+                Self::EntryPointValidationRejected(message)
+            }
+            GasEstimationError::PaymasterRejected { paymaster, message } => {
+                Self::PaymasterValidationRejected(PaymasterValidationRejectedData {
+                    paymaster,
+                    reason: message,
+                })
+            }
+            GasEstimationError::RevertInCallWithMessage(message) => {
+                Self::ExecutionReverted(message)
+            }
+            GasEstimationError::RevertInCallWithBytes(revert_data) => {
+                Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData { revert_data })
+            }
+            GasEstimationError::CallGasLimitTooLow { provided, minimum } => {
+                Self::PrecheckFailed(PrecheckViolation::CallGasLimitTooLow(provided, minimum))
+            }
+            GasEstimationError::Other(error) => Self::Internal(error),
+        }
+    }
+}
+
 impl From<SimulationViolation> for EthRpcError {
     fn from(value: SimulationViolation) -> Self {
+        if let Some(data) = SimulationViolationData::from_violation(&value) {
+            return match value {
+                SimulationViolation::UsedForbiddenOpcode(entity, _, op) => {
+                    Self::OpcodeViolation(entity.kind, op.0, data)
+                }
+                SimulationViolation::FactoryCalledCreate2Twice(_) => {
+                    Self::OpcodeViolation(EntityType::Factory, Opcode::CREATE2, data)
+                }
+                SimulationViolation::InvalidStorageAccess(entity, slot) => {
+                    Self::InvalidStorageAccess(entity.kind, slot.address, slot.slot, data)
+                }
+                _ => Self::OpcodeViolationMap(value, data),
+            };
+        }
         match value {
             SimulationViolation::InvalidSignature => Self::SignatureCheckFailed,
             SimulationViolation::UnintendedRevertWithMessage(
@@ -268,20 +404,7 @@ impl From<SimulationViolation> for EthRpcError {
             SimulationViolation::UnintendedRevertWithMessage(_, reason, _) => {
                 Self::EntryPointValidationRejected(reason)
             }
-            SimulationViolation::UsedForbiddenOpcode(entity, _, op) => {
-                Self::OpcodeViolation(entity.kind, op.0)
-            }
-            SimulationViolation::UsedForbiddenPrecompile(_, _, _)
-            | SimulationViolation::AccessedUndeployedContract(_, _)
-            | SimulationViolation::CalledBannedEntryPointMethod(_)
-            | SimulationViolation::CallHadValue(_) => Self::OpcodeViolationMap(value),
-            SimulationViolation::FactoryCalledCreate2Twice(_) => {
-                Self::OpcodeViolation(EntityType::Factory, Opcode::CREATE2)
-            }
             SimulationViolation::UnstakedPaymasterContext => Self::UnstakedPaymasterContext,
-            SimulationViolation::InvalidStorageAccess(entity, slot) => {
-                Self::InvalidStorageAccess(entity.kind, slot.address, slot.slot)
-            }
             SimulationViolation::NotStaked(stake_data) => {
                 Self::StakeTooLow(Box::new(StakeTooLowData::new(
                     stake_data.entity,
@@ -304,23 +427,30 @@ impl From<EthRpcError> for ErrorObjectOwned {
 
         match error {
             EthRpcError::Internal(_) => rpc_err(INTERNAL_ERROR_CODE, msg),
-            EthRpcError::InvalidParams(_) => rpc_err(INVALID_PARAMS_CODE, msg),
+            EthRpcError::InvalidParams(_)
+            | EthRpcError::InvalidChainId(_)
+            | EthRpcError::SenderNotAllowed(_)
+            | EthRpcError::InvalidSender(_) => rpc_err(INVALID_PARAMS_CODE, msg),
             EthRpcError::EntryPointValidationRejected(_) => {
                 rpc_err(ENTRYPOINT_VALIDATION_REJECTED_CODE, msg)
             }
             EthRpcError::PaymasterValidationRejected(data) => {
                 rpc_err_with_data(PAYMASTER_VALIDATION_REJECTED_CODE, msg, data)
             }
-            EthRpcError::OpcodeViolation(_, _)
-            | EthRpcError::OpcodeViolationMap(_)
-            | EthRpcError::SimulationFailed(_)
+            EthRpcError::OpcodeViolation(_, _, data)
+            | EthRpcError::OpcodeViolationMap(_, data)
+            | EthRpcError::InvalidStorageAccess(_, _, _, data) => {
+                rpc_err_with_data(OPCODE_VIOLATION_CODE, msg, data)
+            }
+            EthRpcError::SimulationFailed(_)
             | EthRpcError::UnstakedAggregator
             | EthRpcError::MultipleRolesViolation(_)
             | EthRpcError::UnstakedPaymasterContext
             | EthRpcError::SenderAddressUsedAsAlternateEntity(_)
             | EthRpcError::PaymasterBalanceTooLow(_, _)
-            | EthRpcError::AssociatedStorageIsAlternateSender
-            | EthRpcError::InvalidStorageAccess(_, _, _) => rpc_err(OPCODE_VIOLATION_CODE, msg),
+            | EthRpcError::AssociatedStorageIsAlternateSender => {
+                rpc_err(OPCODE_VIOLATION_CODE, msg)
+            }
             EthRpcError::OutOfTimeRange(data) => {
                 rpc_err_with_data(OUT_OF_TIME_RANGE_CODE, msg, data)
             }
@@ -336,7 +466,9 @@ impl From<EthRpcError> for ErrorObjectOwned {
             }
             EthRpcError::OperationAlreadyKnown => rpc_err(INVALID_PARAMS_CODE, msg),
             EthRpcError::MaxOperationsReached(_, _) => rpc_err(STAKE_TOO_LOW_CODE, msg),
-            EthRpcError::SignatureCheckFailed => rpc_err(SIGNATURE_CHECK_FAILED_CODE, msg),
+            EthRpcError::SignatureCheckFailed | EthRpcError::InvalidCancellationSignature(_) => {
+                rpc_err(SIGNATURE_CHECK_FAILED_CODE, msg)
+            }
             EthRpcError::PrecheckFailed(_) => rpc_err(CALL_EXECUTION_FAILED_CODE, msg),
             EthRpcError::ExecutionReverted(_) => rpc_err(EXECUTION_REVERTED, msg),
             EthRpcError::ExecutionRevertedWithBytes(data) => {
@@ -362,3 +494,54 @@ impl From<ProviderError> for EthRpcError {
         EthRpcError::Internal(anyhow::anyhow!("provider error: {e:?}"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::utils::to_checksum;
+    use rundler_sim::ViolationOpCode;
+
+    use super::*;
+
+    #[test]
+    fn test_banned_opcode_error_data() {
+        let paymaster = Address::repeat_byte(1);
+        let contract = Address::repeat_byte(2);
+        let violation = SimulationViolation::UsedForbiddenOpcode(
+            Entity::paymaster(paymaster),
+            contract,
+            ViolationOpCode(Opcode::GASPRICE),
+        );
+
+        let error: ErrorObjectOwned = EthRpcError::from(violation).into();
+
+        assert_eq!(error.code(), OPCODE_VIOLATION_CODE);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get()).unwrap();
+        assert_eq!(
+            data,
+            serde_json::json!({
+                "rule": "bannedOpcode",
+                "entity": { "paymaster": to_checksum(&paymaster, None) },
+                "contract": contract,
+                "opcode": "GASPRICE",
+            })
+        );
+    }
+
+    #[test]
+    fn test_call_had_value_error_data() {
+        let account = Address::repeat_byte(1);
+        let violation = SimulationViolation::CallHadValue(Entity::account(account));
+
+        let error: ErrorObjectOwned = EthRpcError::from(violation).into();
+
+        assert_eq!(error.code(), OPCODE_VIOLATION_CODE);
+        let data: serde_json::Value = serde_json::from_str(error.data().unwrap().get()).unwrap();
+        assert_eq!(
+            data,
+            serde_json::json!({
+                "rule": "callHadValue",
+                "entity": { "account": to_checksum(&account, None) },
+            })
+        );
+    }
+}
@@ -18,11 +18,14 @@ pub use api::Settings as EthApiSettings;
 mod error;
 mod server;
 
-use ethers::types::{spoof, Address, H256, U64};
+use ethers::types::{spoof, Address, Bytes, H256, U256, U64};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc};
 use rundler_sim::{GasEstimate, UserOperationOptionalGas};
 
-use crate::types::{RichUserOperation, RpcUserOperation, UserOperationReceipt};
+use crate::types::{
+    RichUserOperation, RpcUserOperation, RpcUserOperationStatus, SendUserOperationBatchResult,
+    UserOperationReceipt,
+};
 
 /// Eth API
 #[rpc(client, server, namespace = "eth")]
@@ -36,6 +39,19 @@ pub trait EthApi {
         entry_point: Address,
     ) -> RpcResult<H256>;
 
+    /// Sends a batch of user operations to the pool. Each operation is
+    /// processed independently, and a result is returned for each one in order.
+    #[method(name = "sendUserOperationBatch")]
+    async fn send_user_operation_batch(
+        &self,
+        ops: Vec<(RpcUserOperation, Address)>,
+    ) -> RpcResult<Vec<SendUserOperationBatchResult>>;
+
+    /// Cancels a pending user operation, given a signature from its sender over
+    /// the cancellation message for the operation hash.
+    #[method(name = "cancelUserOperation")]
+    async fn cancel_user_operation(&self, hash: H256, signature: Bytes) -> RpcResult<bool>;
+
     /// Estimates the gas fields for a user operation.
     #[method(name = "estimateUserOperationGas")]
     async fn estimate_user_operation_gas(
@@ -45,6 +61,14 @@ pub trait EthApi {
         state_override: Option<spoof::State>,
     ) -> RpcResult<GasEstimate>;
 
+    /// Estimates only the pre-verification gas for a user operation.
+    #[method(name = "estimatePreVerificationGas")]
+    async fn estimate_pre_verification_gas(
+        &self,
+        op: UserOperationOptionalGas,
+        entry_point: Address,
+    ) -> RpcResult<U256>;
+
     /// Returns the user operation with the given hash.
     #[method(name = "getUserOperationByHash")]
     async fn get_user_operation_by_hash(&self, hash: H256) -> RpcResult<Option<RichUserOperation>>;
@@ -56,6 +80,11 @@ pub trait EthApi {
         hash: H256,
     ) -> RpcResult<Option<UserOperationReceipt>>;
 
+    /// Returns the status of the user operation with the given hash, combining
+    /// the state of the mempool with recently mined and dropped operations.
+    #[method(name = "getUserOperationStatus")]
+    async fn get_user_operation_status(&self, hash: H256) -> RpcResult<RpcUserOperationStatus>;
+
     /// Returns the supported entry points addresses
     #[method(name = "supportedEntryPoints")]
     async fn supported_entry_points(&self) -> RpcResult<Vec<String>>;
@@ -12,14 +12,17 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use async_trait::async_trait;
-use ethers::types::{spoof, Address, H256, U64};
+use ethers::types::{spoof, Address, Bytes, H256, U256, U64};
 use jsonrpsee::core::RpcResult;
 use rundler_pool::PoolServer;
 use rundler_provider::{EntryPoint, Provider};
 use rundler_sim::{GasEstimate, UserOperationOptionalGas};
 
 use super::{api::EthApi, EthApiServer};
-use crate::types::{RichUserOperation, RpcUserOperation, UserOperationReceipt};
+use crate::types::{
+    RichUserOperation, RpcUserOperation, RpcUserOperationStatus, SendUserOperationBatchResult,
+    UserOperationReceipt,
+};
 
 #[async_trait]
 impl<P, E, PS> EthApiServer for EthApi<P, E, PS>
@@ -33,7 +36,18 @@ where
         op: RpcUserOperation,
         entry_point: Address,
     ) -> RpcResult<H256> {
-        Ok(EthApi::send_user_operation(self, op, entry_point).await?)
+        Ok(EthApi::send_user_operation(self, op, entry_point, None, None).await?)
+    }
+
+    async fn send_user_operation_batch(
+        &self,
+        ops: Vec<(RpcUserOperation, Address)>,
+    ) -> RpcResult<Vec<SendUserOperationBatchResult>> {
+        Ok(EthApi::send_user_operation_batch(self, ops).await?)
+    }
+
+    async fn cancel_user_operation(&self, hash: H256, signature: Bytes) -> RpcResult<bool> {
+        Ok(EthApi::cancel_user_operation(self, hash, signature).await?)
     }
 
     async fn estimate_user_operation_gas(
@@ -47,6 +61,14 @@ where
         Ok(EthApi::estimate_user_operation_gas(self, op, entry_point, state_override).await?)
     }
 
+    async fn estimate_pre_verification_gas(
+        &self,
+        op: UserOperationOptionalGas,
+        entry_point: Address,
+    ) -> RpcResult<U256> {
+        Ok(EthApi::estimate_pre_verification_gas(self, op, entry_point).await?)
+    }
+
     async fn get_user_operation_by_hash(&self, hash: H256) -> RpcResult<Option<RichUserOperation>> {
         Ok(EthApi::get_user_operation_by_hash(self, hash).await?)
     }
@@ -58,6 +80,10 @@ where
         Ok(EthApi::get_user_operation_receipt(self, hash).await?)
     }
 
+    async fn get_user_operation_status(&self, hash: H256) -> RpcResult<RpcUserOperationStatus> {
+        Ok(EthApi::get_user_operation_status(self, hash).await?)
+    }
+
     async fn supported_entry_points(&self) -> RpcResult<Vec<String>> {
         Ok(EthApi::supported_entry_points(self).await?)
     }
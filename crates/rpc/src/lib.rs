@@ -19,6 +19,8 @@
 ))]
 //! JSON-RPC server for the Rundler.
 
+mod compression;
+
 mod debug;
 pub use debug::DebugApiClient;
 
@@ -36,5 +38,9 @@ pub use rundler::RundlerApiClient;
 mod task;
 pub use task::{Args as RpcTaskArgs, RpcTask};
 
+mod timeout;
+
 mod types;
-pub use types::{RichUserOperation, RpcUserOperation, UserOperationReceipt};
+pub use types::{
+    RichUserOperation, RpcUserOperation, SendUserOperationBatchResult, UserOperationReceipt,
+};
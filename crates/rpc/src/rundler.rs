@@ -14,44 +14,81 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use ethers::types::U256;
+use ethers::types::{Address, H256, U256};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
-use rundler_provider::Provider;
+use rundler_pool::PoolServer;
+use rundler_provider::{EntryPoint, Provider};
 use rundler_sim::{FeeEstimator, PrecheckSettings};
+use rundler_types::Timestamp;
 
-use crate::error::rpc_err;
+use crate::{error::rpc_err, eth::EthApi, types::RpcUserOperation};
 
 #[rpc(client, server, namespace = "rundler")]
 pub trait RundlerApi {
     /// Returns the maximum priority fee per gas required by Rundler
     #[method(name = "maxPriorityFeePerGas")]
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256>;
+
+    /// Sends a user operation to the pool, as `eth_sendUserOperation` does,
+    /// with Rundler specific options.
+    ///
+    /// An optional submission deadline, in seconds since the epoch, may be given
+    /// after which the operation is evicted from the pool if it has not been included.
+    ///
+    /// An optional bundler tip, in wei, commits the sender to paying the bundler
+    /// off-chain for including the operation. Bundlers weigh it alongside the
+    /// operation's gas fees when ordering operations for a bundle.
+    #[method(name = "sendUserOperation")]
+    async fn send_user_operation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+        submission_deadline: Option<u64>,
+        bundler_tip: Option<U256>,
+    ) -> RpcResult<H256>;
 }
 
-pub(crate) struct RundlerApi<P: Provider> {
+pub(crate) struct RundlerApi<P, E, PS>
+where
+    P: Provider,
+    E: EntryPoint,
+{
     fee_estimator: FeeEstimator<P>,
+    eth_api: EthApi<P, E, PS>,
 }
 
-impl<P> RundlerApi<P>
+impl<P, E, PS> RundlerApi<P, E, PS>
 where
     P: Provider,
+    E: EntryPoint,
+    PS: PoolServer,
 {
-    pub(crate) fn new(provider: Arc<P>, chain_id: u64, settings: PrecheckSettings) -> Self {
+    pub(crate) fn new(
+        provider: Arc<P>,
+        chain_id: u64,
+        settings: PrecheckSettings,
+        eth_api: EthApi<P, E, PS>,
+    ) -> Self {
         Self {
+            eth_api,
             fee_estimator: FeeEstimator::new(
                 provider,
                 chain_id,
                 settings.priority_fee_mode,
                 settings.bundle_priority_fee_overhead_percent,
+                settings.use_dynamic_max_priority_fee,
+                settings.static_max_priority_fee,
             ),
         }
     }
 }
 
 #[async_trait]
-impl<P> RundlerApiServer for RundlerApi<P>
+impl<P, E, PS> RundlerApiServer for RundlerApi<P, E, PS>
 where
     P: Provider,
+    E: EntryPoint,
+    PS: PoolServer,
 {
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
         let (bundle_fees, _) = self
@@ -64,4 +101,22 @@ where
             .required_op_fees(bundle_fees)
             .max_priority_fee_per_gas)
     }
+
+    async fn send_user_operation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+        submission_deadline: Option<u64>,
+        bundler_tip: Option<U256>,
+    ) -> RpcResult<H256> {
+        Ok(self
+            .eth_api
+            .send_user_operation(
+                op,
+                entry_point,
+                submission_deadline.map(Timestamp::from),
+                bundler_tip,
+            )
+            .await?)
+    }
 }
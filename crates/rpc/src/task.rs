@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::bail;
 use async_trait::async_trait;
@@ -20,11 +20,16 @@ use ethers::{
     types::Address,
 };
 use jsonrpsee::{
+    core::RpcResult,
     server::{middleware::ProxyGetRequestLayer, ServerBuilder},
-    RpcModule,
+    types::error::METHOD_NOT_FOUND_CODE,
+    Methods, RpcModule,
 };
 use hyper::Method;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{Any, CorsLayer},
+};
 use rundler_builder::BuilderServer;
 use rundler_pool::PoolServer;
 use rundler_provider::EntryPoint;
@@ -35,14 +40,19 @@ use rundler_task::{
 };
 use rundler_types::contracts::i_entry_point::IEntryPoint;
 use rundler_utils::eth;
+use serde::Serialize;
+use strum::IntoEnumIterator;
 use tokio_util::sync::CancellationToken;
 
 use crate::{
+    compression,
     debug::{DebugApi, DebugApiServer},
+    error::rpc_err_with_data,
     eth::{EthApi, EthApiServer, EthApiSettings},
     health::{HealthChecker, SystemApiServer},
     metrics::RpcMetricsLogger,
     rundler::{RundlerApi, RundlerApiServer},
+    timeout::MethodTimeoutLayer,
     types::ApiNamespace,
 };
 
@@ -69,6 +79,8 @@ pub struct Args {
     pub estimation_settings: EstimationSettings,
     /// RPC timeout.
     pub rpc_timeout: Duration,
+    /// RPC timeouts for specific methods, overriding `rpc_timeout`.
+    pub rpc_method_timeouts: HashMap<String, Duration>,
     /// Max number of connections.
     pub max_connections: u32,
 }
@@ -124,9 +136,16 @@ where
 
         // Set up health check endpoint via GET /health registers the jsonrpc handler
         let service_builder = tower::ServiceBuilder::new()
+            // Compress responses with an encoding from the request's `Accept-Encoding`
+            // header, if any. This must wrap the proxy below, which reads the body.
+            .map_response(compression::into_hyper_body)
+            .layer(CompressionLayer::new())
             // Proxy `GET /health` requests to internal `system_health` method.
             .layer(ProxyGetRequestLayer::new("/health", "system_health")?)
-            .timeout(self.args.rpc_timeout)
+            .layer(MethodTimeoutLayer::new(
+                self.args.rpc_timeout,
+                self.args.rpc_method_timeouts.clone(),
+            ))
             .layer(cors);
 
         let server = ServerBuilder::default()
@@ -178,9 +197,26 @@ where
         entry_points: Vec<E>,
         module: &mut RpcModule<()>,
     ) -> anyhow::Result<()> {
-        for api in &self.args.api_namespaces {
-            match api {
-                ApiNamespace::Eth => module.merge(
+        for api in ApiNamespace::iter() {
+            let methods: Methods = match api {
+                ApiNamespace::Eth => EthApi::new(
+                    provider.clone(),
+                    entry_points.clone(),
+                    self.args.chain_id,
+                    self.pool.clone(),
+                    self.args.eth_api_settings.clone(),
+                    self.args.estimation_settings,
+                    self.args.precheck_settings,
+                )
+                .into_rpc()
+                .into(),
+                ApiNamespace::Debug => DebugApi::new(self.pool.clone(), self.builder.clone())
+                    .into_rpc()
+                    .into(),
+                ApiNamespace::Rundler => RundlerApi::new(
+                    provider.clone(),
+                    self.args.chain_id,
+                    self.args.precheck_settings,
                     EthApi::new(
                         provider.clone(),
                         entry_points.clone(),
@@ -189,22 +225,92 @@ where
                         self.args.eth_api_settings.clone(),
                         self.args.estimation_settings,
                         self.args.precheck_settings,
-                    )
-                    .into_rpc(),
-                )?,
-                ApiNamespace::Debug => module
-                    .merge(DebugApi::new(self.pool.clone(), self.builder.clone()).into_rpc())?,
-                ApiNamespace::Rundler => module.merge(
-                    RundlerApi::new(
-                        provider.clone(),
-                        self.args.chain_id,
-                        self.args.precheck_settings,
-                    )
-                    .into_rpc(),
-                )?,
+                    ),
+                )
+                .into_rpc()
+                .into(),
+            };
+
+            if self.args.api_namespaces.contains(&api) {
+                module.merge(methods)?;
+            } else {
+                register_disabled_methods(
+                    module,
+                    api,
+                    methods.method_names().collect(),
+                    &self.args.api_namespaces,
+                )?;
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DisabledMethodData {
+    enabled_namespaces: Vec<String>,
+}
+
+/// Registers the methods of a disabled namespace so that calling them returns an
+/// error listing the enabled namespaces, rather than a generic method-not-found.
+fn register_disabled_methods(
+    module: &mut RpcModule<()>,
+    namespace: ApiNamespace,
+    method_names: Vec<&'static str>,
+    enabled_namespaces: &[ApiNamespace],
+) -> anyhow::Result<()> {
+    let data = DisabledMethodData {
+        enabled_namespaces: enabled_namespaces.iter().map(|n| n.to_string()).collect(),
+    };
+    for method_name in method_names {
+        let data = data.clone();
+        module.register_method(method_name, move |_, _| -> RpcResult<()> {
+            Err(rpc_err_with_data(
+                METHOD_NOT_FOUND_CODE,
+                format!(
+                    "Method {method_name} is not available because the {namespace} namespace is not enabled"
+                ),
+                data.clone(),
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonrpsee::core::params::ArrayParams;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_disabled_namespace_method() {
+        let mut module = RpcModule::new(());
+        register_disabled_methods(
+            &mut module,
+            ApiNamespace::Debug,
+            vec!["debug_bundler_clearState"],
+            &[ApiNamespace::Eth],
+        )
+        .unwrap();
+
+        let error = module
+            .call::<_, String>("debug_bundler_clearState", ArrayParams::new())
+            .await
+            .unwrap_err();
+        let jsonrpsee::core::Error::Call(error) = error else {
+            panic!("expected a call error, got {error:?}");
+        };
+        assert_eq!(error.code(), METHOD_NOT_FOUND_CODE);
+        assert_eq!(
+            error.message(),
+            "Method debug_bundler_clearState is not available because the debug namespace is not enabled"
+        );
+        assert_eq!(
+            error.data().map(|data| data.get()),
+            Some(r#"{"enabledNamespaces":["eth"]}"#)
+        );
+    }
+}
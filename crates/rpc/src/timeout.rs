@@ -0,0 +1,221 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use hyper::{body::HttpBody, Body, Request};
+use serde_json::Value;
+use tower::{timeout::Timeout, BoxError, Layer, Service};
+
+/// Largest request body read to find the method names, matching jsonrpsee's
+/// default `max_request_body_size`
+const MAX_REQUEST_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Layer that times out HTTP JSON-RPC requests, using a longer or shorter
+/// timeout for the methods that have one configured.
+#[derive(Clone, Debug)]
+pub(crate) struct MethodTimeoutLayer {
+    timeouts: Arc<MethodTimeouts>,
+}
+
+#[derive(Debug)]
+struct MethodTimeouts {
+    default: Duration,
+    by_method: HashMap<String, Duration>,
+}
+
+impl MethodTimeoutLayer {
+    pub(crate) fn new(default: Duration, by_method: HashMap<String, Duration>) -> Self {
+        Self {
+            timeouts: Arc::new(MethodTimeouts { default, by_method }),
+        }
+    }
+}
+
+impl<S> Layer<S> for MethodTimeoutLayer {
+    type Service = MethodTimeout<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodTimeout {
+            inner,
+            timeouts: Arc::clone(&self.timeouts),
+        }
+    }
+}
+
+/// Service created by [`MethodTimeoutLayer`]
+#[derive(Clone, Debug)]
+pub(crate) struct MethodTimeout<S> {
+    inner: S,
+    timeouts: Arc<MethodTimeouts>,
+}
+
+impl<S> Service<Request<Body>> for MethodTimeout<S>
+where
+    S: Service<Request<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Into<BoxError>,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Take the service that was driven to readiness, leaving a clone behind
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let timeouts = Arc::clone(&self.timeouts);
+        Box::pin(async move {
+            if timeouts.by_method.is_empty() {
+                return Timeout::new(inner, timeouts.default).call(request).await;
+            }
+            // The method names are in the body, so it must be read up front,
+            // within the default timeout
+            let (parts, body) = request.into_parts();
+            let body =
+                tokio::time::timeout(timeouts.default, read_body(body, MAX_REQUEST_BODY_SIZE))
+                    .await??;
+            let timeout = timeouts.timeout_for(&body);
+            let request = Request::from_parts(parts, Body::from(body));
+            Timeout::new(inner, timeout).call(request).await
+        })
+    }
+}
+
+/// Reads a request body, failing once it is longer than `limit` bytes.
+async fn read_body(mut body: Body, limit: usize) -> Result<Vec<u8>, BoxError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Err(format!("request body is larger than {limit} bytes").into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+impl MethodTimeouts {
+    /// Returns the timeout for a request body. A batch gets the longest timeout
+    /// of its methods, and a body that can't be parsed gets the default.
+    fn timeout_for(&self, body: &[u8]) -> Duration {
+        let method_timeout = |call: &Value| {
+            call.get("method")
+                .and_then(Value::as_str)
+                .and_then(|method| self.by_method.get(method))
+                .copied()
+                .unwrap_or(self.default)
+        };
+        match serde_json::from_slice::<Value>(body) {
+            Ok(Value::Array(calls)) => calls
+                .iter()
+                .map(method_timeout)
+                .max()
+                .unwrap_or(self.default),
+            Ok(call) => method_timeout(&call),
+            Err(_) => self.default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use hyper::Response;
+    use tower::{service_fn, ServiceExt};
+
+    use super::*;
+
+    const SLOW_METHOD: &str = "eth_estimateUserOperationGas";
+
+    fn request(body: &str) -> Request<Body> {
+        Request::post("/")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    async fn call_slow_service(body: &str) -> Result<Response<Body>, BoxError> {
+        let layer = MethodTimeoutLayer::new(
+            Duration::from_millis(10),
+            HashMap::from([(SLOW_METHOD.to_string(), Duration::from_secs(10))]),
+        );
+        let service = layer.layer(service_fn(|_: Request<Body>| async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+        service.oneshot(request(body)).await
+    }
+
+    #[tokio::test]
+    async fn test_slow_method_uses_configured_timeout() {
+        let body = format!(r#"{{"jsonrpc":"2.0","id":1,"method":"{SLOW_METHOD}","params":[]}}"#);
+        assert!(call_slow_service(&body).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_other_method_uses_default_timeout() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#;
+        assert!(call_slow_service(body).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_uses_longest_timeout() {
+        let body = format!(
+            r#"[{{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}},{{"jsonrpc":"2.0","id":2,"method":"{SLOW_METHOD}"}}]"#
+        );
+        assert!(call_slow_service(&body).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_body_rejects_oversized_body() {
+        let body = Body::from(vec![0u8; 11]);
+        assert!(read_body(body, 10).await.is_err());
+        let body = Body::from(vec![0u8; 10]);
+        assert_eq!(read_body(body, 10).await.unwrap().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_slow_body_uses_default_timeout() {
+        let (_sender, body) = Body::channel();
+        let request = Request::post("/").body(body).unwrap();
+        let layer = MethodTimeoutLayer::new(
+            Duration::from_millis(10),
+            HashMap::from([(SLOW_METHOD.to_string(), Duration::from_secs(10))]),
+        );
+        let service = layer.layer(service_fn(|_: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::empty()))
+        }));
+        assert!(service.oneshot(request).await.is_err());
+    }
+
+    #[test]
+    fn test_unparseable_body_uses_default_timeout() {
+        let timeouts = MethodTimeouts {
+            default: Duration::from_secs(1),
+            by_method: HashMap::from([(SLOW_METHOD.to_string(), Duration::from_secs(10))]),
+        };
+        assert_eq!(timeouts.timeout_for(b"not json"), Duration::from_secs(1));
+    }
+}
@@ -15,12 +15,15 @@ use ethers::{
     types::{Address, Bytes, Log, TransactionReceipt, H160, H256, U256},
     utils::to_checksum,
 };
-use rundler_pool::{Reputation, ReputationStatus};
+use jsonrpsee::types::ErrorObjectOwned;
+use rundler_pool::{OpStatus, Reputation, ReputationStatus};
 use rundler_types::UserOperation;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 /// API namespace
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumIter, strum::EnumString,
+)]
 #[strum(serialize_all = "lowercase", ascii_case_insensitive)]
 pub enum ApiNamespace {
     Eth,
@@ -165,8 +168,13 @@ pub struct UserOperationReceipt {
     pub actual_gas_cost: U256,
     /// The gas used by this operation
     pub actual_gas_used: U256,
-    /// Whether this operation's execution was successful
-    pub success: bool,
+    /// The gas price this operation effectively paid, its gas cost divided by its
+    /// gas used. Null if the operation used no gas
+    pub effective_gas_price: Option<U256>,
+    /// Whether this operation's execution was successful. Left out of partial
+    /// receipts, as the operation has not executed yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub success: Option<bool>,
     /// If not successful, the revert reason string
     pub reason: String,
     /// Logs emitted by this operation
@@ -175,6 +183,65 @@ pub struct UserOperationReceipt {
     pub receipt: TransactionReceipt,
 }
 
+/// Status of a user operation as tracked by the bundler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum RpcUserOperationStatus {
+    /// The operation is not known to the bundler
+    NotFound,
+    /// The operation is waiting in the mempool
+    Pending,
+    /// The operation has been sent to the chain in a bundle transaction
+    #[serde(rename_all = "camelCase")]
+    Submitted {
+        /// The hash of the bundle transaction
+        transaction_hash: H256,
+    },
+    /// The operation was included on chain
+    #[serde(rename_all = "camelCase")]
+    Mined {
+        /// The hash of the transaction that included the operation
+        transaction_hash: H256,
+        /// The number of the block that included the operation
+        block_number: u64,
+    },
+    /// The operation was removed from the mempool without being included
+    Dropped,
+}
+
+impl From<OpStatus> for RpcUserOperationStatus {
+    fn from(status: OpStatus) -> Self {
+        match status {
+            OpStatus::NotFound => Self::NotFound,
+            OpStatus::Pending => Self::Pending,
+            OpStatus::Submitted { tx_hash } => Self::Submitted {
+                transaction_hash: tx_hash,
+            },
+            OpStatus::Mined {
+                tx_hash,
+                block_number,
+            } => Self::Mined {
+                transaction_hash: tx_hash,
+                block_number,
+            },
+            OpStatus::Dropped => Self::Dropped,
+        }
+    }
+}
+
+/// Result of submitting a single user operation in a batch. Exactly one of
+/// `user_op_hash` and `error` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendUserOperationBatchResult {
+    /// The hash of the user operation, if it was added to the pool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_op_hash: Option<H256>,
+    /// The error that caused the user operation to be rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorObjectOwned>,
+}
+
 /// Reputation of an entity
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
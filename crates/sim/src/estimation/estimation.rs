@@ -38,8 +38,12 @@ use rundler_types::{
 use rundler_utils::{eth, math};
 use tokio::join;
 
-use super::types::{GasEstimate, Settings, UserOperationOptionalGas};
-use crate::{gas, precheck::MIN_CALL_GAS_LIMIT, utils, FeeEstimator};
+use super::types::{GasEstimate, PaymasterValidationData, Settings, UserOperationOptionalGas};
+use crate::{
+    gas::{self, PreVerificationGasBreakdown},
+    precheck::MIN_CALL_GAS_LIMIT,
+    utils, FeeEstimator,
+};
 
 /// Gas estimates will be rounded up to the next multiple of this. Increasing
 /// this value reduces the number of rounds of `eth_call` needed in binary
@@ -72,11 +76,39 @@ pub enum GasEstimationError {
     /// Call reverted with bytes
     #[error("user operation's call reverted: {0:#x}")]
     RevertInCallWithBytes(Bytes),
+    /// The provided call gas limit is too low to start executing the call
+    #[error(
+        "callGasLimit is {provided} but must be at least {minimum} for the operation's call data"
+    )]
+    CallGasLimitTooLow {
+        /// The call gas limit provided with the operation
+        provided: U256,
+        /// The minimum call gas limit for the operation's call data
+        minimum: U256,
+    },
+    /// The paymaster rejected the operation during validation
+    #[error("{message}")]
+    PaymasterRejected {
+        /// The paymaster that rejected the operation
+        paymaster: Address,
+        /// The revert message from validation
+        message: String,
+    },
     /// Other error
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
 
+/// Result of the verification gas search, along with the validation output
+/// needed by the gas estimate
+#[derive(Debug)]
+struct VerificationGasEstimate {
+    verification_gas_limit: U256,
+    valid_after: u64,
+    valid_until: u64,
+    paymaster_validation_data: Option<PaymasterValidationData>,
+}
+
 /// Gas estimator trait
 #[cfg_attr(feature = "test-utils", automock)]
 #[async_trait::async_trait]
@@ -89,6 +121,14 @@ pub trait GasEstimator: Send + Sync + 'static {
         state_override: spoof::State,
         at_price: Option<U256>,
     ) -> Result<GasEstimate, GasEstimationError>;
+
+    /// Returns only the pre-verification gas estimate, without running the
+    /// validation and call simulations needed for the other gas fields.
+    async fn estimate_pre_verification_gas(
+        &self,
+        op: UserOperationOptionalGas,
+        at_price: Option<U256>,
+    ) -> Result<U256, GasEstimationError>;
 }
 
 /// Gas estimator implementation
@@ -116,27 +156,24 @@ impl<P: Provider, E: EntryPoint> GasEstimator for GasEstimatorImpl<P, E> {
             provider, settings, ..
         } = self;
 
+        // Zero is a common placeholder for a limit the caller wants estimated
+        if let Some(provided) = op.call_gas_limit.filter(|limit| !limit.is_zero()) {
+            let minimum = gas::user_operation_min_call_gas_limit(&op.call_data);
+            if provided < minimum {
+                return Err(GasEstimationError::CallGasLimitTooLow { provided, minimum });
+            }
+        }
+
         let (block_hash, _) = provider
             .get_latest_block_hash_and_number()
             .await
             .map_err(anyhow::Error::from)?;
 
-        // Estimate pre verification gas at the current fees
-        // If the user provides fees, use them, otherwise use the current bundle fees
-        let (bundle_fees, base_fee) = self.fee_estimator.required_bundle_fees(None).await?;
-        println!("HC bundle_fees {:?} base_fee {:?} at_price {:?}", bundle_fees, base_fee, at_price);
-        let gas_price = if let Some(at_price) = at_price {
-            at_price
-        } else if let (Some(max_fee), Some(prio_fee)) =
-            (op.max_fee_per_gas, op.max_priority_fee_per_gas)
-        {
-            cmp::min(max_fee, base_fee + prio_fee)
-        } else {
-            base_fee + bundle_fees.max_priority_fee_per_gas
-        };
-        assert!(gas_price > U256::zero());
-
-        let pre_verification_gas = self.estimate_pre_verification_gas(&op, gas_price).await?;
+        let gas_price = self.pre_verification_gas_price(&op, at_price).await?;
+        let breakdown = self
+            .estimate_pre_verification_gas_breakdown(&op, gas_price)
+            .await?;
+        let pre_verification_gas = breakdown.total();
         let op = UserOperation {
             pre_verification_gas,
             ..op.into_user_operation(settings)
@@ -154,7 +191,12 @@ impl<P: Provider, E: EntryPoint> GasEstimator for GasEstimatorImpl<P, E> {
         let (verification_gas_limit, call_gas_limit) = join!(verification_future, call_future);
         tracing::debug!("gas estimation took {}ms", timer.elapsed().as_millis());
 
-        let verification_gas_limit = verification_gas_limit?;
+        let VerificationGasEstimate {
+            verification_gas_limit,
+            valid_after,
+            valid_until,
+            paymaster_validation_data,
+        } = verification_gas_limit?;
 	println!("HC verification_gas_limit {:?}", verification_gas_limit);
         let call_gas_limit = call_gas_limit?;
 
@@ -172,8 +214,24 @@ impl<P: Provider, E: EntryPoint> GasEstimator for GasEstimatorImpl<P, E> {
             )
             .min(settings.max_verification_gas.into()),
             call_gas_limit: call_gas_limit.clamp(MIN_CALL_GAS_LIMIT, settings.max_call_gas.into()),
+            paymaster_validation_data,
+            valid_after: valid_after.into(),
+            valid_until: valid_until.into(),
+            breakdown: settings.pre_verification_gas_breakdown.then_some(breakdown),
         })
     }
+
+    async fn estimate_pre_verification_gas(
+        &self,
+        op: UserOperationOptionalGas,
+        at_price: Option<U256>,
+    ) -> Result<U256, GasEstimationError> {
+        let gas_price = self.pre_verification_gas_price(&op, at_price).await?;
+        Ok(self
+            .estimate_pre_verification_gas_breakdown(&op, gas_price)
+            .await?
+            .total())
+    }
 }
 
 impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
@@ -194,12 +252,14 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
         }
     }
 
+    /// Returns the verification gas limit, along with the time range returned by
+    /// validation and the paymaster validation data if the operation uses a paymaster.
     async fn binary_search_verification_gas(
         &self,
         op: &UserOperation,
         block_hash: H256,
         state_override: &spoof::State,
-    ) -> Result<U256, GasEstimationError> {
+    ) -> Result<VerificationGasEstimate, GasEstimationError> {
         let timer = std::time::Instant::now();
         let simulation_gas = U256::from(self.settings.max_simulate_handle_ops_gas);
         let gas_fee = U256::from(self.settings.validation_estimation_gas_fee);
@@ -237,14 +297,28 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
                 "simulateHandleOp succeeded but should always revert, make sure the entry point contract is deployed and the address is correct"
             ))?;
         }
-        if let Some(message) = self
+        let execution_result = match self
             .entry_point
             .decode_simulate_handle_ops_revert(gas_used.result)
-            .err()
         {
-           println!("HC GasEstimationError {}", message);
-           return Err(GasEstimationError::RevertInValidation(message));
-        }
+            Ok(execution_result) => execution_result,
+            Err(message) => {
+                println!("HC GasEstimationError {}", message);
+                return Err(match op.paymaster() {
+                    // AA3x errors are raised in the paymaster validation phase
+                    Some(paymaster) if message.starts_with("AA3") => {
+                        GasEstimationError::PaymasterRejected { paymaster, message }
+                    }
+                    _ => GasEstimationError::RevertInValidation(message),
+                });
+            }
+        };
+        // Simulation only returns an execution result once the paymaster's
+        // validation phase has passed
+        let paymaster_validation_data = op.paymaster().map(|paymaster| PaymasterValidationData {
+            paymaster,
+            paymaster_accepted: true,
+        });
 
         let run_attempt_returning_error = |gas: u64| async move {
             let max_fee_per_gas = gas_fee
@@ -262,7 +336,7 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
                     op.clone(),
                     Address::zero(),
                     Bytes::new(),
-                    block_hash,
+                    block_hash.into(),
                     simulation_gas,
                     state_override,
                 )
@@ -320,7 +394,12 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
             timer.elapsed().as_millis()
         );
 
-        Ok(min_success_gas.into())
+        Ok(VerificationGasEstimate {
+            verification_gas_limit: min_success_gas.into(),
+            valid_after: execution_result.valid_after,
+            valid_until: execution_result.valid_until,
+            paymaster_validation_data,
+        })
     }
 
     async fn estimate_call_gas(
@@ -383,7 +462,7 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
                     callless_op.clone(),
                     self.entry_point.address(),
                     target_call_data,
-                    block_hash,
+                    block_hash.into(),
                     self.settings.max_simulate_handle_ops_gas.into(),
                     &state_override,
                 )
@@ -433,18 +512,45 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
         }
     }
 
-    async fn estimate_pre_verification_gas(
+    /// Returns the gas price at which to estimate pre verification gas. If the
+    /// user provides fees, use them, otherwise use the current bundle fees.
+    async fn pre_verification_gas_price(
         &self,
         op: &UserOperationOptionalGas,
-        gas_price: U256,
+        at_price: Option<U256>,
     ) -> Result<U256, GasEstimationError> {
+        let (bundle_fees, base_fee) = self.fee_estimator.required_bundle_fees(None).await?;
+        println!("HC bundle_fees {:?} base_fee {:?} at_price {:?}", bundle_fees, base_fee, at_price);
+        let gas_price = if let Some(at_price) = at_price {
+            at_price
+        } else if let (Some(max_fee), Some(prio_fee)) =
+            (op.max_fee_per_gas, op.max_priority_fee_per_gas)
+        {
+            cmp::min(max_fee, base_fee + prio_fee)
+        } else {
+            base_fee + bundle_fees.max_priority_fee_per_gas
+        };
+        if gas_price.is_zero() {
+            Err(anyhow!(
+                "gas price should be nonzero to estimate pre verification gas"
+            ))?;
+        }
+        Ok(gas_price)
+    }
+
+    async fn estimate_pre_verification_gas_breakdown(
+        &self,
+        op: &UserOperationOptionalGas,
+        gas_price: U256,
+    ) -> Result<PreVerificationGasBreakdown, GasEstimationError> {
         //println!("HC in estimate_pre_verification_gas gas_price {:?}", gas_price);
-        Ok(gas::estimate_pre_verification_gas(
+        Ok(gas::estimate_pre_verification_gas_breakdown(
             &op.max_fill(&self.settings),
             &op.random_fill(&self.settings),
             self.entry_point.address(),
             self.provider.clone(),
             self.chain_id,
+            &self.settings.calldata_gas,
             gas_price,
         )
         .await?)
@@ -471,7 +577,7 @@ mod tests {
     use rundler_types::contracts::{get_gas_used::GasUsedResult, i_entry_point::ExecutionResult};
 
     use super::*;
-    use crate::PriorityFeeMode;
+    use crate::{gas::CalldataGasConstants, PriorityFeeMode};
 
     // Gas overhead defaults
     const FIXED: u32 = 21000;
@@ -490,7 +596,14 @@ mod tests {
     }
 
     fn create_fee_estimator(provider: Arc<MockProvider>) -> FeeEstimator<MockProvider> {
-        FeeEstimator::new(provider, 0, PriorityFeeMode::BaseFeePercent(0), 0)
+        FeeEstimator::new(
+            provider,
+            0,
+            PriorityFeeMode::BaseFeePercent(0),
+            0,
+            true,
+            U256::zero(),
+        )
     }
 
     fn create_estimator(
@@ -502,6 +615,8 @@ mod tests {
             max_call_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
             validation_estimation_gas_fee: 1_000_000_000_000,
+            pre_verification_gas_breakdown: false,
+            calldata_gas: CalldataGasConstants::default(),
         };
         let provider = Arc::new(provider);
         let estimator: GasEstimatorImpl<MockProvider, MockEntryPoint> = GasEstimatorImpl::new(
@@ -521,7 +636,7 @@ mod tests {
             nonce: U256::zero(),
             init_code: Bytes::new(),
             call_data: Bytes::new(),
-            call_gas_limit: Some(U256::from(1000)),
+            call_gas_limit: Some(MIN_CALL_GAS_LIMIT),
             verification_gas_limit: Some(U256::from(1000)),
             pre_verification_gas: Some(U256::from(1000)),
             max_fee_per_gas: Some(U256::from(1000)),
@@ -567,9 +682,10 @@ mod tests {
         let (estimator, settings) = create_estimator(entry, provider);
         let user_op = demo_user_op_optional_gas();
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op, U256::zero())
+            .estimate_pre_verification_gas_breakdown(&user_op, U256::zero())
             .await
-            .unwrap();
+            .unwrap()
+            .total();
 
         let u_o = user_op.max_fill(&settings);
 
@@ -604,6 +720,8 @@ mod tests {
             max_call_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
             validation_estimation_gas_fee: 1_000_000_000_000,
+            pre_verification_gas_breakdown: false,
+            calldata_gas: CalldataGasConstants::default(),
         };
 
         // Chose arbitrum
@@ -618,9 +736,10 @@ mod tests {
 
         let user_op = demo_user_op_optional_gas();
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op, U256::zero())
+            .estimate_pre_verification_gas_breakdown(&user_op, U256::zero())
             .await
-            .unwrap();
+            .unwrap()
+            .total();
 
         let u_o = user_op.max_fill(&settings);
 
@@ -643,6 +762,54 @@ mod tests {
         assert_eq!(result + dynamic_gas, estimation);
     }
 
+    #[tokio::test]
+    async fn test_calc_pre_verification_breakdown() {
+        let (mut entry, mut provider) = create_base_config();
+        entry.expect_address().return_const(Address::zero());
+        provider
+            .expect_calc_arbitrum_l1_gas()
+            .returning(|_a, _b| Ok(U256::from(1000)));
+
+        let settings = Settings {
+            max_verification_gas: 10000000000,
+            max_call_gas: 10000000000,
+            max_simulate_handle_ops_gas: 100000000,
+            validation_estimation_gas_fee: 1_000_000_000_000,
+            pre_verification_gas_breakdown: true,
+            calldata_gas: CalldataGasConstants::default(),
+        };
+
+        let provider = Arc::new(provider);
+        let estimator: GasEstimatorImpl<MockProvider, MockEntryPoint> = GasEstimatorImpl::new(
+            Chain::Arbitrum as u64,
+            provider.clone(),
+            entry,
+            settings,
+            create_fee_estimator(provider),
+        );
+
+        let user_op = demo_user_op_optional_gas();
+        let breakdown = estimator
+            .estimate_pre_verification_gas_breakdown(&user_op, U256::zero())
+            .await
+            .unwrap();
+
+        let u_o = user_op.max_fill(&settings);
+        let length_in_words = (u_o.encode().len() + 31) / 32;
+        let call_data_cost = 3936;
+        let fixed_gas_overhead = U256::from(FIXED) / U256::from(BUNDLE_SIZE)
+            + U256::from(PER_USER_OP)
+            + U256::from(PER_USER_OP_WORD) * length_in_words;
+
+        assert_eq!(breakdown.calldata_gas, U256::from(call_data_cost));
+        assert_eq!(breakdown.fixed_gas_overhead, fixed_gas_overhead);
+        assert_eq!(breakdown.l1_gas, U256::from(1000));
+        assert_eq!(
+            breakdown.total(),
+            fixed_gas_overhead + call_data_cost + 1000
+        );
+    }
+
     #[tokio::test]
     async fn test_calc_pre_verification_input_op() {
         let (mut entry, mut provider) = create_base_config();
@@ -657,6 +824,8 @@ mod tests {
             max_call_gas: 10000000000,
             max_simulate_handle_ops_gas: 100000000,
             validation_estimation_gas_fee: 1_000_000_000_000,
+            pre_verification_gas_breakdown: false,
+            calldata_gas: CalldataGasConstants::default(),
         };
 
         // Chose OP
@@ -671,9 +840,10 @@ mod tests {
 
         let user_op = demo_user_op_optional_gas();
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op, U256::zero())
+            .estimate_pre_verification_gas_breakdown(&user_op, U256::zero())
             .await
-            .unwrap();
+            .unwrap()
+            .total();
 
         let u_o = user_op.max_fill(&settings);
 
@@ -759,7 +929,103 @@ mod tests {
             .unwrap();
 
         // the estimation should be the same as the gas usage
-        assert_eq!(gas_usage, estimation);
+        assert_eq!(gas_usage, estimation.verification_gas_limit);
+        assert_eq!(estimation.paymaster_validation_data, None);
+    }
+
+    #[tokio::test]
+    async fn test_binary_search_verification_gas_paymaster_accepted() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000.into();
+
+        entry.expect_address().return_const(Address::zero());
+        entry
+            .expect_decode_simulate_handle_ops_revert()
+            .returning(|_a| {
+                Ok(ExecutionResult {
+                    valid_after: 100,
+                    valid_until: 200,
+                    ..Default::default()
+                })
+            });
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |op, _b, _c, _d, _e, _f| {
+                if op.verification_gas_limit < gas_usage {
+                    return Ok(Err("AA33".to_string()));
+                }
+                Ok(Ok(ExecutionResult::default()))
+            });
+        provider
+            .expect_call()
+            .returning(move |_a, _b, _c| Err(gas_used_revert(gas_usage * 2)));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let paymaster = Address::random();
+        let user_op = UserOperation {
+            paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+            ..demo_user_op()
+        };
+        let estimation = estimator
+            .binary_search_verification_gas(&user_op, H256::zero(), &spoof::state())
+            .await
+            .unwrap();
+
+        assert_eq!(gas_usage, estimation.verification_gas_limit);
+        assert_eq!(estimation.valid_after, 100);
+        assert_eq!(estimation.valid_until, 200);
+        assert_eq!(
+            estimation.paymaster_validation_data,
+            Some(PaymasterValidationData {
+                paymaster,
+                paymaster_accepted: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_search_verification_gas_paymaster_rejected() {
+        let (mut entry, mut provider) = create_base_config();
+
+        entry.expect_address().return_const(Address::zero());
+        entry
+            .expect_decode_simulate_handle_ops_revert()
+            .returning(|_a| Err("AA33 reverted (or OOG)".to_string()));
+        provider
+            .expect_call()
+            .returning(|_a, _b, _c| Err(gas_used_revert(U256::from(20000))));
+
+        let (estimator, _) = create_estimator(entry, provider);
+        let paymaster = Address::random();
+        let user_op = UserOperation {
+            paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+            ..demo_user_op()
+        };
+        let estimation = estimator
+            .binary_search_verification_gas(&user_op, H256::zero(), &spoof::state())
+            .await
+            .err();
+
+        assert!(matches!(
+            estimation,
+            Some(GasEstimationError::PaymasterRejected { paymaster: p, .. }) if p == paymaster
+        ));
+    }
+
+    fn gas_used_revert(gas_used: U256) -> ProviderError {
+        let result_data: Bytes = GasUsedResult {
+            gas_used,
+            success: false,
+            result: Bytes::new(),
+        }
+        .encode()
+        .into();
+
+        ProviderError::JsonRpcError(JsonRpcError {
+            code: -32000,
+            message: "execution reverted".to_string(),
+            data: Some(serde_json::Value::String(result_data.to_string())),
+        })
     }
 
     #[tokio::test]
@@ -1068,6 +1334,56 @@ mod tests {
         assert_eq!(estimation, U256::from(100));
     }
 
+    #[tokio::test]
+    async fn test_estimate_call_gas_uses_configured_simulation_gas() {
+        let (mut entry, mut provider) = create_base_config();
+        let settings = Settings {
+            max_verification_gas: 10000000000,
+            max_call_gas: 10000000000,
+            max_simulate_handle_ops_gas: 123456789,
+            validation_estimation_gas_fee: 1_000_000_000_000,
+            pre_verification_gas_breakdown: false,
+            calldata_gas: CalldataGasConstants::default(),
+        };
+
+        entry.expect_address().return_const(Address::zero());
+        entry
+            .expect_call_spoofed_simulate_op()
+            .withf(|_, _, _, _, gas, _| *gas == U256::from(123456789))
+            .times(1)
+            .returning(|_a, _b, _c, _d, _e, _f| {
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gas_estimate: U256::from(100),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+
+        let provider = Arc::new(provider);
+        let estimator = GasEstimatorImpl::new(
+            0,
+            provider.clone(),
+            entry,
+            settings,
+            create_fee_estimator(provider),
+        );
+        let estimation = estimator
+            .estimate_call_gas(&demo_user_op(), H256::zero(), spoof::state())
+            .await
+            .unwrap();
+
+        assert_eq!(estimation, U256::from(100));
+    }
+
     #[tokio::test]
     async fn test_estimate_call_gas_error() {
         let (mut entry, mut provider) = create_base_config();
@@ -1240,6 +1556,13 @@ mod tests {
         // this number uses the same logic as the pre_verification tests
         assert_eq!(estimation.pre_verification_gas, U256::from(43296));
 
+        // estimating only the pre verification gas gives the same value
+        let pre_verification_gas = estimator
+            .estimate_pre_verification_gas(demo_user_op_optional_gas(), None)
+            .await
+            .unwrap();
+        assert_eq!(pre_verification_gas, estimation.pre_verification_gas);
+
         // gas used increased by 10%
         assert_eq!(
             estimation.verification_gas_limit,
@@ -1250,6 +1573,126 @@ mod tests {
         assert_eq!(estimation.call_gas_limit, U256::from(10000));
     }
 
+    #[tokio::test]
+    async fn test_estimate_pre_verification_gas_only() {
+        let (mut entry, mut provider) = create_base_config();
+        entry.expect_address().return_const(Address::zero());
+        // No simulation calls are expected
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(1000)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(1000)));
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let pre_verification_gas = estimator
+            .estimate_pre_verification_gas(demo_user_op_optional_gas(), None)
+            .await
+            .unwrap();
+        assert!(!pre_verification_gas.is_zero());
+
+        // A zero gas price is an error rather than a panic
+        let result = estimator
+            .estimate_pre_verification_gas(demo_user_op_optional_gas(), Some(U256::zero()))
+            .await;
+        assert!(matches!(result, Err(GasEstimationError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn test_estimation_returns_paymaster_time_range() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000.into();
+
+        entry.expect_address().return_const(Address::zero());
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |op, _b, _c, _d, _e, _f| {
+                if op.verification_gas_limit < gas_usage {
+                    return Ok(Err("AA23".to_string()));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gas_estimate: U256::from(10000),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+        entry
+            .expect_decode_simulate_handle_ops_revert()
+            .returning(|_a| {
+                Ok(ExecutionResult {
+                    pre_op_gas: U256::from(10000),
+                    paid: U256::from(100000),
+                    valid_after: 100,
+                    valid_until: 200,
+                    target_success: true,
+                    target_result: Bytes::new(),
+                })
+            });
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((H256::zero(), U64::zero())));
+        provider.expect_call().returning(move |_a, _b, _c| {
+            let result_data: Bytes = GasUsedResult {
+                gas_used: gas_usage,
+                success: false,
+                result: Bytes::new(),
+            }
+            .encode()
+            .into();
+
+            let json_rpc_error = JsonRpcError {
+                code: -32000,
+                message: "execution reverted".to_string(),
+                data: Some(serde_json::Value::String(result_data.to_string())),
+            };
+            Err(ProviderError::JsonRpcError(json_rpc_error))
+        });
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(1000)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(1000)));
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let paymaster = Address::random();
+        let user_op = UserOperationOptionalGas {
+            paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+            ..demo_user_op_optional_gas()
+        };
+
+        let estimation = estimator
+            .estimate_op_gas(user_op, spoof::state(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(estimation.valid_after, U64::from(100));
+        assert_eq!(estimation.valid_until, U64::from(200));
+        let json = serde_json::to_value(&estimation).unwrap();
+        assert_eq!(json["validAfter"], "0x64");
+        assert_eq!(json["validUntil"], "0xc8");
+        assert_eq!(
+            estimation.paymaster_validation_data,
+            Some(PaymasterValidationData {
+                paymaster,
+                paymaster_accepted: true,
+            })
+        );
+    }
+
     #[tokio::test]
     async fn test_estimation_optional_gas_invalid_settings() {
         let (mut entry, mut provider) = create_base_config();
@@ -1318,6 +1761,8 @@ mod tests {
             max_call_gas: 10,
             max_simulate_handle_ops_gas: 10,
             validation_estimation_gas_fee: 1_000_000_000_000,
+            pre_verification_gas_breakdown: false,
+            calldata_gas: CalldataGasConstants::default(),
         };
 
         let provider = Arc::new(provider);
@@ -1339,4 +1784,29 @@ mod tests {
             Some(GasEstimationError::RevertInValidation(..))
         ));
     }
+
+    #[tokio::test]
+    async fn test_estimation_call_gas_limit_below_call_data_minimum() {
+        let (entry, provider) = create_base_config();
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let user_op = UserOperationOptionalGas {
+            call_data: Bytes::from(vec![1; 1024]),
+            call_gas_limit: Some(MIN_CALL_GAS_LIMIT),
+            ..demo_user_op_optional_gas()
+        };
+        let minimum = gas::user_operation_min_call_gas_limit(&user_op.call_data);
+        // 32 words: 6 gas per word plus 32 * 32 / 512 for memory expansion
+        assert_eq!(minimum, MIN_CALL_GAS_LIMIT + 194);
+
+        let estimation = estimator
+            .estimate_op_gas(user_op, spoof::state(), None)
+            .await
+            .err();
+        assert!(matches!(
+            estimation,
+            Some(GasEstimationError::CallGasLimitTooLow { provided, minimum: m })
+                if provided == MIN_CALL_GAS_LIMIT && m == minimum
+        ));
+    }
 }
@@ -16,4 +16,4 @@ mod estimation;
 pub use estimation::*;
 
 mod types;
-pub use types::{GasEstimate, Settings, UserOperationOptionalGas};
+pub use types::{GasEstimate, PaymasterValidationData, Settings, UserOperationOptionalGas};
@@ -11,12 +11,15 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use ethers::types::{Address, Bytes, U256};
+use ethers::types::{Address, Bytes, U256, U64};
 use rand::RngCore;
 use rundler_types::UserOperation;
 use serde::{Deserialize, Serialize};
 
-use crate::precheck::MIN_CALL_GAS_LIMIT;
+use crate::{
+    gas::{CalldataGasConstants, PreVerificationGasBreakdown},
+    precheck::MIN_CALL_GAS_LIMIT,
+};
 
 /// Settings for gas estimation
 #[derive(Clone, Copy, Debug)]
@@ -33,6 +36,11 @@ pub struct Settings {
     /// gas price.
     /// Clients can use state overrides to set the balance of the fee-payer to at least this value.
     pub validation_estimation_gas_fee: u64,
+    /// Whether to include a breakdown of the pre-verification gas in gas estimates.
+    /// Useful for debugging how the pre-verification gas was derived.
+    pub pre_verification_gas_breakdown: bool,
+    /// Calldata gas costs used in the static portion of the pre-verification gas
+    pub calldata_gas: CalldataGasConstants,
 }
 
 impl Settings {
@@ -227,4 +235,35 @@ pub struct GasEstimate {
     pub verification_gas_limit: U256,
     /// Call gas limit estimate
     pub call_gas_limit: U256,
+    /// Validation data returned from the paymaster's validation phase. Set for
+    /// every operation with a paymaster that accepted it; operations rejected
+    /// by their paymaster fail estimation with
+    /// `GasEstimationError::PaymasterRejected` instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paymaster_validation_data: Option<PaymasterValidationData>,
+    /// The operation is valid after this timestamp, as returned by the
+    /// validation of the account and paymaster
+    #[serde(default)]
+    pub valid_after: U64,
+    /// The operation is valid until this timestamp, as returned by the
+    /// validation of the account and paymaster. Zero means no expiration.
+    #[serde(default)]
+    pub valid_until: U64,
+    /// Breakdown of the pre verification gas estimate. Only populated when
+    /// enabled in the estimation settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<PreVerificationGasBreakdown>,
+}
+
+/// Validation data returned from the validation phase of a paymaster-sponsored
+/// operation during gas estimation
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterValidationData {
+    /// The paymaster that accepted the operation. The validity window,
+    /// combined across the account and the paymaster, is reported in the
+    /// `valid_after` and `valid_until` fields of the gas estimate.
+    pub paymaster: Address,
+    /// Whether the paymaster's validation phase completed without reverting
+    pub paymaster_accepted: bool,
 }
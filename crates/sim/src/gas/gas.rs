@@ -16,7 +16,7 @@ use std::{cmp, fmt::Debug, sync::Arc};
 use anyhow::Context;
 use ethers::{
     abi::AbiEncode,
-    types::{Address, Chain, U256},
+    types::{Address, Bytes, Chain, U256},
 };
 use rundler_provider::Provider;
 use rundler_types::{
@@ -24,38 +24,83 @@ use rundler_types::{
     GasFees, UserOperation,
 };
 use rundler_utils::math;
+use serde::{Deserialize, Serialize};
 use tokio::try_join;
 
 use super::oracle::{
     ConstantOracle, FeeOracle, ProviderOracle, UsageBasedFeeOracle, UsageBasedFeeOracleConfig,
 };
+use crate::precheck::MIN_CALL_GAS_LIMIT;
+
+/// Gas charged per word copied by `CALLDATACOPY`
+const CALL_DATA_COPY_GAS_PER_WORD: u64 = 3;
+/// Linear gas charged per word of memory expansion
+const MEMORY_GAS_PER_WORD: u64 = 3;
+/// Divisor of the squared word count in the quadratic memory expansion cost
+const MEMORY_QUADRATIC_DIVISOR: u64 = 512;
 
 /// Gas overheads for user operations used in calculating the pre-verification gas. See: https://github.com/eth-infinitism/bundler/blob/main/packages/sdk/src/calcPreVerificationGas.ts
 #[derive(Clone, Copy, Debug)]
 pub struct GasOverheads {
     /// The Entrypoint requires a gas buffer for the bundle to account for the gas spent outside of the major steps in the processing of UOs
     pub bundle_transaction_gas_buffer: U256,
-    /// The fixed gas overhead for any EVM transaction
-    pub transaction_gas_overhead: U256,
     per_user_op: U256,
     per_user_op_word: U256,
-    zero_byte: U256,
-    non_zero_byte: U256,
 }
 
 impl Default for GasOverheads {
     fn default() -> Self {
         Self {
             bundle_transaction_gas_buffer: 5_000.into(),
-            transaction_gas_overhead: 21_000.into(),
             per_user_op: 18_300.into(),
             per_user_op_word: 4.into(),
-            zero_byte: 4.into(),
-            non_zero_byte: 16.into(),
         }
     }
 }
 
+/// Calldata gas costs used in the static portion of the pre_verification_gas
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CalldataGasConstants {
+    /// Gas charged per zero byte of calldata
+    pub zero_byte: u64,
+    /// Gas charged per non-zero byte of calldata
+    pub non_zero_byte: u64,
+    /// Fixed gas overhead charged once per transaction
+    pub fixed_overhead: u64,
+}
+
+/// Standard EVM calldata gas costs, as charged on Ethereum mainnet
+pub const EVM_CALLDATA_GAS_CONSTANTS: CalldataGasConstants = CalldataGasConstants {
+    zero_byte: 4,
+    non_zero_byte: 16,
+    fixed_overhead: 21_000,
+};
+
+impl Default for CalldataGasConstants {
+    fn default() -> Self {
+        EVM_CALLDATA_GAS_CONSTANTS
+    }
+}
+
+/// Breakdown of the contributions to a user operation's pre_verification_gas
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreVerificationGasBreakdown {
+    /// Gas charged for the zero and non-zero bytes of the packed user operation
+    pub calldata_gas: U256,
+    /// Fixed per-operation, per-word and transaction overheads
+    pub fixed_gas_overhead: U256,
+    /// Gas charged for the L1 data fee on networks that require it, zero otherwise
+    pub l1_gas: U256,
+}
+
+impl PreVerificationGasBreakdown {
+    /// The total pre_verification_gas, the sum of all of the components
+    pub fn total(&self) -> U256 {
+        self.calldata_gas + self.fixed_gas_overhead + self.l1_gas
+    }
+}
+
 /// Returns the required pre_verification_gas for the given user operation
 ///
 /// `full_op` is either the user operation submitted via `sendUserOperation`
@@ -75,10 +120,37 @@ pub async fn estimate_pre_verification_gas<P: Provider>(
     entry_point: Address,
     provider: Arc<P>,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     gas_price: U256,
 ) -> anyhow::Result<U256> {
+    Ok(estimate_pre_verification_gas_breakdown(
+        full_op,
+        random_op,
+        entry_point,
+        provider,
+        chain_id,
+        calldata_gas,
+        gas_price,
+    )
+    .await?
+    .total())
+}
+
+/// Returns the breakdown of the required pre_verification_gas for the given user operation
+///
+/// See `estimate_pre_verification_gas` for a description of the arguments.
+pub async fn estimate_pre_verification_gas_breakdown<P: Provider>(
+    full_op: &UserOperation,
+    random_op: &UserOperation,
+    entry_point: Address,
+    provider: Arc<P>,
+    chain_id: u64,
+    calldata_gas_constants: &CalldataGasConstants,
+    gas_price: U256,
+) -> anyhow::Result<PreVerificationGasBreakdown> {
     println!("HC entering estimate_pre_verification_gas, gasPrice {:?}", gas_price);
-    let static_gas = calc_static_pre_verification_gas(full_op, true);
+    let (calldata_gas, fixed_gas_overhead) =
+        calc_static_pre_verification_gas_parts(full_op, calldata_gas_constants, true);
     let dynamic_gas = match chain_id {
         _ if ARBITRUM_CHAIN_IDS.contains(&chain_id) => {
             provider
@@ -95,9 +167,17 @@ pub async fn estimate_pre_verification_gas<P: Provider>(
         _ => U256::zero(),
     };
 
-    println!("HC pre_verification_gas {} static {} dynamic", static_gas, dynamic_gas);
-
-    Ok(static_gas + dynamic_gas)
+    println!(
+        "HC pre_verification_gas {} static {} dynamic",
+        calldata_gas + fixed_gas_overhead,
+        dynamic_gas
+    );
+
+    Ok(PreVerificationGasBreakdown {
+        calldata_gas,
+        fixed_gas_overhead,
+        l1_gas: dynamic_gas,
+    })
 }
 
 /// Calculate the required pre_verification_gas for the given user operation and the provided base fee.
@@ -108,9 +188,10 @@ pub async fn calc_required_pre_verification_gas<P: Provider>(
     entry_point: Address,
     provider: Arc<P>,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     base_fee: U256,
 ) -> anyhow::Result<U256> {
-    let static_gas = calc_static_pre_verification_gas(op, true);
+    let static_gas = calc_static_pre_verification_gas(op, calldata_gas, true);
     let dynamic_gas = match chain_id {
         _ if ARBITRUM_CHAIN_IDS.contains(&chain_id) => {
             provider
@@ -153,10 +234,11 @@ pub async fn calc_required_pre_verification_gas<P: Provider>(
 pub fn user_operation_gas_limit(
     uo: &UserOperation,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     assume_single_op_bundle: bool,
     paymaster_post_op: bool,
 ) -> U256 {
-    user_operation_pre_verification_gas_limit(uo, chain_id, assume_single_op_bundle)
+    user_operation_pre_verification_gas_limit(uo, chain_id, calldata_gas, assume_single_op_bundle)
         + uo.call_gas_limit
         + uo.verification_gas_limit
             * verification_gas_limit_multiplier(assume_single_op_bundle, paymaster_post_op)
@@ -166,11 +248,16 @@ pub fn user_operation_gas_limit(
 pub fn user_operation_execution_gas_limit(
     uo: &UserOperation,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     assume_single_op_bundle: bool,
     paymaster_post_op: bool,
 ) -> U256 {
-    user_operation_pre_verification_execution_gas_limit(uo, chain_id, assume_single_op_bundle)
-        + uo.call_gas_limit
+    user_operation_pre_verification_execution_gas_limit(
+        uo,
+        chain_id,
+        calldata_gas,
+        assume_single_op_bundle,
+    ) + uo.call_gas_limit
         + uo.verification_gas_limit
             * verification_gas_limit_multiplier(assume_single_op_bundle, paymaster_post_op)
 }
@@ -179,35 +266,62 @@ pub fn user_operation_execution_gas_limit(
 pub fn user_operation_pre_verification_execution_gas_limit(
     uo: &UserOperation,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     include_fixed_gas_overhead: bool,
 ) -> U256 {
     // On some chains (OP bedrock, Arbitrum) the L1 gas fee is charged via pre_verification_gas
     // but this not part of the EXECUTION gas limit of the transaction.
     // In such cases we only consider the static portion of the pre_verification_gas in the gas limit.
     if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) | ARBITRUM_CHAIN_IDS.contains(&chain_id) {
-        calc_static_pre_verification_gas(uo, include_fixed_gas_overhead)
+        calc_static_pre_verification_gas(uo, calldata_gas, include_fixed_gas_overhead)
     } else {
         uo.pre_verification_gas
     }
 }
 
+/// Returns the minimum call gas limit needed to start executing a user operation's
+/// call: the cost of a call with value plus copying the call data into memory,
+/// including the memory expansion.
+pub fn user_operation_min_call_gas_limit(call_data: &Bytes) -> U256 {
+    let words = U256::from((call_data.len() + 31) / 32);
+    MIN_CALL_GAS_LIMIT
+        + words * (CALL_DATA_COPY_GAS_PER_WORD + MEMORY_GAS_PER_WORD)
+        + words * words / MEMORY_QUADRATIC_DIVISOR
+}
+
 /// Returns the gas limit for the user operation that applies to bundle transaction's limit
 pub fn user_operation_pre_verification_gas_limit(
     uo: &UserOperation,
     chain_id: u64,
+    calldata_gas: &CalldataGasConstants,
     include_fixed_gas_overhead: bool,
 ) -> U256 {
     // On some chains (OP bedrock) the L1 gas fee is charged via pre_verification_gas
     // but this not part of the execution TOTAL limit of the transaction.
     // In such cases we only consider the static portion of the pre_verification_gas in the gas limit.
     if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) {
-        calc_static_pre_verification_gas(uo, include_fixed_gas_overhead)
+        calc_static_pre_verification_gas(uo, calldata_gas, include_fixed_gas_overhead)
     } else {
         uo.pre_verification_gas
     }
 }
 
-fn calc_static_pre_verification_gas(op: &UserOperation, include_fixed_gas_overhead: bool) -> U256 {
+fn calc_static_pre_verification_gas(
+    op: &UserOperation,
+    calldata_gas: &CalldataGasConstants,
+    include_fixed_gas_overhead: bool,
+) -> U256 {
+    let (call_data_cost, fixed_gas_overhead) =
+        calc_static_pre_verification_gas_parts(op, calldata_gas, include_fixed_gas_overhead);
+    call_data_cost + fixed_gas_overhead
+}
+
+/// Returns the calldata cost and the fixed overhead portions of the static pre_verification_gas
+fn calc_static_pre_verification_gas_parts(
+    op: &UserOperation,
+    calldata_gas: &CalldataGasConstants,
+    include_fixed_gas_overhead: bool,
+) -> (U256, U256) {
     let ov = GasOverheads::default();
     let encoded_op = op.clone().encode();
     let length_in_words = encoded_op.len() / 32; // size of packed user op is always a multiple of 32 bytes
@@ -215,22 +329,23 @@ fn calc_static_pre_verification_gas(op: &UserOperation, include_fixed_gas_overhe
         .iter()
         .map(|&x| {
             if x == 0 {
-                ov.zero_byte
+                U256::from(calldata_gas.zero_byte)
             } else {
-                ov.non_zero_byte
+                U256::from(calldata_gas.non_zero_byte)
             }
         })
         .reduce(|a, b| a + b)
         .unwrap_or_default();
 
-    call_data_cost
-        + ov.per_user_op
+    let fixed_gas_overhead = ov.per_user_op
         + ov.per_user_op_word * length_in_words
         + (if include_fixed_gas_overhead {
-            ov.transaction_gas_overhead
+            U256::from(calldata_gas.fixed_overhead)
         } else {
             0.into()
-        })
+        });
+
+    (call_data_cost, fixed_gas_overhead)
 }
 
 fn verification_gas_limit_multiplier(
@@ -323,17 +438,28 @@ impl<P: Provider> FeeEstimator<P> {
     ///
     /// `bundle_priority_fee_overhead_percent` is used to determine the overhead percentage to add
     /// to the network returned priority fee to ensure the bundle priority fee is high enough.
+    ///
+    /// `use_dynamic_max_priority_fee` controls whether the network priority fee is queried. When
+    /// disabled, or on chains without a meaningful network priority fee, `static_max_priority_fee`
+    /// is used instead.
     pub fn new(
         provider: Arc<P>,
         chain_id: u64,
         priority_fee_mode: PriorityFeeMode,
         bundle_priority_fee_overhead_percent: u64,
+        use_dynamic_max_priority_fee: bool,
+        static_max_priority_fee: U256,
     ) -> Self {
         Self {
             provider: provider.clone(),
             priority_fee_mode,
             bundle_priority_fee_overhead_percent,
-            fee_oracle: get_fee_oracle(chain_id, provider),
+            fee_oracle: get_fee_oracle(
+                chain_id,
+                provider,
+                use_dynamic_max_priority_fee,
+                static_max_priority_fee,
+            ),
         }
     }
 
@@ -349,7 +475,27 @@ impl<P: Provider> FeeEstimator<P> {
         min_fees: Option<GasFees>,
     ) -> anyhow::Result<(GasFees, U256)> {
         let (base_fee, priority_fee) = try_join!(self.get_base_fee(), self.get_priority_fee())?;
+        Ok((self.bundle_fees(base_fee, priority_fee, min_fees), base_fee))
+    }
+
+    /// Returns the required fees for a bundle given an already known base fee.
+    ///
+    /// `min_fees` has the same meaning as in `required_bundle_fees`.
+    pub async fn required_bundle_fees_at_base_fee(
+        &self,
+        base_fee: U256,
+        min_fees: Option<GasFees>,
+    ) -> anyhow::Result<GasFees> {
+        let priority_fee = self.get_priority_fee().await?;
+        Ok(self.bundle_fees(base_fee, priority_fee, min_fees))
+    }
 
+    fn bundle_fees(
+        &self,
+        base_fee: U256,
+        priority_fee: U256,
+        min_fees: Option<GasFees>,
+    ) -> GasFees {
         let required_fees = min_fees.unwrap_or_default();
 
         let max_priority_fee_per_gas =
@@ -363,13 +509,10 @@ impl<P: Provider> FeeEstimator<P> {
         let max_fee_per_gas = required_fees
             .max_fee_per_gas
             .max(base_fee + max_priority_fee_per_gas);
-        Ok((
-            GasFees {
-                max_fee_per_gas,
-                max_priority_fee_per_gas,
-            },
-            base_fee,
-        ))
+        GasFees {
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        }
     }
 
     /// Returns the required operation fees for the given bundle fees.
@@ -414,15 +557,20 @@ pub fn get_min_max_priority_fee_per_gas(chain_id: u64) -> U256 {
     }
 }
 
-fn get_fee_oracle<P>(chain_id: u64, provider: Arc<P>) -> Arc<Box<dyn FeeOracle>>
+fn get_fee_oracle<P>(
+    chain_id: u64,
+    provider: Arc<P>,
+    use_dynamic_max_priority_fee: bool,
+    static_max_priority_fee: U256,
+) -> Arc<Box<dyn FeeOracle>>
 where
     P: Provider + Debug,
 {
     let minimum_fee = get_min_max_priority_fee_per_gas(chain_id);
     println!("HC get_fee_oracle minimum {:?} chain {:?} is_optimism {:?}", minimum_fee, &chain_id, OP_BEDROCK_CHAIN_IDS.contains(&chain_id));
 
-    if ARBITRUM_CHAIN_IDS.contains(&chain_id) {
-        Arc::new(Box::new(ConstantOracle::new(U256::zero())))
+    if !use_dynamic_max_priority_fee || ARBITRUM_CHAIN_IDS.contains(&chain_id) {
+        Arc::new(Box::new(ConstantOracle::new(static_max_priority_fee)))
     } else if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) || POLYGON_CHAIN_IDS.contains(&chain_id) {
         let config = UsageBasedFeeOracleConfig {
             minimum_fee,
@@ -433,3 +581,117 @@ where
         Arc::new(Box::new(ProviderOracle::new(provider)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Bytes;
+
+    use super::*;
+
+    fn demo_op() -> UserOperation {
+        UserOperation {
+            call_data: Bytes::from(vec![0, 1, 2, 0, 0, 3]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_mainnet_calldata_gas_constants() {
+        let constants = CalldataGasConstants::default();
+        assert_eq!(constants, EVM_CALLDATA_GAS_CONSTANTS);
+
+        let op = demo_op();
+        let encoded_op = op.clone().encode();
+        let non_zero_bytes = encoded_op.iter().filter(|&&b| b != 0).count();
+        let zero_bytes = encoded_op.len() - non_zero_bytes;
+
+        let (calldata_gas, fixed_gas_overhead) =
+            calc_static_pre_verification_gas_parts(&op, &constants, true);
+        assert_eq!(
+            calldata_gas,
+            U256::from(zero_bytes * 4 + non_zero_bytes * 16)
+        );
+        assert_eq!(
+            fixed_gas_overhead,
+            U256::from(18_300 + 4 * (encoded_op.len() / 32) + 21_000)
+        );
+        assert_eq!(
+            calc_static_pre_verification_gas(&op, &constants, true),
+            calldata_gas + fixed_gas_overhead
+        );
+    }
+
+    #[test]
+    fn test_configured_calldata_gas_constants() {
+        let constants = CalldataGasConstants {
+            zero_byte: 1,
+            non_zero_byte: 4,
+            fixed_overhead: 10_000,
+        };
+        let op = UserOperation {
+            pre_verification_gas: 1.into(),
+            ..demo_op()
+        };
+
+        // the configured constants set the static portion on chains that
+        // charge L1 fees via the pre_verification_gas
+        assert_eq!(
+            user_operation_pre_verification_gas_limit(
+                &op,
+                Chain::Optimism as u64,
+                &constants,
+                true
+            ),
+            calc_static_pre_verification_gas(&op, &constants, true)
+        );
+        assert_ne!(
+            user_operation_pre_verification_gas_limit(
+                &op,
+                Chain::Optimism as u64,
+                &constants,
+                true
+            ),
+            user_operation_pre_verification_gas_limit(
+                &op,
+                Chain::Optimism as u64,
+                &EVM_CALLDATA_GAS_CONSTANTS,
+                true
+            )
+        );
+        // other chains use the op's own pre_verification_gas
+        assert_eq!(
+            user_operation_pre_verification_gas_limit(&op, Chain::Mainnet as u64, &constants, true),
+            op.pre_verification_gas
+        );
+    }
+
+    #[test]
+    fn test_custom_calldata_gas_constants() {
+        let l2_constants = CalldataGasConstants {
+            zero_byte: 1,
+            non_zero_byte: 4,
+            fixed_overhead: 10_000,
+        };
+
+        let op = demo_op();
+        let (mainnet_calldata_gas, mainnet_fixed_gas_overhead) =
+            calc_static_pre_verification_gas_parts(&op, &EVM_CALLDATA_GAS_CONSTANTS, true);
+        let (l2_calldata_gas, l2_fixed_gas_overhead) =
+            calc_static_pre_verification_gas_parts(&op, &l2_constants, true);
+
+        // every byte costs a quarter of the mainnet cost
+        assert_eq!(l2_calldata_gas * 4, mainnet_calldata_gas);
+        assert_eq!(
+            mainnet_fixed_gas_overhead - l2_fixed_gas_overhead,
+            U256::from(11_000)
+        );
+
+        // the transaction overhead is only included when requested
+        let (_, l2_fixed_gas_overhead_without_tx) =
+            calc_static_pre_verification_gas_parts(&op, &l2_constants, false);
+        assert_eq!(
+            l2_fixed_gas_overhead - l2_fixed_gas_overhead_without_tx,
+            U256::from(10_000)
+        );
+    }
+}
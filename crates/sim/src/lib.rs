@@ -32,7 +32,7 @@
 
 mod estimation;
 pub use estimation::{
-    GasEstimate, GasEstimationError, GasEstimator, GasEstimatorImpl,
+    GasEstimate, GasEstimationError, GasEstimator, GasEstimatorImpl, PaymasterValidationData,
     Settings as EstimationSettings, UserOperationOptionalGas,
 };
 
@@ -23,7 +23,7 @@ use rundler_types::{GasFees, UserOperation};
 use rundler_utils::math;
 
 use crate::{
-    gas::{self, get_min_max_priority_fee_per_gas},
+    gas::{self, get_min_max_priority_fee_per_gas, CalldataGasConstants},
     types::ViolationError,
 };
 
@@ -69,10 +69,16 @@ pub struct Settings {
     pub bundle_priority_fee_overhead_percent: u64,
     /// The priority fee mode to use for calculating required user operation priority fee.
     pub priority_fee_mode: gas::PriorityFeeMode,
+    /// Whether to query the network for the max priority fee.
+    pub use_dynamic_max_priority_fee: bool,
+    /// Max priority fee to use when the network is not queried.
+    pub static_max_priority_fee: U256,
     /// Percentage of the current network base fee that a user operation must have to be accepted into the mempool.
     pub base_fee_accept_percent: u64,
     /// Percentage of the preVerificationGas that a user operation must have to be accepted into the mempool.
     pub pre_verification_gas_accept_percent: u64,
+    /// Calldata gas costs used in the static portion of the preVerificationGas
+    pub calldata_gas: CalldataGasConstants,
 }
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -82,10 +88,13 @@ impl Default for Settings {
             max_verification_gas: 5_000_000.into(),
             bundle_priority_fee_overhead_percent: 0,
             priority_fee_mode: gas::PriorityFeeMode::BaseFeePercent(0),
+            use_dynamic_max_priority_fee: true,
+            static_max_priority_fee: U256::zero(),
             max_total_execution_gas: 10_000_000.into(),
             chain_id: 1,
             base_fee_accept_percent: 50,
             pre_verification_gas_accept_percent: 100,
+            calldata_gas: CalldataGasConstants::default(),
         }
     }
 }
@@ -150,6 +159,8 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
                 settings.chain_id,
                 settings.priority_fee_mode,
                 settings.bundle_priority_fee_overhead_percent,
+                settings.use_dynamic_max_priority_fee,
+                settings.static_max_priority_fee,
             ),
             cache: RwLock::new(AsyncDataCache { fees: None }),
         }
@@ -197,6 +208,7 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
             chain_id,
             max_verification_gas,
             max_total_execution_gas,
+            calldata_gas,
             ..
         } = self.settings;
         let AsyncData {
@@ -215,7 +227,8 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
 
         // compute the worst case total gas limit by assuming the UO is in its own bundle and has a postOp call.
         // This is conservative and potentially may invalidate some very large UOs that would otherwise be valid.
-        let gas_limit = gas::user_operation_execution_gas_limit(op, chain_id, true, true);
+        let gas_limit =
+            gas::user_operation_execution_gas_limit(op, chain_id, &calldata_gas, true, true);
         if gas_limit > max_total_execution_gas {
             violations.push(PrecheckViolation::TotalGasLimitTooHigh(
                 gas_limit,
@@ -262,10 +275,12 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
             ));
         }
 
-        if op.call_gas_limit < MIN_CALL_GAS_LIMIT {
+        // Matches the minimum that gas estimation enforces
+        let min_call_gas_limit = gas::user_operation_min_call_gas_limit(&op.call_data);
+        if op.call_gas_limit < min_call_gas_limit {
             violations.push(PrecheckViolation::CallGasLimitTooLow(
                 op.call_gas_limit,
-                MIN_CALL_GAS_LIMIT,
+                min_call_gas_limit,
             ));
         }
         violations
@@ -387,6 +402,7 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
             self.entry_point.address(),
             self.provider.clone(),
             self.settings.chain_id,
+            &self.settings.calldata_gas,
             base_fee,
         )
         .await
@@ -508,8 +524,11 @@ mod tests {
             max_total_execution_gas: 10_000_000.into(),
             bundle_priority_fee_overhead_percent: 0,
             priority_fee_mode: gas::PriorityFeeMode::BaseFeePercent(100),
+            use_dynamic_max_priority_fee: true,
+            static_max_priority_fee: U256::zero(),
             base_fee_accept_percent: 100,
             pre_verification_gas_accept_percent: 100,
+            calldata_gas: CalldataGasConstants::default(),
         };
         let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, test_settings);
         let op = UserOperation {
@@ -541,6 +560,32 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_check_gas_call_data_minimum() {
+        let (provider, entry_point) = create_base_config();
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, Settings::default());
+        let mut op = UserOperation {
+            call_data: Bytes::from(vec![1; 64]),
+            call_gas_limit: MIN_CALL_GAS_LIMIT,
+            ..Default::default()
+        };
+
+        // The flat minimum doesn't cover copying the call data
+        let min_call_gas_limit = gas::user_operation_min_call_gas_limit(&op.call_data);
+        assert!(min_call_gas_limit > MIN_CALL_GAS_LIMIT);
+        let res = prechecker.check_gas(&op, get_test_async_data());
+        assert!(res.contains(&PrecheckViolation::CallGasLimitTooLow(
+            MIN_CALL_GAS_LIMIT,
+            min_call_gas_limit,
+        )));
+
+        op.call_gas_limit = min_call_gas_limit;
+        let res = prechecker.check_gas(&op, get_test_async_data());
+        assert!(!res
+            .iter()
+            .any(|v| matches!(v, PrecheckViolation::CallGasLimitTooLow(..))));
+    }
+
     #[tokio::test]
     async fn test_check_payer_paymaster_deposit_too_low() {
         let (provider, entry_point) = create_base_config();
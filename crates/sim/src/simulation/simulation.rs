@@ -52,7 +52,8 @@ use crate::{
 pub struct SimulationResult {
     /// The mempool IDs that support this operation
     pub mempools: Vec<H256>,
-    /// Block hash this operation was simulated against
+    /// Block hash this operation was simulated against. When simulating
+    /// against a block tag such as pending, the hash of the latest block.
     pub block_hash: H256,
     /// Block number this operation was simulated against
     pub block_number: Option<u64>,
@@ -116,10 +117,12 @@ impl From<Error> for SimulationError {
 pub trait Simulator: Send + Sync + 'static {
     /// Simulate a user operation, returning simulation information
     /// upon success, or simulation violations.
+    ///
+    /// Simulates against the latest block if no block is given.
     async fn simulate_validation(
         &self,
         op: UserOperation,
-        block_hash: Option<H256>,
+        block_id: Option<BlockId>,
         expected_code_hash: Option<H256>,
     ) -> Result<SimulationResult, SimulationError>;
 }
@@ -542,22 +545,27 @@ where
     async fn simulate_validation(
         &self,
         op: UserOperation,
-        block_hash: Option<H256>,
+        block_id: Option<BlockId>,
         expected_code_hash: Option<H256>,
     ) -> Result<SimulationResult, SimulationError> {
-        let (block_hash, block_number) = match block_hash {
+        let (block_hash, block_number, block_id) = match block_id {
             // If we are given a block_hash, we return a None block number, avoiding an extra call
-            Some(block_hash) => (block_hash, None),
-            None => {
+            Some(BlockId::Hash(block_hash)) => (block_hash, None, block_hash.into()),
+            // Other block ids, such as the pending tag, have no fixed hash, so
+            // record the latest block they build on
+            block_id => {
                 let hash_and_num = self
                     .provider
                     .get_latest_block_hash_and_number()
                     .await
                     .map_err(anyhow::Error::from)?;
-                (hash_and_num.0, Some(hash_and_num.1.as_u64()))
+                (
+                    hash_and_num.0,
+                    Some(hash_and_num.1.as_u64()),
+                    block_id.unwrap_or(hash_and_num.0.into()),
+                )
             }
         };
-        let block_id = block_hash.into();
         let mut context = match self.create_context(op.clone(), block_id).await {
             Ok(context) => context,
             error @ Err(_) => error?,
@@ -696,6 +704,33 @@ pub enum SimulationViolation {
     AggregatorValidationFailed,
 }
 
+impl SimulationViolation {
+    /// Returns the category of the violation, used to tag metrics
+    pub fn category(&self) -> &'static str {
+        match self {
+            SimulationViolation::InvalidSignature => "signature",
+            SimulationViolation::UsedForbiddenOpcode(..) => "banned_opcode",
+            SimulationViolation::UsedForbiddenPrecompile(..) => "banned_precompile",
+            SimulationViolation::AccessedUndeployedContract(..) => "undeployed_contract",
+            SimulationViolation::FactoryCalledCreate2Twice(_) => "create2",
+            SimulationViolation::InvalidStorageAccess(..) => "storage_access",
+            SimulationViolation::CalledBannedEntryPointMethod(_) => "entry_point_method",
+            SimulationViolation::CallHadValue(_) => "call_value",
+            SimulationViolation::CodeHashChanged => "code_hash",
+            SimulationViolation::NotStaked(_)
+            | SimulationViolation::UnstakedPaymasterContext
+            | SimulationViolation::UnstakedAggregator => "stake",
+            SimulationViolation::UnintendedRevertWithMessage(..)
+            | SimulationViolation::UnintendedRevert(..) => "revert",
+            SimulationViolation::DidNotRevert | SimulationViolation::WrongNumberOfPhases(_) => {
+                "entry_point"
+            }
+            SimulationViolation::OutOfGas(_) => "out_of_gas",
+            SimulationViolation::AggregatorValidationFailed => "aggregator",
+        }
+    }
+}
+
 /// A wrapper around Opcode that implements extra traits
 #[derive(Debug, PartialEq, Clone, parse_display::Display, Eq)]
 #[display("{0:?}")]
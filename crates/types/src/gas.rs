@@ -13,6 +13,7 @@
 
 use ethers::types::{transaction::eip2718::TypedTransaction, U256};
 use rundler_utils::math;
+use strum::{Display, EnumString};
 
 /// Gas fees for a user operation or transaction
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
@@ -30,11 +31,29 @@ impl From<&TypedTransaction> for GasFees {
                 max_fee_per_gas: tx.max_fee_per_gas.unwrap_or_default(),
                 max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or_default(),
             },
+            TypedTransaction::Legacy(tx) => {
+                let gas_price = tx.gas_price.unwrap_or_default();
+                Self {
+                    max_fee_per_gas: gas_price,
+                    max_priority_fee_per_gas: gas_price,
+                }
+            }
             _ => Self::default(),
         }
     }
 }
 
+/// Type of transaction used to submit bundles
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, Eq, PartialEq)]
+#[strum(serialize_all = "snake_case")]
+pub enum TransactionType {
+    /// EIP-1559 transaction, priced with a max fee and max priority fee
+    #[default]
+    Eip1559,
+    /// Legacy transaction, priced with a single gas price equal to the max fee
+    Legacy,
+}
+
 impl GasFees {
     /// Increase the gas fees by a percentage
     pub fn increase_by_percent(self, percent: u64) -> Self {
@@ -34,13 +34,13 @@ mod entity;
 pub use entity::{Entity, EntityType, EntityUpdate, EntityUpdateType};
 
 mod gas;
-pub use gas::GasFees;
+pub use gas::{GasFees, TransactionType};
 
 mod timestamp;
 pub use timestamp::{Timestamp, ValidTimeRange};
 
 mod user_operation;
-pub use user_operation::UserOperationId;
+pub use user_operation::{UserOperationId, UserOperationStructureError};
 
 mod storage;
 pub use storage::StorageSlot;
@@ -13,7 +13,7 @@
 
 use ethers::{
     abi::{encode, Token},
-    types::{Address, Bytes, H256, U256},
+    types::{Address, Bytes, Signature, H256, U256},
     utils::keccak256,
 };
 use strum::IntoEnumIterator;
@@ -26,6 +26,17 @@ use crate::{
 /// Number of bytes in the fixed size portion of an ABI encoded user operation
 const PACKED_USER_OPERATION_FIXED_LEN: usize = 480;
 
+/// Error for a user operation field that is structurally malformed
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UserOperationStructureError {
+    /// `paymaster_and_data` is non-empty but too short to contain a paymaster address
+    #[error("paymasterAndData must be empty or at least 20 bytes, but was {0} bytes")]
+    PaymasterAndDataTooShort(usize),
+    /// `init_code` is non-empty but too short to contain a factory address
+    #[error("initCode must be empty or at least 20 bytes, but was {0} bytes")]
+    InitCodeTooShort(usize),
+}
+
 /// Unique identifier for a user operation from a given sender
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct UserOperationId {
@@ -72,9 +83,20 @@ impl UserOperation {
 
     /// Returns the maximum cost, in wei, of this user operation
     pub fn max_gas_cost(&self) -> U256 {
+        self.required_prefund()
+    }
+
+    /// Returns the prefund, in wei, that the entry point requires from the
+    /// sender or paymaster before executing this user operation.
+    ///
+    /// Matches `EntryPoint._getRequiredPrefund`: the verification gas limit is
+    /// counted three times when a paymaster is present to cover `validatePaymasterUserOp`
+    /// and `postOp`.
+    pub fn required_prefund(&self) -> U256 {
         let mul = if self.paymaster().is_some() { 3 } else { 1 };
-        self.max_fee_per_gas
-            * (self.pre_verification_gas + self.call_gas_limit + self.verification_gas_limit * mul)
+        let required_gas =
+            self.call_gas_limit + self.verification_gas_limit * mul + self.pre_verification_gas;
+        required_gas * self.max_fee_per_gas
     }
 
     /// Get the address of the paymaster entity associated with this user operation, if any
@@ -93,6 +115,37 @@ impl UserOperation {
         }
     }
 
+    /// Checks that the variable length fields of this user operation are well formed.
+    ///
+    /// Non-empty `init_code` and `paymaster_and_data` must begin with the
+    /// address of their entity, otherwise the entity would be silently ignored.
+    pub fn validate_structure(&self) -> Result<(), UserOperationStructureError> {
+        if !self.init_code.is_empty() && self.init_code.len() < 20 {
+            return Err(UserOperationStructureError::InitCodeTooShort(
+                self.init_code.len(),
+            ));
+        }
+        if !self.paymaster_and_data.is_empty() && self.paymaster_and_data.len() < 20 {
+            return Err(UserOperationStructureError::PaymasterAndDataTooShort(
+                self.paymaster_and_data.len(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Recovers the address that signed this user operation's hash with a
+    /// standard ECDSA signature over the EIP-191 prefixed op hash, as used by
+    /// simple EOA-owned accounts.
+    ///
+    /// Returns `None` if the signature is not a 65 byte ECDSA signature, as is
+    /// the case for contract or aggregated signatures, or if recovery fails.
+    pub fn recover_signer(&self, entry_point: Address, chain_id: u64) -> Option<Address> {
+        let signature = Signature::try_from(self.signature.as_ref()).ok()?;
+        signature
+            .recover(self.op_hash(entry_point, chain_id).as_bytes())
+            .ok()
+    }
+
     /// Efficient calculation of the size of a packed user operation
     pub fn abi_encoded_size(&self) -> usize {
         PACKED_USER_OPERATION_FIXED_LEN
@@ -177,11 +230,16 @@ mod tests {
 
     use ethers::{
         abi::AbiEncode,
+        signers::{LocalWallet, Signer},
         types::{Bytes, U256},
+        utils::hash_message,
     };
 
     use super::*;
 
+    const SIGNER_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ENTRY_POINT: &str = "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789";
+
     #[test]
     fn test_hash_zeroed() {
         // Testing a user operation hash against the hash generated by the
@@ -291,6 +349,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_required_prefund() {
+        let mut operation = UserOperation {
+            call_gas_limit: 10000.into(),
+            verification_gas_limit: 100000.into(),
+            pre_verification_gas: 100.into(),
+            max_fee_per_gas: 99999.into(),
+            ..Default::default()
+        };
+        // (10000 + 100000 + 100) * 99999
+        assert_eq!(operation.required_prefund(), 11_009_889_900_u64.into());
+
+        operation.paymaster_and_data =
+            "0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"
+                .parse()
+                .unwrap();
+        // (10000 + 3 * 100000 + 100) * 99999
+        assert_eq!(operation.required_prefund(), 31_009_689_900_u64.into());
+        assert_eq!(operation.max_gas_cost(), operation.required_prefund());
+    }
+
     #[test]
     fn test_get_address_from_field() {
         let paymaster_and_data: Bytes =
@@ -306,6 +385,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_entities_all() {
+        let sender = Address::random();
+        let factory = Address::random();
+        let paymaster = Address::random();
+        let op = UserOperation {
+            sender,
+            init_code: [factory.as_bytes(), &[1u8, 2, 3][..]].concat().into(),
+            paymaster_and_data: [paymaster.as_bytes(), &[4u8, 5, 6][..]].concat().into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            op.entities().collect::<Vec<_>>(),
+            vec![
+                Entity::account(sender),
+                Entity::paymaster(paymaster),
+                Entity::factory(factory),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entities_only_paymaster() {
+        let sender = Address::random();
+        let paymaster = Address::random();
+        let op = UserOperation {
+            sender,
+            paymaster_and_data: paymaster.as_bytes().to_vec().into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            op.entities().collect::<Vec<_>>(),
+            vec![Entity::account(sender), Entity::paymaster(paymaster)]
+        );
+    }
+
+    #[test]
+    fn test_entities_only_factory() {
+        let sender = Address::random();
+        let factory = Address::random();
+        let op = UserOperation {
+            sender,
+            init_code: [factory.as_bytes(), &[1u8, 2, 3][..]].concat().into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            op.entities().collect::<Vec<_>>(),
+            vec![Entity::account(sender), Entity::factory(factory)]
+        );
+    }
+
+    #[test]
+    fn test_entities_short_fields() {
+        let sender = Address::random();
+        let op = UserOperation {
+            sender,
+            init_code: Bytes::from_str("0x0123456789abcdef").unwrap(),
+            paymaster_and_data: Bytes::from_str("0x0123456789").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            op.entities().collect::<Vec<_>>(),
+            vec![Entity::account(sender)]
+        );
+    }
+
     #[test]
     fn test_abi_encoded_size() {
         let user_operation = UserOperation {
@@ -329,4 +474,69 @@ mod tests {
             user_operation.abi_encoded_size()
         );
     }
+
+    #[test]
+    fn test_validate_structure_paymaster_and_data_too_short() {
+        let op = UserOperation {
+            paymaster_and_data: Bytes::from_str("0x0123456789").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            op.validate_structure(),
+            Err(UserOperationStructureError::PaymasterAndDataTooShort(5))
+        );
+    }
+
+    #[test]
+    fn test_validate_structure_init_code_too_short() {
+        let op = UserOperation {
+            init_code: Bytes::from_str("0x0123456789abcdef").unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            op.validate_structure(),
+            Err(UserOperationStructureError::InitCodeTooShort(8))
+        );
+    }
+
+    #[test]
+    fn test_validate_structure_valid() {
+        let op = UserOperation {
+            init_code: Bytes::from_str("0x0123456789abcdef0123456789abcdef01234567abcd").unwrap(),
+            paymaster_and_data: Bytes::from_str("0x0123456789abcdef0123456789abcdef01234567")
+                .unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(op.validate_structure(), Ok(()));
+    }
+
+    #[test]
+    fn test_recover_signer() {
+        let wallet: LocalWallet = SIGNER_KEY.parse().unwrap();
+        let entry_point = ENTRY_POINT.parse().unwrap();
+        let mut op = UserOperation {
+            sender: Address::random(),
+            nonce: 1.into(),
+            ..Default::default()
+        };
+        let signature = wallet
+            .sign_hash(hash_message(op.op_hash(entry_point, 1)))
+            .unwrap();
+        op.signature = signature.to_vec().into();
+
+        assert_eq!(op.recover_signer(entry_point, 1), Some(wallet.address()));
+        // Signed for chain 1, so recovering for another chain yields some other address
+        assert_ne!(op.recover_signer(entry_point, 5), Some(wallet.address()));
+    }
+
+    #[test]
+    fn test_recover_signer_non_ecdsa_signature() {
+        let entry_point = ENTRY_POINT.parse().unwrap();
+        let mut op = UserOperation::default();
+        // Aggregated ops typically have an empty signature
+        assert_eq!(op.recover_signer(entry_point, 1), None);
+
+        op.signature = vec![1; 64].into();
+        assert_eq!(op.recover_signer(entry_point, 1), None);
+    }
 }
@@ -20,7 +20,7 @@ use ethers::{
     abi::{AbiDecode, RawLog},
     contract::ContractError,
     providers::{
-        Http, HttpRateLimitRetryPolicy, Middleware, Provider, RetryClient, RetryClientBuilder,
+        Http, HttpRateLimitRetryPolicy, Middleware, Provider, RetryClient, RetryClientBuilder, Ws,
     },
     types::{Address, Bytes, Log},
 };
@@ -82,6 +82,16 @@ pub fn new_provider(
     Ok(Arc::new(provider))
 }
 
+/// Construct a new Ethers provider connected to a websocket URL.
+///
+/// Websocket providers support subscriptions, which HTTP providers do not.
+pub async fn new_ws_provider(url: &str) -> anyhow::Result<Arc<Provider<Ws>>> {
+    let ws = Ws::connect(url)
+        .await
+        .context("should connect to websocket provider")?;
+    Ok(Arc::new(Provider::new(ws)))
+}
+
 /// Converts an ethers `Log` into an ethabi `RawLog`.
 pub fn log_to_raw_log(log: Log) -> RawLog {
     let Log { topics, data, .. } = log;